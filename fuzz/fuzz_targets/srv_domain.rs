@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsip_dns::records::SrvDomain;
+
+// SRV names come from untrusted server responses: parsing must never panic, and whatever parses
+// must render back to a name that parses the same
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data)
+        && let Ok(srv_domain) = SrvDomain::try_from(input)
+    {
+        let rendered = srv_domain.to_string();
+        assert_eq!(SrvDomain::try_from(rendered.as_str()).ok(), Some(srv_domain));
+    }
+});
@@ -0,0 +1,125 @@
+use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord};
+use crate::{DnsClient, DnsError};
+#[cfg(feature = "hickory-dns")]
+use hickory_proto::rr::{RData, RecordType};
+use rsip::{Domain, Error};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A lookup seen by a [CapturingDnsClient], along with what the inner client answered
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "query", rename_all = "lowercase")]
+pub enum RecordedQuery {
+    Naptr {
+        #[serde(with = "crate::serde_fields::domain")]
+        domain: Domain,
+        result: Result<Option<NaptrRecord>, DnsError>,
+    },
+    Srv {
+        domain: SrvDomain,
+        result: Result<Option<SrvRecord>, DnsError>,
+    },
+    /// The error of an A/AAAA lookup is kept as its message
+    Ip {
+        #[serde(with = "crate::serde_fields::domain")]
+        domain: Domain,
+        result: Result<AddrRecord, String>,
+    },
+}
+
+/// The lookups captured by a [CapturingDnsClient] in the order they were made, which a
+/// [ReplayDnsClient](crate::ReplayDnsClient) serves back. Stored as JSON.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Recording {
+    pub queries: Vec<RecordedQuery>,
+}
+
+impl Recording {
+    /// Writes the recording to `path` as JSON
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self).map_err(io::Error::other)
+    }
+
+    /// Reads a recording written by [Recording::save]
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        serde_json::from_reader(reader).map_err(io::Error::other)
+    }
+}
+
+/// Wraps another [DnsClient] and records every NAPTR, SRV and A/AAAA lookup along with its
+/// result, so that a resolution (e.g. one that misbehaved in production) can be replayed later
+/// on with no network through a [ReplayDnsClient](crate::ReplayDnsClient).
+///
+/// Clones share the recording.
+#[derive(Debug, Clone)]
+pub struct CapturingDnsClient<C: DnsClient> {
+    inner: C,
+    queries: Arc<Mutex<Vec<RecordedQuery>>>,
+}
+
+impl<C: DnsClient> CapturingDnsClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner, queries: Default::default() }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// What has been recorded so far
+    pub fn recording(&self) -> Recording {
+        Recording { queries: self.queries.lock().expect("recording lock poisoned").clone() }
+    }
+
+    /// Writes what has been recorded so far to `path`, see [Recording::save]
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.recording().save(path)
+    }
+
+    fn record(&self, query: RecordedQuery) {
+        self.queries.lock().expect("recording lock poisoned").push(query);
+    }
+}
+
+impl<C: DnsClient> DnsClient for CapturingDnsClient<C> {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        let naptr_record = self.inner.naptr_lookup(domain.clone()).await;
+        self.record(RecordedQuery::Naptr { domain, result: Ok(naptr_record.clone()) });
+        naptr_record
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        let srv_record = self.inner.srv_lookup(domain.clone()).await;
+        self.record(RecordedQuery::Srv { domain, result: Ok(srv_record.clone()) });
+        srv_record
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        let addr_record = self.inner.ip_lookup(domain.clone()).await;
+        let result = addr_record.as_ref().map(Clone::clone).map_err(ToString::to_string);
+        self.record(RecordedQuery::Ip { domain, result });
+        addr_record
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let result = self.inner.try_naptr_lookup(domain.clone()).await;
+        self.record(RecordedQuery::Naptr { domain, result: result.clone() });
+        result
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        let result = self.inner.try_srv_lookup(domain.clone()).await;
+        self.record(RecordedQuery::Srv { domain, result: result.clone() });
+        result
+    }
+
+    /// Raw lookups are passed through without being recorded
+    #[cfg(feature = "hickory-dns")]
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        self.inner.raw_lookup(name, rtype).await
+    }
+}
@@ -0,0 +1,19 @@
+//! [DnsClient](crate::DnsClient) wrappers that add behavior on top of another dns client.
+
+#[cfg(feature = "serde")]
+mod capturing_dns_client;
+mod override_dns_client;
+mod pool_dns_client;
+mod racing_dns_client;
+#[cfg(feature = "serde")]
+mod replay_dns_client;
+mod throttled_dns_client;
+
+#[cfg(feature = "serde")]
+pub use capturing_dns_client::{CapturingDnsClient, RecordedQuery, Recording};
+pub use override_dns_client::OverrideDnsClient;
+pub use pool_dns_client::{PoolDnsClient, SelectionStrategy};
+pub use racing_dns_client::RacingDnsClient;
+#[cfg(feature = "serde")]
+pub use replay_dns_client::ReplayDnsClient;
+pub use throttled_dns_client::ThrottledDnsClient;
@@ -0,0 +1,116 @@
+use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord, domain_key};
+use crate::{DEFAULT_TTL, DnsClient, DnsError};
+#[cfg(feature = "hickory-dns")]
+use hickory_proto::rr::{RData, RecordType};
+use rsip::{Domain, Error};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// Wraps another [DnsClient] and answers A/AAAA lookups for a fixed set of domains locally, much
+/// like an `/etc/hosts` file. Useful for split-horizon setups and tests.
+///
+/// Any other domain, as well as every NAPTR and SRV lookup, is delegated to the inner client.
+/// Address records that come back in the ADDITIONAL section of a SRV (or NAPTR) response are
+/// replaced with the overrides too, so an overridden domain always resolves to its overrides and
+/// never to what the inner client found for it.
+///
+/// Domains are matched case-insensitively, ignoring any trailing dot.
+#[derive(Debug, Clone)]
+pub struct OverrideDnsClient<C: DnsClient> {
+    inner: C,
+    overrides: Arc<HashMap<String, Vec<IpAddr>>>,
+    ttl: u32,
+}
+
+impl<C: DnsClient> OverrideDnsClient<C> {
+    pub fn new(inner: C, overrides: HashMap<Domain, Vec<IpAddr>>) -> Self {
+        let overrides = overrides
+            .into_iter()
+            .map(|(domain, ip_addrs)| (normalize(&domain), ip_addrs))
+            .collect::<HashMap<_, _>>();
+
+        Self { inner, overrides: Arc::new(overrides), ttl: DEFAULT_TTL }
+    }
+
+    /// Set the TTL reported for the overridden address records, [DEFAULT_TTL] by default
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// The overridden address record for the given domain, if there is one
+    pub fn override_for(&self, domain: &Domain) -> Option<AddrRecord> {
+        self.overrides.get(&normalize(domain)).map(|ip_addrs| AddrRecord {
+            domain: domain.clone(),
+            ip_addrs: ip_addrs.clone(),
+            ttl: self.ttl,
+            per_family_ttl: None,
+            source: None,
+        })
+    }
+
+    fn apply_to_srv(&self, srv_record: &mut SrvRecord) {
+        for target in srv_record.targets() {
+            if let Some(addr_record) = self.override_for(&target) {
+                srv_record.additional_hosts.insert(domain_key(&target), addr_record);
+            }
+        }
+    }
+
+    fn apply_to_naptr(&self, naptr_record: &mut NaptrRecord) {
+        for srv_record in naptr_record.additional_srvs.values_mut() {
+            self.apply_to_srv(srv_record);
+        }
+    }
+}
+
+impl<C: DnsClient> DnsClient for OverrideDnsClient<C> {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        let mut naptr_record = self.inner.naptr_lookup(domain).await?;
+        self.apply_to_naptr(&mut naptr_record);
+        Some(naptr_record)
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        let mut srv_record = self.inner.srv_lookup(domain).await?;
+        self.apply_to_srv(&mut srv_record);
+        Some(srv_record)
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        match self.override_for(&domain) {
+            Some(addr_record) => Ok(addr_record),
+            None => self.inner.ip_lookup(domain).await,
+        }
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let mut naptr_record = self.inner.try_naptr_lookup(domain).await?;
+        if let Some(naptr_record) = naptr_record.as_mut() {
+            self.apply_to_naptr(naptr_record);
+        }
+        Ok(naptr_record)
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        let mut srv_record = self.inner.try_srv_lookup(domain).await?;
+        if let Some(srv_record) = srv_record.as_mut() {
+            self.apply_to_srv(srv_record);
+        }
+        Ok(srv_record)
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        self.inner.raw_lookup(name, rtype).await
+    }
+}
+
+fn normalize(domain: &Domain) -> String {
+    domain.to_string().trim_end_matches('.').to_ascii_lowercase()
+}
@@ -0,0 +1,117 @@
+use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord};
+use crate::{DnsClient, DnsError};
+#[cfg(feature = "hickory-dns")]
+use hickory_proto::rr::{RData, RecordType};
+use rsip::{Domain, Error};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+/// How a [PoolDnsClient] picks the client each query is sent to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionStrategy {
+    /// Each client in turn, in the order of the pool
+    RoundRobin,
+    /// Any client, uniformly at random
+    Random,
+    /// Any client at random, in proportion to its weight: one weight per client, in the order of
+    /// the pool. A client with a weight of 0 is never picked, unless all of them are.
+    Weighted(Vec<u32>),
+}
+
+/// Spreads the queries over several [DnsClient]s (e.g. a set of recursive resolvers), sending
+/// each one to a single client picked by the [SelectionStrategy].
+///
+/// Unlike [RacingDnsClient](super::RacingDnsClient), a query is never sent twice: whatever the
+/// picked client answers is the answer, even if another one would have found something. Clones
+/// share the selection state, so a clone given to the [Context](crate::Context) of every
+/// lookup keeps rotating through the pool across all of them.
+#[derive(Debug, Clone)]
+pub struct PoolDnsClient<C: DnsClient> {
+    clients: Arc<[C]>,
+    strategy: SelectionStrategy,
+    /// Queries sent so far, the round robin position
+    next: Arc<AtomicUsize>,
+}
+
+impl<C: DnsClient> PoolDnsClient<C> {
+    /// # Panics
+    ///
+    /// Panics if there is no client at all, or if the [weights](SelectionStrategy::Weighted)
+    /// don't match the clients one to one.
+    pub fn new(clients: Vec<C>, strategy: SelectionStrategy) -> Self {
+        assert!(!clients.is_empty(), "can't pool no dns client at all");
+        if let SelectionStrategy::Weighted(weights) = &strategy {
+            assert_eq!(weights.len(), clients.len(), "every pooled dns client needs a weight");
+        }
+
+        Self { clients: clients.into(), strategy, next: Arc::new(AtomicUsize::new(0)) }
+    }
+
+    pub fn clients(&self) -> &[C] {
+        &self.clients
+    }
+
+    pub fn strategy(&self) -> &SelectionStrategy {
+        &self.strategy
+    }
+
+    /// The client the next query goes to
+    fn pick(&self) -> &C {
+        let index = match &self.strategy {
+            SelectionStrategy::RoundRobin => {
+                self.next.fetch_add(1, Ordering::Relaxed) % self.clients.len()
+            }
+            SelectionStrategy::Random => rand::random_range(0..self.clients.len()),
+            SelectionStrategy::Weighted(weights) => weighted_index(weights),
+        };
+
+        &self.clients[index]
+    }
+}
+
+impl<C: DnsClient> DnsClient for PoolDnsClient<C> {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.pick().naptr_lookup(domain).await
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        self.pick().srv_lookup(domain).await
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        self.pick().ip_lookup(domain).await
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        self.pick().try_naptr_lookup(domain).await
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        self.pick().try_srv_lookup(domain).await
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        self.pick().raw_lookup(name, rtype).await
+    }
+}
+
+/// A random index, each one as likely as its weight, or uniformly picked when all weights are 0
+fn weighted_index(weights: &[u32]) -> usize {
+    let total = weights.iter().map(|weight| *weight as u64).sum::<u64>();
+    if total == 0 {
+        return rand::random_range(0..weights.len());
+    }
+
+    let pick = rand::random_range(0..total);
+    let mut running = 0;
+    weights
+        .iter()
+        .position(|weight| {
+            running += *weight as u64;
+            running > pick
+        })
+        .expect("pick is below the total weight")
+}
@@ -0,0 +1,107 @@
+use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord};
+use crate::{DnsClient, DnsError};
+use futures::future::{Either, select};
+#[cfg(feature = "hickory-dns")]
+use hickory_proto::rr::{RData, RecordType};
+use rsip::{Domain, Error};
+use std::pin::pin;
+
+/// Sends every query to two [DnsClient]s at once (e.g. a primary and a secondary resolver) and
+/// answers with whichever of them answers first, for resilience against a slow or unreachable
+/// resolver.
+///
+/// An answer only wins the race when it is a success: a record (or an address record) found.
+/// When the first client to answer found nothing or failed, the answer of the other one is
+/// awaited and returned, whatever it is. Both queries are sent every time, and the slower one is
+/// dropped as soon as the other one has won.
+#[derive(Debug, Clone)]
+pub struct RacingDnsClient<A: DnsClient, B: DnsClient> {
+    primary: A,
+    secondary: B,
+}
+
+impl<A: DnsClient, B: DnsClient> RacingDnsClient<A, B> {
+    pub fn new(primary: A, secondary: B) -> Self {
+        Self { primary, secondary }
+    }
+
+    pub fn primary(&self) -> &A {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &B {
+        &self.secondary
+    }
+}
+
+impl<A: DnsClient, B: DnsClient> DnsClient for RacingDnsClient<A, B> {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        race(
+            self.primary.naptr_lookup(domain.clone()),
+            self.secondary.naptr_lookup(domain),
+            Option::is_some,
+        )
+        .await
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        race(
+            self.primary.srv_lookup(domain.clone()),
+            self.secondary.srv_lookup(domain),
+            Option::is_some,
+        )
+        .await
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        race(
+            self.primary.ip_lookup(domain.clone()),
+            self.secondary.ip_lookup(domain),
+            Result::is_ok,
+        )
+        .await
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        race(
+            self.primary.try_naptr_lookup(domain.clone()),
+            self.secondary.try_naptr_lookup(domain),
+            |answer| matches!(answer, Ok(Some(_))),
+        )
+        .await
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        race(
+            self.primary.try_srv_lookup(domain.clone()),
+            self.secondary.try_srv_lookup(domain),
+            |answer| matches!(answer, Ok(Some(_))),
+        )
+        .await
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        race(
+            self.primary.raw_lookup(name.clone(), rtype),
+            self.secondary.raw_lookup(name, rtype),
+            |answer| answer.as_ref().is_ok_and(|rdata| !rdata.is_empty()),
+        )
+        .await
+    }
+}
+
+/// The first answer that `succeeded` accepts, or the answer of the slower query when the faster
+/// one isn't accepted
+async fn race<T>(
+    primary: impl Future<Output = T>,
+    secondary: impl Future<Output = T>,
+    succeeded: impl Fn(&T) -> bool,
+) -> T {
+    match select(pin!(primary), pin!(secondary)).await {
+        Either::Left((answer, _)) if succeeded(&answer) => answer,
+        Either::Left((_, secondary)) => secondary.await,
+        Either::Right((answer, _)) if succeeded(&answer) => answer,
+        Either::Right((_, primary)) => primary.await,
+    }
+}
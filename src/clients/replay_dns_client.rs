@@ -0,0 +1,88 @@
+use super::capturing_dns_client::{RecordedQuery, Recording};
+use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord};
+use crate::{DnsClient, DnsError};
+use rsip::{Domain, Error};
+use std::sync::{Arc, Mutex};
+
+/// Answers the lookups out of a [Recording] made by a
+/// [CapturingDnsClient](crate::CapturingDnsClient), with no network at all, turning a captured
+/// resolution into a deterministic test case.
+///
+/// Each lookup is answered by the first recorded query of the same type and domain that hasn't
+/// been served yet, so that a domain queried several times gets its answers in the recorded
+/// order. A lookup that wasn't recorded fails (with [DnsError::Other] or [Error::Unexpected]).
+///
+/// Clones share what has been served.
+#[derive(Debug, Clone)]
+pub struct ReplayDnsClient {
+    queries: Arc<Mutex<Vec<RecordedQuery>>>,
+}
+
+impl ReplayDnsClient {
+    pub fn new(recording: Recording) -> Self {
+        Self { queries: Arc::new(Mutex::new(recording.queries)) }
+    }
+
+    /// How many recorded queries haven't been served yet
+    pub fn remaining(&self) -> usize {
+        self.queries.lock().expect("replay lock poisoned").len()
+    }
+
+    /// Takes the first recorded query matching `served`
+    fn take(&self, served: impl Fn(&RecordedQuery) -> bool) -> Option<RecordedQuery> {
+        let mut queries = self.queries.lock().expect("replay lock poisoned");
+        let index = queries.iter().position(served)?;
+        Some(queries.remove(index))
+    }
+
+    fn naptr_result(&self, domain: &Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let naptr = |query: &RecordedQuery| match query {
+            RecordedQuery::Naptr { domain: recorded, .. } => recorded == domain,
+            _ => false,
+        };
+        match self.take(naptr) {
+            Some(RecordedQuery::Naptr { result, .. }) => result,
+            _ => Err(DnsError::Other(format!("no recorded NAPTR answer for {}", domain))),
+        }
+    }
+
+    fn srv_result(&self, domain: &SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        let srv = |query: &RecordedQuery| match query {
+            RecordedQuery::Srv { domain: recorded, .. } => recorded == domain,
+            _ => false,
+        };
+        match self.take(srv) {
+            Some(RecordedQuery::Srv { result, .. }) => result,
+            _ => Err(DnsError::Other(format!("no recorded SRV answer for {}", domain))),
+        }
+    }
+}
+
+impl DnsClient for ReplayDnsClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.naptr_result(&domain).ok().flatten()
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        self.srv_result(&domain).ok().flatten()
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        let ip = |query: &RecordedQuery| match query {
+            RecordedQuery::Ip { domain: recorded, .. } => *recorded == domain,
+            _ => false,
+        };
+        match self.take(ip) {
+            Some(RecordedQuery::Ip { result, .. }) => result.map_err(Error::Unexpected),
+            _ => Err(Error::Unexpected(format!("no recorded A/AAAA answer for {}", domain))),
+        }
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        self.naptr_result(&domain)
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        self.srv_result(&domain)
+    }
+}
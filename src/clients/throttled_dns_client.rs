@@ -0,0 +1,72 @@
+use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord};
+use crate::{DnsClient, DnsError};
+#[cfg(feature = "hickory-dns")]
+use hickory_proto::rr::{RData, RecordType};
+use rsip::{Domain, Error};
+use std::sync::Arc;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Wraps another [DnsClient] and caps the number of queries in flight at once, so that a large
+/// fan-out [Lookup](crate::Lookup), or many lookups at the same time, can't flood the name
+/// server. Queries over the limit wait for a slot in the order they were made.
+///
+/// Clones share the limit, so a clone given to the [Context](crate::Context) of every lookup
+/// throttles all of them together.
+#[derive(Debug, Clone)]
+pub struct ThrottledDnsClient<C: DnsClient> {
+    inner: C,
+    semaphore: Arc<Semaphore>,
+}
+
+impl<C: DnsClient> ThrottledDnsClient<C> {
+    /// Allow at most `max_in_flight` queries at once (a zero limit is raised to one)
+    pub fn new(inner: C, max_in_flight: usize) -> Self {
+        Self { inner, semaphore: Arc::new(Semaphore::new(max_in_flight.max(1))) }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// How many more queries can start right now without waiting
+    pub fn available_slots(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    async fn slot(&self) -> SemaphorePermit<'_> {
+        self.semaphore.acquire().await.expect("the semaphore is never closed")
+    }
+}
+
+impl<C: DnsClient> DnsClient for ThrottledDnsClient<C> {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        let _slot = self.slot().await;
+        self.inner.naptr_lookup(domain).await
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        let _slot = self.slot().await;
+        self.inner.srv_lookup(domain).await
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        let _slot = self.slot().await;
+        self.inner.ip_lookup(domain).await
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let _slot = self.slot().await;
+        self.inner.try_naptr_lookup(domain).await
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        let _slot = self.slot().await;
+        self.inner.try_srv_lookup(domain).await
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        let _slot = self.slot().await;
+        self.inner.raw_lookup(name, rtype).await
+    }
+}
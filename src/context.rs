@@ -1,14 +1,23 @@
-use crate::DnsClient;
-use rsip::{Error, Host, Port, Scheme, Transport, Uri};
+use crate::{
+    DEFAULT_TTL, DnsClient, IpFamily, NoopRanker, TargetRanker,
+    records::{MAX_LABEL_LENGTH, MAX_NAME_LENGTH, NaptrEntry, NaptrServices},
+    resolvables::{DEFAULT_MAX_NAPTR_DEPTH, PFlagHook},
+};
+use rsip::{Domain, Error, Host, Param, Port, Scheme, Transport, Uri};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// This is the main context struct that is used by the [Lookup](super::Lookup) to figure out what
 /// procedures it should apply.
-/// It can manually initialized by populating every field, or by using the
-/// [Context::initialize_from] method which can be handy if you already have the URI of the host.
+/// It can manually initialized by populating every field, through the [ContextBuilder] (see
+/// [Context::builder]) or by using the [Context::initialize_from] method which can be handy if you
+/// already have the URI of the host.
 ///
 /// [Context::initialize_from] can return an error if the URI transport constraints and `supported_transports`
 /// don't overlap.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Context<C: DnsClient> {
     pub secure: bool,
     pub host: Host,
@@ -16,6 +25,108 @@ pub struct Context<C: DnsClient> {
     pub transport: Option<Transport>,
     pub dns_client: C,
     pub supported_transports: SupportedTransports,
+    /// When NAPTR yields nothing, query the SRV records of all the available transports
+    /// concurrently instead of one after the other. Targets are still returned in transport
+    /// preference order, those of a transport as soon as its own query completes. Off by
+    /// default, as it trades extra queries for latency.
+    pub concurrent_srv_fallbacks: bool,
+    /// Skip the NAPTR query of a domain without port or transport and go straight to the SRV
+    /// records of the available transports. Useful when the domain is known to publish SRV
+    /// records only.
+    pub skip_naptr: bool,
+    /// Which fallbacks a domain without port or transport may go through, see [FallbackPolicy].
+    pub fallback_policy: FallbackPolicy,
+    /// How many non-terminal NAPTR entries are followed in a chain, defaults to
+    /// [DEFAULT_MAX_NAPTR_DEPTH].
+    pub max_naptr_depth: usize,
+    /// Port used for a transport whenever neither the uri nor an SRV record gives one (the
+    /// A/AAAA fallbacks and NAPTR entries with the `A` flag), instead of the standard default
+    /// port of the transport.
+    pub default_ports: HashMap<Transport, Port>,
+    /// Domains tried in order once the host is exhausted, each one going through the whole
+    /// NAPTR/SRV/A flow on its own (e.g. a backup domain after the primary one). Only a domain
+    /// host without port or transport ([Lookup::JustDomain](crate::Lookup::JustDomain))
+    /// falls back to them.
+    pub backup_domains: Vec<Domain>,
+    /// Only resolve domains into addresses of this family, for hosts on an IPv4-only or
+    /// IPv6-only network. `None` (the default) keeps both. An ip address host is used as is.
+    pub ip_family: Option<IpFamily>,
+    /// When neither NAPTR nor SRV yields anything, resolve the domain itself into a target for
+    /// each available transport in preference order (e.g. UDP then TCP), each on its default
+    /// port, instead of for the default transport only. Every transport costs its own A/AAAA
+    /// query.
+    pub synthesize_all_transports: bool,
+    /// Once a SRV record is known, send the A/AAAA queries of all of its targets at once, with
+    /// at most that many in flight (0 meaning no limit), instead of one after the other as the
+    /// targets are consumed. Trades extra queries for latency, `None` (the default) keeps them
+    /// lazy.
+    pub concurrent_srv_targets: Option<usize>,
+    /// TTL, in seconds, of the target of an ip address host, which comes with none of its own.
+    /// Defaults to [DEFAULT_TTL].
+    pub default_ttl: u32,
+    /// Time budget of the whole resolution, measured from the first
+    /// [resolve_next](crate::ResolvableExt::resolve_next) of the [Lookup](crate::Lookup). Once
+    /// it has elapsed the lookup yields nothing more, whatever candidates are left, and a query
+    /// still in flight is given up. `None` (the default) puts no bound on it.
+    pub overall_deadline: Option<Duration>,
+    /// Reorders the targets returned by
+    /// [Lookup::resolve_all_sorted](crate::Lookup::resolve_all_sorted) out of what is known
+    /// about them beyond DNS, e.g. an [RttRanker](crate::RttRanker). Defaults to a
+    /// [NoopRanker].
+    pub target_ranker: Arc<dyn TargetRanker>,
+    /// Handed the NAPTR entries with the `P` flag, whose protocol specific rewrite is up to the
+    /// application (e.g. ENUM-like schemes). Either way, they are not resolved into targets.
+    pub on_p_flag: Option<PFlagHook>,
+    /// Return every IPv6 target of the lookup before any IPv4 one, like `getaddrinfo` does on
+    /// IPv6-first hosts, each family keeping the NAPTR/SRV/A order. This needs the whole lookup
+    /// to be resolved (every query sent) before the first target is returned. Off by default,
+    /// keeping the resolution order.
+    pub prefer_ipv6: bool,
+    /// The only NAPTR services followed, when set: the entries of any other service are dropped,
+    /// even one that maps to a transport. `None` (the default) follows every service with a
+    /// supported transport.
+    pub allowed_services: Option<Vec<NaptrServices>>,
+    /// The IPv6 scope (interface index) of an ip address host, given to its target (see
+    /// [Target::scope_id](crate::Target::scope_id)). Needed to reach a link-local address, it's
+    /// ignored for a domain host. [Context::initialize_from] takes it from the zone of the URI
+    /// host, like `sip:[fe80::1%25eth0]`.
+    pub scope_id: Option<u32>,
+    /// How many DNS queries the whole resolution may send, A/AAAA lookups counting as one, to
+    /// bound what a domain fanning out into huge NAPTR/SRV trees costs. Once it's spent, the
+    /// [Lookup](crate::Lookup) sends no more queries and yields nothing more, even targets it
+    /// could have resolved out of records it already has. `None` (the default) puts no bound
+    /// on it.
+    pub query_budget: Option<u32>,
+}
+
+impl<C: DnsClient + Default> Default for Context<C> {
+    fn default() -> Self {
+        Self {
+            secure: false,
+            host: Default::default(),
+            port: None,
+            transport: None,
+            dns_client: Default::default(),
+            supported_transports: Default::default(),
+            concurrent_srv_fallbacks: false,
+            skip_naptr: false,
+            fallback_policy: Default::default(),
+            max_naptr_depth: DEFAULT_MAX_NAPTR_DEPTH,
+            default_ports: HashMap::new(),
+            backup_domains: vec![],
+            ip_family: None,
+            synthesize_all_transports: false,
+            concurrent_srv_targets: None,
+            default_ttl: DEFAULT_TTL,
+            overall_deadline: None,
+            target_ranker: Arc::new(NoopRanker),
+            on_p_flag: None,
+            prefer_ipv6: false,
+            allowed_services: None,
+            scope_id: None,
+            query_budget: None,
+        }
+    }
 }
 
 impl<C: DnsClient> Context<C> {
@@ -23,12 +134,12 @@ impl<C: DnsClient> Context<C> {
         match self.secure {
             true => self
                 .supported_transports
-                .0
+                .transports
                 .clone()
                 .into_iter()
                 .filter(|transport| Transport::secure_transports().contains(transport))
                 .collect::<Vec<Transport>>(),
-            false => self.supported_transports.0.clone(),
+            false => self.supported_transports.transports.clone(),
         }
     }
 
@@ -36,14 +147,14 @@ impl<C: DnsClient> Context<C> {
         match self.secure {
             true => self
                 .supported_transports
-                .0
+                .transports
                 .clone()
                 .into_iter()
                 .filter(|transport| Transport::secure_protocols().contains(transport))
                 .collect::<Vec<Transport>>(),
             false => self
                 .supported_transports
-                .0
+                .transports
                 .clone()
                 .into_iter()
                 .filter(|transport| Transport::protocols().contains(transport))
@@ -53,6 +164,19 @@ impl<C: DnsClient> Context<C> {
 }
 
 impl<C: DnsClient> Context<C> {
+    /// Start building a context around the given dns client, see [ContextBuilder].
+    pub fn builder(dns_client: C) -> ContextBuilder<C> {
+        ContextBuilder::new(dns_client)
+    }
+
+    /// Builds a context out of the URI of the host, per RFC 3263 section 4: the scheme tells
+    /// whether it's secure, and the `transport` parameter and the port are kept as constraints.
+    ///
+    /// When the URI has a `maddr` parameter, the `maddr` is resolved instead of the URI host
+    /// (RFC 3261 section 19.1.1). An IPv6 host with a zone (RFC 6874), like `[fe80::1%25eth0]`,
+    /// is used as is within that zone, see [Context::scope_id]. A domain that can't be queried
+    /// (empty, or with characters a domain can't have) is rejected upfront, see
+    /// [ContextBuilder::build].
     pub fn initialize_from(
         uri: Uri,
         dns_client: C,
@@ -71,13 +195,37 @@ impl<C: DnsClient> Context<C> {
 
         //TODO: add the same for available transports
 
+        let host = maddr_host(&uri).unwrap_or(uri.host_with_port.host);
+        let (host, scope_id) = match zoned_ip_addr(&host)? {
+            Some((ip_addr, scope_id)) => (Host::IpAddr(ip_addr), Some(scope_id)),
+            None => (host, None),
+        };
+        check_host(&host)?;
+
         Ok(Self {
             transport,
             secure,
-            host: uri.host_with_port.host,
+            host,
             port: uri.host_with_port.port,
             dns_client,
             supported_transports,
+            concurrent_srv_fallbacks: false,
+            skip_naptr: false,
+            fallback_policy: Default::default(),
+            max_naptr_depth: DEFAULT_MAX_NAPTR_DEPTH,
+            default_ports: HashMap::new(),
+            backup_domains: vec![],
+            ip_family: None,
+            synthesize_all_transports: false,
+            concurrent_srv_targets: None,
+            default_ttl: DEFAULT_TTL,
+            overall_deadline: None,
+            target_ranker: Arc::new(NoopRanker),
+            on_p_flag: None,
+            prefer_ipv6: false,
+            allowed_services: None,
+            scope_id,
+            query_budget: None,
         })
     }
 
@@ -90,6 +238,291 @@ impl<C: DnsClient> Context<C> {
             },
         }
     }
+
+    pub(crate) fn default_port_for(&self, transport: Transport) -> Port {
+        default_port_from(&self.default_ports, self.secure, transport)
+    }
+
+    /// The same context around another dns client
+    pub(crate) fn with_dns_client<D: DnsClient>(self, dns_client: D) -> Context<D> {
+        Context {
+            secure: self.secure,
+            host: self.host,
+            port: self.port,
+            transport: self.transport,
+            dns_client,
+            supported_transports: self.supported_transports,
+            concurrent_srv_fallbacks: self.concurrent_srv_fallbacks,
+            skip_naptr: self.skip_naptr,
+            fallback_policy: self.fallback_policy,
+            max_naptr_depth: self.max_naptr_depth,
+            default_ports: self.default_ports,
+            backup_domains: self.backup_domains,
+            ip_family: self.ip_family,
+            synthesize_all_transports: self.synthesize_all_transports,
+            concurrent_srv_targets: self.concurrent_srv_targets,
+            default_ttl: self.default_ttl,
+            overall_deadline: self.overall_deadline,
+            target_ranker: self.target_ranker,
+            on_p_flag: self.on_p_flag,
+            prefer_ipv6: self.prefer_ipv6,
+            allowed_services: self.allowed_services,
+            scope_id: self.scope_id,
+            query_budget: self.query_budget,
+        }
+    }
+}
+
+/// Fluent builder for [Context].
+///
+/// Defaults to an insecure context without explicit transport or port that supports any
+/// transport ([SupportedTransports::any]). The host has no sensible default, so it needs to be
+/// set before calling [ContextBuilder::build].
+#[derive(Debug, Clone)]
+pub struct ContextBuilder<C: DnsClient> {
+    secure: bool,
+    host: Option<Host>,
+    port: Option<Port>,
+    transport: Option<Transport>,
+    dns_client: C,
+    supported_transports: SupportedTransports,
+    concurrent_srv_fallbacks: bool,
+    skip_naptr: bool,
+    fallback_policy: FallbackPolicy,
+    max_naptr_depth: usize,
+    default_ports: HashMap<Transport, Port>,
+    backup_domains: Vec<Domain>,
+    ip_family: Option<IpFamily>,
+    synthesize_all_transports: bool,
+    concurrent_srv_targets: Option<usize>,
+    default_ttl: u32,
+    overall_deadline: Option<Duration>,
+    target_ranker: Arc<dyn TargetRanker>,
+    on_p_flag: Option<PFlagHook>,
+    prefer_ipv6: bool,
+    allowed_services: Option<Vec<NaptrServices>>,
+    scope_id: Option<u32>,
+    query_budget: Option<u32>,
+}
+
+impl<C: DnsClient> ContextBuilder<C> {
+    pub fn new(dns_client: C) -> Self {
+        Self {
+            secure: false,
+            host: None,
+            port: None,
+            transport: None,
+            dns_client,
+            supported_transports: SupportedTransports::any(),
+            concurrent_srv_fallbacks: false,
+            skip_naptr: false,
+            fallback_policy: Default::default(),
+            max_naptr_depth: DEFAULT_MAX_NAPTR_DEPTH,
+            default_ports: HashMap::new(),
+            backup_domains: vec![],
+            ip_family: None,
+            synthesize_all_transports: false,
+            concurrent_srv_targets: None,
+            default_ttl: DEFAULT_TTL,
+            overall_deadline: None,
+            target_ranker: Arc::new(NoopRanker),
+            on_p_flag: None,
+            prefer_ipv6: false,
+            allowed_services: None,
+            scope_id: None,
+            query_budget: None,
+        }
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn host(mut self, host: impl Into<Host>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    pub fn port(mut self, port: impl Into<Port>) -> Self {
+        self.port = Some(port.into());
+        self
+    }
+
+    pub fn transport(mut self, transport: Transport) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    pub fn supported_transports(
+        mut self,
+        supported_transports: impl Into<SupportedTransports>,
+    ) -> Self {
+        self.supported_transports = supported_transports.into();
+        self
+    }
+
+    pub fn concurrent_srv_fallbacks(mut self, concurrent_srv_fallbacks: bool) -> Self {
+        self.concurrent_srv_fallbacks = concurrent_srv_fallbacks;
+        self
+    }
+
+    pub fn skip_naptr(mut self, skip_naptr: bool) -> Self {
+        self.skip_naptr = skip_naptr;
+        self
+    }
+
+    pub fn fallback_policy(mut self, fallback_policy: FallbackPolicy) -> Self {
+        self.fallback_policy = fallback_policy;
+        self
+    }
+
+    pub fn max_naptr_depth(mut self, max_naptr_depth: usize) -> Self {
+        self.max_naptr_depth = max_naptr_depth;
+        self
+    }
+
+    /// Overrides the default port of `transport`, see [Context::default_ports].
+    pub fn default_port(mut self, transport: Transport, port: impl Into<Port>) -> Self {
+        self.default_ports.insert(transport, port.into());
+        self
+    }
+
+    /// Adds a domain tried once the host and the previously added backup domains are exhausted,
+    /// see [Context::backup_domains].
+    pub fn backup_domain(mut self, domain: impl Into<Domain>) -> Self {
+        self.backup_domains.push(domain.into());
+        self
+    }
+
+    /// Restricts the resolved addresses to `ip_family`, see [Context::ip_family].
+    pub fn ip_family(mut self, ip_family: IpFamily) -> Self {
+        self.ip_family = Some(ip_family);
+        self
+    }
+
+    /// Falls back to every available transport, see [Context::synthesize_all_transports].
+    pub fn synthesize_all_transports(mut self, synthesize_all_transports: bool) -> Self {
+        self.synthesize_all_transports = synthesize_all_transports;
+        self
+    }
+
+    /// Resolves the targets of SRV records concurrently, see [Context::concurrent_srv_targets].
+    pub fn concurrent_srv_targets(mut self, limit: usize) -> Self {
+        self.concurrent_srv_targets = Some(limit);
+        self
+    }
+
+    /// Sets the TTL of the target of an ip address host, see [Context::default_ttl].
+    pub fn default_ttl(mut self, ttl: u32) -> Self {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Bounds the time the whole resolution may take, see [Context::overall_deadline].
+    pub fn overall_deadline(mut self, overall_deadline: Duration) -> Self {
+        self.overall_deadline = Some(overall_deadline);
+        self
+    }
+
+    /// Sets the ranker of the targets, see [Context::target_ranker].
+    pub fn target_ranker(mut self, target_ranker: impl TargetRanker + 'static) -> Self {
+        self.target_ranker = Arc::new(target_ranker);
+        self
+    }
+
+    /// Hands the NAPTR entries with the `P` flag to `hook`, see [Context::on_p_flag].
+    pub fn on_p_flag(mut self, hook: impl Fn(&NaptrEntry) + Send + Sync + 'static) -> Self {
+        self.on_p_flag = Some(PFlagHook::new(hook));
+        self
+    }
+
+    /// Returns the IPv6 targets first, see [Context::prefer_ipv6].
+    pub fn prefer_ipv6(mut self, prefer_ipv6: bool) -> Self {
+        self.prefer_ipv6 = prefer_ipv6;
+        self
+    }
+
+    /// Only follows the NAPTR entries of the given services, see [Context::allowed_services].
+    pub fn allowed_services(mut self, allowed_services: Vec<NaptrServices>) -> Self {
+        self.allowed_services = Some(allowed_services);
+        self
+    }
+
+    /// Sets the IPv6 scope of an ip address host, see [Context::scope_id].
+    pub fn scope_id(mut self, scope_id: u32) -> Self {
+        self.scope_id = Some(scope_id);
+        self
+    }
+
+    /// Bounds the DNS queries the whole resolution may send, see [Context::query_budget].
+    pub fn query_budget(mut self, query_budget: u32) -> Self {
+        self.query_budget = Some(query_budget);
+        self
+    }
+
+    /// Builds the [Context], failing if the host is missing or is a domain that can't be queried:
+    /// an empty one, one with empty or too long labels, or one with characters other than
+    /// letters, digits, `-`, `_` and `.` (non ASCII letters of internationalized domains are
+    /// let through).
+    pub fn build(self) -> Result<Context<C>, Error> {
+        let host = match self.host {
+            Some(host) => host,
+            None => return Err(Error::Unexpected("can't build context without a host".into())),
+        };
+        check_host(&host)?;
+
+        Ok(Context {
+            secure: self.secure,
+            host,
+            port: self.port,
+            transport: self.transport,
+            dns_client: self.dns_client,
+            supported_transports: self.supported_transports,
+            concurrent_srv_fallbacks: self.concurrent_srv_fallbacks,
+            skip_naptr: self.skip_naptr,
+            fallback_policy: self.fallback_policy,
+            max_naptr_depth: self.max_naptr_depth,
+            default_ports: self.default_ports,
+            backup_domains: self.backup_domains,
+            ip_family: self.ip_family,
+            synthesize_all_transports: self.synthesize_all_transports,
+            concurrent_srv_targets: self.concurrent_srv_targets,
+            default_ttl: self.default_ttl,
+            overall_deadline: self.overall_deadline,
+            target_ranker: self.target_ranker,
+            on_p_flag: self.on_p_flag,
+            prefer_ipv6: self.prefer_ipv6,
+            allowed_services: self.allowed_services,
+            scope_id: self.scope_id,
+            query_budget: self.query_budget,
+        })
+    }
+}
+
+/// The overridden default port of `transport`, or its standard one, see [default_port]
+pub(crate) fn default_port_from(
+    default_ports: &HashMap<Transport, Port>,
+    secure: bool,
+    transport: Transport,
+) -> Port {
+    default_ports.get(&transport).copied().unwrap_or_else(|| default_port(secure, transport))
+}
+
+/// The port of a target that neither the uri nor a SRV record gives one (RFC 3263 section 4.2):
+/// 5060 for SIP over UDP, TCP and SCTP, 5061 over TLS, 80 over WS and 443 over WSS.
+///
+/// A `secure` (sips) target uses the port of the secure counterpart of the transport, e.g. 5061
+/// for sips over TCP (which runs over TLS) and 443 over WS.
+pub fn default_port(secure: bool, transport: Transport) -> Port {
+    let port: u16 = match (secure, transport) {
+        (false, Transport::Ws) => 80,
+        (_, Transport::Ws | Transport::Wss) => 443,
+        (false, Transport::Udp | Transport::Tcp | Transport::Sctp) => 5060,
+        _ => 5061,
+    };
+
+    port.into()
 }
 
 fn secure_from_scheme(scheme: Scheme) -> Result<bool, rsip::Error> {
@@ -100,34 +533,155 @@ fn secure_from_scheme(scheme: Scheme) -> Result<bool, rsip::Error> {
     }
 }
 
+/// The host of the `maddr` parameter of the URI, if it has one
+fn maddr_host(uri: &Uri) -> Option<Host> {
+    uri.params.iter().find_map(|param| match param {
+        Param::Maddr(maddr) => {
+            let maddr = maddr.to_string();
+            Some(match maddr.parse::<IpAddr>() {
+                Ok(ip_addr) => Host::IpAddr(ip_addr),
+                Err(_) => Host::Domain(maddr.into()),
+            })
+        }
+        _ => None,
+    })
+}
+
+/// The IPv6 address and scope of a zone-scoped IPv6 literal host (RFC 6874), like
+/// `[fe80::1%25eth0]`, which doesn't parse as a plain ip address and is left as a domain. The zone
+/// is an interface index or, on unix, an interface name.
+fn zoned_ip_addr(host: &Host) -> Result<Option<(IpAddr, u32)>, Error> {
+    let Host::Domain(domain) = host else {
+        return Ok(None);
+    };
+    let literal = domain.to_string();
+    let literal = literal.strip_prefix('[').and_then(|l| l.strip_suffix(']')).unwrap_or(&literal);
+    let Some((ip_addr, zone)) = literal.split_once("%25").or_else(|| literal.split_once('%'))
+    else {
+        return Ok(None);
+    };
+    let Ok(ip_addr) = ip_addr.parse::<Ipv6Addr>() else {
+        return Ok(None);
+    };
+
+    match zone.parse::<u32>().ok().or_else(|| interface_index(zone)) {
+        Some(scope_id) => Ok(Some((ip_addr.into(), scope_id))),
+        None => Err(Error::Unexpected(format!(
+            "can't build context with IPv6 zone {:?}: no such interface",
+            zone
+        ))),
+    }
+}
+
+#[cfg(unix)]
+fn interface_index(name: &str) -> Option<u32> {
+    let name = std::ffi::CString::new(name).ok()?;
+    // SAFETY: `name` is a valid NUL terminated string that outlives the call
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => None,
+        index => Some(index),
+    }
+}
+
+#[cfg(not(unix))]
+fn interface_index(_name: &str) -> Option<u32> {
+    None
+}
+
+/// Rejects a domain host that would only fail later on, deep in the DNS queries
+fn check_host(host: &Host) -> Result<(), Error> {
+    let Host::Domain(domain) = host else {
+        return Ok(());
+    };
+    let domain = domain.to_string();
+    let name = domain.strip_suffix('.').unwrap_or(&domain);
+    let invalid = |reason: String| {
+        Error::Unexpected(format!("can't build context with domain {:?}: {}", domain, reason))
+    };
+
+    if name.is_empty() {
+        return Err(Error::Unexpected("can't build context with an empty domain".into()));
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(invalid(format!("longer than {} characters", MAX_NAME_LENGTH)));
+    }
+    if let Some(c) = name.chars().find(|c| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))) {
+        return Err(invalid(format!("illegal character {:?}", c)));
+    }
+    for label in name.split('.') {
+        match label {
+            "" => return Err(invalid("empty label".into())),
+            label if label.len() > MAX_LABEL_LENGTH => {
+                return Err(invalid(format!("label longer than {} characters", MAX_LABEL_LENGTH)));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Restricts the mechanisms a domain without port or transport is resolved through
+/// (RFC 3263 section 4.1), so that a failing mechanism returns nothing instead of silently
+/// falling through to the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FallbackPolicy {
+    /// NAPTR, then SRV for each available transport, then A/AAAA on the domain itself
+    #[default]
+    Full,
+    /// NAPTR only
+    NaptrOnly,
+    /// SRV for each available transport only, NAPTR is not queried
+    SrvOnly,
+    /// NAPTR, then SRV for each available transport, but never A/AAAA on the domain itself
+    NoAddrFallback,
+}
+
 /// Simple struct that allows you to specify whether all `rsip` transports are available or only
 /// specific ones. Used here as a type safety to order to avoid edge cases of `Option<Vec<T>>`..
+///
+/// SRV fallbacks are always attempted in the order of the transports given here. A list built
+/// with [SupportedTransports::ordered] is also an explicit preference: NAPTR entries with the
+/// same order and preference are then tried following it, instead of the order of the record.
 #[derive(Debug, Clone)]
-pub struct SupportedTransports(Vec<Transport>);
+pub struct SupportedTransports {
+    transports: Vec<Transport>,
+    ordered: bool,
+}
 
 impl SupportedTransports {
     pub fn any() -> Self {
-        Self(Transport::all().into())
+        Self::only(Transport::all().into())
     }
 
     pub fn only(transports: Vec<Transport>) -> Self {
-        Self(transports)
+        Self { transports, ordered: false }
+    }
+
+    /// The given transports, most preferred first (e.g. TLS, then TCP, then UDP)
+    pub fn ordered(transports: Vec<Transport>) -> Self {
+        Self { transports, ordered: true }
     }
 
     pub fn all(&self) -> &Vec<Transport> {
-        &self.0
+        &self.transports
+    }
+
+    /// Whether the order of the transports is an explicit preference
+    pub fn is_ordered(&self) -> bool {
+        self.ordered
     }
 }
 
 impl From<SupportedTransports> for Vec<Transport> {
     fn from(from: SupportedTransports) -> Self {
-        from.0
+        from.transports
     }
 }
 
 impl From<Vec<Transport>> for SupportedTransports {
     fn from(from: Vec<Transport>) -> Self {
-        Self(from)
+        Self::only(from)
     }
 }
 
@@ -1,5 +1,7 @@
+use crate::DnsError;
 use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord};
-use async_trait::async_trait;
+#[cfg(feature = "hickory-dns")]
+use hickory_proto::rr::{RData, RecordType};
 use rsip::{Domain, Error};
 
 /// This trait needs to be implemented by any dns client used inside the [Context](super::Context).
@@ -9,11 +11,61 @@ use rsip::{Domain, Error};
 ///
 /// Note that whether [DnsClient::ip_lookup] queries for an A or an AAAA or both records is up
 /// to the DNS client used.
-#[async_trait]
-pub trait DnsClient: Clone + Sync + Send {
+///
+/// [DnsClient::try_naptr_lookup] and [DnsClient::try_srv_lookup] tell a missing record apart
+/// from a failed query, which lets the lookup retry a NAPTR query that hit a server failure
+/// instead of going straight to the fallbacks. They default to the `Option` returning methods,
+/// reporting every `None` as a missing record, so clients that can make the distinction should
+/// override them.
+///
+/// Behind the `hickory-dns` feature flag, [DnsClient::raw_lookup] queries any other record type
+/// (TXT, CAA, ...) through the same client. The hickory clients implement it natively; by
+/// default it fails, so other clients only need to implement it if they can answer such queries.
+///
+/// The methods are plain `async fn`s (native async functions in traits) rather than going
+/// through `async_trait`, so no future gets boxed on a lookup: implementors simply write
+/// `async fn` in their impl, without any attribute. The returned futures are required to be
+/// [Send] so that lookups can run on a multi-threaded runtime. The trait was never object safe
+/// (it requires [Clone]), hence this costs no `dyn DnsClient` support.
+///
+/// Clients are also required to be `'static`, so that the SRV fallbacks fired all at once (see
+/// [Context::concurrent_srv_fallbacks](crate::Context::concurrent_srv_fallbacks)) can stay in
+/// flight while the targets of the first ones are already being returned.
+pub trait DnsClient: Clone + Sync + Send + 'static {
     // returns an Option since RFC 3263 alg can continue even without this
-    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord>;
+    fn naptr_lookup(&self, domain: Domain) -> impl Future<Output = Option<NaptrRecord>> + Send;
     // returns an Option since RFC 3263 alg can continue even without this
-    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord>;
-    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error>;
+    fn srv_lookup(&self, domain: SrvDomain) -> impl Future<Output = Option<SrvRecord>> + Send;
+    fn ip_lookup(&self, domain: Domain) -> impl Future<Output = Result<AddrRecord, Error>> + Send;
+
+    /// `Ok(None)` when the domain has no NAPTR record (NXDOMAIN/NODATA), `Err` when the query
+    /// itself failed
+    fn try_naptr_lookup(
+        &self,
+        domain: Domain,
+    ) -> impl Future<Output = Result<Option<NaptrRecord>, DnsError>> + Send {
+        async move { Ok(self.naptr_lookup(domain).await) }
+    }
+
+    /// `Ok(None)` when the domain has no SRV record (NXDOMAIN/NODATA), `Err` when the query
+    /// itself failed
+    fn try_srv_lookup(
+        &self,
+        domain: SrvDomain,
+    ) -> impl Future<Output = Result<Option<SrvRecord>, DnsError>> + Send {
+        async move { Ok(self.srv_lookup(domain).await) }
+    }
+
+    /// The data of every `rtype` record found for `name`, or an empty list when there is none
+    /// (NXDOMAIN/NODATA). `Err` when the query itself failed, or when the client does not
+    /// support raw lookups, which is the default.
+    #[cfg(feature = "hickory-dns")]
+    fn raw_lookup(
+        &self,
+        name: Domain,
+        rtype: RecordType,
+    ) -> impl Future<Output = Result<Vec<RData>, DnsError>> + Send {
+        let _ = (name, rtype);
+        async { Err(DnsError::Other("raw lookups are not supported by this dns client".into())) }
+    }
 }
@@ -0,0 +1,116 @@
+use std::{fmt, io, sync::Arc};
+
+/// Why a DNS query failed to produce an answer, as returned by the `try_*` methods of
+/// [DnsClient](super::DnsClient).
+///
+/// Note that a domain that does not exist (NXDOMAIN) or that has no records of the requested
+/// type (NODATA) is not an error: it is an authoritative answer, reported as `Ok(None)`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DnsError {
+    /// The server failed to process the query (SERVFAIL)
+    ServerFailure,
+    /// The server refused to answer the query (REFUSED)
+    Refused,
+    /// No response arrived in time
+    Timeout,
+    /// The server couldn't be reached, like a socket that can't be bound or a connection that
+    /// was reset. The underlying [io::Error] is its [source](std::error::Error::source).
+    Io {
+        /// What was being done when the error happened
+        context: String,
+        #[cfg_attr(feature = "serde", serde(with = "io_error"))]
+        source: Arc<io::Error>,
+    },
+    /// Any other failure, like a malformed response or another response code
+    Other(String),
+}
+
+impl DnsError {
+    /// Whether asking again (possibly another server) could give a different outcome
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::ServerFailure | Self::Timeout)
+    }
+
+    /// An [DnsError::Io] that happened while doing `context`
+    pub fn io(context: impl Into<String>, error: io::Error) -> Self {
+        Self::Io { context: context.into(), source: Arc::new(error) }
+    }
+}
+
+impl PartialEq for DnsError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::ServerFailure, Self::ServerFailure)
+            | (Self::Refused, Self::Refused)
+            | (Self::Timeout, Self::Timeout) => true,
+            (Self::Other(error), Self::Other(other_error)) => error == other_error,
+            // io errors can't be compared, their kinds and messages stand in for them
+            (
+                Self::Io { context, source },
+                Self::Io { context: other_context, source: other_source },
+            ) => {
+                context == other_context
+                    && source.kind() == other_source.kind()
+                    && source.to_string() == other_source.to_string()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for DnsError {}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ServerFailure => write!(f, "DNS server failure"),
+            Self::Refused => write!(f, "DNS query refused"),
+            Self::Timeout => write!(f, "DNS query timeout"),
+            Self::Other(error) => write!(f, "{}", error),
+            Self::Io { context, source } => write!(f, "{}: {}", context, source),
+        }
+    }
+}
+
+impl std::error::Error for DnsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DnsError {
+    fn from(error: io::Error) -> Self {
+        Self::io("DNS I/O error", error)
+    }
+}
+
+impl From<DnsError> for rsip::Error {
+    fn from(error: DnsError) -> Self {
+        rsip::Error::Unexpected(error.to_string())
+    }
+}
+
+/// Only the message of an io error survives a round trip, it comes back as an
+/// [io::ErrorKind::Other] error
+#[cfg(feature = "serde")]
+mod io_error {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::{io, sync::Arc};
+
+    pub fn serialize<S: Serializer>(
+        error: &Arc<io::Error>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(error)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<io::Error>, D::Error> {
+        String::deserialize(deserializer).map(|message| Arc::new(io::Error::other(message)))
+    }
+}
@@ -1,9 +1,15 @@
-use async_trait::async_trait;
-use std::{convert::TryInto, net::IpAddr};
-
-use crate::{DnsClient, SrvDomain, records::*};
-use hickory_proto::rr::record_type::RecordType;
-use hickory_resolver::TokioResolver;
+use std::time::Duration;
+
+use super::{
+    addr_record_from_lookup, missing_or_failed, no_addresses, parse_naptr, query_metrics,
+    rdata_from_lookup,
+};
+use crate::{DEFAULT_TTL, DnsClient, DnsError, SrvDomain, records::*};
+use hickory_proto::{
+    ProtoError, ProtoErrorKind,
+    rr::{RData, record_type::RecordType},
+};
+use hickory_resolver::{ResolveError, TokioResolver, lookup::Lookup};
 
 use rsip::{Domain, Error};
 
@@ -13,82 +19,126 @@ use rsip::{Domain, Error};
 #[derive(Debug, Clone)]
 pub struct AsyncHickoryClient {
     resolver: TokioResolver,
+    timeout: Option<Duration>,
+    ttl_bounds: TtlBounds,
 }
 
 impl AsyncHickoryClient {
     pub fn new(resolver: TokioResolver) -> Self {
-        Self { resolver }
+        Self { resolver, timeout: None, ttl_bounds: Default::default() }
     }
-}
 
-#[async_trait]
-impl DnsClient for AsyncHickoryClient {
-    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
-        self.resolver.lookup(domain.to_string(), RecordType::NAPTR).await.ok().map(|lookup| {
-            // Extract minimum TTL from all records (standard practice for RRsets)
-            let ttl = lookup.record_iter().map(|record| record.ttl()).min().unwrap_or(300);
+    /// Bounds every individual lookup to the given duration, on top of whatever timeout the
+    /// resolver itself is configured with. A lookup that elapses is treated as a failed one.
+    pub fn with_timeout(resolver: TokioResolver, timeout: Duration) -> Self {
+        Self { timeout: Some(timeout), ..Self::new(resolver) }
+    }
+
+    /// Clamp the TTL of every returned record into the given bounds
+    pub fn with_ttl_bounds(mut self, ttl_bounds: TtlBounds) -> Self {
+        self.ttl_bounds = ttl_bounds;
+        self
+    }
 
-            let entries = lookup
-                .into_iter()
-                .filter_map(|rdata| rdata.try_into().ok())
-                .collect::<Vec<NaptrEntry>>();
+    /// Looks `name` up through the resolver, see [query_metrics]
+    async fn lookup(&self, name: String, record_type: RecordType) -> Result<Lookup, ResolveError> {
+        let lookup = self.resolver.lookup(name, record_type);
 
-            NaptrRecord::new(entries, domain, ttl)
+        query_metrics::measured(record_type, async {
+            match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, lookup)
+                    .await
+                    .unwrap_or_else(|_| Err(ProtoError::from(ProtoErrorKind::Timeout).into())),
+                None => lookup.await,
+            }
         })
+        .await
+    }
+}
+
+impl DnsClient for AsyncHickoryClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.try_naptr_lookup(domain).await.ok().flatten()
     }
 
     async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
-        self.resolver.lookup(domain.to_string(), RecordType::SRV).await.ok().map(|lookup| {
-            // Extract minimum TTL from all SRV records (standard practice for RRsets)
-            let ttl = lookup.record_iter().map(|record| record.ttl()).min().unwrap_or(300);
-
-            let entries = lookup
-                .record_iter()
-                .filter_map(|record| match record.data() {
-                    hickory_proto::rr::record_data::RData::SRV(srv) => Some(SrvEntry {
-                        priority: srv.priority(),
-                        weight: srv.weight(),
-                        port: srv.port().into(),
-                        target: srv.target().to_string().into(),
-                    }),
-                    _ => None,
-                })
-                .collect::<Vec<SrvEntry>>();
-
-            SrvRecord::new(entries, domain, ttl)
-        })
+        self.try_srv_lookup(domain).await.ok().flatten()
     }
 
     async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
-        // Try A records first
-        let mut ip_addrs = Vec::new();
-        let mut min_ttl = u32::MAX;
-
-        if let Ok(lookup) = self.resolver.lookup(domain.to_string(), RecordType::A).await {
-            for record in lookup.record_iter() {
-                min_ttl = min_ttl.min(record.ttl());
-                if let hickory_proto::rr::record_data::RData::A(a) = record.data() {
-                    ip_addrs.push(IpAddr::V4(a.0));
+        let mut addr_record: Option<AddrRecord> = None;
+        // the error of the last lookup that failed, as opposed to finding no records
+        let mut failure = None;
+
+        // A records first, then AAAA
+        for record_type in [RecordType::A, RecordType::AAAA] {
+            match self.lookup(domain.to_string(), record_type).await {
+                Ok(lookup) => {
+                    if let Some(found) = addr_record_from_lookup(domain.clone(), &lookup) {
+                        addr_record = Some(match addr_record {
+                            Some(addr_record) => addr_record.merge(found)?,
+                            None => found,
+                        });
+                    }
                 }
-            }
-        }
-
-        // Try AAAA records
-        if let Ok(lookup) = self.resolver.lookup(domain.to_string(), RecordType::AAAA).await {
-            for record in lookup.record_iter() {
-                min_ttl = min_ttl.min(record.ttl());
-                if let hickory_proto::rr::record_data::RData::AAAA(aaaa) = record.data() {
-                    ip_addrs.push(IpAddr::V6(aaaa.0));
+                Err(error) => {
+                    if let Err(error) = missing_or_failed::<()>(error) {
+                        failure = Some(error);
+                    }
                 }
             }
         }
 
-        if ip_addrs.is_empty() {
-            return Err(Error::Unexpected(format!("No A or AAAA records found for {}", domain)));
+        match addr_record {
+            Some(addr_record) => Ok(self.ttl_bounds.apply_to_addr(addr_record)),
+            None => Err(no_addresses(&domain, failure)),
         }
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let lookup = match self.lookup(domain.to_string(), RecordType::NAPTR).await {
+            Ok(lookup) => lookup,
+            Err(error) => return missing_or_failed(error),
+        };
 
-        let ttl = if min_ttl == u32::MAX { 300 } else { min_ttl };
+        // Extract minimum TTL from all records (standard practice for RRsets)
+        let ttl = lookup.record_iter().map(|record| record.ttl()).min().unwrap_or(DEFAULT_TTL);
 
-        Ok(AddrRecord { domain, ip_addrs, ttl })
+        let entries =
+            lookup.iter().filter_map(|rdata| parse_naptr(rdata).ok()).collect::<Vec<NaptrEntry>>();
+
+        Ok(Some(self.ttl_bounds.apply_to_naptr(NaptrRecord::new(entries, domain, ttl))))
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        let lookup = match self.lookup(domain.to_string(), RecordType::SRV).await {
+            Ok(lookup) => lookup,
+            Err(error) => return missing_or_failed(error),
+        };
+
+        // Extract minimum TTL from all SRV records (standard practice for RRsets)
+        let ttl = lookup.record_iter().map(|record| record.ttl()).min().unwrap_or(DEFAULT_TTL);
+
+        let entries = lookup
+            .record_iter()
+            .filter_map(|record| match record.data() {
+                hickory_proto::rr::record_data::RData::SRV(srv) => Some(SrvEntry {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port().into(),
+                    target: srv.target().to_string().into(),
+                }),
+                _ => None,
+            })
+            .collect::<Vec<SrvEntry>>();
+
+        Ok(Some(self.ttl_bounds.apply_to_srv(SrvRecord::new(entries, domain, ttl))))
+    }
+
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        match self.lookup(name.to_string(), rtype).await {
+            Ok(lookup) => Ok(rdata_from_lookup(&lookup, rtype)),
+            Err(error) => missing_or_failed(error).map(|_: Option<()>| Vec::new()),
+        }
     }
 }
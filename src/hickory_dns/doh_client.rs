@@ -0,0 +1,191 @@
+//! DNS-over-HTTPS client (RFC 8484).
+//!
+//! Queries are POSTed as `application/dns-message` bodies and the responses go through the same
+//! message handling as [RecursiveHickoryClient](super::RecursiveHickoryClient), so records in the
+//! ADDITIONAL section are kept as well.
+
+use hickory_proto::op::Message;
+use hickory_proto::rr::{Name, RData, RecordType};
+use ipnet::IpNet;
+use rsip::{Domain, Error};
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::message::{self, QueryOptions};
+use super::query_metrics;
+use crate::{DnsClient, DnsError, SrvDomain, records::*};
+
+const DNS_MESSAGE: &str = "application/dns-message";
+
+/// DNS client that sends its queries to a DoH endpoint, e.g. `https://dns.example.com/dns-query`.
+///
+/// The underlying [reqwest::Client] is reference counted, so cloning a `DohClient` (or passing
+/// an existing client via [DohClient::with_http_client]) shares its connection pool.
+#[derive(Debug, Clone)]
+pub struct DohClient {
+    endpoint: reqwest::Url,
+    http_client: reqwest::Client,
+    timeout: Duration,
+    ttl_bounds: TtlBounds,
+    options: QueryOptions,
+}
+
+impl DohClient {
+    /// Create a new DohClient with default timeout (5 seconds)
+    pub fn new(endpoint: &str) -> Result<Self, Error> {
+        Self::with_timeout(endpoint, Duration::from_secs(5))
+    }
+
+    /// Create a new DohClient with custom timeout
+    pub fn with_timeout(endpoint: &str, timeout: Duration) -> Result<Self, Error> {
+        Self::with_http_client(endpoint, reqwest::Client::new(), timeout)
+    }
+
+    /// Create a new DohClient on top of an existing [reqwest::Client]
+    pub fn with_http_client(
+        endpoint: &str,
+        http_client: reqwest::Client,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let endpoint = reqwest::Url::parse(endpoint)
+            .map_err(|e| Error::Unexpected(format!("Invalid DoH endpoint: {}", e)))?;
+
+        Ok(Self {
+            endpoint,
+            http_client,
+            timeout,
+            ttl_bounds: Default::default(),
+            options: Default::default(),
+        })
+    }
+
+    /// Clamp the TTL of every returned record into the given bounds
+    pub fn with_ttl_bounds(mut self, ttl_bounds: TtlBounds) -> Self {
+        self.ttl_bounds = ttl_bounds;
+        self
+    }
+
+    /// Whether queries set the RD (recursion desired) bit, which they do by default, see
+    /// [with_recursion_desired](super::RecursiveHickoryClient::with_recursion_desired) on the
+    /// recursive client.
+    pub fn with_recursion_desired(mut self, recursion_desired: bool) -> Self {
+        self.options.recursion_desired = recursion_desired;
+        self
+    }
+
+    /// Attach an EDNS client subnet option (RFC 7871) with `prefix` to every query, see
+    /// [with_client_subnet](super::RecursiveHickoryClient::with_client_subnet) on the recursive
+    /// client.
+    pub fn with_client_subnet(mut self, prefix: IpNet) -> Self {
+        self.options.client_subnet = Some(prefix.trunc().into());
+        self
+    }
+
+    /// Only accept responses with the AD (authentic data) bit set, see
+    /// [with_require_authentic_data](super::RecursiveHickoryClient::with_require_authentic_data)
+    /// on the recursive client.
+    pub fn with_require_authentic_data(mut self, require_authentic_data: bool) -> Self {
+        self.options.require_authentic_data = require_authentic_data;
+        self
+    }
+
+    /// Send a DNS query and return the full response message along with the address of the
+    /// server that answered, when known, see [query_metrics]
+    async fn query(
+        &self,
+        name: Name,
+        record_type: RecordType,
+    ) -> Result<(Message, Option<SocketAddr>), DnsError> {
+        query_metrics::measured(record_type, self.send_query(name, record_type)).await
+    }
+
+    async fn send_query(
+        &self,
+        name: Name,
+        record_type: RecordType,
+    ) -> Result<(Message, Option<SocketAddr>), DnsError> {
+        // RFC 8484 section 4.1: use a zero id so that responses are cache friendly
+        let query_bytes = self.options.build_query(0, name, record_type)?;
+
+        let response = self
+            .http_client
+            .post(self.endpoint.clone())
+            .header(reqwest::header::CONTENT_TYPE, DNS_MESSAGE)
+            .header(reqwest::header::ACCEPT, DNS_MESSAGE)
+            .timeout(self.timeout)
+            .body(query_bytes)
+            .send()
+            .await
+            .map_err(|e| match e.is_timeout() {
+                true => DnsError::Timeout,
+                false => DnsError::Other(format!("Failed to send DoH query: {}", e)),
+            })?;
+
+        if !response.status().is_success() {
+            return Err(DnsError::Other(format!(
+                "DoH query failed with HTTP status: {}",
+                response.status()
+            )));
+        }
+        let source = response.remote_addr();
+
+        let response_bytes = response.bytes().await.map_err(|e| match e.is_timeout() {
+            true => DnsError::Timeout,
+            false => DnsError::Other(format!("Failed to receive DoH response: {}", e)),
+        })?;
+
+        Ok((self.options.checked_response(0, &response_bytes)?, source))
+    }
+}
+
+impl DnsClient for DohClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.try_naptr_lookup(domain).await.ok().flatten()
+    }
+
+    async fn srv_lookup(&self, srv_domain: SrvDomain) -> Option<SrvRecord> {
+        self.try_srv_lookup(srv_domain).await.ok().flatten()
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        let name = Name::from_str(&domain.to_string())
+            .map_err(|e| Error::Unexpected(format!("Invalid domain name: {}", e)))?;
+
+        let a_response = self.query(name.clone(), RecordType::A).await.ok();
+        let aaaa_response = self.query(name, RecordType::AAAA).await.ok();
+        let source = a_response.iter().chain(&aaaa_response).find_map(|(_, source)| *source);
+
+        message::resolved_addr_record(
+            domain,
+            a_response.as_ref().map(|(response, _)| response),
+            aaaa_response.as_ref().map(|(response, _)| response),
+            &self.ttl_bounds,
+            source,
+        )
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let name = Name::from_str(&domain.to_string())
+            .map_err(|e| DnsError::Other(format!("Invalid domain name: {}", e)))?;
+        let (response, source) = self.query(name, RecordType::NAPTR).await?;
+
+        Ok(message::resolved_naptr_record(domain, &response, &self.ttl_bounds, source))
+    }
+
+    async fn try_srv_lookup(&self, srv_domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        let name = Name::from_str(&srv_domain.to_string())
+            .map_err(|e| DnsError::Other(format!("Invalid domain name: {}", e)))?;
+        let (response, source) = self.query(name, RecordType::SRV).await?;
+
+        Ok(message::resolved_srv_record(srv_domain, &response, &self.ttl_bounds, source))
+    }
+
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        let name = Name::from_str(&name.to_string())
+            .map_err(|e| DnsError::Other(format!("Invalid domain name: {}", e)))?;
+        let (response, _) = self.query(name, rtype).await?;
+
+        Ok(message::rdata_from(&response, rtype))
+    }
+}
@@ -0,0 +1,451 @@
+//! Wire level helpers shared by the clients that talk DNS messages directly
+//! ([RecursiveHickoryClient](super::RecursiveHickoryClient), its blocking counterpart
+//! [SyncRecursiveClient](super::SyncRecursiveClient) and, over HTTPS, `DohClient`).
+//!
+//! Building the query, checking the response, framing it over a stream and turning a response
+//! [Message] into records (including the ADDITIONAL section) does not depend on how the message
+//! travels, nor on whether the client blocks, so it lives here.
+
+use hickory_proto::op::{Edns, Message, OpCode, Query, ResponseCode};
+use hickory_proto::rr::rdata::opt::{ClientSubnet, EdnsOption};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use rsip::{Domain, Error};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::{IpAddr, SocketAddr};
+
+use super::parse_naptr;
+use crate::{DEFAULT_TTL, DnsError, SrvDomain, records::*};
+
+/// How many CNAME records are followed in the ADDITIONAL section before giving up
+const MAX_CNAME_CHAIN: usize = 8;
+
+/// Size of the buffer UDP responses are read into. A datagram that fills it entirely may have
+/// been cut short by the socket, so it is treated as truncated.
+pub(crate) const UDP_BUFFER_SIZE: usize = 4096;
+
+/// Build and serialize a query for `name`, recursive unless `recursion_desired` is false, asking
+/// for the AD bit (RFC 6840) when `authentic_data` is set, and carrying an EDNS client subnet
+/// option (RFC 7871) when one is given
+pub(crate) fn build_query(
+    id: u16,
+    name: Name,
+    record_type: RecordType,
+    recursion_desired: bool,
+    authentic_data: bool,
+    client_subnet: Option<ClientSubnet>,
+) -> Result<Vec<u8>, DnsError> {
+    let mut message = Message::new();
+    message.set_id(id);
+    message.set_recursion_desired(recursion_desired);
+    message.set_authentic_data(authentic_data);
+    message.set_op_code(OpCode::Query);
+    message.add_query(Query::query(name, record_type));
+    if let Some(client_subnet) = client_subnet {
+        // the OPT record also advertises the payload size, keep it in line with the receive buffer
+        let mut edns = Edns::new();
+        edns.set_max_payload(UDP_BUFFER_SIZE as u16);
+        edns.options_mut().insert(EdnsOption::Subnet(client_subnet));
+        message.set_edns(edns);
+    }
+
+    message.to_vec().map_err(|e| DnsError::Other(format!("Failed to serialize DNS query: {}", e)))
+}
+
+/// What the recursive clients set on their queries and require from the responses
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct QueryOptions {
+    /// Set the RD (recursion desired) bit
+    pub(crate) recursion_desired: bool,
+    /// Ask for the AD (authentic data) bit and reject the responses without it
+    pub(crate) require_authentic_data: bool,
+    /// The EDNS client subnet option (RFC 7871) every query carries, when set
+    pub(crate) client_subnet: Option<ClientSubnet>,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        Self { recursion_desired: true, require_authentic_data: false, client_subnet: None }
+    }
+}
+
+impl QueryOptions {
+    /// Build and serialize a query for `name` with these options, see [build_query]
+    pub(crate) fn build_query(
+        &self,
+        id: u16,
+        name: Name,
+        record_type: RecordType,
+    ) -> Result<Vec<u8>, DnsError> {
+        build_query(
+            id,
+            name,
+            record_type,
+            self.recursion_desired,
+            self.require_authentic_data,
+            self.client_subnet,
+        )
+    }
+
+    /// The query to send again over TCP, along with its new id, when the response received over
+    /// UDP is truncated (see [is_truncated]): whatever didn't fit in the datagram is lost
+    pub(crate) fn tcp_retry(
+        &self,
+        response_bytes: &[u8],
+        name: Name,
+        record_type: RecordType,
+    ) -> Result<Option<(u16, Vec<u8>)>, DnsError> {
+        if !is_truncated(response_bytes) {
+            return Ok(None);
+        }
+
+        debug_event!(name = %name, "truncated UDP response, retrying over TCP");
+        let id = rand::random();
+        Ok(Some((id, self.build_query(id, name, record_type)?)))
+    }
+
+    /// Parse the response to the query `id` (see [parse_response]), rejecting one that answers
+    /// another query, or that is not authenticated when [require_authentic_data] is set
+    ///
+    /// [require_authentic_data]: Self::require_authentic_data
+    pub(crate) fn checked_response(&self, id: u16, bytes: &[u8]) -> Result<Message, DnsError> {
+        let response = parse_response(bytes)?;
+        if response.id() != id {
+            return Err(DnsError::Other(format!(
+                "DNS response id {} does not match query id {}",
+                response.id(),
+                id
+            )));
+        }
+        if self.require_authentic_data && !response.authentic_data() {
+            return Err(DnsError::Other(
+                "DNS response is not authenticated (AD bit not set)".to_string(),
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+/// Prefix the query with its length, the two byte framing of RFC 1035 section 4.2.2 used over
+/// TCP and TLS streams
+pub(crate) fn framed(query_bytes: &[u8]) -> Result<Vec<u8>, DnsError> {
+    let len = u16::try_from(query_bytes.len())
+        .map_err(|_| DnsError::Other("DNS query too large for stream framing".to_string()))?;
+
+    let mut framed = Vec::with_capacity(query_bytes.len() + 2);
+    framed.extend_from_slice(&len.to_be_bytes());
+    framed.extend_from_slice(query_bytes);
+    Ok(framed)
+}
+
+/// The buffer the response is read into, sized by the length prefix read off the stream first
+pub(crate) fn unframed_buffer(len_prefix: [u8; 2]) -> Vec<u8> {
+    vec![0u8; u16::from_be_bytes(len_prefix) as usize]
+}
+
+/// The NAPTR record of the response as the clients return it: its TTLs clamped into
+/// `ttl_bounds` and its additional records stamped with the name server that answered, when
+/// known
+pub(crate) fn resolved_naptr_record(
+    domain: Domain,
+    response: &Message,
+    ttl_bounds: &TtlBounds,
+    source: Option<SocketAddr>,
+) -> Option<NaptrRecord> {
+    naptr_record_from(domain, response).map(|naptr_record| {
+        let mut naptr_record = ttl_bounds.apply_to_naptr(naptr_record);
+        for srv_record in naptr_record.additional_srvs.values_mut() {
+            srv_record.source = source;
+        }
+        for addr_record in naptr_record.additional_hosts.values_mut() {
+            addr_record.source = source;
+        }
+        naptr_record
+    })
+}
+
+/// The SRV record of the response as the clients return it, see [resolved_naptr_record]
+pub(crate) fn resolved_srv_record(
+    srv_domain: SrvDomain,
+    response: &Message,
+    ttl_bounds: &TtlBounds,
+    source: Option<SocketAddr>,
+) -> Option<SrvRecord> {
+    srv_record_from(srv_domain, response).map(|srv_record| {
+        let srv_record = ttl_bounds.apply_to_srv(srv_record);
+        match source {
+            Some(source) => srv_record.with_source(source),
+            None => srv_record,
+        }
+    })
+}
+
+/// The addresses of the A and AAAA responses as the clients return them, see
+/// [resolved_naptr_record]
+pub(crate) fn resolved_addr_record(
+    domain: Domain,
+    a_response: Option<&Message>,
+    aaaa_response: Option<&Message>,
+    ttl_bounds: &TtlBounds,
+    source: Option<SocketAddr>,
+) -> Result<AddrRecord, Error> {
+    addr_record_from(domain, a_response, aaaa_response).map(|addr_record| {
+        let addr_record = ttl_bounds.apply_to_addr(addr_record);
+        match source {
+            Some(source) => addr_record.with_source(source),
+            None => addr_record,
+        }
+    })
+}
+
+/// Whether a response received over UDP is incomplete and must be asked again over TCP: either
+/// the server set the TC bit, or the datagram filled the whole receive buffer
+pub(crate) fn is_truncated(bytes: &[u8]) -> bool {
+    // the TC bit is the second lowest bit of the third header byte
+    bytes.len() >= UDP_BUFFER_SIZE || bytes.get(2).is_some_and(|flags| flags & 0x02 != 0)
+}
+
+/// Parse a response, rejecting the response codes that mean the query failed.
+///
+/// `NXDomain` is not a failure: the response simply carries no answers.
+pub(crate) fn parse_response(bytes: &[u8]) -> Result<Message, DnsError> {
+    let response = Message::from_vec(bytes)
+        .map_err(|e| DnsError::Other(format!("Failed to parse DNS response: {}", e)))?;
+
+    match response.response_code() {
+        ResponseCode::NoError | ResponseCode::NXDomain => Ok(response),
+        ResponseCode::ServFail => Err(DnsError::ServerFailure),
+        ResponseCode::Refused => Err(DnsError::Refused),
+        code => Err(DnsError::Other(format!("DNS query failed with response code: {:?}", code))),
+    }
+}
+
+/// Build a [NaptrRecord] out of the ANSWER section, keeping any SRV and A/AAAA records
+/// found in the ADDITIONAL section
+pub(crate) fn naptr_record_from(domain: Domain, response: &Message) -> Option<NaptrRecord> {
+    // Extract NAPTR records from ANSWER section
+    let entries: Vec<NaptrEntry> =
+        response.answers().iter().filter_map(|record| parse_naptr(record.data()).ok()).collect();
+
+    if entries.is_empty() {
+        return None;
+    }
+
+    let ttl = min_ttl(response.answers());
+
+    // Parse SRV records from ADDITIONAL section
+    let mut srv_map: HashMap<String, (SrvDomain, Vec<SrvEntry>, u32)> = HashMap::new();
+    for record in response.additionals() {
+        if let RData::SRV(srv) = record.data() {
+            let srv_name = record.name().to_string();
+            if let Ok(srv_domain) = SrvDomain::try_from(srv_name.as_str()) {
+                let entry = SrvEntry {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port().into(),
+                    target: srv.target().to_string().into(),
+                };
+
+                srv_map
+                    .entry(srv_name.clone())
+                    .or_insert_with(|| (srv_domain, Vec::new(), record.ttl()))
+                    .1
+                    .push(entry);
+            }
+        }
+    }
+
+    // Parse A/AAAA records from ADDITIONAL section
+    let additional_hosts = additional_hosts(response);
+
+    // Build SrvRecord objects, their A/AAAA records are kept once at the NAPTR level so that a
+    // target shared by several SRV records isn't duplicated
+    let additional_srvs: HashMap<SrvDomain, SrvRecord> = srv_map
+        .into_iter()
+        .map(|(_, (srv_domain, entries, srv_ttl))| {
+            (srv_domain.clone(), SrvRecord::new(entries, srv_domain, srv_ttl))
+        })
+        .collect();
+
+    Some(
+        NaptrRecord::with_additional_srvs(entries, domain, ttl, additional_srvs)
+            .with_shared_hosts(additional_hosts),
+    )
+}
+
+/// Build a [SrvRecord] out of the ANSWER section, keeping any A/AAAA records found in the
+/// ADDITIONAL section
+pub(crate) fn srv_record_from(srv_domain: SrvDomain, response: &Message) -> Option<SrvRecord> {
+    // Extract SRV records from ANSWER section
+    let entries: Vec<SrvEntry> = response
+        .answers()
+        .iter()
+        .filter_map(|record| {
+            if let RData::SRV(srv) = record.data() {
+                Some(SrvEntry {
+                    priority: srv.priority(),
+                    weight: srv.weight(),
+                    port: srv.port().into(),
+                    target: srv.target().to_string().into(),
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if entries.is_empty() {
+        return None;
+    }
+
+    let ttl = min_ttl(response.answers());
+
+    // Parse A/AAAA records from ADDITIONAL section
+    let additional_hosts = additional_hosts(response);
+
+    Some(SrvRecord::with_additional_hosts(entries, srv_domain, ttl, additional_hosts))
+}
+
+/// Merge the A and AAAA answers into a single [AddrRecord], through [AddrRecord::merge]
+pub(crate) fn addr_record_from(
+    domain: Domain,
+    a_response: Option<&Message>,
+    aaaa_response: Option<&Message>,
+) -> Result<AddrRecord, Error> {
+    let mut records = a_response
+        .into_iter()
+        .chain(aaaa_response)
+        .filter_map(|response| answered_addr_record(domain.clone(), response));
+    let first = records
+        .next()
+        .ok_or_else(|| Error::Unexpected(format!("No A or AAAA records found for {}", domain)))?;
+
+    records.try_fold(first, AddrRecord::merge)
+}
+
+/// The A/AAAA records of the ANSWER section of a single response, if any
+fn answered_addr_record(domain: Domain, response: &Message) -> Option<AddrRecord> {
+    let answers = response
+        .answers()
+        .iter()
+        .filter_map(|record| match record.data() {
+            RData::A(a) => Some((IpAddr::V4(a.0), record.ttl())),
+            RData::AAAA(aaaa) => Some((IpAddr::V6(aaaa.0), record.ttl())),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    (!answers.is_empty()).then(|| addr_record_with_ttls(domain, answers))
+}
+
+/// The data of every `record_type` record of the ANSWER section, leaving out the CNAME records
+/// that led to them
+pub(crate) fn rdata_from(response: &Message, record_type: RecordType) -> Vec<RData> {
+    response
+        .answers()
+        .iter()
+        .filter(|record| record.record_type() == record_type)
+        .map(|record| record.data().clone())
+        .collect()
+}
+
+/// Build an [AddrRecord] out of (ip, ttl) pairs, tracking the minimum TTL per family
+pub(crate) fn addr_record_with_ttls(
+    domain: Domain,
+    addrs_with_ttl: Vec<(IpAddr, u32)>,
+) -> AddrRecord {
+    let family_ttl = |v4: bool| {
+        addrs_with_ttl.iter().filter(|(ip, _)| ip.is_ipv4() == v4).map(|(_, ttl)| *ttl).min()
+    };
+    let (v4_ttl, v6_ttl) = (family_ttl(true), family_ttl(false));
+    let ttl = v4_ttl.into_iter().chain(v6_ttl).min().unwrap_or(DEFAULT_TTL);
+    let ip_addrs = addrs_with_ttl.into_iter().map(|(ip, _)| ip).collect();
+
+    AddrRecord {
+        domain,
+        ip_addrs,
+        ttl,
+        per_family_ttl: Some((v4_ttl.unwrap_or(ttl), v6_ttl.unwrap_or(ttl))),
+        source: None,
+    }
+}
+
+/// Parse A/AAAA records from ADDITIONAL section into AddrRecord map.
+///
+/// CNAME records found there are followed as well, so that a SRV target which is an alias gets
+/// the A/AAAA records of its canonical name, with a TTL no longer than any CNAME in the chain.
+fn additional_hosts(message: &Message) -> HashMap<Domain, AddrRecord> {
+    let mut host_map: HashMap<Domain, Vec<(IpAddr, u32)>> = HashMap::new();
+    let mut aliases: HashMap<Domain, (Domain, u32)> = HashMap::new();
+
+    // Collect all A, AAAA and CNAME records from additional section
+    for record in message.additionals() {
+        let domain = domain_key(&record.name().to_string().into());
+        let ttl = record.ttl();
+
+        match record.data() {
+            RData::A(a) => {
+                host_map.entry(domain.clone()).or_default().push((IpAddr::V4(a.0), ttl));
+            }
+            RData::AAAA(aaaa) => {
+                host_map.entry(domain.clone()).or_default().push((IpAddr::V6(aaaa.0), ttl));
+            }
+            RData::CNAME(cname) => {
+                aliases.insert(domain, (domain_key(&cname.0.to_string().into()), ttl));
+            }
+            _ => {}
+        }
+    }
+
+    // Resolve every alias through the CNAME chain to the addresses of its canonical name
+    let alias_hosts = aliases
+        .keys()
+        .filter(|alias| !host_map.contains_key(*alias))
+        .filter_map(|alias| {
+            let (canonical, chain_ttl) = canonical_name(alias, &aliases)?;
+            let addrs_with_ttl = host_map.get(&canonical)?;
+
+            Some((
+                alias.clone(),
+                addrs_with_ttl.iter().map(|(ip, ttl)| (*ip, (*ttl).min(chain_ttl))).collect(),
+            ))
+        })
+        .collect::<Vec<(Domain, Vec<(IpAddr, u32)>)>>();
+    host_map.extend(alias_hosts);
+
+    // Convert to AddrRecord map with minimum TTL per domain and family
+    host_map
+        .into_iter()
+        .map(|(domain, addrs_with_ttl)| {
+            (domain.clone(), addr_record_with_ttls(domain, addrs_with_ttl))
+        })
+        .collect()
+}
+
+/// Follow the CNAME chain starting at `alias`, returning the name at its end along with the
+/// minimum TTL of the CNAME records on the way. Gives up on loops and overly long chains.
+fn canonical_name(
+    alias: &Domain,
+    aliases: &HashMap<Domain, (Domain, u32)>,
+) -> Option<(Domain, u32)> {
+    let mut name = alias;
+    let mut chain_ttl = u32::MAX;
+
+    for _ in 0..MAX_CNAME_CHAIN {
+        match aliases.get(name) {
+            Some((target, ttl)) => {
+                name = target;
+                chain_ttl = chain_ttl.min(*ttl);
+            }
+            None => return Some((name.clone(), chain_ttl)),
+        }
+    }
+
+    None
+}
+
+/// Calculate minimum TTL from a set of records
+fn min_ttl(records: &[Record]) -> u32 {
+    records.iter().map(|r| r.ttl()).min().unwrap_or(DEFAULT_TTL)
+}
@@ -1,45 +1,148 @@
 //! This module holds [DnsClient](crate::DnsClient) trait implementations on top of
 //! [hickory-dns](https://docs.rs/hickory-resolver/0.25.2/hickory_resolver/).
 //!
-//! 2 clients are provided, one async built on top of
+//! 2 clients are built on top of the `hickory-dns` resolvers, one async ([AsyncHickoryClient])
+//! on top of
 //! [TokioAsyncResolver](https://docs.rs/hickory-resolver/0.25.2/hickory_resolver/type.TokioAsyncResolver.html)
-//! of `hickory-dns` and one sync built on top of [Resolver](https://docs.rs/hickory-resolver/0.25.2/hickory_resolver/struct.Resolver.html)
-//! of hickory-dns. Each variant accepts the respective `hickory-dns` resolver, so you get enormous
-//! freedom and `rsip-dns` shouldn't restrict you in any way.
+//! and one sync ([HickoryClient]) on top of
+//! [Resolver](https://docs.rs/hickory-resolver/0.25.2/hickory_resolver/struct.Resolver.html).
+//! Each variant accepts the respective `hickory-dns` resolver, so you get enormous freedom and
+//! `rsip-dns` shouldn't restrict you in any way.
+//!
+//! 3 more clients query a name server directly through `hickory-proto`, keeping the records of
+//! the ADDITIONAL section of the responses:
+//! * [RecursiveHickoryClient], async, over UDP (and TCP for truncated responses) or, with the
+//!   `dns-over-tls` feature, over TLS
+//! * [SyncRecursiveClient], its blocking counterpart, for code outside of any async runtime
+//! * `DohClient`, with the `dns-over-https` feature, over HTTPS (RFC 8484)
 //!
 //! In more advanced scenarios, you might want to build a custom dns client that will implement
 //! query caching etc.
 
 mod async_hickory_client;
+#[cfg(feature = "dns-over-https")]
+mod doh_client;
 mod hickory_client;
+mod message;
+mod query_metrics;
 mod recursive_client;
+mod shared_socket;
+mod sync_recursive_client;
 
 pub use async_hickory_client::AsyncHickoryClient;
+#[cfg(feature = "dns-over-https")]
+pub use doh_client::DohClient;
 pub use hickory_client::HickoryClient;
 pub use recursive_client::RecursiveHickoryClient;
+pub use sync_recursive_client::SyncRecursiveClient;
+
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+use crate::{DnsError, records::*};
+use hickory_proto::ProtoErrorKind;
+use hickory_proto::op::ResponseCode;
+use hickory_proto::rr::{
+    RecordType,
+    rdata::{naptr::NAPTR, srv::SRV},
+    record_data::RData,
+};
+use hickory_resolver::{ResolveError, lookup::Lookup};
+
+use rsip::{Domain, Error};
+
+/// A failed resolver lookup is either an authoritative "no such record" (NXDOMAIN/NODATA),
+/// reported as `Ok(None)`, or a query that failed
+pub(crate) fn missing_or_failed<T>(error: ResolveError) -> Result<Option<T>, DnsError> {
+    match error.proto().map(|proto| proto.kind()) {
+        Some(ProtoErrorKind::NoRecordsFound { response_code, .. }) => match *response_code {
+            ResponseCode::NoError | ResponseCode::NXDomain => Ok(None),
+            ResponseCode::ServFail => Err(DnsError::ServerFailure),
+            ResponseCode::Refused => Err(DnsError::Refused),
+            code => {
+                Err(DnsError::Other(format!("DNS query failed with response code: {:?}", code)))
+            }
+        },
+        Some(ProtoErrorKind::Timeout) => Err(DnsError::Timeout),
+        _ => Err(DnsError::Other(error.to_string())),
+    }
+}
+
+/// The error of an A/AAAA lookup that came back without any address: a domain that has no such
+/// records is told apart from a query that failed, whose error is kept
+pub(crate) fn no_addresses(domain: &Domain, failure: Option<DnsError>) -> Error {
+    match failure {
+        Some(error) => Error::Unexpected(format!("A/AAAA lookup for {} failed: {}", domain, error)),
+        None => Error::Unexpected(format!("No A or AAAA records found for {}", domain)),
+    }
+}
+
+/// The addresses answered to an A or AAAA lookup, if any. They all get the lowest TTL of the
+/// lookup, which also covers the CNAME records that led to them.
+pub(crate) fn addr_record_from_lookup(domain: Domain, lookup: &Lookup) -> Option<AddrRecord> {
+    let ttl = lookup.record_iter().map(|record| record.ttl()).min()?;
+    let addrs_with_ttl = lookup
+        .record_iter()
+        .filter_map(|record| match record.data() {
+            RData::A(a) => Some((IpAddr::V4(a.0), ttl)),
+            RData::AAAA(aaaa) => Some((IpAddr::V6(aaaa.0), ttl)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    match addrs_with_ttl.is_empty() {
+        true => None,
+        false => Some(message::addr_record_with_ttls(domain, addrs_with_ttl)),
+    }
+}
+
+/// The data of every `record_type` record of a lookup, leaving out the CNAME records that led to
+/// them
+pub(crate) fn rdata_from_lookup(lookup: &Lookup, record_type: RecordType) -> Vec<RData> {
+    lookup
+        .record_iter()
+        .filter(|record| record.record_type() == record_type)
+        .map(|record| record.data().clone())
+        .collect()
+}
+
+/// The conversion of a NAPTR record into a [NaptrEntry] shared by every client, so that they
+/// can't parse the same record differently. Flags and services that aren't recognized are kept
+/// (as [NaptrFlags::Other] and [NaptrServices::Other]) and reported through a `tracing` warning,
+/// as they usually reveal a mis-provisioned record.
+pub(crate) fn parse_naptr(rdata: &RData) -> Result<NaptrEntry, Error> {
+    match rdata {
+        RData::NAPTR(naptr) => Ok(naptr_entry_from(naptr)),
+        _ => Err(Error::Unexpected("Unexpected DNS record, was expecting NAPTR".into())),
+    }
+}
 
-use std::convert::{TryFrom, TryInto};
+fn naptr_entry_from(naptr: &NAPTR) -> NaptrEntry {
+    let flags = NaptrFlags::from(naptr.flags());
+    let services = NaptrServices::from_bytes_lossy(naptr.services());
 
-use crate::records::*;
-use hickory_proto::rr::{rdata::srv::SRV, record_data::RData};
+    if !flags.is_recognized() {
+        warn_event!(replacement = %naptr.replacement(), ?flags, "unrecognized NAPTR flags");
+    }
+    if matches!(services, NaptrServices::Other(_)) {
+        warn_event!(replacement = %naptr.replacement(), ?services, "unrecognized NAPTR services");
+    }
 
-use rsip::Error;
+    NaptrEntry {
+        order: naptr.order(),
+        preference: naptr.preference(),
+        flags,
+        services,
+        regexp: naptr.regexp().to_vec(),
+        replacement: naptr.replacement().to_string().into(),
+    }
+}
 
 impl TryFrom<RData> for NaptrEntry {
     type Error = Error;
 
     fn try_from(rdata: RData) -> Result<Self, Self::Error> {
-        match rdata {
-            RData::NAPTR(entry) => Ok(Self {
-                order: entry.order(),
-                preference: entry.preference(),
-                flags: entry.flags().into(),
-                services: entry.services().try_into()?,
-                regexp: entry.regexp().to_vec(),
-                replacement: entry.replacement().to_string().into(),
-            }),
-            _ => Err(Error::Unexpected("Unexpected DNS record, was expecting NAPTR".into())),
-        }
+        parse_naptr(&rdata)
     }
 }
 
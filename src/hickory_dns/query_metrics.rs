@@ -0,0 +1,42 @@
+//! Query metrics of the hickory clients, recorded through the [metrics](https://docs.rs/metrics)
+//! facade so that any exporter works:
+//! - `dns_queries_total`, a counter of the queries sent
+//! - `dns_query_duration_seconds`, a histogram of how long they took to be answered (or to fail)
+//!
+//! Both are labeled by `record_type` (e.g. `SRV`). Without the `metrics` feature nothing is
+//! recorded, measuring a query compiles down to the query itself.
+
+use hickory_proto::rr::RecordType;
+
+/// Runs the `query` of `record_type`, measuring it
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) async fn measured<T>(record_type: RecordType, query: impl Future<Output = T>) -> T {
+    #[cfg(feature = "metrics")]
+    let started = std::time::Instant::now();
+    let output = query.await;
+    #[cfg(feature = "metrics")]
+    record(record_type, started.elapsed());
+
+    output
+}
+
+/// Runs the blocking `query` of `record_type`, measuring it
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn measured_blocking<T>(record_type: RecordType, query: impl FnOnce() -> T) -> T {
+    #[cfg(feature = "metrics")]
+    let started = std::time::Instant::now();
+    let output = query();
+    #[cfg(feature = "metrics")]
+    record(record_type, started.elapsed());
+
+    output
+}
+
+#[cfg(feature = "metrics")]
+fn record(record_type: RecordType, elapsed: std::time::Duration) {
+    let record_type = record_type.to_string();
+
+    metrics::counter!("dns_queries_total", "record_type" => record_type.clone()).increment(1);
+    metrics::histogram!("dns_query_duration_seconds", "record_type" => record_type)
+        .record(elapsed.as_secs_f64());
+}
@@ -2,19 +2,34 @@
 //!
 //! This client can access the ADDITIONAL section of DNS responses, enabling
 //! single-query resolution of SRV records with their corresponding A/AAAA records.
+//!
+//! Queries are sent over plaintext UDP by default, and asked again over TCP when the response
+//! is truncated (TC bit set, or a datagram filling the whole receive buffer). With the
+//! `dns-over-tls` feature enabled, [`RecursiveHickoryClient::with_dot`] sends them over a TLS
+//! connection instead (RFC 7858).
+//!
+//! Each UDP query uses its own socket unless [`RecursiveHickoryClient::shared_socket`] is set,
+//! in which case a single connected socket is reused by all queries (and all clones of the
+//! client), with responses matched to their queries by message id. Sockets (UDP, TCP and TLS
+//! alike) bind to the unspecified address of the name server family, or to the address given
+//! with [`RecursiveHickoryClient::with_local_addr`].
 
-use async_trait::async_trait;
-use hickory_proto::op::{Message, Query};
+use hickory_proto::op::Message;
 use hickory_proto::rr::{Name, RData, RecordType};
+use ipnet::IpNet;
 use rsip::{Domain, Error};
-use std::collections::HashMap;
-use std::convert::TryFrom;
-use std::net::{IpAddr, SocketAddr};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
-use tokio::net::UdpSocket;
 
-use crate::{records::*, DnsClient, SrvDomain};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpSocket, TcpStream};
+
+use super::message::{self, QueryOptions};
+use super::query_metrics;
+use super::shared_socket::{SharedSocketSlot, connected_socket};
+use crate::{DnsClient, DnsError, SrvDomain, records::*};
 
 /// Recursive DNS client that uses hickory-proto directly to access
 /// the ADDITIONAL section of DNS responses.
@@ -25,292 +40,350 @@ use crate::{records::*, DnsClient, SrvDomain};
 pub struct RecursiveHickoryClient {
     name_server: SocketAddr,
     timeout: Duration,
+    transport: QueryTransport,
+    shared_socket: Option<Arc<SharedSocketSlot>>,
+    local_addr: Option<SocketAddr>,
+    ttl_bounds: TtlBounds,
+    options: QueryOptions,
+}
+
+/// How queries reach the name server
+#[derive(Debug, Clone)]
+enum QueryTransport {
+    Udp,
+    #[cfg(feature = "dns-over-tls")]
+    Tls {
+        server_name: String,
+        config: Arc<rustls::ClientConfig>,
+    },
 }
 
 impl RecursiveHickoryClient {
     /// Create a new RecursiveHickoryClient with default timeout (5 seconds)
     pub fn new(name_server: SocketAddr) -> Self {
-        Self { name_server, timeout: Duration::from_secs(5) }
+        Self::with_timeout(name_server, Duration::from_secs(5))
     }
 
     /// Create a new RecursiveHickoryClient with custom timeout
     pub fn with_timeout(name_server: SocketAddr, timeout: Duration) -> Self {
-        Self { name_server, timeout }
+        Self {
+            name_server,
+            timeout,
+            transport: QueryTransport::Udp,
+            shared_socket: None,
+            local_addr: None,
+            ttl_bounds: Default::default(),
+            options: Default::default(),
+        }
     }
 
-    /// Send a DNS query and return the full response message
-    async fn query(
-        &self,
-        name: Name,
-        record_type: RecordType,
-    ) -> Result<Message, Error> {
-        // Create UDP socket
-        let socket = UdpSocket::bind("0.0.0.0:0")
-            .await
-            .map_err(|e| Error::Unexpected(format!("Failed to bind UDP socket: {}", e)))?;
+    /// Create a new RecursiveHickoryClient that sends its queries over DNS-over-TLS.
+    ///
+    /// The server certificate is verified against the webpki root store and `server_name`
+    /// is used both for SNI and for certificate validation.
+    #[cfg(feature = "dns-over-tls")]
+    pub fn with_dot(name_server: SocketAddr, server_name: String, timeout: Duration) -> Self {
+        let roots =
+            rustls::RootCertStore::from_iter(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let config =
+            rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+
+        Self::with_dot_config(name_server, server_name, timeout, Arc::new(config))
+    }
 
-        socket
-            .connect(self.name_server)
-            .await
-            .map_err(|e| Error::Unexpected(format!("Failed to connect to DNS server: {}", e)))?;
+    /// Same as [`with_dot`](Self::with_dot) but with a caller provided rustls configuration,
+    /// useful for private resolvers or custom trust anchors.
+    #[cfg(feature = "dns-over-tls")]
+    pub fn with_dot_config(
+        name_server: SocketAddr,
+        server_name: String,
+        timeout: Duration,
+        config: Arc<rustls::ClientConfig>,
+    ) -> Self {
+        Self {
+            name_server,
+            timeout,
+            transport: QueryTransport::Tls { server_name, config },
+            shared_socket: None,
+            local_addr: None,
+            ttl_bounds: Default::default(),
+            options: Default::default(),
+        }
+    }
 
-        // Build DNS query message
-        let mut message = Message::new();
-        message.set_id(rand::random());
-        message.set_recursion_desired(true); // Enable recursion for additional records
-        message.set_op_code(hickory_proto::op::OpCode::Query);
-        message.add_query(Query::query(name, record_type));
+    /// Reuse a single long-lived UDP socket for all queries instead of binding one per query.
+    ///
+    /// The socket is created on the first query and shared with every clone of the client, and
+    /// created again should it fail. Has no effect on DNS-over-TLS clients.
+    pub fn shared_socket(mut self) -> Self {
+        self.shared_socket = Some(Arc::default());
+        self
+    }
 
-        // Serialize and send
-        let query_bytes = message
-            .to_vec()
-            .map_err(|e| Error::Unexpected(format!("Failed to serialize DNS query: {}", e)))?;
+    /// Bind the sockets to `local_addr`, so that queries leave from a specific source address
+    /// (and port, unless it is 0) on multi-homed hosts, whether they go over UDP, TCP or TLS.
+    pub fn with_local_addr(mut self, local_addr: SocketAddr) -> Self {
+        self.local_addr = Some(local_addr);
+        self
+    }
 
-        socket
-            .send(&query_bytes)
-            .await
-            .map_err(|e| Error::Unexpected(format!("Failed to send DNS query: {}", e)))?;
+    /// Clamp the TTL of every returned record into the given bounds
+    pub fn with_ttl_bounds(mut self, ttl_bounds: TtlBounds) -> Self {
+        self.ttl_bounds = ttl_bounds;
+        self
+    }
 
-        // Receive response with timeout
-        let mut response_buf = vec![0u8; 4096];
-        let len = tokio::time::timeout(self.timeout, socket.recv(&mut response_buf))
-            .await
-            .map_err(|_| Error::Unexpected("DNS query timeout".to_string()))?
-            .map_err(|e| Error::Unexpected(format!("Failed to receive DNS response: {}", e)))?;
-
-        // Parse response
-        let response = Message::from_vec(&response_buf[..len])
-            .map_err(|e| Error::Unexpected(format!("Failed to parse DNS response: {}", e)))?;
-
-        // Check response code
-        if response.response_code() != hickory_proto::op::ResponseCode::NoError {
-            return Err(Error::Unexpected(format!(
-                "DNS query failed with response code: {:?}",
-                response.response_code()
-            )));
-        }
+    /// Attach an EDNS client subnet option (RFC 7871) with `prefix` to every query, so that
+    /// the name server can tailor its answers to the network the client sits in.
+    ///
+    /// The host bits past the prefix length are cleared before anything is sent.
+    pub fn with_client_subnet(mut self, prefix: IpNet) -> Self {
+        self.options.client_subnet = Some(prefix.trunc().into());
+        self
+    }
 
-        Ok(response)
+    /// Whether queries set the RD (recursion desired) bit, which they do by default. Turn it off
+    /// to query an authoritative server directly rather than a recursive resolver.
+    ///
+    /// The ADDITIONAL section fast path keeps working against an authoritative server: it
+    /// returns the glue A/AAAA records of the SRV (and NAPTR) targets it is authoritative for
+    /// along with the answer. The targets outside of its zones come without addresses though,
+    /// and their A/AAAA queries are then sent to that same server, which can't answer them
+    /// without recursion.
+    pub fn with_recursion_desired(mut self, recursion_desired: bool) -> Self {
+        self.options.recursion_desired = recursion_desired;
+        self
     }
 
-    /// Parse A/AAAA records from ADDITIONAL section into AddrRecord map
-    fn parse_additional_hosts(&self, message: &Message) -> HashMap<Domain, AddrRecord> {
-        let mut host_map: HashMap<Domain, Vec<(IpAddr, u32)>> = HashMap::new();
-
-        // Collect all A and AAAA records from additional section
-        for record in message.additionals() {
-            let domain: Domain = record.name().to_string().into();
-            let ttl = record.ttl();
-
-            match record.data() {
-                RData::A(a) => {
-                    host_map
-                        .entry(domain.clone())
-                        .or_default()
-                        .push((IpAddr::V4(a.0), ttl));
-                }
-                RData::AAAA(aaaa) => {
-                    host_map
-                        .entry(domain.clone())
-                        .or_default()
-                        .push((IpAddr::V6(aaaa.0), ttl));
-                }
-                _ => {}
-            }
-        }
+    /// Only accept responses with the AD (authentic data) bit set, i.e. answers the name server
+    /// validated with DNSSEC. Queries then set the AD bit themselves to ask for it (RFC 6840), and
+    /// any response without it fails with [DnsError::Other], including NXDOMAIN ones.
+    ///
+    /// The AD bit is only as trustworthy as the path to the name server: use it with a local
+    /// validating resolver or over DNS-over-TLS.
+    pub fn with_require_authentic_data(mut self, require_authentic_data: bool) -> Self {
+        self.options.require_authentic_data = require_authentic_data;
+        self
+    }
 
-        // Convert to AddrRecord map with minimum TTL per domain
-        host_map
-            .into_iter()
-            .map(|(domain, addrs_with_ttl)| {
-                let min_ttl = addrs_with_ttl.iter().map(|(_, ttl)| *ttl).min().unwrap_or(300);
-                let ip_addrs = addrs_with_ttl.into_iter().map(|(ip, _)| ip).collect();
-                (domain.clone(), AddrRecord { domain, ip_addrs, ttl: min_ttl })
-            })
-            .collect()
+    /// The address every socket binds to, the unspecified address of the name server family
+    /// unless one was given
+    fn bind_addr(&self) -> SocketAddr {
+        self.local_addr.unwrap_or_else(|| match self.name_server {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        })
     }
 
-    /// Calculate minimum TTL from a set of records
-    fn calculate_min_ttl(&self, records: &[&hickory_proto::rr::Record]) -> u32 {
-        records.iter().map(|r| r.ttl()).min().unwrap_or(300)
+    /// Send a DNS query and return the full response message, see [query_metrics]
+    async fn query(&self, name: Name, record_type: RecordType) -> Result<Message, DnsError> {
+        query_metrics::measured(record_type, self.send_query(name, record_type)).await
     }
-}
 
-#[async_trait]
-impl DnsClient for RecursiveHickoryClient {
-    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
-        let name = Name::from_str(&domain.to_string()).ok()?;
-        let response = self.query(name, RecordType::NAPTR).await.ok()?;
-
-        // Extract NAPTR records from ANSWER section
-        let entries: Vec<NaptrEntry> = response
-            .answers()
-            .iter()
-            .filter_map(|record| {
-                if let RData::NAPTR(naptr) = record.data() {
-                    let flags = match String::from_utf8(naptr.flags().to_vec()) {
-                        Ok(s) if s.eq_ignore_ascii_case("s") => NaptrFlags::S,
-                        Ok(s) if s.eq_ignore_ascii_case("a") => NaptrFlags::A,
-                        Ok(s) if s.eq_ignore_ascii_case("u") => NaptrFlags::U,
-                        Ok(s) if s.eq_ignore_ascii_case("p") => NaptrFlags::P,
-                        _ => NaptrFlags::Other(naptr.flags().to_vec()),
-                    };
-
-                    let services = match String::from_utf8(naptr.services().to_vec()) {
-                        Ok(s) if s.eq_ignore_ascii_case("sip+d2t") => NaptrServices::SipD2t,
-                        Ok(s) if s.eq_ignore_ascii_case("sip+d2u") => NaptrServices::SipD2u,
-                        Ok(s) if s.eq_ignore_ascii_case("sip+d2s") => NaptrServices::SipD2s,
-                        Ok(s) if s.eq_ignore_ascii_case("sip+d2w") => NaptrServices::SipD2w,
-                        Ok(s) if s.eq_ignore_ascii_case("sips+d2t") => NaptrServices::SipsD2t,
-                        Ok(s) if s.eq_ignore_ascii_case("sips+d2u") => NaptrServices::SipsD2u,
-                        Ok(s) if s.eq_ignore_ascii_case("sips+d2s") => NaptrServices::SipsD2s,
-                        Ok(s) if s.eq_ignore_ascii_case("sips+d2w") => NaptrServices::SipsD2w,
-                        Ok(s) => NaptrServices::Other(s),
-                        Err(_) => NaptrServices::Other(
-                            String::from_utf8_lossy(naptr.services()).to_string(),
-                        ),
-                    };
-
-                    Some(NaptrEntry {
-                        order: naptr.order(),
-                        preference: naptr.preference(),
-                        flags,
-                        services,
-                        regexp: naptr.regexp().to_vec(),
-                        replacement: naptr.replacement().to_string().into(),
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect();
+    async fn send_query(&self, name: Name, record_type: RecordType) -> Result<Message, DnsError> {
+        let (id, response_bytes) = match (&self.transport, &self.shared_socket) {
+            (QueryTransport::Udp, Some(shared_socket)) => {
+                let shared_socket = shared_socket.get(self.bind_addr(), self.name_server).await?;
+                // the id is picked by the shared socket so that in-flight queries never collide
+                let pending = shared_socket.register()?;
+                let id = pending.id();
+                let query_bytes = self.options.build_query(id, name.clone(), record_type)?;
 
-        if entries.is_empty() {
-            return None;
-        }
+                (id, pending.exchange(&query_bytes, self.timeout).await?)
+            }
+            (QueryTransport::Udp, None) => {
+                let id = rand::random();
+                let query_bytes = self.options.build_query(id, name.clone(), record_type)?;
 
-        let ttl = self.calculate_min_ttl(&response.answers().iter().collect::<Vec<_>>());
-
-        // Parse SRV records from ADDITIONAL section
-        let mut srv_map: HashMap<String, (SrvDomain, Vec<SrvEntry>, u32)> = HashMap::new();
-        for record in response.additionals() {
-            if let RData::SRV(srv) = record.data() {
-                let srv_name = record.name().to_string();
-                if let Ok(srv_domain) = SrvDomain::try_from(srv_name.as_str()) {
-                    let entry = SrvEntry {
-                        priority: srv.priority(),
-                        weight: srv.weight(),
-                        port: srv.port().into(),
-                        target: srv.target().to_string().into(),
-                    };
-
-                    srv_map
-                        .entry(srv_name.clone())
-                        .or_insert_with(|| (srv_domain, Vec::new(), record.ttl()))
-                        .1
-                        .push(entry);
-                }
+                (id, self.exchange_udp(id, &query_bytes).await?)
             }
-        }
+            #[cfg(feature = "dns-over-tls")]
+            (QueryTransport::Tls { server_name, config }, _) => {
+                let id = rand::random();
+                let query_bytes = self.options.build_query(id, name.clone(), record_type)?;
 
-        // Parse A/AAAA records from ADDITIONAL section
-        let additional_hosts = self.parse_additional_hosts(&response);
-
-        // Build SrvRecord objects with their additional hosts
-        let additional_srvs: HashMap<SrvDomain, SrvRecord> = srv_map
-            .into_iter()
-            .map(|(_, (srv_domain, entries, srv_ttl))| {
-                // Filter additional_hosts to only include targets from this SRV
-                let srv_additional_hosts: HashMap<Domain, AddrRecord> = entries
-                    .iter()
-                    .filter_map(|entry| {
-                        additional_hosts
-                            .get(&entry.target)
-                            .map(|addr| (entry.target.clone(), addr.clone()))
-                    })
-                    .collect();
-
-                let srv_record = SrvRecord::with_additional_hosts(
-                    entries,
-                    srv_domain.clone(),
-                    srv_ttl,
-                    srv_additional_hosts,
-                );
-
-                (srv_domain, srv_record)
-            })
-            .collect();
-
-        Some(NaptrRecord::with_additional_srvs(entries, domain, ttl, additional_srvs))
+                (id, self.exchange_tls(server_name, config, &query_bytes).await?)
+            }
+        };
+
+        let tcp_retry = match self.transport {
+            QueryTransport::Udp => self.options.tcp_retry(&response_bytes, name, record_type)?,
+            #[cfg(feature = "dns-over-tls")]
+            QueryTransport::Tls { .. } => None,
+        };
+        let (id, response_bytes) = match tcp_retry {
+            Some((id, query_bytes)) => (id, self.exchange_tcp(&query_bytes).await?),
+            None => (id, response_bytes),
+        };
+
+        self.options.checked_response(id, &response_bytes)
     }
 
-    async fn srv_lookup(&self, srv_domain: SrvDomain) -> Option<SrvRecord> {
-        let name = Name::from_str(&srv_domain.to_string()).ok()?;
-        let response = self.query(name, RecordType::SRV).await.ok()?;
-
-        // Extract SRV records from ANSWER section
-        let entries: Vec<SrvEntry> = response
-            .answers()
-            .iter()
-            .filter_map(|record| {
-                if let RData::SRV(srv) = record.data() {
-                    Some(SrvEntry {
-                        priority: srv.priority(),
-                        weight: srv.weight(),
-                        port: srv.port().into(),
-                        target: srv.target().to_string().into(),
-                    })
-                } else {
-                    None
+    /// Send the serialized query in a single UDP datagram and wait for the reply, ignoring
+    /// any datagram that doesn't carry the query id
+    async fn exchange_udp(&self, id: u16, query_bytes: &[u8]) -> Result<Vec<u8>, DnsError> {
+        let socket = connected_socket(self.bind_addr(), self.name_server).await?;
+
+        socket.send(query_bytes).await.map_err(|e| DnsError::io("Failed to send DNS query", e))?;
+
+        // Receive response with timeout
+        let mut response_buf = vec![0u8; message::UDP_BUFFER_SIZE];
+        let len = tokio::time::timeout(self.timeout, async {
+            loop {
+                let len = socket
+                    .recv(&mut response_buf)
+                    .await
+                    .map_err(|e| DnsError::io("Failed to receive DNS response", e))?;
+
+                if len >= 2 && u16::from_be_bytes([response_buf[0], response_buf[1]]) == id {
+                    return Ok(len);
                 }
-            })
-            .collect();
+            }
+        })
+        .await
+        .map_err(|_| DnsError::Timeout)??;
+
+        response_buf.truncate(len);
+        Ok(response_buf)
+    }
 
-        if entries.is_empty() {
-            return None;
+    /// Open a TCP connection to the name server and exchange the query over it, used when the
+    /// UDP response was truncated
+    async fn exchange_tcp(&self, query_bytes: &[u8]) -> Result<Vec<u8>, DnsError> {
+        tokio::time::timeout(self.timeout, async {
+            let stream = self.connect_tcp().await?;
+
+            exchange_stream(stream, query_bytes).await
+        })
+        .await
+        .map_err(|_| DnsError::Timeout)?
+    }
+
+    /// Open a TCP connection to the name server from a socket bound to [bind_addr](Self::bind_addr)
+    async fn connect_tcp(&self) -> Result<TcpStream, DnsError> {
+        let socket = match self.name_server {
+            SocketAddr::V4(_) => TcpSocket::new_v4(),
+            SocketAddr::V6(_) => TcpSocket::new_v6(),
         }
+        .and_then(|socket| socket.bind(self.bind_addr()).map(|_| socket))
+        .map_err(|e| DnsError::io("Failed to bind TCP socket", e))?;
 
-        let ttl = self.calculate_min_ttl(&response.answers().iter().collect::<Vec<_>>());
+        socket
+            .connect(self.name_server)
+            .await
+            .map_err(|e| DnsError::io("Failed to connect to DNS server", e))
+    }
 
-        // Parse A/AAAA records from ADDITIONAL section
-        let additional_hosts = self.parse_additional_hosts(&response);
+    /// Open a TLS connection to the name server and exchange the query over it
+    #[cfg(feature = "dns-over-tls")]
+    async fn exchange_tls(
+        &self,
+        server_name: &str,
+        config: &Arc<rustls::ClientConfig>,
+        query_bytes: &[u8],
+    ) -> Result<Vec<u8>, DnsError> {
+        let server_name = rustls::pki_types::ServerName::try_from(server_name.to_string())
+            .map_err(|e| DnsError::Other(format!("Invalid TLS server name: {}", e)))?;
+        let connector = tokio_rustls::TlsConnector::from(config.clone());
+
+        tokio::time::timeout(self.timeout, async {
+            let stream = self.connect_tcp().await?;
+            let stream = connector
+                .connect(server_name, stream)
+                .await
+                .map_err(|e| DnsError::io("TLS handshake failed", e))?;
+
+            exchange_stream(stream, query_bytes).await
+        })
+        .await
+        .map_err(|_| DnsError::Timeout)?
+    }
+}
 
-        Some(SrvRecord::with_additional_hosts(entries, srv_domain, ttl, additional_hosts))
+/// Exchange a single query over a stream, using the two byte length prefix
+/// framing of RFC 1035 section 4.2.2
+async fn exchange_stream<S>(mut stream: S, query_bytes: &[u8]) -> Result<Vec<u8>, DnsError>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    stream
+        .write_all(&message::framed(query_bytes)?)
+        .await
+        .map_err(|e| DnsError::io("Failed to send DNS query", e))?;
+    stream.flush().await.map_err(|e| DnsError::io("Failed to send DNS query", e))?;
+
+    let mut len_buf = [0u8; 2];
+    stream
+        .read_exact(&mut len_buf)
+        .await
+        .map_err(|e| DnsError::io("Failed to receive DNS response", e))?;
+
+    let mut response = message::unframed_buffer(len_buf);
+    stream
+        .read_exact(&mut response)
+        .await
+        .map_err(|e| DnsError::io("Failed to receive DNS response", e))?;
+
+    Ok(response)
+}
+
+impl DnsClient for RecursiveHickoryClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.try_naptr_lookup(domain).await.ok().flatten()
+    }
+
+    async fn srv_lookup(&self, srv_domain: SrvDomain) -> Option<SrvRecord> {
+        self.try_srv_lookup(srv_domain).await.ok().flatten()
     }
 
     async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
         let name = Name::from_str(&domain.to_string())
             .map_err(|e| Error::Unexpected(format!("Invalid domain name: {}", e)))?;
 
-        let mut ip_addrs = Vec::new();
-        let mut min_ttl = u32::MAX;
-
-        // Try A records first
-        if let Ok(response) = self.query(name.clone(), RecordType::A).await {
-            for record in response.answers() {
-                if let RData::A(a) = record.data() {
-                    ip_addrs.push(IpAddr::V4(a.0));
-                    min_ttl = min_ttl.min(record.ttl());
-                }
-            }
-        }
+        // Try A records first, then AAAA
+        let a_response = self.query(name.clone(), RecordType::A).await.ok();
+        let aaaa_response = self.query(name, RecordType::AAAA).await.ok();
+
+        message::resolved_addr_record(
+            domain,
+            a_response.as_ref(),
+            aaaa_response.as_ref(),
+            &self.ttl_bounds,
+            Some(self.name_server),
+        )
+    }
 
-        // Try AAAA records
-        if let Ok(response) = self.query(name, RecordType::AAAA).await {
-            for record in response.answers() {
-                if let RData::AAAA(aaaa) = record.data() {
-                    ip_addrs.push(IpAddr::V6(aaaa.0));
-                    min_ttl = min_ttl.min(record.ttl());
-                }
-            }
-        }
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let name = Name::from_str(&domain.to_string())
+            .map_err(|e| DnsError::Other(format!("Invalid domain name: {}", e)))?;
+        let response = self.query(name, RecordType::NAPTR).await?;
+
+        Ok(message::resolved_naptr_record(
+            domain,
+            &response,
+            &self.ttl_bounds,
+            Some(self.name_server),
+        ))
+    }
 
-        if ip_addrs.is_empty() {
-            return Err(Error::Unexpected(format!("No A or AAAA records found for {}", domain)));
-        }
+    async fn try_srv_lookup(&self, srv_domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        let name = Name::from_str(&srv_domain.to_string())
+            .map_err(|e| DnsError::Other(format!("Invalid domain name: {}", e)))?;
+        let response = self.query(name, RecordType::SRV).await?;
+
+        Ok(message::resolved_srv_record(
+            srv_domain,
+            &response,
+            &self.ttl_bounds,
+            Some(self.name_server),
+        ))
+    }
 
-        let ttl = if min_ttl == u32::MAX { 300 } else { min_ttl };
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        let name = Name::from_str(&name.to_string())
+            .map_err(|e| DnsError::Other(format!("Invalid domain name: {}", e)))?;
+        let response = self.query(name, rtype).await?;
 
-        Ok(AddrRecord { domain, ip_addrs, ttl })
+        Ok(message::rdata_from(&response, rtype))
     }
 }
@@ -0,0 +1,201 @@
+//! A long-lived connected UDP socket shared by all the queries of a
+//! [RecursiveHickoryClient](super::RecursiveHickoryClient).
+//!
+//! Responses are read by a single background task and handed to the query that is waiting for
+//! them based on the message id, so concurrent queries never pick up each other's responses.
+//! Should that task stop (the socket failed), the queries fail right away and the next one
+//! connects a new socket.
+
+use super::message;
+use crate::DnsError;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+/// The queries waiting for their response, `None` once the reader has stopped
+type PendingQueries = Arc<Mutex<Option<HashMap<u16, oneshot::Sender<Vec<u8>>>>>>;
+
+/// The [SharedUdpSocket] of a client and of all its clones, connected on the first query and
+/// again whenever the previous one has closed
+#[derive(Debug, Default)]
+pub(crate) struct SharedSocketSlot(tokio::sync::Mutex<Option<Arc<SharedUdpSocket>>>);
+
+#[derive(Debug)]
+pub(crate) struct SharedUdpSocket {
+    socket: Arc<UdpSocket>,
+    pending: PendingQueries,
+    reader: JoinHandle<()>,
+}
+
+/// A query registered on a [SharedUdpSocket], its id stays reserved until this is dropped
+pub(crate) struct PendingQuery<'a> {
+    id: u16,
+    shared: &'a SharedUdpSocket,
+    response: oneshot::Receiver<Vec<u8>>,
+}
+
+impl SharedUdpSocket {
+    /// Bind a socket connected to `name_server` and start reading responses from it
+    pub(crate) async fn connect(
+        local_addr: SocketAddr,
+        name_server: SocketAddr,
+    ) -> Result<Self, DnsError> {
+        let socket = Arc::new(connected_socket(local_addr, name_server).await?);
+        let pending = PendingQueries::new(Mutex::new(Some(HashMap::new())));
+        let reader = tokio::spawn(read_responses(socket.clone(), pending.clone()));
+
+        Ok(Self { socket, pending, reader })
+    }
+
+    /// Reserve a message id that is not used by any other in-flight query, failing when the
+    /// socket is closed or when every id is taken
+    pub(crate) fn register(&self) -> Result<PendingQuery<'_>, DnsError> {
+        let (sender, response) = oneshot::channel();
+        let mut pending = self.pending.lock().expect("pending queries lock poisoned");
+        let pending = pending.as_mut().ok_or_else(closed)?;
+
+        // from a random id on, so that ids stay unpredictable while the search is bounded
+        let start = rand::random::<u16>();
+        let id = (0..=u16::MAX)
+            .map(|offset| start.wrapping_add(offset))
+            .find(|id| !pending.contains_key(id))
+            .ok_or_else(|| {
+                DnsError::Other("Every DNS message id is in flight on the shared UDP socket".into())
+            })?;
+        pending.insert(id, sender);
+
+        Ok(PendingQuery { id, shared: self, response })
+    }
+
+    /// Whether the reader has stopped, no response will ever come through the socket again
+    fn is_closed(&self) -> bool {
+        !self.pending.lock().is_ok_and(|pending| pending.is_some())
+    }
+}
+
+impl SharedSocketSlot {
+    /// The shared socket, connected to `name_server` unless the current one is still open
+    pub(crate) async fn get(
+        &self,
+        local_addr: SocketAddr,
+        name_server: SocketAddr,
+    ) -> Result<Arc<SharedUdpSocket>, DnsError> {
+        let mut slot = self.0.lock().await;
+        match slot.as_ref() {
+            Some(shared) if !shared.is_closed() => Ok(shared.clone()),
+            _ => {
+                debug_event!(name_server = %name_server, "connecting the shared UDP socket");
+                let shared = Arc::new(SharedUdpSocket::connect(local_addr, name_server).await?);
+                *slot = Some(shared.clone());
+                Ok(shared)
+            }
+        }
+    }
+}
+
+impl Drop for SharedUdpSocket {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl PendingQuery<'_> {
+    pub(crate) fn id(&self) -> u16 {
+        self.id
+    }
+
+    /// Send the serialized query (which must carry [id](Self::id)) and wait for its response
+    pub(crate) async fn exchange(
+        mut self,
+        query_bytes: &[u8],
+        timeout: Duration,
+    ) -> Result<Vec<u8>, DnsError> {
+        self.shared
+            .socket
+            .send(query_bytes)
+            .await
+            .map_err(|e| DnsError::io("Failed to send DNS query", e))?;
+
+        tokio::time::timeout(timeout, &mut self.response)
+            .await
+            .map_err(|_| DnsError::Timeout)?
+            .map_err(|_| closed())
+    }
+}
+
+impl Drop for PendingQuery<'_> {
+    fn drop(&mut self) {
+        // the reader removes the entry when it delivers the response, after which the id may
+        // have been registered again: only the entry whose receiver is this one is removed
+        self.response.close();
+        if let Ok(mut pending) = self.shared.pending.lock()
+            && let Some(pending) = pending.as_mut()
+            && pending.get(&self.id).is_some_and(oneshot::Sender::is_closed)
+        {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+fn closed() -> DnsError {
+    DnsError::Other("Shared UDP socket is closed".to_string())
+}
+
+/// Bind a UDP socket to `local_addr` and connect it to `name_server`
+pub(crate) async fn connected_socket(
+    local_addr: SocketAddr,
+    name_server: SocketAddr,
+) -> Result<UdpSocket, DnsError> {
+    if local_addr.is_ipv4() != name_server.is_ipv4() {
+        return Err(DnsError::Other(format!(
+            "Local address {} can't reach name server {}, address families differ",
+            local_addr, name_server
+        )));
+    }
+
+    let socket = UdpSocket::bind(local_addr)
+        .await
+        .map_err(|e| DnsError::io("Failed to bind UDP socket", e))?;
+
+    socket
+        .connect(name_server)
+        .await
+        .map_err(|e| DnsError::io("Failed to connect to DNS server", e))?;
+
+    Ok(socket)
+}
+
+/// Hand every datagram to the query waiting for its id, dropping the ones nobody waits for
+async fn read_responses(socket: Arc<UdpSocket>, pending: PendingQueries) {
+    let mut buf = vec![0u8; message::UDP_BUFFER_SIZE];
+
+    loop {
+        let len = match socket.recv(&mut buf).await {
+            Ok(len) => len,
+            // an ICMP unreachable from an earlier datagram, the socket itself is still fine
+            Err(e) if e.kind() == ErrorKind::ConnectionRefused => continue,
+            Err(_) => break,
+        };
+        if len < 2 {
+            continue;
+        }
+
+        let id = u16::from_be_bytes([buf[0], buf[1]]);
+        let sender = pending.lock().ok().and_then(|mut pending| pending.as_mut()?.remove(&id));
+        if let Some(sender) = sender {
+            let _ = sender.send(buf[..len].to_vec());
+        }
+    }
+
+    // wake up everyone still waiting, they will fail instead of timing out, and so will the
+    // queries registered from now on
+    debug_event!("shared UDP socket reader stopped");
+    if let Ok(mut pending) = pending.lock() {
+        *pending = None;
+    }
+}
@@ -0,0 +1,244 @@
+//! Blocking counterpart of [RecursiveHickoryClient](super::RecursiveHickoryClient), for tools
+//! and CLIs that don't run on Tokio.
+//!
+//! Queries are sent over a [std::net::UdpSocket] (and over TCP when the response is truncated),
+//! and are built, checked and turned into records by the same message handling as the async
+//! client, so records in the ADDITIONAL section are kept as well.
+
+use hickory_proto::op::Message;
+use hickory_proto::rr::{Name, RData, RecordType};
+use ipnet::IpNet;
+use rsip::{Domain, Error};
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use super::message::{self, QueryOptions};
+use super::query_metrics;
+use crate::{DnsClient, DnsError, SrvDomain, records::*};
+
+/// Blocking DNS client sending its queries straight to a (recursive) name server.
+///
+/// The `blocking_*` methods query the name server on the calling thread. [DnsClient] is
+/// implemented on top of them so that a whole [Lookup](crate::Lookup) can be driven without
+/// Tokio, e.g. with `futures::executor::block_on`, each query blocking until it's answered.
+/// Don't use it inside a Tokio runtime, whose worker threads must not block: use
+/// [RecursiveHickoryClient](super::RecursiveHickoryClient) there.
+#[derive(Debug, Clone)]
+pub struct SyncRecursiveClient {
+    name_server: SocketAddr,
+    timeout: Duration,
+    ttl_bounds: TtlBounds,
+    options: QueryOptions,
+}
+
+impl SyncRecursiveClient {
+    /// Create a new SyncRecursiveClient with default timeout (5 seconds)
+    pub fn new(name_server: SocketAddr) -> Self {
+        Self::with_timeout(name_server, Duration::from_secs(5))
+    }
+
+    /// Create a new SyncRecursiveClient with custom timeout, for each query
+    pub fn with_timeout(name_server: SocketAddr, timeout: Duration) -> Self {
+        Self { name_server, timeout, ttl_bounds: Default::default(), options: Default::default() }
+    }
+
+    /// Clamp the TTL of every returned record into the given bounds
+    pub fn with_ttl_bounds(mut self, ttl_bounds: TtlBounds) -> Self {
+        self.ttl_bounds = ttl_bounds;
+        self
+    }
+
+    /// Whether queries set the RD (recursion desired) bit, which they do by default. Turn it off
+    /// to query an authoritative server directly, like with
+    /// [RecursiveHickoryClient](super::RecursiveHickoryClient).
+    pub fn with_recursion_desired(mut self, recursion_desired: bool) -> Self {
+        self.options.recursion_desired = recursion_desired;
+        self
+    }
+
+    /// Attach an EDNS client subnet option (RFC 7871) with `prefix` to every query, see
+    /// [with_client_subnet](super::RecursiveHickoryClient::with_client_subnet) on the async client.
+    pub fn with_client_subnet(mut self, prefix: IpNet) -> Self {
+        self.options.client_subnet = Some(prefix.trunc().into());
+        self
+    }
+
+    /// Only accept responses with the AD (authentic data) bit set, see
+    /// [with_require_authentic_data](super::RecursiveHickoryClient::with_require_authentic_data)
+    /// on the async client.
+    pub fn with_require_authentic_data(mut self, require_authentic_data: bool) -> Self {
+        self.options.require_authentic_data = require_authentic_data;
+        self
+    }
+
+    /// Blocking [DnsClient::try_naptr_lookup]
+    pub fn blocking_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let response = self.query(name_from(&domain)?, RecordType::NAPTR)?;
+
+        Ok(message::resolved_naptr_record(
+            domain,
+            &response,
+            &self.ttl_bounds,
+            Some(self.name_server),
+        ))
+    }
+
+    /// Blocking [DnsClient::try_srv_lookup]
+    pub fn blocking_srv_lookup(
+        &self,
+        srv_domain: SrvDomain,
+    ) -> Result<Option<SrvRecord>, DnsError> {
+        let response = self.query(name_from(&srv_domain)?, RecordType::SRV)?;
+
+        Ok(message::resolved_srv_record(
+            srv_domain,
+            &response,
+            &self.ttl_bounds,
+            Some(self.name_server),
+        ))
+    }
+
+    /// Blocking [DnsClient::ip_lookup]
+    pub fn blocking_ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        let name = name_from(&domain).map_err(|e| Error::Unexpected(e.to_string()))?;
+
+        // Try A records first, then AAAA
+        let a_response = self.query(name.clone(), RecordType::A).ok();
+        let aaaa_response = self.query(name, RecordType::AAAA).ok();
+
+        message::resolved_addr_record(
+            domain,
+            a_response.as_ref(),
+            aaaa_response.as_ref(),
+            &self.ttl_bounds,
+            Some(self.name_server),
+        )
+    }
+
+    /// Blocking [DnsClient::raw_lookup]
+    pub fn blocking_raw_lookup(
+        &self,
+        name: Domain,
+        rtype: RecordType,
+    ) -> Result<Vec<RData>, DnsError> {
+        let response = self.query(name_from(&name)?, rtype)?;
+
+        Ok(message::rdata_from(&response, rtype))
+    }
+
+    /// Send a DNS query and return the full response message, see [query_metrics]
+    fn query(&self, name: Name, record_type: RecordType) -> Result<Message, DnsError> {
+        query_metrics::measured_blocking(record_type, || self.send_query(name, record_type))
+    }
+
+    fn send_query(&self, name: Name, record_type: RecordType) -> Result<Message, DnsError> {
+        let id = rand::random();
+        let query_bytes = self.options.build_query(id, name.clone(), record_type)?;
+        let response_bytes = self.exchange_udp(id, &query_bytes)?;
+
+        let (id, response_bytes) =
+            match self.options.tcp_retry(&response_bytes, name, record_type)? {
+                Some((id, query_bytes)) => (id, self.exchange_tcp(&query_bytes)?),
+                None => (id, response_bytes),
+            };
+
+        self.options.checked_response(id, &response_bytes)
+    }
+
+    /// Send the serialized query in a single UDP datagram and wait for the reply, ignoring
+    /// any datagram that doesn't carry the query id
+    fn exchange_udp(&self, id: u16, query_bytes: &[u8]) -> Result<Vec<u8>, DnsError> {
+        let bind_addr: SocketAddr = match self.name_server {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let socket = UdpSocket::bind(bind_addr).map_err(io_error("Failed to bind UDP socket"))?;
+        socket.connect(self.name_server).map_err(io_error("Failed to connect to DNS server"))?;
+        socket.send(query_bytes).map_err(io_error("Failed to send DNS query"))?;
+
+        // the timeout covers the whole exchange, however many stray datagrams arrive
+        let deadline = Instant::now() + self.timeout;
+        let mut response_buf = vec![0u8; message::UDP_BUFFER_SIZE];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(DnsError::Timeout);
+            }
+            socket
+                .set_read_timeout(Some(remaining))
+                .map_err(io_error("Failed to set the socket timeout"))?;
+            let len = socket
+                .recv(&mut response_buf)
+                .map_err(io_error("Failed to receive DNS response"))?;
+
+            if len >= 2 && u16::from_be_bytes([response_buf[0], response_buf[1]]) == id {
+                response_buf.truncate(len);
+                return Ok(response_buf);
+            }
+        }
+    }
+
+    /// Open a TCP connection to the name server and exchange the query over it, with the two
+    /// byte length prefix framing of RFC 1035 section 4.2.2
+    fn exchange_tcp(&self, query_bytes: &[u8]) -> Result<Vec<u8>, DnsError> {
+        let framed = message::framed(query_bytes)?;
+
+        let mut stream = TcpStream::connect_timeout(&self.name_server, self.timeout)
+            .map_err(io_error("Failed to connect to DNS server"))?;
+        stream
+            .set_read_timeout(Some(self.timeout))
+            .and_then(|_| stream.set_write_timeout(Some(self.timeout)))
+            .map_err(io_error("Failed to set the socket timeout"))?;
+
+        stream.write_all(&framed).map_err(io_error("Failed to send DNS query"))?;
+
+        let mut len_buf = [0u8; 2];
+        stream.read_exact(&mut len_buf).map_err(io_error("Failed to receive DNS response"))?;
+        let mut response = message::unframed_buffer(len_buf);
+        stream.read_exact(&mut response).map_err(io_error("Failed to receive DNS response"))?;
+
+        Ok(response)
+    }
+}
+
+impl DnsClient for SyncRecursiveClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.blocking_naptr_lookup(domain).ok().flatten()
+    }
+
+    async fn srv_lookup(&self, srv_domain: SrvDomain) -> Option<SrvRecord> {
+        self.blocking_srv_lookup(srv_domain).ok().flatten()
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        self.blocking_ip_lookup(domain)
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        self.blocking_naptr_lookup(domain)
+    }
+
+    async fn try_srv_lookup(&self, srv_domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        self.blocking_srv_lookup(srv_domain)
+    }
+
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        self.blocking_raw_lookup(name, rtype)
+    }
+}
+
+fn name_from(domain: &impl ToString) -> Result<Name, DnsError> {
+    Name::from_str(&domain.to_string())
+        .map_err(|e| DnsError::Other(format!("Invalid domain name: {}", e)))
+}
+
+/// Maps the io errors of `context`, an elapsed socket timeout being reported as `WouldBlock` on
+/// unix and as `TimedOut` elsewhere
+fn io_error(context: &str) -> impl Fn(io::Error) -> DnsError + '_ {
+    move |e| match e.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => DnsError::Timeout,
+        _ => DnsError::io(context, e),
+    }
+}
@@ -24,7 +24,6 @@
 //! # #[derive(Debug, Clone, Default)]
 //! # pub struct CustomDnsClient;
 //! #
-//! # #[async_trait::async_trait]
 //! # impl rsip_dns::DnsClient for CustomDnsClient {
 //! #     async fn naptr_lookup(&self, _domain: rsip::Domain) -> Option<NaptrRecord> {
 //! #         panic!("should never call naptr_entries_for, yet it did!")
@@ -44,7 +43,8 @@
 //!     transport: Some(Transport::Udp),
 //!     port: Some(Port::from(5060)),
 //!     dns_client: my_dns_client,
-//!     supported_transports: Default::default()
+//!     supported_transports: Default::default(),
+//!     ..Default::default()
 //! };
 //!```
 //!
@@ -60,7 +60,6 @@
 //! # #[derive(Debug, Clone, Default)]
 //! # pub struct CustomDnsClient;
 //! #
-//! # #[async_trait::async_trait]
 //! # impl rsip_dns::DnsClient for CustomDnsClient {
 //! #     async fn naptr_lookup(&self, _domain: rsip::Domain) -> Option<NaptrRecord> {
 //! #         panic!("should never call naptr_entries_for, yet it did!")
@@ -101,7 +100,6 @@
 //! # #[derive(Debug, Clone, Default)]
 //! # pub struct CustomDnsClient;
 //! #
-//! # #[async_trait::async_trait]
 //! # impl rsip_dns::DnsClient for CustomDnsClient {
 //! #     async fn naptr_lookup(&self, _domain: rsip::Domain) -> Option<NaptrRecord> {
 //! #         panic!("should never call naptr_entries_for, yet it did!")
@@ -138,7 +136,7 @@
 //!         Some(Target {
 //!             ip_addr,
 //!             port,
-//!             transport, ttl,
+//!             transport, ttl, ..
 //!         }) => println!("next tuple: ({:?}, {:?}, {:?}, {:?})", ip_addr, port, transport, ttl),
 //!         None => break,
 //!     }
@@ -207,30 +205,51 @@
 //! `Resolvable` type and other types that are built on top of `Resolvable` or implement
 //! `ResolvableExt` trait.
 
+#[macro_use]
+mod trace;
+
+mod clients;
 mod context;
 mod dns_client;
+mod error;
 mod lookup;
+#[cfg(feature = "serde")]
+mod serde_fields;
 mod target;
 
 pub mod records;
 pub mod resolvables;
 
-pub use context::{Context, SupportedTransports};
+#[cfg(feature = "serde")]
+pub use clients::{CapturingDnsClient, RecordedQuery, Recording, ReplayDnsClient};
+pub use clients::{
+    OverrideDnsClient, PoolDnsClient, RacingDnsClient, SelectionStrategy, ThrottledDnsClient,
+};
+pub use context::{Context, ContextBuilder, FallbackPolicy, SupportedTransports, default_port};
 pub use dns_client::DnsClient;
-pub use lookup::Lookup;
+pub use error::DnsError;
+pub use lookup::{
+    AddrNode, CountingDnsClient, IpFamily, JustDomainLookup, Lookup, LookupMeta, NaptrBranch,
+    NaptrChild, NaptrNode, NoopRanker, ResolutionMethod, ResolutionReport, ResolutionTree,
+    RttRanker, SrvBranch, SrvNode, TargetRanker, TargetSortPolicy, resolution_tree, resolve_many,
+};
 pub use records::SrvDomain;
 pub use resolvables::ResolvableExt;
-pub use target::Target;
+pub use target::{DEFAULT_TTL, Target};
 
 #[cfg(feature = "hickory-dns")]
 mod hickory_dns;
 #[cfg(feature = "hickory-dns")]
 pub use hickory_dns::AsyncHickoryClient;
+#[cfg(feature = "dns-over-https")]
+pub use hickory_dns::DohClient;
 #[cfg(feature = "hickory-dns")]
 pub use hickory_dns::HickoryClient;
 #[cfg(feature = "hickory-dns")]
 pub use hickory_dns::RecursiveHickoryClient;
 #[cfg(feature = "hickory-dns")]
+pub use hickory_dns::SyncRecursiveClient;
+#[cfg(feature = "hickory-dns")]
 pub use hickory_proto;
 #[cfg(feature = "hickory-dns")]
 pub use hickory_resolver;
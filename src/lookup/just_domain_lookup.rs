@@ -5,15 +5,20 @@
 //! NAPTR or SRV resolutions.
 
 use crate::{
-    Context, DnsClient, Target,
+    Context, DnsClient, FallbackPolicy, IpFamily, ResolutionMethod, Target,
     records::SrvDomain,
     resolvables::{
-        ResolvableAddrRecord, ResolvableExt, ResolvableNaptrRecord, ResolvableSrvRecord,
-        ResolvableState,
+        NaptrOutcome, ResolvableAddrRecord, ResolvableExt, ResolvableNaptrRecord,
+        ResolvableSrvRecord, ResolvableState, ResolvableVec,
     },
 };
 use async_trait::async_trait;
-use rsip::{Domain, Transport};
+use futures::{
+    future::{BoxFuture, FutureExt},
+    stream::{FuturesOrdered, StreamExt},
+};
+use rsip::{Domain, Error, Port, Transport};
+use std::sync::{Mutex, PoisonError};
 
 /// State machine for just_domain_lookup that implements lazy fallback evaluation.
 ///
@@ -21,17 +26,31 @@ use rsip::{Domain, Transport};
 /// primary method fails to produce results.
 ///
 /// Flow:
-/// 1. Try NAPTR (which internally queries SRV for NAPTR results)
+/// 1. Try NAPTR (which internally queries SRV for NAPTR results), unless
+///    [Context::skip_naptr] is set in which case the lookup starts at step 3
 /// 2. If NAPTR produced results → Done (no fallbacks)
-/// 3. If NAPTR failed → Try SRV for each supported transport
-/// 4. If any SRV produced results → Done
-/// 5. If all SRV failed → Try A/AAAA on base domain
+/// 3. If the NAPTR query hit a transient failure (SERVFAIL, timeout) → Retry it once
+/// 4. If there is no NAPTR record or NAPTR failed → Try SRV for each supported transport, in the
+///    order of [Context::supported_transports] (one after the other, or all at once when
+///    [Context::concurrent_srv_fallbacks] is set)
+/// 5. If any SRV produced results → Done
+/// 6. If all SRV failed → Try A/AAAA on base domain, with the default transport (or with each
+///    available transport in turn when [Context::synthesize_all_transports] is set)
+///
+/// The [FallbackPolicy] of the context can cut this flow short, ending the lookup instead of
+/// moving to the next step.
+///
+/// Once the flow of the domain is done, the same flow starts over for each of the
+/// [Context::backup_domains], in order.
 #[derive(Debug, Clone)]
 pub struct JustDomainLookup<C>
 where
     C: DnsClient,
 {
     state: JustDomainLookupState<C>,
+    peeked: Option<Target>,
+    /// The lookup of the next backup domain, if any
+    next: Option<Box<JustDomainLookup<C>>>,
 }
 
 #[derive(Debug, Clone)]
@@ -43,20 +62,38 @@ where
     TryingNaptr {
         naptr: ResolvableNaptrRecord<C>,
         fallback_config: FallbackConfig<C>,
+        /// Whether the NAPTR query has already been retried after a transient failure
+        retried: bool,
     },
     /// NAPTR failed, trying SRV for each supported transport
     TryingSrvFallbacks {
         srv_lookups: Vec<ResolvableSrvRecord<C>>,
         current_index: usize,
         any_produced_results: bool,
-        addr_fallback: ResolvableAddrRecord<C>,
+        /// The SRV queries fired all at once, handing their lookups over to `srv_lookups` in
+        /// transport order as they complete
+        in_flight: Option<InFlightSrvQueries<C>>,
+        /// A/AAAA lookup on the base domain, unless the fallback policy forbids it
+        addr_fallback: Option<ResolvableVec<ResolvableAddrRecord<C>, Target>>,
     },
     /// SRV fallbacks failed, trying A/AAAA on base domain
-    TryingAddrFallback { addr: ResolvableAddrRecord<C> },
+    TryingAddrFallback { addr: ResolvableVec<ResolvableAddrRecord<C>, Target> },
     /// All methods exhausted
     Done,
 }
 
+/// SRV lookups whose query has been fired but not handed over yet. The first one is handed over
+/// as soon as its own query completes, whatever the state of the others.
+struct InFlightSrvQueries<C>
+where
+    C: DnsClient,
+{
+    /// The lookups not handed over yet, as they were before their query was fired
+    pending: Vec<ResolvableSrvRecord<C>>,
+    /// Only ever reached through `&mut`, the mutex merely keeps the lookup [Sync]
+    queries: Mutex<FuturesOrdered<BoxFuture<'static, ResolvableSrvRecord<C>>>>,
+}
+
 #[derive(Debug, Clone)]
 struct FallbackConfig<C>
 where
@@ -66,7 +103,12 @@ where
     domain: Domain,
     available_protocols: Vec<Transport>,
     secure: bool,
-    default_transport: Transport,
+    /// Transports (and their default port) the A/AAAA fallback targets, in preference order
+    addr_transports: Vec<(Transport, Port)>,
+    concurrent_srv: bool,
+    policy: FallbackPolicy,
+    family: Option<IpFamily>,
+    target_concurrency: Option<usize>,
 }
 
 impl<C> JustDomainLookup<C>
@@ -74,111 +116,193 @@ where
     C: DnsClient,
 {
     /// Create a new JustDomainLookup from a Context
+    ///
+    /// # Panics
+    ///
+    /// Panics if the context host is not a domain, use [JustDomainLookup::try_new] instead.
+    #[deprecated(note = "panics on non-domain hosts, use `JustDomainLookup::try_new` instead")]
     pub fn new(ctx: Context<C>) -> Self {
-        let domain = match ctx.host {
-            rsip::Host::Domain(ref d) => d.clone(),
-            _ => panic!("JustDomainLookup requires a domain"),
+        Self::try_new(ctx).expect("JustDomainLookup requires a domain")
+    }
+
+    /// Create a new JustDomainLookup from a Context, failing if the context host is not a domain
+    pub fn try_new(ctx: Context<C>) -> Result<Self, Error> {
+        let domain = match &ctx.host {
+            rsip::Host::Domain(domain) => domain.clone(),
+            host => {
+                return Err(Error::Unexpected(format!(
+                    "JustDomainLookup requires a domain, got {}",
+                    host
+                )));
+            }
         };
 
-        let default_transport = match ctx.secure {
-            true => Transport::default_secure_transport(),
-            false => Transport::default_insecure_transport(),
+        Ok(Self::from_domain(domain, ctx))
+    }
+
+    pub(crate) fn from_domain(domain: Domain, mut ctx: Context<C>) -> Self {
+        let next = match ctx.backup_domains.is_empty() {
+            true => None,
+            false => {
+                let backup_domain = ctx.backup_domains.remove(0);
+                Some(Box::new(Self::from_domain(backup_domain, ctx.clone())))
+            }
+        };
+
+        Self { next, ..Self::single_domain(domain, ctx) }
+    }
+
+    /// The lookup of `domain` alone, ignoring the backup domains of the context
+    fn single_domain(domain: Domain, ctx: Context<C>) -> Self {
+        // an explicit transport (like a `transport` uri param) restricts the whole resolution
+        let (available_transports, available_protocols, secure, default_transport) =
+            match ctx.transport {
+                Some(transport) => (
+                    vec![transport],
+                    vec![transport.protocol()],
+                    ctx.secure || Transport::secure_transports().contains(&transport),
+                    transport,
+                ),
+                None => (
+                    ctx.available_transports(),
+                    ctx.available_protocols(),
+                    ctx.secure,
+                    match ctx.secure {
+                        true => Transport::default_secure_transport(),
+                        false => Transport::default_insecure_transport(),
+                    },
+                ),
+            };
+
+        // the A/AAAA fallback only targets the default transport, unless asked to synthesize a
+        // target for every available transport
+        let addr_transports = match ctx.synthesize_all_transports {
+            true => available_transports.clone(),
+            false => vec![default_transport],
         };
 
         let fallback_config = FallbackConfig {
             dns_client: ctx.dns_client.clone(),
             domain: domain.clone(),
-            available_protocols: ctx.available_protocols(),
-            secure: ctx.secure,
-            default_transport,
+            available_protocols,
+            secure,
+            addr_transports: addr_transports
+                .into_iter()
+                .map(|transport| (transport, ctx.default_port_for(transport)))
+                .collect(),
+            concurrent_srv: ctx.concurrent_srv_fallbacks,
+            policy: ctx.fallback_policy,
+            family: ctx.ip_family,
+            target_concurrency: ctx.concurrent_srv_targets,
         };
 
-        let naptr =
-            ResolvableNaptrRecord::new(ctx.dns_client.clone(), domain, ctx.available_transports());
+        if ctx.skip_naptr || ctx.fallback_policy == FallbackPolicy::SrvOnly {
+            debug_event!(domain = %domain, "skipping NAPTR, starting with SRV fallbacks");
+            return Self { state: fallback_config.srv_fallbacks(), peeked: None, next: None };
+        }
+
+        let mut naptr =
+            ResolvableNaptrRecord::new(ctx.dns_client.clone(), domain, available_transports)
+                .with_max_depth(ctx.max_naptr_depth)
+                .with_default_ports(ctx.default_ports.clone())
+                .with_family(ctx.ip_family)
+                .with_target_concurrency(ctx.concurrent_srv_targets)
+                .with_p_flag_hook(ctx.on_p_flag.clone())
+                .with_allowed_services(ctx.allowed_services.clone());
+        if ctx.supported_transports.is_ordered() {
+            naptr = naptr.with_transport_preference();
+        }
 
         Self {
-            state: JustDomainLookupState::TryingNaptr {
-                naptr,
-                fallback_config,
-            },
+            state: JustDomainLookupState::TryingNaptr { naptr, fallback_config, retried: false },
+            peeked: None,
+            next: None,
         }
     }
 }
 
-#[async_trait]
-impl<C> ResolvableExt<Target> for JustDomainLookup<C>
+impl<C> JustDomainLookup<C>
 where
     C: DnsClient,
 {
-    fn state(&self) -> ResolvableState {
-        match &self.state {
-            JustDomainLookupState::TryingNaptr { naptr, .. } => naptr.state(),
-            JustDomainLookupState::TryingSrvFallbacks { srv_lookups, current_index, .. } => {
-                srv_lookups.get(*current_index).map(|s| s.state()).unwrap_or(ResolvableState::Empty)
-            }
-            JustDomainLookupState::TryingAddrFallback { addr } => addr.state(),
-            JustDomainLookupState::Done => ResolvableState::Empty,
+    fn transition(&mut self, state: JustDomainLookupState<C>) {
+        debug_event!(from = self.state.name(), to = state.name(), "JustDomainLookup transition");
+        self.state = state;
+    }
+
+    /// The procedure behind the target that has just been returned
+    pub(crate) fn method(&self) -> Option<ResolutionMethod> {
+        match self.state {
+            JustDomainLookupState::TryingNaptr { .. } => Some(ResolutionMethod::Naptr),
+            JustDomainLookupState::TryingSrvFallbacks { .. } => Some(ResolutionMethod::Srv),
+            JustDomainLookupState::TryingAddrFallback { .. } => Some(ResolutionMethod::Addr),
+            JustDomainLookupState::Done => None,
         }
     }
 
-    async fn resolve_next(&mut self) -> Option<Target> {
+    /// The targets left in the current step of the flow, the peeked one included
+    async fn step_target_count(&mut self) -> usize {
+        let count = match &mut self.state {
+            JustDomainLookupState::TryingNaptr { naptr, .. } => naptr.target_count().await,
+            JustDomainLookupState::TryingSrvFallbacks {
+                srv_lookups,
+                current_index,
+                in_flight,
+                ..
+            } => {
+                if let Some(in_flight) = in_flight {
+                    while let Some(srv_lookup) = in_flight.next().await {
+                        srv_lookups.push(srv_lookup);
+                    }
+                }
+                let mut count = 0;
+                for srv_lookup in srv_lookups.iter_mut().skip(*current_index) {
+                    count += srv_lookup.target_count().await;
+                }
+                count
+            }
+            JustDomainLookupState::TryingAddrFallback { addr } => addr.target_count().await,
+            JustDomainLookupState::Done => 0,
+        };
+
+        usize::from(self.peeked.is_some()) + count
+    }
+
+    /// Next target of the current domain only
+    async fn resolve_next_of_domain(&mut self) -> Option<Target> {
         loop {
             match &mut self.state {
-                JustDomainLookupState::TryingNaptr { naptr, fallback_config } => {
-                    // Check state before calling resolve_next to see if initialized
-                    let state_before = naptr.state();
-
+                JustDomainLookupState::TryingNaptr { naptr, fallback_config, retried } => {
                     match naptr.resolve_next().await {
                         Some(target) => {
                             return Some(target);
                         }
                         None => {
-                            // NAPTR exhausted - check if it was initialized and produced results
-                            // If state was NonEmpty before this call, NAPTR had processed results
-                            // If state was Unset, NAPTR never initialized (failed)
-                            let state_after = naptr.state();
-
-                            // NAPTR succeeded if it transitioned from Unset to Empty/NonEmpty
-                            // or if it was NonEmpty (had results)
-                            let naptr_succeeded = !matches!(state_before, ResolvableState::Unset)
-                                || !matches!(state_after, ResolvableState::Unset);
-
-                            if naptr_succeeded && matches!(state_after, ResolvableState::Empty) {
-                                // NAPTR was processed (not Unset anymore) and is now exhausted
+                            let retry = !*retried
+                                && matches!(
+                                    naptr.outcome(),
+                                    Some(NaptrOutcome::Failed(error)) if error.is_transient()
+                                );
+
+                            if matches!(naptr.outcome(), Some(NaptrOutcome::Found)) {
+                                // NAPTR record found and all its targets are exhausted
                                 // This means it succeeded and we don't need fallbacks
-                                self.state = JustDomainLookupState::Done;
+                                self.transition(JustDomainLookupState::Done);
+                                return None;
+                            } else if retry {
+                                // The server failed rather than telling us there is no record,
+                                // ask once more before giving up on NAPTR
+                                debug_event!("retrying NAPTR query after a transient failure");
+                                naptr.reset();
+                                *retried = true;
+                            } else if fallback_config.policy == FallbackPolicy::NaptrOnly {
+                                // NAPTR failed and the policy doesn't allow any fallback
+                                self.transition(JustDomainLookupState::Done);
                                 return None;
                             } else {
-                                // NAPTR failed (still Unset) or never had records, try SRV fallbacks
-                                let srv_lookups: Vec<ResolvableSrvRecord<C>> = fallback_config
-                                    .available_protocols
-                                    .iter()
-                                    .map(|transport| {
-                                        let srv_domain = SrvDomain {
-                                            secure: fallback_config.secure,
-                                            protocol: transport.protocol(),
-                                            domain: fallback_config.domain.clone(),
-                                        };
-                                        ResolvableSrvRecord::new(
-                                            fallback_config.dns_client.clone(),
-                                            srv_domain,
-                                        )
-                                    })
-                                    .collect();
-
-                                let addr_fallback = ResolvableAddrRecord::new(
-                                    fallback_config.dns_client.clone(),
-                                    fallback_config.domain.clone(),
-                                    fallback_config.default_transport.default_port(),
-                                    fallback_config.default_transport,
-                                );
-
-                                self.state = JustDomainLookupState::TryingSrvFallbacks {
-                                    srv_lookups,
-                                    current_index: 0,
-                                    any_produced_results: false,
-                                    addr_fallback,
-                                };
+                                // No (usable) NAPTR record or NAPTR failed, try SRV fallbacks
+                                let srv_fallbacks = fallback_config.srv_fallbacks();
+                                self.transition(srv_fallbacks);
                                 // Continue to next iteration to try SRV fallbacks
                             }
                         }
@@ -189,8 +313,17 @@ where
                     srv_lookups,
                     current_index,
                     any_produced_results,
+                    in_flight,
                     addr_fallback,
                 } => {
+                    if *current_index == srv_lookups.len()
+                        && let Some(in_flight) = in_flight
+                        && let Some(srv_lookup) = in_flight.next().await
+                    {
+                        // The queries of the other transports are left in flight meanwhile
+                        srv_lookups.push(srv_lookup);
+                    }
+
                     // Try current SRV lookup
                     if let Some(current_srv) = srv_lookups.get_mut(*current_index) {
                         match current_srv.resolve_next().await {
@@ -208,13 +341,16 @@ where
                         // All SRV fallbacks exhausted
                         if *any_produced_results {
                             // At least one SRV worked, we're done
-                            self.state = JustDomainLookupState::Done;
+                            self.transition(JustDomainLookupState::Done);
                             return None;
-                        } else {
+                        } else if let Some(addr) = addr_fallback.clone() {
                             // All SRV failed, try A/AAAA fallback
-                            let addr = addr_fallback.clone();
-                            self.state = JustDomainLookupState::TryingAddrFallback { addr };
+                            self.transition(JustDomainLookupState::TryingAddrFallback { addr });
                             // Continue to next iteration to try A/AAAA
+                        } else {
+                            // All SRV failed and the policy doesn't allow the A/AAAA fallback
+                            self.transition(JustDomainLookupState::Done);
+                            return None;
                         }
                     }
                 }
@@ -224,7 +360,7 @@ where
                         Some(target) => return Some(target),
                         None => {
                             // A/AAAA exhausted, nothing left to try
-                            self.state = JustDomainLookupState::Done;
+                            self.transition(JustDomainLookupState::Done);
                             return None;
                         }
                     }
@@ -235,3 +371,197 @@ where
         }
     }
 }
+
+impl<C> JustDomainLookupState<C>
+where
+    C: DnsClient,
+{
+    #[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+    fn name(&self) -> &'static str {
+        match self {
+            Self::TryingNaptr { .. } => "TryingNaptr",
+            Self::TryingSrvFallbacks { .. } => "TryingSrvFallbacks",
+            Self::TryingAddrFallback { .. } => "TryingAddrFallback",
+            Self::Done => "Done",
+        }
+    }
+}
+
+impl<C> FallbackConfig<C>
+where
+    C: DnsClient,
+{
+    /// SRV lookups for each available protocol, followed by the A/AAAA fallback
+    fn srv_fallbacks(&self) -> JustDomainLookupState<C> {
+        let srv_lookups: Vec<ResolvableSrvRecord<C>> = self
+            .available_protocols
+            .iter()
+            .map(|transport| {
+                let srv_domain = match self.secure {
+                    true => SrvDomain::secure(self.domain.clone(), *transport),
+                    false => SrvDomain::insecure(self.domain.clone(), *transport),
+                };
+                ResolvableSrvRecord::new(self.dns_client.clone(), srv_domain)
+                    .with_family(self.family)
+                    .with_target_concurrency(self.target_concurrency)
+            })
+            .collect();
+
+        let addr_fallback = match self.policy {
+            FallbackPolicy::Full => Some(ResolvableVec::non_empty(
+                self.addr_transports
+                    .iter()
+                    .map(|(transport, port)| {
+                        ResolvableAddrRecord::new_with_family(
+                            self.dns_client.clone(),
+                            self.domain.clone(),
+                            *port,
+                            *transport,
+                            self.family,
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )),
+            _ => None,
+        };
+
+        // Fire all SRV queries at once when asked to, targets are still consumed in order
+        let (srv_lookups, in_flight) = match self.concurrent_srv {
+            true => (vec![], Some(InFlightSrvQueries::fire(srv_lookups))),
+            false => (srv_lookups, None),
+        };
+
+        JustDomainLookupState::TryingSrvFallbacks {
+            srv_lookups,
+            current_index: 0,
+            any_produced_results: false,
+            in_flight,
+            addr_fallback,
+        }
+    }
+}
+
+impl<C> InFlightSrvQueries<C>
+where
+    C: DnsClient,
+{
+    fn fire(srv_lookups: Vec<ResolvableSrvRecord<C>>) -> Self {
+        let queries = srv_lookups
+            .iter()
+            .cloned()
+            .map(|mut srv_lookup| {
+                async move {
+                    srv_lookup.prefetch().await;
+                    srv_lookup
+                }
+                .boxed()
+            })
+            .collect();
+
+        Self { pending: srv_lookups, queries: Mutex::new(queries) }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// The next lookup in transport order, once its query has completed
+    async fn next(&mut self) -> Option<ResolvableSrvRecord<C>> {
+        let queries = self.queries.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let srv_lookup = queries.next().await?;
+        self.pending.remove(0);
+        Some(srv_lookup)
+    }
+}
+
+/// A clone fires its own queries for the lookups not handed over yet
+impl<C> Clone for InFlightSrvQueries<C>
+where
+    C: DnsClient,
+{
+    fn clone(&self) -> Self {
+        Self::fire(self.pending.clone())
+    }
+}
+
+impl<C> std::fmt::Debug for InFlightSrvQueries<C>
+where
+    C: DnsClient + std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InFlightSrvQueries").field("pending", &self.pending).finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<C> ResolvableExt<Target> for JustDomainLookup<C>
+where
+    C: DnsClient,
+{
+    fn state(&self) -> ResolvableState {
+        if self.peeked.is_some() {
+            return ResolvableState::NonEmpty;
+        }
+
+        let state = match &self.state {
+            JustDomainLookupState::TryingNaptr { naptr, .. } => naptr.state(),
+            JustDomainLookupState::TryingSrvFallbacks {
+                srv_lookups,
+                current_index,
+                in_flight,
+                ..
+            } => match srv_lookups.get(*current_index) {
+                Some(srv_lookup) => srv_lookup.state(),
+                None if in_flight.as_ref().is_some_and(|in_flight| !in_flight.is_empty()) => {
+                    ResolvableState::Unset
+                }
+                None => ResolvableState::Empty,
+            },
+            JustDomainLookupState::TryingAddrFallback { addr } => addr.state(),
+            JustDomainLookupState::Done => ResolvableState::Empty,
+        };
+
+        match (state, &self.next) {
+            (ResolvableState::Empty, Some(next)) => next.state(),
+            (state, _) => state,
+        }
+    }
+
+    async fn resolve_next(&mut self) -> Option<Target> {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+
+        loop {
+            if let Some(target) = self.resolve_next_of_domain().await {
+                return Some(target);
+            }
+
+            // the domain is exhausted, move on to the next backup domain
+            match self.next.take() {
+                Some(next) => {
+                    debug_event!("domain exhausted, moving on to the next backup domain");
+                    *self = *next;
+                }
+                None => return None,
+            }
+        }
+    }
+
+    fn lookahead(&mut self) -> Option<&mut Option<Target>> {
+        Some(&mut self.peeked)
+    }
+
+    /// Counts the targets of the step of the flow the lookup is at (see
+    /// [ResolvableExt::target_count]). A step that yields nothing is moved past by peeking at
+    /// the next target, but the fallbacks and backup domains that come after a step yielding
+    /// targets are not counted.
+    async fn target_count(&mut self) -> usize {
+        let count = self.step_target_count().await;
+        if count > 0 || self.peek_next().await.is_none() {
+            return count;
+        }
+
+        self.step_target_count().await
+    }
+}
@@ -1,23 +1,350 @@
-use crate::{Context, DnsClient, Target, records::SrvDomain, resolvables::*};
+use crate::{
+    Context, DnsClient, Target,
+    records::{SrvDomain, SrvRecord},
+    resolvables::*,
+};
 use async_trait::async_trait;
+use futures::stream::{self, Stream};
 use rsip::{Domain, Host, Port, Transport};
-use std::net::IpAddr;
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+use tokio::time::Instant;
 
 mod just_domain_lookup;
-use just_domain_lookup::JustDomainLookup;
+mod resolution_report;
+mod resolution_tree;
+mod resolve_many;
+mod target_ranker;
+mod target_sort_policy;
+pub use just_domain_lookup::JustDomainLookup;
+pub use resolution_report::{CountingDnsClient, ResolutionMethod, ResolutionReport};
+pub use resolution_tree::{
+    AddrNode, NaptrBranch, NaptrChild, NaptrNode, ResolutionTree, SrvBranch, SrvNode,
+    resolution_tree,
+};
+pub use resolve_many::resolve_many;
+pub use target_ranker::{NoopRanker, RttRanker, TargetRanker};
+pub use target_sort_policy::{IpFamily, TargetSortPolicy};
 
+use resolution_report::QueryBudget;
+
+/// The entry point of the resolution: created out of a [Context], it yields the
+/// (ip, port, transport) tuples of the target through [ResolvableExt::resolve_next].
+///
+/// Which RFC 3263 procedure is followed depends on the context, hence the variant. Each one
+/// carries its state machine along with the [LookupMeta] of the lookup.
 #[derive(Debug, Clone)]
 pub enum Lookup<C>
 where
     C: DnsClient,
 {
-    IpAddr(ResolvableIpAddr),
-    DomainWithPort(ResolvableAddrRecord<C>),
+    IpAddr {
+        inner: ResolvableIpAddr,
+        meta: LookupMeta<C>,
+    },
+    DomainWithPort {
+        inner: ResolvableAddrRecord<CountingDnsClient<C>>,
+        meta: LookupMeta<C>,
+    },
     //This variant uses only the given transport as RFC says, but I have a feeling that we should
     //add an exhaustive variant that apart from the given transport, tries AddrRecords for the given
     //available transports.
-    DomainWithTransport(ResolvableVec<ResolvableEnum<C>, Target>),
-    JustDomain(JustDomainLookup<C>),
+    DomainWithTransport {
+        inner: ResolvableVec<ResolvableEnum<CountingDnsClient<C>>, Target>,
+        meta: LookupMeta<C>,
+    },
+    JustDomain {
+        inner: JustDomainLookup<CountingDnsClient<C>>,
+        meta: LookupMeta<C>,
+    },
+}
+
+/// What a [Lookup] keeps track of besides its state machine, whatever the procedure it follows
+#[derive(Debug, Clone)]
+pub struct LookupMeta<C>
+where
+    C: DnsClient,
+{
+    /// The context the lookup was created out of, around the counting dns client, from which
+    /// [Lookup::reset] builds the state machine again
+    ctx: Context<CountingDnsClient<C>>,
+    report: ResolutionReport,
+    /// The procedure behind the target returned by the latest resolution
+    last_method: Option<ResolutionMethod>,
+    /// Queries sent through the dns client, shared with every resolvable
+    queries: Arc<AtomicUsize>,
+    /// See [Context::query_budget], shared with the dns client of every resolvable
+    query_budget: Option<Arc<QueryBudget>>,
+    /// When the overall deadline elapses, set by the first resolution
+    deadline: Option<Instant>,
+    /// The SRV domain of the host and the transport of the context, `None` for an ip address
+    srv_domain: Option<SrvDomain>,
+    /// Every target of the lookup, IPv6 ones first, along with the procedure behind each, once
+    /// drained out of the state machine for [Context::prefer_ipv6]
+    reordered: Option<VecDeque<(Target, Option<ResolutionMethod>)>>,
+    /// The procedure behind the latest target taken out of `reordered`
+    reordered_method: Option<ResolutionMethod>,
+    /// Kept apart from the state machine, which `reordered` may have drained already
+    peeked: Option<Target>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl<C> Lookup<C>
+where
+    C: DnsClient,
+{
+    /// Starts the resolution over, as if the lookup had just been created out of its [Context]:
+    /// the next [resolve_next](ResolvableExt::resolve_next) issues the DNS queries again and
+    /// yields the targets from the top.
+    ///
+    /// Nothing is cached between runs, so every query is paid for again, which also means any
+    /// record that changed (or whose TTL was refreshed) in the meantime is picked up. The
+    /// overall deadline and the query budget, if any, start over too.
+    pub fn reset(&mut self) {
+        debug_event!("lookup reset");
+        let mut meta = self.meta().clone();
+        meta.deadline = None;
+        meta.last_method = None;
+        meta.reordered = None;
+        meta.reordered_method = None;
+        meta.peeked = None;
+        if let Some(query_budget) = &meta.query_budget {
+            query_budget.reset();
+        }
+
+        *self = Self::assemble(meta);
+    }
+
+    /// Drains the lookup and returns all the remaining targets sorted by the given policy, then
+    /// ranked by the [TargetRanker] of the context.
+    ///
+    /// Unlike [resolve_next](ResolvableExt::resolve_next), every DNS query the resolution may
+    /// need is performed before anything is returned: this trades the latency to the first
+    /// target for a globally ordered candidate set.
+    pub async fn resolve_all_sorted(&mut self, policy: TargetSortPolicy) -> Vec<Target> {
+        let mut targets = vec![];
+        while let Some(target) = self.resolve_next().await {
+            targets.push(target);
+        }
+
+        self.meta().ctx.target_ranker.rank(policy.sort(targets))
+    }
+
+    /// The remaining targets as a stream of socket addresses, for APIs that connect to a
+    /// [SocketAddr] directly. Each target is resolved lazily as the stream is polled, exactly
+    /// like [resolve_next](ResolvableExt::resolve_next), and its transport is left out.
+    pub fn socket_addrs(&mut self) -> impl Stream<Item = SocketAddr> + Send + '_ {
+        stream::unfold(self, |lookup| async move {
+            let target = lookup.resolve_next().await?;
+            Some((target.socket_addr(), lookup))
+        })
+    }
+
+    /// Drives the RFC 3263 "try the next target on failure" loop: each target is handed to `f`
+    /// (typically a connection attempt) as it is resolved, until one succeeds. Returns the
+    /// first `Ok`, or the errors of every failed attempt, in order, once the lookup is
+    /// exhausted (an empty `Vec` meaning there was no target at all).
+    ///
+    /// The lookup is left where the successful attempt stopped, so that calling it again
+    /// carries on with the following targets.
+    pub async fn resolve_until<F, Fut, T, E>(&mut self, mut f: F) -> Result<T, Vec<E>>
+    where
+        F: FnMut(Target) -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut errors = vec![];
+        while let Some(target) = self.resolve_next().await {
+            match f(target).await {
+                Ok(value) => return Ok(value),
+                Err(error) => {
+                    debug_event!("attempt on target failed, trying the next one");
+                    errors.push(error)
+                }
+            }
+        }
+
+        Err(errors)
+    }
+
+    /// Performs the SRV query of the host, under the transport of the context (or its default
+    /// one), and returns the record as is, without resolving any of its targets: meant for
+    /// inspecting priorities, weights and targets, e.g. from monitoring tools.
+    ///
+    /// It is independent of the resolution: no target is consumed, and the query is sent every
+    /// time, without being counted in the [ResolutionReport] nor charged to the
+    /// [query budget](Context::query_budget). `None` when the host is an ip address, when there
+    /// is no such record or when the query fails.
+    pub async fn srv_only(&self) -> Option<SrvRecord> {
+        let srv_domain = self.meta().srv_domain.clone()?;
+        debug_event!(domain = %srv_domain, "SRV only query");
+        self.meta().ctx.dns_client.inner().try_srv_lookup(srv_domain).await.ok().flatten()
+    }
+
+    /// The RFC 3263 procedure (NAPTR, SRV, A/AAAA fallback...) that produced the target returned
+    /// by the latest [resolve_next](ResolvableExt::resolve_next), `None` before the first target
+    /// and once the lookup is exhausted.
+    pub fn last_resolution_method(&self) -> Option<ResolutionMethod> {
+        self.meta().last_method
+    }
+
+    /// Returns what the lookup went through to resolve the targets returned so far (see
+    /// [ResolutionReport]) and starts a new report. It covers everything since the lookup was
+    /// created or the previous report was taken, [resets](Lookup::reset) included.
+    pub fn take_report(&mut self) -> ResolutionReport {
+        let meta = self.meta_mut();
+        ResolutionReport {
+            queries: meta.queries.swap(0, Ordering::Relaxed),
+            ..std::mem::take(&mut meta.report)
+        }
+    }
+
+    fn meta(&self) -> &LookupMeta<C> {
+        match self {
+            Self::IpAddr { meta, .. }
+            | Self::DomainWithPort { meta, .. }
+            | Self::DomainWithTransport { meta, .. }
+            | Self::JustDomain { meta, .. } => meta,
+        }
+    }
+
+    fn meta_mut(&mut self) -> &mut LookupMeta<C> {
+        match self {
+            Self::IpAddr { meta, .. }
+            | Self::DomainWithPort { meta, .. }
+            | Self::DomainWithTransport { meta, .. }
+            | Self::JustDomain { meta, .. } => meta,
+        }
+    }
+
+    /// The state machine of the procedure the context of `meta` calls for, from the top
+    fn assemble(meta: LookupMeta<C>) -> Self {
+        let ctx = meta.ctx.clone();
+        match ctx.host {
+            Host::IpAddr(ip_addr) => ip_addr_lookup(ip_addr, ctx, meta),
+            Host::Domain(ref domain) => match (ctx.port, ctx.transport) {
+                (Some(port), _) => domain_with_port_lookup(domain.clone(), port, ctx, meta),
+                (None, Some(transport)) => {
+                    domain_with_transport_lookup(domain.clone(), transport, ctx, meta)
+                }
+                (None, None) => just_domain_lookup(domain.clone(), ctx, meta),
+            },
+        }
+    }
+
+    /// The next target, all the IPv6 ones first when [Context::prefer_ipv6] is set: the whole
+    /// state machine is then drained on the first call, and partitioned by family
+    async fn resolve_reordered(&mut self) -> Option<Target> {
+        if let Some(peeked) = self.meta_mut().peeked.take() {
+            return Some(peeked);
+        }
+        if !self.meta().ctx.prefer_ipv6 {
+            return self.resolve_within_budget().await;
+        }
+
+        self.drain_reordered().await;
+        let meta = self.meta_mut();
+        let (target, method) = meta.reordered.as_mut()?.pop_front()?;
+        meta.reordered_method = method;
+
+        Some(target)
+    }
+
+    async fn drain_reordered(&mut self) {
+        if self.meta().reordered.is_some() {
+            return;
+        }
+
+        let mut targets = vec![];
+        while let Some(target) = self.resolve_within_budget().await {
+            let method = self.method();
+            targets.push((target, method));
+        }
+        debug_event!(targets = targets.len(), "lookup drained, IPv6 targets first");
+        let (v6, v4): (Vec<_>, Vec<_>) =
+            targets.into_iter().partition(|(target, _)| IpFamily::V6.contains(&target.ip_addr));
+        self.meta_mut().reordered = Some(v6.into_iter().chain(v4).collect());
+    }
+
+    /// The next target of the state machine, or `None` once the query budget is exhausted, even
+    /// if the target didn't need any query
+    async fn resolve_within_budget(&mut self) -> Option<Target> {
+        let target = self.resolve_within_deadline().await;
+        let query_budget = self.meta().query_budget.as_ref();
+        match query_budget.is_some_and(|query_budget| query_budget.is_exhausted()) {
+            true => {
+                debug_event!("lookup query budget exhausted");
+                None
+            }
+            false => target,
+        }
+    }
+
+    /// The next target of the state machine, or `None` once the overall deadline has elapsed,
+    /// even if the resolution was still waiting for a query
+    async fn resolve_within_deadline(&mut self) -> Option<Target> {
+        let meta = self.meta_mut();
+        let Some(overall_deadline) = meta.ctx.overall_deadline else {
+            return self.resolve_inner().await;
+        };
+
+        let deadline = *meta.deadline.get_or_insert_with(|| Instant::now() + overall_deadline);
+        let target = match Instant::now() < deadline {
+            true => tokio::time::timeout_at(deadline, self.resolve_inner()).await.ok(),
+            false => None,
+        };
+
+        target.unwrap_or_else(|| {
+            debug_event!("lookup deadline elapsed");
+            None
+        })
+    }
+
+    async fn resolve_inner(&mut self) -> Option<Target> {
+        match self {
+            Self::IpAddr { inner, .. } => inner.resolve_next().await,
+            Self::DomainWithPort { inner, .. } => inner.resolve_next().await,
+            Self::DomainWithTransport { inner, .. } => inner.resolve_next().await,
+            Self::JustDomain { inner, .. } => inner.resolve_next().await,
+        }
+    }
+
+    /// Whether the next target has already been resolved by a peek, hence accounted for
+    fn has_peeked(&self) -> bool {
+        self.meta().peeked.is_some()
+    }
+
+    fn record(&mut self, target: Option<&Target>, peeked: bool) {
+        if let (Some(target), false) = (target, peeked) {
+            let method = self.method();
+            self.meta_mut().report.record(method, target.transport, target.ttl);
+        }
+    }
+
+    /// The procedure behind the target that has just been returned
+    fn method(&self) -> Option<ResolutionMethod> {
+        if self.meta().reordered.is_some() {
+            return self.meta().reordered_method;
+        }
+
+        match self {
+            Self::IpAddr { .. } => Some(ResolutionMethod::IpAddr),
+            Self::DomainWithPort { .. } => Some(ResolutionMethod::Addr),
+            Self::DomainWithTransport { inner, .. } => {
+                inner.front().map(|resolvable| match resolvable {
+                    ResolvableEnum::IpAddr(_) => ResolutionMethod::IpAddr,
+                    ResolvableEnum::AddrRecord(_) => ResolutionMethod::Addr,
+                    ResolvableEnum::SrvRecord(_) => ResolutionMethod::Srv,
+                    ResolvableEnum::NaptrRecord(_) => ResolutionMethod::Naptr,
+                })
+            }
+            Self::JustDomain { inner, .. } => inner.method(),
+        }
+    }
 }
 
 #[async_trait]
@@ -26,21 +353,82 @@ where
     C: DnsClient,
 {
     fn state(&self) -> ResolvableState {
+        if self.has_peeked()
+            || self.meta().reordered.as_ref().is_some_and(|reordered| !reordered.is_empty())
+        {
+            return ResolvableState::NonEmpty;
+        }
+
         match self {
-            Self::IpAddr(inner) => inner.state(),
-            Self::DomainWithPort(inner) => inner.state(),
-            Self::DomainWithTransport(inner) => inner.state(),
-            Self::JustDomain(inner) => inner.state(),
+            Self::IpAddr { inner, .. } => inner.state(),
+            Self::DomainWithPort { inner, .. } => inner.state(),
+            Self::DomainWithTransport { inner, .. } => inner.state(),
+            Self::JustDomain { inner, .. } => inner.state(),
         }
     }
 
+    #[cfg(not(feature = "tracing"))]
     async fn resolve_next(&mut self) -> Option<Target> {
-        match self {
-            Self::IpAddr(inner) => inner.resolve_next().await,
-            Self::DomainWithPort(inner) => inner.resolve_next().await,
-            Self::DomainWithTransport(inner) => inner.resolve_next().await,
-            Self::JustDomain(inner) => inner.resolve_next().await,
+        let peeked = self.has_peeked();
+        let target = self.resolve_reordered().await;
+        self.record(target.as_ref(), peeked);
+        let method = target.as_ref().and_then(|_| self.method());
+        self.meta_mut().last_method = method;
+
+        target
+    }
+
+    #[cfg(feature = "tracing")]
+    async fn resolve_next(&mut self) -> Option<Target> {
+        use tracing::Instrument;
+
+        let peeked = self.has_peeked();
+        let span = self.meta().span.clone();
+        let target = self.resolve_reordered().instrument(span.clone()).await;
+        self.record(target.as_ref(), peeked);
+        let method = target.as_ref().and_then(|_| self.method());
+        self.meta_mut().last_method = method;
+        span.in_scope(|| match &target {
+            Some(target) => tracing::debug!(
+                ip_addr = %target.ip_addr,
+                port = %target.port,
+                transport = %target.transport,
+                ttl = target.ttl,
+                "resolved next target"
+            ),
+            None => tracing::debug!("lookup exhausted"),
+        });
+
+        target
+    }
+
+    fn lookahead(&mut self) -> Option<&mut Option<Target>> {
+        Some(&mut self.meta_mut().peeked)
+    }
+
+    async fn target_count(&mut self) -> usize {
+        if self.meta().ctx.prefer_ipv6 {
+            self.drain_reordered().await;
+            let peeked = self.has_peeked();
+            return self.meta().reordered.as_ref().map_or(0, VecDeque::len) + usize::from(peeked);
         }
+
+        let peeked = self.has_peeked();
+        let (count, inner_peeked) = match self {
+            Self::IpAddr { inner, .. } => (inner.target_count().await, inner.lookahead()),
+            Self::DomainWithPort { inner, .. } => (inner.target_count().await, inner.lookahead()),
+            Self::DomainWithTransport { inner, .. } => {
+                (inner.target_count().await, inner.lookahead())
+            }
+            Self::JustDomain { inner, .. } => (inner.target_count().await, inner.lookahead()),
+        };
+        // counting might have peeked at the next target, which is accounted for like any peek
+        if !peeked && let Some(target) = inner_peeked.and_then(Option::take) {
+            self.record(Some(&target), false);
+            self.meta_mut().peeked = Some(target);
+        }
+
+        usize::from(peeked) + count
     }
 }
 
@@ -49,60 +437,120 @@ where
     C: DnsClient,
 {
     fn from(ctx: Context<C>) -> Self {
-        match ctx.host {
-            Host::IpAddr(ip_addr) => ip_addr_lookup(ip_addr, ctx),
-            Host::Domain(ref domain) => match (ctx.port, ctx.transport) {
-                (Some(port), _) => domain_with_port_lookup(domain.clone(), port, ctx),
-                (None, Some(transport)) => {
-                    domain_with_transport_lookup(domain.clone(), transport, ctx)
-                }
-                (None, None) => just_domain_lookup(domain.clone(), ctx),
-            },
-        }
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "lookup",
+            host = %ctx.host,
+            port = ?ctx.port,
+            transport = ?ctx.transport,
+            secure = ctx.secure,
+        );
+
+        let queries = Arc::new(AtomicUsize::new(0));
+        let query_budget = ctx.query_budget.map(|budget| Arc::new(QueryBudget::new(budget)));
+        let dns_client =
+            CountingDnsClient::new(ctx.dns_client.clone(), queries.clone(), query_budget.clone());
+        let srv_domain = match ctx.host {
+            Host::IpAddr(_) => None,
+            Host::Domain(ref domain) => {
+                Some(srv_domain_of(domain.clone(), ctx.default_transport(), ctx.secure))
+            }
+        };
+
+        Self::assemble(LookupMeta {
+            ctx: ctx.with_dns_client(dns_client),
+            report: Default::default(),
+            last_method: None,
+            queries,
+            query_budget,
+            deadline: None,
+            srv_domain,
+            reordered: None,
+            reordered_method: None,
+            peeked: None,
+            #[cfg(feature = "tracing")]
+            span,
+        })
     }
 }
 
-fn ip_addr_lookup<C: DnsClient>(ip_addr: IpAddr, ctx: Context<C>) -> Lookup<C> {
-    Lookup::IpAddr(ResolvableIpAddr::new(
-        ip_addr,
-        ctx.default_transport().default_port(),
-        ctx.default_transport(),
-    ))
+/// An ip address needs no query at all: the single target uses the given port and transport,
+/// falling back to the defaults of the scheme, and the default TTL and scope of the context
+fn ip_addr_lookup<C: DnsClient>(
+    ip_addr: IpAddr,
+    ctx: Context<CountingDnsClient<C>>,
+    meta: LookupMeta<C>,
+) -> Lookup<C> {
+    let transport = ctx.default_transport();
+    let port = ctx.port.unwrap_or_else(|| ctx.default_port_for(transport));
+
+    let inner = ResolvableIpAddr::new_with_ttl(ip_addr, port, transport, ctx.default_ttl)
+        .with_scope_id(ctx.scope_id);
+
+    Lookup::IpAddr { inner, meta }
 }
 
-fn domain_with_port_lookup<C: DnsClient>(domain: Domain, port: Port, ctx: Context<C>) -> Lookup<C> {
-    Lookup::DomainWithPort(ResolvableAddrRecord::new(
+fn domain_with_port_lookup<C: DnsClient>(
+    domain: Domain,
+    port: Port,
+    ctx: Context<CountingDnsClient<C>>,
+    meta: LookupMeta<C>,
+) -> Lookup<C> {
+    let inner = ResolvableAddrRecord::new_with_family(
         ctx.dns_client.clone(),
         domain,
         port,
         ctx.default_transport(),
-    ))
+        ctx.ip_family,
+    );
+
+    Lookup::DomainWithPort { inner, meta }
+}
+
+/// RFC 3263 section 4.1: a secure transport (like TLS) is looked up under the `_sips` service
+fn srv_domain_of(domain: Domain, transport: Transport, secure: bool) -> SrvDomain {
+    match secure {
+        true => SrvDomain::secure(domain, transport),
+        false => SrvDomain::new(domain, transport),
+    }
 }
 
 fn domain_with_transport_lookup<C: DnsClient>(
     domain: Domain,
     transport: Transport,
-    ctx: Context<C>,
+    ctx: Context<CountingDnsClient<C>>,
+    meta: LookupMeta<C>,
 ) -> Lookup<C> {
-    let mut lookups: Vec<ResolvableEnum<C>> = vec![];
+    let mut lookups: Vec<ResolvableEnum<CountingDnsClient<C>>> = vec![];
 
-    let srv_domain = SrvDomain { secure: ctx.secure, protocol: transport.protocol(), domain };
-    lookups.push(ResolvableSrvRecord::new(ctx.dns_client.clone(), srv_domain.clone()).into());
+    let srv_domain = srv_domain_of(domain, transport, ctx.secure);
+    let default_port = ctx.default_port_for(srv_domain.transport());
     lookups.push(
-        ResolvableAddrRecord::new(
+        ResolvableSrvRecord::new(ctx.dns_client.clone(), srv_domain.clone())
+            .with_family(ctx.ip_family)
+            .with_target_concurrency(ctx.concurrent_srv_targets)
+            .into(),
+    );
+    lookups.push(
+        ResolvableAddrRecord::new_with_family(
             ctx.dns_client,
             srv_domain.domain.clone(),
-            srv_domain.transport().default_port(),
+            default_port,
             srv_domain.transport(),
+            ctx.ip_family,
         )
         .into(),
     );
 
-    Lookup::DomainWithTransport(ResolvableVec::non_empty(lookups))
+    Lookup::DomainWithTransport { inner: ResolvableVec::non_empty(lookups), meta }
 }
 
-fn just_domain_lookup<C: DnsClient>(_domain: Domain, ctx: Context<C>) -> Lookup<C> {
-    Lookup::JustDomain(JustDomainLookup::new(ctx))
+fn just_domain_lookup<C: DnsClient>(
+    domain: Domain,
+    ctx: Context<CountingDnsClient<C>>,
+    meta: LookupMeta<C>,
+) -> Lookup<C> {
+    Lookup::JustDomain { inner: JustDomainLookup::from_domain(domain, ctx), meta }
 }
 
 /*
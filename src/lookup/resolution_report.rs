@@ -0,0 +1,175 @@
+use crate::records::{AddrRecord, NaptrRecord, SrvDomain, SrvRecord};
+use crate::{DnsClient, DnsError};
+#[cfg(feature = "hickory-dns")]
+use hickory_proto::rr::{RData, RecordType};
+use rsip::{Domain, Error, Transport};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+};
+
+/// What a [Lookup](super::Lookup) went through to resolve the targets it returned, see
+/// [Lookup::take_report](super::Lookup::take_report).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResolutionReport {
+    /// The procedure the last returned target came from, `None` when nothing was returned
+    pub method: Option<ResolutionMethod>,
+    /// How many DNS queries were sent, A/AAAA lookups counting as one
+    pub queries: usize,
+    /// The transport of the last returned target, the one a caller that stops at the first
+    /// target it can reach ends up with
+    pub transport: Option<Transport>,
+    /// The TTL of every returned target, in order
+    pub ttls: Vec<u32>,
+}
+
+/// The RFC 3263 procedure a target was resolved through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolutionMethod {
+    /// A NAPTR record (and the SRV and A/AAAA records it points to)
+    Naptr,
+    /// A SRV record (and the A/AAAA records of its targets)
+    Srv,
+    /// The A/AAAA records of the domain itself
+    Addr,
+    /// An ip address host, that needs no query at all
+    IpAddr,
+}
+
+impl ResolutionReport {
+    /// Accounts for a target that has just been returned
+    pub(crate) fn record(
+        &mut self,
+        method: Option<ResolutionMethod>,
+        transport: Transport,
+        ttl: u32,
+    ) {
+        self.method = method;
+        self.transport = Some(transport);
+        self.ttls.push(ttl);
+    }
+}
+
+/// What is left of the [query budget](crate::Context::query_budget) of a
+/// [Lookup](super::Lookup), shared by every clone of its dns client
+#[derive(Debug)]
+pub(crate) struct QueryBudget {
+    budget: u32,
+    spent: AtomicU32,
+    /// Set once a query has been turned down, which is when the lookup stops
+    exhausted: AtomicBool,
+}
+
+impl QueryBudget {
+    pub(crate) fn new(budget: u32) -> Self {
+        Self { budget, spent: AtomicU32::new(0), exhausted: AtomicBool::new(false) }
+    }
+
+    /// Takes a query out of the budget, `false` (exhausting it) when nothing is left
+    fn take(&self) -> bool {
+        let taken = self
+            .spent
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |spent| {
+                (spent < self.budget).then_some(spent + 1)
+            })
+            .is_ok();
+        if !taken {
+            self.exhausted.store(true, Ordering::Relaxed);
+        }
+        taken
+    }
+
+    pub(crate) fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Gives the whole budget back, for a lookup that starts over
+    pub(crate) fn reset(&self) {
+        self.spent.store(0, Ordering::Relaxed);
+        self.exhausted.store(false, Ordering::Relaxed);
+    }
+}
+
+/// Wraps the dns client of a [Lookup](super::Lookup) to count the queries sent through it.
+///
+/// With a [QueryBudget], a query that doesn't fit in it is not sent at all and answers as if
+/// nothing was found.
+#[derive(Debug, Clone)]
+pub struct CountingDnsClient<C: DnsClient> {
+    inner: C,
+    queries: Arc<AtomicUsize>,
+    budget: Option<Arc<QueryBudget>>,
+}
+
+impl<C: DnsClient> CountingDnsClient<C> {
+    pub(crate) fn new(
+        inner: C,
+        queries: Arc<AtomicUsize>,
+        budget: Option<Arc<QueryBudget>>,
+    ) -> Self {
+        Self { inner, queries, budget }
+    }
+
+    /// The wrapped dns client, whose queries are neither counted nor charged to the budget
+    pub(crate) fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Whether the query can be sent, accounting for it if so
+    fn count(&self) -> bool {
+        if let Some(budget) = &self.budget
+            && !budget.take()
+        {
+            return false;
+        }
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+const BUDGET_EXHAUSTED: &str = "DNS query budget exhausted";
+
+impl<C: DnsClient> DnsClient for CountingDnsClient<C> {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        match self.count() {
+            true => self.inner.naptr_lookup(domain).await,
+            false => None,
+        }
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        match self.count() {
+            true => self.inner.srv_lookup(domain).await,
+            false => None,
+        }
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        match self.count() {
+            true => self.inner.ip_lookup(domain).await,
+            false => Err(Error::Unexpected(BUDGET_EXHAUSTED.into())),
+        }
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        match self.count() {
+            true => self.inner.try_naptr_lookup(domain).await,
+            false => Err(DnsError::Other(BUDGET_EXHAUSTED.into())),
+        }
+    }
+
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        match self.count() {
+            true => self.inner.try_srv_lookup(domain).await,
+            false => Err(DnsError::Other(BUDGET_EXHAUSTED.into())),
+        }
+    }
+
+    #[cfg(feature = "hickory-dns")]
+    async fn raw_lookup(&self, name: Domain, rtype: RecordType) -> Result<Vec<RData>, DnsError> {
+        match self.count() {
+            true => self.inner.raw_lookup(name, rtype).await,
+            false => Err(DnsError::Other(BUDGET_EXHAUSTED.into())),
+        }
+    }
+}
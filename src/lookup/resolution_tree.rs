@@ -0,0 +1,245 @@
+use super::srv_domain_of;
+use crate::records::{
+    AddrRecord, NaptrEntry, NaptrFlags, NaptrRecord, NaptrServices, SrvDomain, SrvEntry, SrvRecord,
+    domain_key,
+};
+use crate::resolvables::is_non_terminal;
+use crate::{Context, DnsClient};
+use rsip::{Domain, Host, Transport};
+use std::net::IpAddr;
+use std::pin::Pin;
+
+/// The records the host of a [Context] resolves through, from NAPTR to SRV to A/AAAA, see
+/// [resolution_tree]. A record that wasn't found shows up without any children.
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionTree {
+    /// The NAPTR record of the host, when it was queried
+    pub naptr: Option<NaptrNode>,
+    /// The SRV records of the host that were queried, in transport preference order
+    pub srvs: Vec<SrvNode>,
+    /// The A/AAAA records of the host itself, when they were queried
+    pub addrs: Option<AddrNode>,
+}
+
+/// A NAPTR record and what each of its entries expands into
+#[derive(Debug, Clone)]
+pub struct NaptrNode {
+    pub domain: Domain,
+    pub ttl: u32,
+    pub branches: Vec<NaptrBranch>,
+}
+
+/// A NAPTR entry, along with the record it points to
+#[derive(Debug, Clone)]
+pub struct NaptrBranch {
+    pub entry: NaptrEntry,
+    /// `None` when the entry isn't followed: its service has no available (or allowed)
+    /// transport, its flag isn't supported, or it would exceed the NAPTR depth or loop
+    pub child: Option<NaptrChild>,
+}
+
+/// What a NAPTR entry points to, depending on its flag
+#[derive(Debug, Clone)]
+pub enum NaptrChild {
+    /// A NAPTR record (empty flag), the rewrite goes on there
+    Naptr(Box<NaptrNode>),
+    /// A SRV record (`S` flag)
+    Srv(SrvNode),
+    /// The A/AAAA records of a host (`A` flag)
+    Addr(AddrNode),
+}
+
+/// A SRV record and the A/AAAA records of each of its targets
+#[derive(Debug, Clone)]
+pub struct SrvNode {
+    pub domain: SrvDomain,
+    pub ttl: u32,
+    pub branches: Vec<SrvBranch>,
+}
+
+/// A SRV entry, along with the A/AAAA records of its target
+#[derive(Debug, Clone)]
+pub struct SrvBranch {
+    pub entry: SrvEntry,
+    pub addrs: AddrNode,
+}
+
+/// The A/AAAA records of a host
+#[derive(Debug, Clone)]
+pub struct AddrNode {
+    pub domain: Domain,
+    pub ip_addrs: Vec<IpAddr>,
+    pub ttl: u32,
+}
+
+/// Resolves the host of `ctx` into the [ResolutionTree] of the records it goes through, e.g. for
+/// a `dig`-like `--explain` output, where a [Lookup](super::Lookup) only yields the targets.
+///
+/// It takes the RFC 3263 steps of a lookup with the [full](crate::FallbackPolicy::Full) fallback
+/// policy, following NAPTR entries the same way, but expands every branch upfront, including
+/// those a lookup would never get to. Records of the ADDITIONAL section are used as they came.
+/// An ip address host needs no query at all, its tree is empty.
+pub async fn resolution_tree<C: DnsClient>(ctx: &Context<C>) -> ResolutionTree {
+    let mut tree = ResolutionTree::default();
+    let Host::Domain(domain) = &ctx.host else {
+        return tree;
+    };
+    let resolver = TreeResolver {
+        dns_client: ctx.dns_client.clone(),
+        available_transports: ctx.available_transports(),
+        allowed_services: ctx.allowed_services.clone(),
+    };
+
+    match (ctx.port, ctx.transport) {
+        (Some(_), _) => {}
+        (None, Some(transport)) => {
+            let srv_domain = srv_domain_of(domain.clone(), transport, ctx.secure);
+            tree.srvs.push(resolver.srv_node(srv_domain, None).await);
+        }
+        (None, None) => {
+            if !ctx.skip_naptr {
+                let naptr = resolver.naptr_node(domain.clone(), ctx.max_naptr_depth, vec![]).await;
+                let followed = naptr.branches.iter().any(|branch| branch.child.is_some());
+                tree.naptr = Some(naptr);
+                if followed {
+                    return tree;
+                }
+            }
+
+            for transport in resolver.available_transports.clone() {
+                let srv_domain = srv_domain_of(domain.clone(), transport, ctx.secure);
+                tree.srvs.push(resolver.srv_node(srv_domain, None).await);
+            }
+            if tree.srvs.iter().any(|srv| !srv.branches.is_empty()) {
+                return tree;
+            }
+        }
+    }
+
+    tree.addrs = Some(resolver.addr_node(domain.clone(), None).await);
+    tree
+}
+
+/// Holds what the expansion needs out of the context, without borrowing it across queries
+struct TreeResolver<C: DnsClient> {
+    dns_client: C,
+    available_transports: Vec<Transport>,
+    allowed_services: Option<Vec<NaptrServices>>,
+}
+
+impl<C: DnsClient> TreeResolver<C> {
+    /// Boxed, as NAPTR records may point to NAPTR records
+    fn naptr_node(
+        &self,
+        domain: Domain,
+        depth: usize,
+        visited: Vec<Domain>,
+    ) -> Pin<Box<dyn Future<Output = NaptrNode> + Send + '_>> {
+        Box::pin(async move {
+            let Some(naptr_record) = self.dns_client.naptr_lookup(domain.clone()).await else {
+                return NaptrNode { domain, ttl: 0, branches: vec![] };
+            };
+
+            let mut branches = vec![];
+            for entry in naptr_record.iter() {
+                let child = self.naptr_child(&naptr_record, entry, depth, &visited).await;
+                branches.push(NaptrBranch { entry: entry.clone(), child });
+            }
+
+            NaptrNode { domain, ttl: naptr_record.ttl, branches }
+        })
+    }
+
+    async fn naptr_child(
+        &self,
+        naptr_record: &NaptrRecord,
+        entry: &NaptrEntry,
+        depth: usize,
+        visited: &[Domain],
+    ) -> Option<NaptrChild> {
+        if !self.allowed_services.as_ref().is_none_or(|allowed| allowed.contains(&entry.services)) {
+            return None;
+        }
+        let transport = entry.services.transport()?;
+        if !self.available_transports.contains(&transport) {
+            return None;
+        }
+
+        if is_non_terminal(&entry.flags) {
+            let mut visited = visited.to_vec();
+            visited.push(naptr_record.domain.clone());
+            if depth == 0
+                || visited.iter().any(|domain| domain_key(domain) == domain_key(&entry.replacement))
+            {
+                return None;
+            }
+            let naptr = self.naptr_node(entry.replacement.clone(), depth - 1, visited).await;
+            return Some(NaptrChild::Naptr(Box::new(naptr)));
+        }
+
+        match entry.flags {
+            NaptrFlags::A => {
+                let addrs = self.addr_node(entry.replacement.clone(), Some(naptr_record)).await;
+                Some(NaptrChild::Addr(addrs))
+            }
+            NaptrFlags::S => {
+                let srv_domain = SrvDomain::try_from(entry.clone()).ok()?;
+                let srv = match naptr_record.get_additional_srv(&srv_domain) {
+                    Some(srv_record) => self.srv_node_from(srv_record, Some(naptr_record)).await,
+                    None => self.srv_node(srv_domain, Some(naptr_record)).await,
+                };
+                Some(NaptrChild::Srv(srv))
+            }
+            _ => None,
+        }
+    }
+
+    /// The targets also take their addresses from the ADDITIONAL section of `naptr_record`
+    async fn srv_node(&self, srv_domain: SrvDomain, naptr_record: Option<&NaptrRecord>) -> SrvNode {
+        match self.dns_client.srv_lookup(srv_domain.clone()).await {
+            Some(srv_record) => self.srv_node_from(&srv_record, naptr_record).await,
+            None => SrvNode { domain: srv_domain, ttl: 0, branches: vec![] },
+        }
+    }
+
+    async fn srv_node_from(
+        &self,
+        srv_record: &SrvRecord,
+        naptr_record: Option<&NaptrRecord>,
+    ) -> SrvNode {
+        let mut branches = vec![];
+        for entry in srv_record.entries.iter() {
+            let addrs = match srv_record.get_additional_for_target(&entry.target) {
+                Some(addr_record) => AddrNode::from(addr_record),
+                None => self.addr_node(entry.target.clone(), naptr_record).await,
+            };
+            branches.push(SrvBranch { entry: entry.clone(), addrs });
+        }
+
+        SrvNode { domain: srv_record.domain.clone(), ttl: srv_record.ttl, branches }
+    }
+
+    /// The A/AAAA records of `domain`, out of the ADDITIONAL section of `naptr_record` when there
+    async fn addr_node(&self, domain: Domain, naptr_record: Option<&NaptrRecord>) -> AddrNode {
+        if let Some(addr_record) =
+            naptr_record.and_then(|naptr_record| naptr_record.get_additional_for_target(&domain))
+        {
+            return AddrNode::from(addr_record);
+        }
+
+        match self.dns_client.ip_lookup(domain.clone()).await {
+            Ok(addr_record) => AddrNode::from(&addr_record),
+            Err(_) => AddrNode { domain, ip_addrs: vec![], ttl: 0 },
+        }
+    }
+}
+
+impl From<&AddrRecord> for AddrNode {
+    fn from(addr_record: &AddrRecord) -> Self {
+        Self {
+            domain: addr_record.domain.clone(),
+            ip_addrs: addr_record.ip_addrs.clone(),
+            ttl: addr_record.ttl,
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use super::Lookup;
+use crate::{Context, DnsClient, Target, resolvables::ResolvableExt};
+use futures::stream::{self, Stream, StreamExt};
+
+/// Drives the [Lookup] of each context to exhaustion, at most `concurrency` of them at once
+/// (0 is treated as 1), and yields all the targets of each one along with its index in
+/// `contexts`, e.g. for a proxy resolving many next hops at the same time.
+///
+/// Results come in the order the lookups complete, not in the order of `contexts`. The stream
+/// is lazy: lookups only start as it is polled, so a slow consumer holds back new lookups
+/// instead of piling up results.
+pub fn resolve_many<C>(
+    contexts: Vec<Context<C>>,
+    concurrency: usize,
+) -> impl Stream<Item = (usize, Vec<Target>)> + Send
+where
+    C: DnsClient,
+{
+    stream::iter(contexts.into_iter().enumerate())
+        .map(|(index, context)| async move {
+            let mut lookup = Lookup::from(context);
+            let mut targets = vec![];
+            while let Some(target) = lookup.resolve_next().await {
+                targets.push(target);
+            }
+            (index, targets)
+        })
+        .buffer_unordered(concurrency.max(1))
+}
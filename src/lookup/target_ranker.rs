@@ -0,0 +1,67 @@
+use crate::Target;
+use rsip::Transport;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Reorders the whole set of resolved targets out of what is known about them beyond DNS (e.g.
+/// how fast they answered before), see [Context::target_ranker](crate::Context::target_ranker).
+///
+/// It is applied by [Lookup::resolve_all_sorted](super::Lookup::resolve_all_sorted), after the
+/// [TargetSortPolicy](super::TargetSortPolicy): [resolve_next](crate::ResolvableExt::resolve_next)
+/// yields the targets one by one, hence can't rank them.
+pub trait TargetRanker: std::fmt::Debug + Send + Sync {
+    /// Reorders the targets, most preferred first
+    fn rank(&self, targets: Vec<Target>) -> Vec<Target>;
+}
+
+/// The default [TargetRanker], which leaves the targets as they are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoopRanker;
+
+impl TargetRanker for NoopRanker {
+    fn rank(&self, targets: Vec<Target>) -> Vec<Target> {
+        targets
+    }
+}
+
+/// A [TargetRanker] putting the targets with the lowest measured round trip time first.
+///
+/// The RTTs are fed back with [RttRanker::record] as the targets are used, and only the latest
+/// one of an (ip, transport) pair is kept. Clones share the measurements, so a clone given to
+/// the [Context](crate::Context) of every lookup ranks by what any of them recorded. Targets
+/// without a measurement go last, in the order they were given.
+#[derive(Debug, Clone, Default)]
+pub struct RttRanker {
+    rtts: Arc<Mutex<HashMap<(IpAddr, Transport), Duration>>>,
+}
+
+impl RttRanker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the round trip time measured towards `ip_addr` over `transport`
+    pub fn record(&self, ip_addr: IpAddr, transport: Transport, rtt: Duration) {
+        self.rtts.lock().expect("rtts lock poisoned").insert((ip_addr, transport), rtt);
+    }
+
+    /// The latest round trip time recorded for `ip_addr` over `transport`, if any
+    pub fn rtt(&self, ip_addr: IpAddr, transport: Transport) -> Option<Duration> {
+        self.rtts.lock().expect("rtts lock poisoned").get(&(ip_addr, transport)).copied()
+    }
+}
+
+impl TargetRanker for RttRanker {
+    fn rank(&self, mut targets: Vec<Target>) -> Vec<Target> {
+        let rtts = self.rtts.lock().expect("rtts lock poisoned");
+        // stable, so that targets with the same RTT (or none) keep their order
+        targets.sort_by_key(|target| match rtts.get(&(target.ip_addr, target.transport)) {
+            Some(rtt) => (false, *rtt),
+            None => (true, Duration::ZERO),
+        });
+
+        targets
+    }
+}
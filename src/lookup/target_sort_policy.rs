@@ -0,0 +1,85 @@
+use crate::Target;
+use rsip::Transport;
+use std::net::IpAddr;
+
+/// Global order applied by [Lookup::resolve_all_sorted](super::Lookup::resolve_all_sorted) to
+/// the whole set of resolved targets.
+///
+/// Targets are ordered by transport first, then by the order they were resolved in (which
+/// already follows NAPTR order/preference and SRV priority/weight), and the addresses of a
+/// single host are finally ordered by family. Consecutive targets are the addresses of a single
+/// host when they share their port, transport and [siblings](Target::siblings): a target without
+/// siblings is a host on its own.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetSortPolicy {
+    /// Transports in order of preference, targets of a transport that is not listed go last.
+    /// When empty, the transports keep the order they were resolved in.
+    pub transports: Vec<Transport>,
+    /// The address family that goes first among the addresses of a single host, `None` keeps
+    /// the order of the A/AAAA answer
+    pub ip_family: Option<IpFamily>,
+}
+
+/// An ip address family, see [TargetSortPolicy::ip_family] and
+/// [Context::ip_family](crate::Context::ip_family)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+impl IpFamily {
+    /// Whether the given ip address belongs to the family
+    pub fn contains(&self, ip_addr: &IpAddr) -> bool {
+        match self {
+            Self::V4 => ip_addr.is_ipv4(),
+            Self::V6 => ip_addr.is_ipv6(),
+        }
+    }
+}
+
+impl TargetSortPolicy {
+    pub fn new(transports: Vec<Transport>) -> Self {
+        Self { transports, ip_family: None }
+    }
+
+    pub fn with_ip_family(mut self, ip_family: IpFamily) -> Self {
+        self.ip_family = Some(ip_family);
+        self
+    }
+
+    /// Sort the targets, which are expected in the order they were resolved in
+    pub fn sort(&self, targets: Vec<Target>) -> Vec<Target> {
+        let mut host = 0;
+        let mut keyed: Vec<((usize, usize, usize), Target)> = Vec::with_capacity(targets.len());
+        for target in targets {
+            if keyed.last().is_some_and(|(_, previous)| !same_host(previous, &target)) {
+                host += 1;
+            }
+            let key = (self.transport_rank(target.transport), host, self.family_rank(&target));
+            keyed.push((key, target));
+        }
+
+        keyed.sort_by_key(|(key, _)| *key);
+        keyed.into_iter().map(|(_, target)| target).collect()
+    }
+
+    fn transport_rank(&self, transport: Transport) -> usize {
+        self.transports.iter().position(|t| *t == transport).unwrap_or(self.transports.len())
+    }
+
+    fn family_rank(&self, target: &Target) -> usize {
+        match (self.ip_family, target.ip_addr.is_ipv6()) {
+            (Some(IpFamily::V4), true) | (Some(IpFamily::V6), false) => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// Whether both targets are addresses of the same host, as told by the addresses they carry
+fn same_host(previous: &Target, target: &Target) -> bool {
+    previous.siblings.is_some()
+        && previous.siblings == target.siblings
+        && previous.port == target.port
+        && previous.transport == target.transport
+}
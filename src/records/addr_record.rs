@@ -1,16 +1,107 @@
-use rsip::Domain;
-use std::net::IpAddr;
+use super::domain_key;
+use crate::{DEFAULT_TTL, Target};
+use rsip::{Domain, Error, Port, Transport};
+use std::net::{IpAddr, SocketAddr};
 
 /// Simple struct that holds the A record details (domain and ip entries)
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AddrRecord {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::domain"))]
     pub domain: Domain,
     pub ip_addrs: Vec<IpAddr>,
+    /// Minimum TTL across all the ip entries
     pub ttl: u32,
+    /// Minimum TTL of the A and AAAA entries respectively, when the client tracks them
+    /// separately. A family without entries carries the overall `ttl`.
+    pub per_family_ttl: Option<(u32, u32)>,
+    /// The DNS server that answered, for clients that know it (like the
+    /// `RecursiveHickoryClient`). Only meant for diagnostics.
+    pub source: Option<SocketAddr>,
+}
+
+impl AddrRecord {
+    /// TTL that applies to the given ip address, falling back to `ttl` when no per family
+    /// TTLs are known
+    pub fn ttl_for(&self, ip: IpAddr) -> u32 {
+        match (self.per_family_ttl, ip) {
+            (Some((v4_ttl, _)), IpAddr::V4(_)) => v4_ttl,
+            (Some((_, v6_ttl)), IpAddr::V6(_)) => v6_ttl,
+            (None, _) => self.ttl,
+        }
+    }
+
+    /// A target for each ip address, in order, reached through the given port and transport and
+    /// carrying the TTL of its address (see [ttl_for](Self::ttl_for))
+    pub fn into_targets(self, port: Port, transport: Transport) -> Vec<Target> {
+        self.ip_addrs
+            .iter()
+            .map(|ip_addr| Target {
+                ip_addr: *ip_addr,
+                port,
+                transport,
+                ttl: self.ttl_for(*ip_addr),
+                scope_id: None,
+                siblings: None,
+            })
+            .collect()
+    }
+
+    /// Sets the DNS server that answered
+    pub fn with_source(mut self, source: SocketAddr) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Combines two records of the same domain, typically the answers to its A and AAAA
+    /// queries: the addresses are joined (each one kept once) and the lower of the two TTLs is
+    /// kept, per family as well. Fails if the records are not about the same domain.
+    pub fn merge(mut self, other: AddrRecord) -> Result<AddrRecord, Error> {
+        if domain_key(&self.domain) != domain_key(&other.domain) {
+            return Err(Error::Unexpected(format!(
+                "can't merge the addresses of {} into {}",
+                other.domain, self.domain
+            )));
+        }
+
+        let ttl = self.ttl.min(other.ttl);
+        let family_ttl = |v4: bool| {
+            [&self, &other]
+                .into_iter()
+                .flat_map(|record| {
+                    record
+                        .ip_addrs
+                        .iter()
+                        .filter(|ip| ip.is_ipv4() == v4)
+                        .map(move |ip| record.ttl_for(*ip))
+                })
+                .min()
+        };
+        let per_family_ttl = self
+            .per_family_ttl
+            .or(other.per_family_ttl)
+            .map(|_| (family_ttl(true).unwrap_or(ttl), family_ttl(false).unwrap_or(ttl)));
+
+        for ip_addr in other.ip_addrs {
+            if !self.ip_addrs.contains(&ip_addr) {
+                self.ip_addrs.push(ip_addr);
+            }
+        }
+        self.ttl = ttl;
+        self.per_family_ttl = per_family_ttl;
+        self.source = self.source.or(other.source);
+        Ok(self)
+    }
 }
 
 impl From<(Domain, Vec<IpAddr>)> for AddrRecord {
     fn from(tuple: (Domain, Vec<IpAddr>)) -> Self {
-        Self { domain: tuple.0, ip_addrs: tuple.1, ttl: 300 }
+        Self {
+            domain: tuple.0,
+            ip_addrs: tuple.1,
+            ttl: DEFAULT_TTL,
+            per_family_ttl: None,
+            source: None,
+        }
     }
 }
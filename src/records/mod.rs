@@ -3,16 +3,26 @@
 mod addr_record;
 mod naptr_record;
 mod srv_record;
+mod ttl_bounds;
 
 pub use addr_record::AddrRecord;
-pub use naptr_record::{NaptrEntry, NaptrFlags, NaptrRecord, NaptrServices};
-pub use srv_record::{SrvEntry, SrvRecord};
+pub use naptr_record::{NaptrEntry, NaptrFlags, NaptrRecord, NaptrRegexp, NaptrServices};
+#[cfg(feature = "testing-utils")]
+pub use srv_record::weight_distribution;
+pub use srv_record::{SrvEntry, SrvRecord, merge_srv_records};
+pub use ttl_bounds::TtlBounds;
 
 use rsip::{Domain, Error, Transport};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 
 /// Simple struct that holds the srv domain properties (namely actual domain, protocol and whether
 /// it's secure or not).
+///
+/// The fields are public, hence nothing prevents a struct literal from combining them into a
+/// name that makes no sense (like `_sip._tls`): prefer [SrvDomain::new] (or
+/// [SrvDomain::secure]/[SrvDomain::insecure]) which derive them from a transport, or
+/// [SrvDomain::try_new] which validates them.
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct SrvDomain {
     pub domain: Domain,
@@ -20,7 +30,82 @@ pub struct SrvDomain {
     pub secure: bool,
 }
 
+/// The form of the domains keying the additional hosts of the records: lowercase and without the
+/// trailing dot of a fully qualified name, so that `Host.example.com.` and `host.example.com`
+/// (as a SRV target might be written) match the same A/AAAA records
+pub(crate) fn domain_key(domain: &Domain) -> Domain {
+    domain.to_string().trim_end_matches('.').to_ascii_lowercase().into()
+}
+
+/// The additional host of `target` in `hosts`, keyed by [domain_key] or not: a map that was
+/// filled directly (rather than through the constructors) might hold a key like
+/// `Host.example.com.`, found through a slower scan
+pub(crate) fn additional_host<'a>(
+    hosts: &'a HashMap<Domain, AddrRecord>,
+    target: &Domain,
+) -> Option<&'a AddrRecord> {
+    let key = domain_key(target);
+    hosts.get(&key).or_else(|| {
+        hosts
+            .iter()
+            .find_map(|(domain, addr_record)| (domain_key(domain) == key).then_some(addr_record))
+    })
+}
+
+/// Rekeys a map of additional hosts by [domain_key]
+pub(crate) fn keyed_hosts(hosts: HashMap<Domain, AddrRecord>) -> HashMap<Domain, AddrRecord> {
+    hosts.into_iter().map(|(domain, addr_record)| (domain_key(&domain), addr_record)).collect()
+}
+
+/// The TTL of a target reached through a chain of records (e.g. SRV then A/AAAA): a chain is
+/// only valid as long as its shortest lived link, so this is the minimum of their TTLs
+pub(crate) fn chain_ttl(referrer_ttl: u32, ttl: u32) -> u32 {
+    referrer_ttl.min(ttl)
+}
+
 impl SrvDomain {
+    /// The SRV domain `transport` is looked up under (RFC 3263 section 4.1): the `_sips`
+    /// service for a secure transport (TLS, WSS, ...) and `_sip` otherwise, along with the
+    /// protocol the transport runs over (e.g. TCP for TLS).
+    pub fn new(domain: Domain, transport: Transport) -> Self {
+        Self {
+            secure: Transport::secure_transports().contains(&transport),
+            protocol: transport.protocol(),
+            domain,
+        }
+    }
+
+    /// Validates that `secure` agrees with `protocol`: an insecure SRV domain can't run over a
+    /// secure transport (like TLS), and a secure one needs a protocol that has a secure
+    /// counterpart (`_sips._udp` has none). A secure transport is accepted for a secure domain
+    /// and stored as the protocol it runs over, e.g. `(Tls, true)` is `_sips._tcp`.
+    pub fn try_new(domain: Domain, protocol: Transport, secure: bool) -> Result<Self, Error> {
+        let srv_domain = Self { secure, protocol: protocol.protocol(), domain };
+
+        let consistent = match secure {
+            true => Transport::secure_transports().contains(&srv_domain.transport()),
+            false => protocol == protocol.protocol(),
+        };
+        match consistent {
+            true => Ok(srv_domain),
+            false => Err(Error::Unexpected(format!(
+                "inconsistent SrvDomain for {}: {} can't be used with secure set to {}",
+                srv_domain.domain, protocol, secure
+            ))),
+        }
+    }
+
+    /// The `_sips` SRV domain of `transport`, whether the transport itself is secure or not
+    /// (e.g. `_sips._tcp` for TCP, as a sips URI asks for)
+    pub fn secure(domain: Domain, transport: Transport) -> Self {
+        Self { secure: true, ..Self::new(domain, transport) }
+    }
+
+    /// The `_sip` SRV domain of `transport`, whether the transport itself is secure or not
+    pub fn insecure(domain: Domain, transport: Transport) -> Self {
+        Self { secure: false, ..Self::new(domain, transport) }
+    }
+
     pub fn transport(&self) -> Transport {
         match (self.secure, self.protocol) {
             (true, Transport::Tcp) => Transport::Tls,
@@ -96,6 +181,16 @@ impl TryFrom<&str> for SrvDomain {
             .map_err(|_| Error::tokenizer(("SrvDomain scheme", from)))?;
         let scheme: rsip::Scheme =
             rsip::common::uri::scheme::Tokenizer::from(scheme.as_bytes()).try_into()?;
+        let secure = match scheme {
+            rsip::Scheme::Sip => false,
+            rsip::Scheme::Sips => true,
+            scheme => {
+                return Err(Error::ParseError(format!(
+                    "invalid SrvDomain {}: unsupported service {}",
+                    from, scheme
+                )));
+            }
+        };
 
         let (domain, (_, transport, _)) = (
             tag::<_, _, nom::error::Error<&str>>("_"),
@@ -104,13 +199,81 @@ impl TryFrom<&str> for SrvDomain {
         )
             .parse(rem)
             .map_err(|_| Error::tokenizer(("SrvDomain transport", from)))?;
-        let transport: rsip::Transport =
-            rsip::common::transport::Tokenizer::from(transport.as_bytes()).try_into()?;
+        let transport: rsip::Transport = rsip::common::transport::Tokenizer::from(
+            transport.as_bytes(),
+        )
+        .try_into()
+        .map_err(|_| {
+            Error::ParseError(format!("invalid SrvDomain {}: unknown protocol {}", from, transport))
+        })?;
+        //secure transports (like tls) are expressed through the _sips service, otherwise the
+        //SrvDomain wouldn't render back to the same string
+        if transport != transport.protocol() {
+            return Err(Error::ParseError(format!(
+                "invalid SrvDomain {}: {} is not a valid SRV protocol",
+                from, transport
+            )));
+        }
 
-        Ok(Self {
-            secure: scheme.is_sips()?,
-            protocol: transport.protocol(),
-            domain: domain.into(),
-        })
+        check_srv_target(from, domain)?;
+
+        Ok(Self { secure, protocol: transport, domain: domain.into() })
+    }
+}
+
+/// Longest name (without the trailing dot) and label DNS allows, RFC 1035 section 2.3.4
+pub(crate) const MAX_NAME_LENGTH: usize = 253;
+pub(crate) const MAX_LABEL_LENGTH: usize = 63;
+
+/// Rejects what can't be the domain of a SRV name, as it may come from untrusted server data:
+/// empty labels, labels or names longer than DNS allows, control characters or whitespace, and
+/// another `_` prefixed label which would mean there were more than a service and a protocol
+fn check_srv_target(from: &str, domain: &str) -> Result<(), Error> {
+    let invalid =
+        |reason: &str| Error::ParseError(format!("invalid SrvDomain {}: {}", from, reason));
+
+    if domain.is_empty() {
+        return Err(invalid("missing domain"));
+    }
+    if from.trim_end_matches('.').len() > MAX_NAME_LENGTH {
+        return Err(invalid("name too long"));
+    }
+    for label in domain.strip_suffix('.').unwrap_or(domain).split('.') {
+        match label {
+            "" => return Err(invalid("empty label")),
+            label if label.len() > MAX_LABEL_LENGTH => return Err(invalid("label too long")),
+            label if label.starts_with('_') => return Err(invalid("unexpected underscore label")),
+            label if label.chars().any(|c| c.is_control() || c.is_whitespace()) => {
+                return Err(invalid("invalid character"));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+impl std::str::FromStr for SrvDomain {
+    type Err = rsip::Error;
+
+    fn from_str(from: &str) -> Result<Self, Self::Err> {
+        Self::try_from(from)
+    }
+}
+
+/// Encoded as its string form (like `_sip._udp.example.com`), so that it can key the
+/// `additional_srvs` map of a [NaptrRecord] in formats that only allow string keys (like JSON)
+#[cfg(feature = "serde")]
+impl serde::Serialize for SrvDomain {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SrvDomain {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let srv_domain = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Self::try_from(srv_domain.as_str()).map_err(serde::de::Error::custom)
     }
 }
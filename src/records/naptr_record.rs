@@ -1,4 +1,4 @@
-use super::{SrvDomain, SrvRecord};
+use super::{AddrRecord, SrvDomain, SrvRecord, additional_host, keyed_hosts};
 use rsip::{Domain, Error, Transport};
 use std::collections::HashMap;
 use std::collections::VecDeque;
@@ -6,29 +6,50 @@ use std::convert::TryFrom;
 
 /// Simple struct that holds the NAPTR record details (domain and naptr entries)
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NaptrRecord {
     pub entries: Vec<NaptrEntry>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::domain"))]
     pub domain: Domain,
     pub ttl: u32,
     /// Additional SRV records returned in the DNS ADDITIONAL section.
     /// This enables single-query resolution when DNS server supports recursion.
     pub additional_srvs: HashMap<SrvDomain, SrvRecord>,
+    /// A/AAAA records returned in the DNS ADDITIONAL section, shared by all the additional SRV
+    /// records so that a target used by several of them is kept only once. Keyed like the
+    /// additional hosts of a [SrvRecord].
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::domain_map"))]
+    pub additional_hosts: HashMap<Domain, AddrRecord>,
 }
 
 /// Simple struct that resembles the NAPTR record entries
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NaptrEntry {
     pub order: u16,
     pub preference: u16,
     pub flags: NaptrFlags,
     pub services: NaptrServices,
     pub regexp: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::domain"))]
     pub replacement: Domain,
 }
 
+/// Parsed representation of the NAPTR entry `regexp` field, which is expressed as
+/// `<delimiter>pattern<delimiter>replacement<delimiter>flags`
+/// ([RFC 3402](https://datatracker.ietf.org/doc/html/rfc3402#section-3.2)).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NaptrRegexp {
+    pub delimiter: char,
+    pub pattern: String,
+    pub replacement: String,
+    pub flags: String,
+}
+
 //TODO: this should be a vec of NaptrFlag, with some handy methods to check if there is only 1
 //specific flag or a specific flag is contained (in our case S flag is what we care)
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NaptrFlags {
     S,
     A,
@@ -39,7 +60,8 @@ pub enum NaptrFlags {
 
 //TODO: this should be a vec of NaptrServices, with some handy methods to check if there is only 1
 //specific service or a specific service is contained (in our case SIP(S)+D2x services is what we care)
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NaptrServices {
     SipD2t,
     SipD2u,
@@ -52,22 +74,85 @@ pub enum NaptrServices {
     Other(String),
 }
 
+/// Flags are matched case-insensitively (RFC 3403), anything else (no flag, several ones or a
+/// non-ASCII one) is kept as is in [NaptrFlags::Other]
 impl From<&[u8]> for NaptrFlags {
     fn from(from: &[u8]) -> Self {
         match from {
-            s if s == b"S" => Self::S,
-            s if s == b"A" => Self::A,
-            s if s == b"A" => Self::U,
-            s if s == b"P" => Self::P,
-            s => Self::Other(s.to_vec()),
+            [flag] if flag.eq_ignore_ascii_case(&b'S') => Self::S,
+            [flag] if flag.eq_ignore_ascii_case(&b'A') => Self::A,
+            [flag] if flag.eq_ignore_ascii_case(&b'U') => Self::U,
+            [flag] if flag.eq_ignore_ascii_case(&b'P') => Self::P,
+            _ => Self::Other(from.to_vec()),
         }
     }
 }
 
+impl NaptrFlags {
+    /// Whether the flags are known: one of the single flags, or none at all (a non-terminal
+    /// entry). Anything else usually means a mis-provisioned record.
+    pub fn is_recognized(&self) -> bool {
+        !matches!(self, Self::Other(flags) if !flags.is_empty())
+    }
+}
+
 impl NaptrEntry {
     pub fn total_weight(&self) -> u16 {
         self.order + self.preference
     }
+
+    /// The `(order, preference)` the entries of a record are processed by, lowest first
+    /// ([RFC 3403](https://datatracker.ietf.org/doc/html/rfc3403#section-4.1)): the preference
+    /// only breaks ties between entries of the same order.
+    pub fn sort_key(&self) -> (u16, u16) {
+        (self.order, self.preference)
+    }
+
+    /// Returns the parsed `regexp` field, or `None` when it is empty, not valid UTF-8 or
+    /// doesn't have exactly 3 delimited parts.
+    pub fn parsed_regexp(&self) -> Option<NaptrRegexp> {
+        NaptrRegexp::parse(&self.regexp)
+    }
+}
+
+impl NaptrRegexp {
+    fn parse(regexp: &[u8]) -> Option<Self> {
+        let regexp = std::str::from_utf8(regexp).ok()?;
+        let mut chars = regexp.chars();
+
+        //the delimiter can be any character apart from digits and the escape character
+        let delimiter = chars.next()?;
+        if delimiter.is_ascii_digit() || delimiter == '\\' {
+            return None;
+        }
+
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in chars {
+            let part = parts.last_mut()?;
+            match (escaped, c) {
+                (true, c) if c == delimiter => {
+                    part.push(c);
+                    escaped = false;
+                }
+                (true, c) => {
+                    part.push('\\');
+                    part.push(c);
+                    escaped = false;
+                }
+                (false, '\\') => escaped = true,
+                (false, c) if c == delimiter => parts.push(String::new()),
+                (false, c) => part.push(c),
+            }
+        }
+
+        match (escaped, <[String; 3]>::try_from(parts)) {
+            (false, Ok([pattern, replacement, flags])) => {
+                Some(Self { delimiter, pattern, replacement, flags })
+            }
+            _ => None,
+        }
+    }
 }
 
 impl From<NaptrRecord> for Vec<NaptrEntry> {
@@ -85,7 +170,13 @@ impl From<NaptrRecord> for VecDeque<NaptrEntry> {
 impl NaptrRecord {
     /// Create a new NaptrRecord without additional SRV records (backward compatible)
     pub fn new(entries: Vec<NaptrEntry>, domain: Domain, ttl: u32) -> Self {
-        Self { entries, domain, ttl, additional_srvs: HashMap::new() }
+        Self {
+            entries,
+            domain,
+            ttl,
+            additional_srvs: HashMap::new(),
+            additional_hosts: HashMap::new(),
+        }
     }
 
     /// Create a new NaptrRecord with additional SRV records from DNS ADDITIONAL section
@@ -95,7 +186,13 @@ impl NaptrRecord {
         ttl: u32,
         additional_srvs: HashMap<SrvDomain, SrvRecord>,
     ) -> Self {
-        Self { entries, domain, ttl, additional_srvs }
+        Self { entries, domain, ttl, additional_srvs, additional_hosts: HashMap::new() }
+    }
+
+    /// Sets the A/AAAA records from DNS ADDITIONAL section, shared by all additional SRV records
+    pub fn with_shared_hosts(mut self, additional_hosts: HashMap<Domain, AddrRecord>) -> Self {
+        self.additional_hosts = keyed_hosts(additional_hosts);
+        self
     }
 
     /// Get additional SRV record for a specific NAPTR replacement domain
@@ -103,6 +200,16 @@ impl NaptrRecord {
         self.additional_srvs.get(srv_domain)
     }
 
+    /// Get additional AddrRecord for a SRV target, looking at the shared hosts first and then at
+    /// the hosts of each additional SRV record
+    pub fn get_additional_for_target(&self, target: &Domain) -> Option<&AddrRecord> {
+        additional_host(&self.additional_hosts, target).or_else(|| {
+            self.additional_srvs
+                .values()
+                .find_map(|srv_record| srv_record.get_additional_for_target(target))
+        })
+    }
+
     /// Check if all NAPTR entries have corresponding additional SRV records
     pub fn has_complete_additional_srvs(&self) -> bool {
         self.entries.iter().all(|entry| {
@@ -115,6 +222,50 @@ impl NaptrRecord {
         })
     }
 
+    /// Get coverage ratio of additional SRV records (0.0 to 1.0): the fraction of the SRV names
+    /// that the `S` entries of the given transports point to and which were pre-resolved in the
+    /// ADDITIONAL section
+    pub fn srv_coverage(&self, available_transports: &[Transport]) -> f64 {
+        let expected = self.expected_srvs(|transport| available_transports.contains(&transport));
+        if expected.is_empty() {
+            return 1.0;
+        }
+        let covered = expected
+            .iter()
+            .filter(|srv_domain| self.additional_srvs.contains_key(*srv_domain))
+            .count();
+        covered as f64 / expected.len() as f64
+    }
+
+    /// Check if the ADDITIONAL section spares any follow-up query: every `S` entry has its SRV
+    /// record there, and every target of those SRV records has its A/AAAA records there too
+    pub fn has_complete_srvs(&self) -> bool {
+        self.expected_srvs(|_| true).iter().all(|srv_domain| {
+            self.additional_srvs.get(srv_domain).is_some_and(|srv_record| {
+                srv_record
+                    .entries
+                    .iter()
+                    .all(|entry| self.get_additional_for_target(&entry.target).is_some())
+            })
+        })
+    }
+
+    /// The (deduplicated) SRV names pointed to by the `S` entries whose transport is accepted
+    fn expected_srvs(&self, accept: impl Fn(Transport) -> bool) -> Vec<SrvDomain> {
+        let mut srv_domains: Vec<SrvDomain> = Vec::new();
+        for entry in self.entries.iter().filter(|entry| matches!(entry.flags, NaptrFlags::S)) {
+            if !entry.services.transport().is_some_and(&accept) {
+                continue;
+            }
+            if let Ok(srv_domain) = SrvDomain::try_from(entry.clone())
+                && !srv_domains.contains(&srv_domain)
+            {
+                srv_domains.push(srv_domain);
+            }
+        }
+        srv_domains
+    }
+
     pub fn as_slice(&self) -> &[NaptrEntry] {
         self.entries.as_slice()
     }
@@ -123,10 +274,10 @@ impl NaptrRecord {
         self.entries.iter()
     }
 
+    /// Orders the entries by their [sort key](NaptrEntry::sort_key), entries with the same key
+    /// keeping the order of the record
     pub fn sorted(mut self) -> Self {
-        use std::cmp::Reverse;
-
-        self.entries.sort_by_key(|b| Reverse(b.total_weight()));
+        self.entries.sort_by_key(NaptrEntry::sort_key);
         self
     }
 }
@@ -141,6 +292,12 @@ impl IntoIterator for NaptrRecord {
 }
 
 impl NaptrServices {
+    /// The transport the service resolves to (RFC 3263 for UDP, TCP and SCTP, RFC 7118 for
+    /// WebSocket), the `SIPS` services mapping to the secure counterpart of their transport.
+    ///
+    /// Entries of a service without transport are filtered out of the resolution:
+    /// * `SIPS+D2U` has no counterpart in [Transport], as there is no DTLS variant
+    /// * [NaptrServices::Other] services aren't SIP ones (e.g. `E2U+sip` of ENUM)
     pub fn transport(&self) -> Option<Transport> {
         match self {
             Self::SipD2t => Some(Transport::Tcp),
@@ -149,12 +306,19 @@ impl NaptrServices {
             Self::SipD2w => Some(Transport::Ws),
             Self::SipsD2t => Some(Transport::Tls),
             Self::SipsD2u => None,
-            Self::SipsD2s => None,
+            Self::SipsD2s => Some(Transport::TlsSctp),
             Self::SipsD2w => Some(Transport::Wss),
-            _ => None,
+            Self::Other(_) => None,
         }
     }
 
+    /// Parses the services field like [TryFrom] does, but never fails: an unknown service (or
+    /// invalid UTF-8, lossily converted) is kept in [NaptrServices::Other]
+    pub fn from_bytes_lossy(from: &[u8]) -> Self {
+        Self::try_from(from)
+            .unwrap_or_else(|_| Self::Other(String::from_utf8_lossy(from).into_owned()))
+    }
+
     pub fn secure(&self) -> bool {
         match self {
             Self::SipD2t => false,
@@ -165,7 +329,7 @@ impl NaptrServices {
             Self::SipsD2u => true,
             Self::SipsD2s => true,
             Self::SipsD2w => true,
-            _ => false,
+            Self::Other(_) => false,
         }
     }
 }
@@ -1,31 +1,42 @@
-use super::{AddrRecord, SrvDomain};
+use super::{AddrRecord, SrvDomain, additional_host, keyed_hosts};
 use rsip::{Domain, Port, Transport};
 use std::collections::HashMap;
+use std::net::SocketAddr;
 
 /// Simple struct that holds the SRV record details (domain and srv entries)
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SrvRecord {
     pub entries: Vec<SrvEntry>,
     pub domain: SrvDomain,
     pub ttl: u32,
     /// Additional A/AAAA records returned in the DNS ADDITIONAL section.
     /// This enables single-query resolution when DNS server supports recursion.
+    /// Keyed by lowercase domain without trailing dot when filled by the constructors, though
+    /// [get_additional_for_target](Self::get_additional_for_target) matches any other key too.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::domain_map"))]
     pub additional_hosts: HashMap<Domain, AddrRecord>,
+    /// The DNS server that answered, for clients that know it (like the
+    /// `RecursiveHickoryClient`). Only meant for diagnostics.
+    pub source: Option<SocketAddr>,
 }
 
 /// Simple struct that resembles the SRV record entries
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SrvEntry {
     pub priority: u16,
     pub weight: u16,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::port"))]
     pub port: Port,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::domain"))]
     pub target: Domain,
 }
 
 impl SrvRecord {
     /// Create a new SrvRecord without additional hosts (backward compatible)
     pub fn new(entries: Vec<SrvEntry>, domain: SrvDomain, ttl: u32) -> Self {
-        Self { entries, domain, ttl, additional_hosts: HashMap::new() }
+        Self { entries, domain, ttl, additional_hosts: HashMap::new(), source: None }
     }
 
     /// Create a new SrvRecord with additional hosts from DNS ADDITIONAL section
@@ -35,17 +46,27 @@ impl SrvRecord {
         ttl: u32,
         additional_hosts: HashMap<Domain, AddrRecord>,
     ) -> Self {
-        Self { entries, domain, ttl, additional_hosts }
+        Self { entries, domain, ttl, additional_hosts: keyed_hosts(additional_hosts), source: None }
     }
 
-    /// Get additional AddrRecord for a specific target domain
+    /// Sets the DNS server that answered, on the record and on its additional hosts
+    pub fn with_source(mut self, source: SocketAddr) -> Self {
+        self.source = Some(source);
+        for addr_record in self.additional_hosts.values_mut() {
+            addr_record.source = Some(source);
+        }
+        self
+    }
+
+    /// Get additional AddrRecord for a specific target domain, regardless of its case and of a
+    /// trailing dot
     pub fn get_additional_for_target(&self, target: &Domain) -> Option<&AddrRecord> {
-        self.additional_hosts.get(target)
+        additional_host(&self.additional_hosts, target)
     }
 
     /// Check if all SRV targets have corresponding additional records
     pub fn has_complete_additionals(&self) -> bool {
-        self.entries.iter().all(|entry| self.additional_hosts.contains_key(&entry.target))
+        self.entries.iter().all(|entry| self.get_additional_for_target(&entry.target).is_some())
     }
 
     /// Get coverage ratio of additional records (0.0 to 1.0)
@@ -56,7 +77,7 @@ impl SrvRecord {
         let covered = self
             .entries
             .iter()
-            .filter(|entry| self.additional_hosts.contains_key(&entry.target))
+            .filter(|entry| self.get_additional_for_target(&entry.target).is_some())
             .count();
         covered as f64 / self.entries.len() as f64
     }
@@ -79,6 +100,161 @@ impl SrvRecord {
         self.entries.sort_by_key(|b| Reverse(b.total_weight()));
         self
     }
+
+    /// Orders the entries by priority, spreading the first pick of each priority over its
+    /// entries in proportion to their weights, across calls that share the same `cursor`.
+    ///
+    /// [SrvRecord::sorted] always returns the same order, so every lookup ends up on the same
+    /// target, and the one-shot weighted shuffle of
+    /// [RFC 2782](https://datatracker.ietf.org/doc/html/rfc2782) is only fair on average. Here
+    /// the caller persists the cursor (it is advanced on every call) and over any `W`
+    /// consecutive calls, `W` being the sum of the weights of a priority, each entry of that
+    /// priority comes first exactly `weight` times, interleaved with the others rather than in a
+    /// row. Useful when the same record drives many short-lived connections, like in an
+    /// outbound proxy.
+    ///
+    /// The rest of each priority follows by descending weight. When all the weights of a
+    /// priority are 0, its entries take turns.
+    pub fn weighted_round_robin(&self, cursor: &mut u64) -> Vec<SrvEntry> {
+        use std::cmp::Reverse;
+
+        let position = *cursor;
+        *cursor = cursor.wrapping_add(1);
+
+        let mut priorities = self.entries.iter().map(|entry| entry.priority).collect::<Vec<_>>();
+        priorities.sort_unstable();
+        priorities.dedup();
+
+        priorities
+            .into_iter()
+            .flat_map(|priority| {
+                let mut group = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.priority == priority)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let first = group.remove(weighted_pick(&group, position));
+                group.sort_by_key(|entry| Reverse(entry.weight));
+
+                std::iter::once(first).chain(group)
+            })
+            .collect()
+    }
+
+    /// Groups the entries by priority, lowest (most preferred) first, each group in the random
+    /// weighted order of [RFC 2782](https://datatracker.ietf.org/doc/html/rfc2782).
+    ///
+    /// Meant for failover logic that tries all the targets of a tier at once and only moves to
+    /// the next tier when all of them failed.
+    pub fn priority_tiers(&self) -> Vec<Vec<SrvEntry>> {
+        let mut priorities = self.entries.iter().map(|entry| entry.priority).collect::<Vec<_>>();
+        priorities.sort_unstable();
+        priorities.dedup();
+
+        priorities
+            .into_iter()
+            .map(|priority| {
+                weighted_shuffle(
+                    self.entries
+                        .iter()
+                        .filter(|entry| entry.priority == priority)
+                        .cloned()
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Flattens the SRV records of several transports into a single list of entries to try in
+/// order, each along with the transport it was found under.
+///
+/// Entries are ordered by priority first, across all the records, then by transport in the
+/// order of `transport_pref` (transports that are not listed go last, in the order of
+/// `records`), and finally by descending weight like [SrvRecord::sorted] does.
+pub fn merge_srv_records(
+    records: Vec<(Transport, SrvRecord)>,
+    transport_pref: &[Transport],
+) -> Vec<(SrvEntry, Transport)> {
+    use std::cmp::Reverse;
+
+    let transport_rank = |transport: &Transport| {
+        transport_pref.iter().position(|preferred| preferred == transport).unwrap_or(usize::MAX)
+    };
+
+    let mut merged = records
+        .into_iter()
+        .flat_map(|(transport, record)| {
+            record.entries.into_iter().map(move |entry| (entry, transport))
+        })
+        .collect::<Vec<_>>();
+    merged.sort_by_key(|(entry, transport)| {
+        (entry.priority, transport_rank(transport), Reverse(entry.weight))
+    });
+
+    merged
+}
+
+/// Orders the entries of a single priority the way RFC 2782 picks them: each pick is random,
+/// weighted by the entries left, and entries with a weight of 0 only have a small chance to come
+/// before the others.
+fn weighted_shuffle(mut group: Vec<SrvEntry>) -> Vec<SrvEntry> {
+    // the RFC places the entries with a weight of 0 first, so that they are picked when the
+    // random number is 0
+    group.sort_by_key(|entry| entry.weight != 0);
+
+    let mut shuffled = Vec::with_capacity(group.len());
+    while !group.is_empty() {
+        let total = group.iter().map(|entry| entry.weight as u32).sum::<u32>();
+        let pick = rand::random_range(0..=total);
+        let mut running = 0;
+        let index = group
+            .iter()
+            .position(|entry| {
+                running += entry.weight as u32;
+                running >= pick
+            })
+            .expect("pick is at most the total weight");
+        shuffled.push(group.remove(index));
+    }
+
+    shuffled
+}
+
+/// Index of the entry that comes first at the given cursor position. The weight slots of the
+/// group are walked with a stride coprime to their total, so every slot is hit once per round.
+fn weighted_pick(group: &[SrvEntry], position: u64) -> usize {
+    let total = group.iter().map(|entry| entry.weight as u64).sum::<u64>();
+    if total == 0 {
+        return (position % group.len() as u64) as usize;
+    }
+
+    let slot = (position % total) * stride(total) % total;
+    let mut upper = 0;
+    group
+        .iter()
+        .position(|entry| {
+            upper += entry.weight as u64;
+            slot < upper
+        })
+        .expect("slot is below the total weight")
+}
+
+/// Close to the golden ratio of `total`, so that consecutive picks land far apart
+fn stride(total: u64) -> u64 {
+    let mut stride = ((total as f64 * 0.618) as u64).max(1);
+    while gcd(stride, total) != 1 {
+        stride += 1;
+    }
+    stride
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    match b {
+        0 => a,
+        b => gcd(b, a % b),
+    }
 }
 
 impl SrvEntry {
@@ -129,6 +305,27 @@ impl testing_utils::Randomize for SrvEntry {
     }
 }
 
+/// Tallies how often each target comes first over `iterations` selections of the record, to
+/// check the weights of a record (or of a custom [DnsClient](crate::DnsClient)) against
+/// [RFC 2782](https://datatracker.ietf.org/doc/html/rfc2782).
+///
+/// [SrvRecord::sorted] is deterministic, so the selections go through
+/// [SrvRecord::weighted_round_robin] instead, starting from a fresh cursor: only the entries of
+/// the lowest priority ever come first, and over a multiple of their total weight each of them
+/// comes first exactly `weight` times.
+#[cfg(feature = "testing-utils")]
+pub fn weight_distribution(record: &SrvRecord, iterations: usize) -> HashMap<Domain, usize> {
+    let mut cursor = 0;
+    let mut distribution = HashMap::new();
+    for _ in 0..iterations {
+        if let Some(first) = record.weighted_round_robin(&mut cursor).into_iter().next() {
+            *distribution.entry(first.target).or_insert(0) += 1;
+        }
+    }
+
+    distribution
+}
+
 /*
 #[cfg(feature = "test-utils")]
 impl crate::Randomize for SrvRecord {
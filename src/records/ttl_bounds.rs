@@ -0,0 +1,64 @@
+use super::{AddrRecord, NaptrRecord, SrvRecord};
+
+/// Range the TTL of the records returned by a dns client is clamped into, so that a server
+/// handing out a TTL of 0 (or of several days) doesn't dictate the cache and retry cadence.
+///
+/// The default bounds (`0..=u32::MAX`) leave every TTL untouched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TtlBounds {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl TtlBounds {
+    pub fn new(min: u32, max: u32) -> Self {
+        Self { min, max }
+    }
+
+    /// Clamp the given TTL into the bounds, `max` wins if the bounds are inverted
+    pub fn clamp(&self, ttl: u32) -> u32 {
+        ttl.max(self.min).min(self.max)
+    }
+
+    /// Clamp the TTL of the record, including the per family TTLs
+    pub fn apply_to_addr(&self, mut addr_record: AddrRecord) -> AddrRecord {
+        addr_record.ttl = self.clamp(addr_record.ttl);
+        addr_record.per_family_ttl = addr_record
+            .per_family_ttl
+            .map(|(v4_ttl, v6_ttl)| (self.clamp(v4_ttl), self.clamp(v6_ttl)));
+        addr_record
+    }
+
+    /// Clamp the TTL of the record and of its additional hosts
+    pub fn apply_to_srv(&self, mut srv_record: SrvRecord) -> SrvRecord {
+        srv_record.ttl = self.clamp(srv_record.ttl);
+        srv_record.additional_hosts = srv_record
+            .additional_hosts
+            .into_iter()
+            .map(|(domain, addr_record)| (domain, self.apply_to_addr(addr_record)))
+            .collect();
+        srv_record
+    }
+
+    /// Clamp the TTL of the record, of its additional SRV records and of all the additional hosts
+    pub fn apply_to_naptr(&self, mut naptr_record: NaptrRecord) -> NaptrRecord {
+        naptr_record.ttl = self.clamp(naptr_record.ttl);
+        naptr_record.additional_hosts = naptr_record
+            .additional_hosts
+            .into_iter()
+            .map(|(domain, addr_record)| (domain, self.apply_to_addr(addr_record)))
+            .collect();
+        naptr_record.additional_srvs = naptr_record
+            .additional_srvs
+            .into_iter()
+            .map(|(srv_domain, srv_record)| (srv_domain, self.apply_to_srv(srv_record)))
+            .collect();
+        naptr_record
+    }
+}
+
+impl Default for TtlBounds {
+    fn default() -> Self {
+        Self { min: 0, max: u32::MAX }
+    }
+}
@@ -17,9 +17,12 @@ pub use resolvable::Resolvable;
 pub use resolvable_addr_record::ResolvableAddrRecord;
 pub use resolvable_enum::ResolvableEnum;
 pub use resolvable_ip_addr::ResolvableIpAddr;
-pub use resolvable_naptr_record::ResolvableNaptrRecord;
+pub(crate) use resolvable_naptr_record::is_non_terminal;
+pub use resolvable_naptr_record::{
+    DEFAULT_MAX_NAPTR_DEPTH, NaptrOutcome, PFlagHook, ResolvableNaptrRecord,
+};
 pub use resolvable_srv_record::ResolvableSrvRecord;
-pub use resolvable_vec::ResolvableVec;
+pub use resolvable_vec::{CandidateSource, EagerCandidates, ResolvableVec};
 
 use async_trait::async_trait;
 use std::collections::VecDeque;
@@ -66,6 +69,47 @@ where
     /// (it's just an `async fn resolve_next(&mut self) -> Option<I>`).
     async fn resolve_next(&mut self) -> Option<I>;
 
+    /// The one slot buffer backing [ResolvableExt::peek_next], holding an item that has been
+    /// resolved but not returned yet. [ResolvableExt::resolve_next] must return what is in there
+    /// before resolving anything new.
+    ///
+    /// Types that don't keep such a buffer return `None` (the default) and can't be peeked.
+    fn lookahead(&mut self) -> Option<&mut Option<I>> {
+        None
+    }
+
+    /// Resolves the next item without consuming it: the following call to
+    /// [ResolvableExt::resolve_next] (or to this method) returns that same item.
+    ///
+    /// Like `resolve_next`, this might need one or more DNS queries, but only the first time.
+    async fn peek_next(&mut self) -> Option<&I>
+    where
+        Self: std::marker::Send,
+    {
+        if self.lookahead()?.is_none() {
+            let next = self.resolve_next().await;
+            *self.lookahead()? = next;
+        }
+
+        self.lookahead()?.as_ref()
+    }
+
+    /// Counts the items left to resolve, the peeked one included, without consuming any of them.
+    ///
+    /// This resolves as much of the structure as the count needs: NAPTR and SRV queries are
+    /// sent, but a domain whose A/AAAA records aren't known yet counts as one item (sparing its
+    /// query) unless it is a [ResolvableAddrRecord] counted on its own. The count is hence exact
+    /// once every address is known, and an estimate otherwise. Nothing is queried twice: the
+    /// following calls to [ResolvableExt::resolve_next] use what has been resolved here.
+    ///
+    /// Types that can't tell return a lower bound (the default): 1 unless they are empty.
+    async fn target_count(&mut self) -> usize
+    where
+        Self: std::marker::Send,
+    {
+        usize::from(!self.is_empty())
+    }
+
     fn is_empty(&self) -> bool {
         matches!(self.state(), ResolvableState::Empty)
     }
@@ -98,6 +142,17 @@ where
             None => None,
         }
     }
+
+    fn lookahead(&mut self) -> Option<&mut Option<I>> {
+        self.as_mut()?.lookahead()
+    }
+
+    async fn target_count(&mut self) -> usize {
+        match self {
+            Some(inner) => inner.target_count().await,
+            None => 0,
+        }
+    }
 }
 
 #[async_trait]
@@ -125,4 +180,17 @@ where
             None => None,
         }
     }
+
+    // the front is the one resolve_next asks first, so that's where a peeked item must be kept
+    fn lookahead(&mut self) -> Option<&mut Option<T>> {
+        self.front_mut()?.lookahead()
+    }
+
+    async fn target_count(&mut self) -> usize {
+        let mut count = 0;
+        for inner in self.iter_mut() {
+            count += inner.target_count().await;
+        }
+        count
+    }
 }
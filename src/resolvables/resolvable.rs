@@ -3,7 +3,7 @@ use async_trait::async_trait;
 use std::collections::VecDeque;
 
 #[derive(Debug, Clone)]
-pub struct Resolvable<T>(ResolvableInner<T>)
+pub struct Resolvable<T>(ResolvableInner<T>, Option<T>)
 where
     T: ResolvableItem;
 
@@ -13,11 +13,25 @@ where
     T: ResolvableItem,
 {
     fn state(&self) -> ResolvableState {
-        self.0.state()
+        match self.1 {
+            Some(_) => ResolvableState::NonEmpty,
+            None => self.0.state(),
+        }
     }
 
     async fn resolve_next(&mut self) -> Option<T> {
-        self.0.resolve_next().await
+        match self.1.take() {
+            Some(peeked) => Some(peeked),
+            None => self.0.resolve_next().await,
+        }
+    }
+
+    fn lookahead(&mut self) -> Option<&mut Option<T>> {
+        Some(&mut self.1)
+    }
+
+    async fn target_count(&mut self) -> usize {
+        usize::from(self.1.is_some()) + self.0.target_count().await
     }
 }
 
@@ -26,15 +40,15 @@ where
     T: ResolvableItem,
 {
     pub fn unset() -> Self {
-        Self(ResolvableInner::Unset)
+        Self(ResolvableInner::Unset, None)
     }
 
     pub fn empty() -> Self {
-        Self(ResolvableInner::Empty)
+        Self(ResolvableInner::Empty, None)
     }
 
     pub fn non_empty(stuff: impl Into<VecDeque<T>>) -> Self {
-        Self(ResolvableInner::NonEmpty(stuff.into()))
+        Self(ResolvableInner::NonEmpty(stuff.into()), None)
     }
 }
 
@@ -43,7 +57,7 @@ where
     T: ResolvableItem,
 {
     fn default() -> Self {
-        Self(Default::default())
+        Self(Default::default(), None)
     }
 }
 
@@ -52,7 +66,7 @@ where
     T: ResolvableItem,
 {
     fn from(from: Vec<T>) -> Self {
-        Self(from.into())
+        Self(from.into(), None)
     }
 }
 
@@ -96,6 +110,13 @@ where
             }
         }
     }
+
+    async fn target_count(&mut self) -> usize {
+        match self {
+            Self::NonEmpty(data) => data.len(),
+            _ => 0,
+        }
+    }
 }
 
 
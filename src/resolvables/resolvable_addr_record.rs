@@ -1,9 +1,11 @@
 use crate::{
-    DnsClient, Target,
+    DnsClient, IpFamily, Target,
+    records::chain_ttl,
     resolvables::{ResolvableExt, ResolvableIpAddr, ResolvableState, ResolvableVec},
 };
 use async_trait::async_trait;
 use rsip::{Domain, Port, Transport};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct ResolvableAddrRecord<C>
@@ -14,7 +16,13 @@ where
     domain: Domain,
     port: Port,
     transport: Transport,
+    /// Only the addresses of this family are kept, when set
+    family: Option<IpFamily>,
+    /// The TTL of the record this domain was reached through (e.g. a SRV record), which caps
+    /// the TTL of the targets when set
+    ttl_cap: Option<u32>,
     resolvable_ip_addrs: ResolvableVec<ResolvableIpAddr, Target>,
+    peeked: Option<Target>,
 }
 
 #[async_trait]
@@ -23,16 +31,33 @@ where
     C: DnsClient,
 {
     fn state(&self) -> ResolvableState {
-        self.resolvable_ip_addrs.state()
+        match self.peeked {
+            Some(_) => ResolvableState::NonEmpty,
+            None => self.resolvable_ip_addrs.state(),
+        }
     }
 
     async fn resolve_next(&mut self) -> Option<Target> {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+
         if self.resolvable_ip_addrs.is_unset() {
             self.resolve_domain().await;
         }
 
         self.resolvable_ip_addrs.resolve_next().await
     }
+
+    fn lookahead(&mut self) -> Option<&mut Option<Target>> {
+        Some(&mut self.peeked)
+    }
+
+    // exact, at the cost of the A/AAAA query
+    async fn target_count(&mut self) -> usize {
+        self.prefetch().await;
+        usize::from(self.peeked.is_some()) + self.resolvable_ip_addrs.target_count().await
+    }
 }
 
 impl<C> ResolvableAddrRecord<C>
@@ -40,7 +65,35 @@ where
     C: DnsClient,
 {
     pub fn new(dns_client: C, domain: Domain, port: Port, transport: Transport) -> Self {
-        Self { dns_client, domain, port, transport, resolvable_ip_addrs: Default::default() }
+        Self::new_with_family(dns_client, domain, port, transport, None)
+    }
+
+    /// Same as [new](Self::new), but only the addresses of the given family are resolved into
+    /// targets (`None` keeps both), for hosts that can only reach one of them.
+    pub fn new_with_family(
+        dns_client: C,
+        domain: Domain,
+        port: Port,
+        transport: Transport,
+        family: Option<IpFamily>,
+    ) -> Self {
+        Self {
+            dns_client,
+            domain,
+            port,
+            transport,
+            family,
+            ttl_cap: None,
+            resolvable_ip_addrs: Default::default(),
+            peeked: None,
+        }
+    }
+
+    /// Caps the TTL of the targets by the TTL of the record the domain was reached through (the
+    /// minimum of both is used), `None` keeps the TTL of the A/AAAA records
+    pub fn with_ttl_cap(mut self, ttl_cap: Option<u32>) -> Self {
+        self.ttl_cap = ttl_cap;
+        self
     }
 
     /// Create a ResolvableAddrRecord from a pre-resolved ResolvableIpAddr.
@@ -57,24 +110,50 @@ where
             domain,
             port,
             transport,
+            family: None,
+            ttl_cap: None,
             resolvable_ip_addrs: ResolvableVec::non_empty(vec![resolvable_ip]),
+            peeked: None,
+        }
+    }
+
+    /// Performs the A/AAAA query if it hasn't been performed yet, see
+    /// [ResolvableSrvRecord::with_target_concurrency](super::ResolvableSrvRecord::with_target_concurrency).
+    pub async fn prefetch(&mut self) {
+        if self.resolvable_ip_addrs.is_unset() {
+            self.resolve_domain().await;
         }
     }
 
     async fn resolve_domain(&mut self) {
+        debug_event!(domain = %self.domain, "A/AAAA query");
         match self.dns_client.ip_lookup(self.domain.clone()).await {
             Ok(a_record) => {
-                let ttl = a_record.ttl;
-                let resolvable_ip_addrs = a_record
-                    .ip_addrs
+                debug_event!(
+                    domain = %self.domain,
+                    ip_addrs = a_record.ip_addrs.len(),
+                    ttl = a_record.ttl,
+                    "A/AAAA query returned"
+                );
+                let mut targets = a_record.into_targets(self.port, self.transport);
+                targets.retain(|target| {
+                    self.family.is_none_or(|family| family.contains(&target.ip_addr))
+                });
+                let siblings =
+                    Arc::new(targets.iter().map(|target| target.ip_addr).collect::<Vec<_>>());
+                let resolvable_ip_addrs = targets
                     .into_iter()
-                    .map(|ip_addr| {
-                        ResolvableIpAddr::new_with_ttl(ip_addr, self.port, self.transport, ttl)
+                    .map(|mut target| {
+                        if let Some(ttl_cap) = self.ttl_cap {
+                            target.ttl = chain_ttl(ttl_cap, target.ttl);
+                        }
+                        ResolvableIpAddr::from(target.with_siblings(Some(siblings.clone())))
                     })
                     .collect::<Vec<_>>();
                 self.resolvable_ip_addrs = ResolvableVec::non_empty(resolvable_ip_addrs)
             }
-            Err(_) => {
+            Err(_error) => {
+                debug_event!(domain = %self.domain, error = %_error, "A/AAAA query failed");
                 self.resolvable_ip_addrs = ResolvableVec::empty();
             }
         }
@@ -40,6 +40,26 @@ where
             Self::NaptrRecord(inner) => inner.resolve_next().await,
         }
     }
+
+    fn lookahead(&mut self) -> Option<&mut Option<Target>> {
+        match self {
+            Self::IpAddr(inner) => inner.lookahead(),
+            Self::AddrRecord(inner) => inner.lookahead(),
+            Self::SrvRecord(inner) => inner.lookahead(),
+            Self::NaptrRecord(inner) => inner.lookahead(),
+        }
+    }
+
+    async fn target_count(&mut self) -> usize {
+        match self {
+            Self::IpAddr(inner) => inner.target_count().await,
+            // a domain whose addresses aren't known yet counts as one, sparing its query
+            Self::AddrRecord(inner) if inner.is_unset() => 1,
+            Self::AddrRecord(inner) => inner.target_count().await,
+            Self::SrvRecord(inner) => inner.target_count().await,
+            Self::NaptrRecord(inner) => inner.target_count().await,
+        }
+    }
 }
 
 impl<C: DnsClient> From<ResolvableIpAddr> for ResolvableEnum<C> {
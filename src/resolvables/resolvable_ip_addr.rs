@@ -1,10 +1,11 @@
 use crate::{
     resolvables::{Resolvable, ResolvableExt, ResolvableState},
-    Target,
+    Target, DEFAULT_TTL,
 };
 use async_trait::async_trait;
 use rsip::{Port, Transport};
 use std::net::IpAddr;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct ResolvableIpAddr {
@@ -12,31 +13,79 @@ pub struct ResolvableIpAddr {
     port: Port,
     transport: Transport,
     ttl: u32,
+    scope_id: Option<u32>,
+    siblings: Option<Arc<Vec<IpAddr>>>,
+    peeked: Option<Target>,
 }
 
 #[async_trait]
 impl ResolvableExt<Target> for ResolvableIpAddr {
     fn state(&self) -> ResolvableState {
-        self.ip_addr.state()
+        match self.peeked {
+            Some(_) => ResolvableState::NonEmpty,
+            None => self.ip_addr.state(),
+        }
     }
 
     async fn resolve_next(&mut self) -> Option<Target> {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+
         self.ip_addr.resolve_next().await.map(|ip_addr| Target {
             ip_addr,
             port: self.port,
             transport: self.transport,
             ttl: self.ttl,
+            scope_id: self.scope_id,
+            siblings: self.siblings.clone(),
         })
     }
+
+    fn lookahead(&mut self) -> Option<&mut Option<Target>> {
+        Some(&mut self.peeked)
+    }
+
+    async fn target_count(&mut self) -> usize {
+        usize::from(self.peeked.is_some()) + self.ip_addr.target_count().await
+    }
 }
 
 impl ResolvableIpAddr {
     pub fn new(ip_addr: IpAddr, port: Port, transport: Transport) -> Self {
-        Self { ip_addr: Resolvable::non_empty(vec![ip_addr]), port, transport, ttl: 300 }
+        Self::new_with_ttl(ip_addr, port, transport, DEFAULT_TTL)
     }
 
     pub fn new_with_ttl(ip_addr: IpAddr, port: Port, transport: Transport, ttl: u32) -> Self {
-        Self { ip_addr: Resolvable::non_empty(vec![ip_addr]), port, transport, ttl }
+        Self {
+            ip_addr: Resolvable::non_empty(vec![ip_addr]),
+            port,
+            transport,
+            ttl,
+            scope_id: None,
+            siblings: None,
+            peeked: None,
+        }
+    }
+
+    /// Resolves into a target within the given IPv6 scope, see [Target::scope_id]
+    pub fn with_scope_id(mut self, scope_id: Option<u32>) -> Self {
+        self.scope_id = scope_id;
+        self
+    }
+
+    /// Resolves into a target carrying the addresses of its host, see [Target::siblings]
+    pub fn with_siblings(mut self, siblings: Option<Arc<Vec<IpAddr>>>) -> Self {
+        self.siblings = siblings;
+        self
+    }
+}
+
+impl From<Target> for ResolvableIpAddr {
+    fn from(target: Target) -> Self {
+        Self::new_with_ttl(target.ip_addr, target.port, target.transport, target.ttl)
+            .with_scope_id(target.scope_id)
+            .with_siblings(target.siblings)
     }
 }
 
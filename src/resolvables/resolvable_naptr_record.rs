@@ -1,11 +1,58 @@
 use crate::{
-    DnsClient, Target,
-    records::NaptrFlags,
-    resolvables::{ResolvableExt, ResolvableSrvRecord, ResolvableState, ResolvableVec},
+    DnsClient, DnsError, IpFamily, Target,
+    context::default_port_from,
+    records::{NaptrEntry, NaptrFlags, NaptrRecord, NaptrServices, domain_key},
+    resolvables::{
+        ResolvableAddrRecord, ResolvableEnum, ResolvableExt, ResolvableSrvRecord, ResolvableState,
+        ResolvableVec,
+    },
 };
 use async_trait::async_trait;
-use rsip::{Domain, Transport};
+use rsip::{Domain, Port, Transport};
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::fmt;
+use std::sync::Arc;
+
+/// How many non-terminal NAPTR hops are followed by default, see
+/// [ResolvableNaptrRecord::with_max_depth].
+pub const DEFAULT_MAX_NAPTR_DEPTH: usize = 3;
+
+/// What the NAPTR query of a [ResolvableNaptrRecord] came back with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NaptrOutcome {
+    /// The domain has a NAPTR record
+    Found,
+    /// The domain has no NAPTR record (NXDOMAIN/NODATA)
+    NotFound,
+    /// The domain has a NAPTR record, but none of its entries can be followed: it has no entry
+    /// at all (a NODATA answer some resolvers hand over as an empty record), or none with a
+    /// supported service and flag. Treated like [NotFound](Self::NotFound) by the fallbacks.
+    Empty,
+    /// The query failed, see [DnsClient::try_naptr_lookup]
+    Failed(DnsError),
+}
+
+/// Application defined handling of the NAPTR entries with the `P` flag, which the resolution
+/// itself ignores, see [Context::on_p_flag](crate::Context::on_p_flag)
+#[derive(Clone)]
+pub struct PFlagHook(Arc<dyn Fn(&NaptrEntry) + Send + Sync>);
+
+impl PFlagHook {
+    pub fn new(hook: impl Fn(&NaptrEntry) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    pub fn call(&self, entry: &NaptrEntry) {
+        (self.0)(entry)
+    }
+}
+
+impl fmt::Debug for PFlagHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PFlagHook")
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct ResolvableNaptrRecord<C>
@@ -15,7 +62,27 @@ where
     dns_client: C,
     domain: Domain,
     available_transports: Vec<Transport>,
-    resolvable_srv_records: ResolvableVec<ResolvableSrvRecord<C>, Target>,
+    /// Break ties between entries of equal order and preference using the order of
+    /// `available_transports`
+    prefer_transport_order: bool,
+    max_depth: usize,
+    /// Ports used for the entries with the `A` flag, which carry no port of their own
+    default_ports: HashMap<Transport, Port>,
+    /// Only the addresses of this family are kept, when set
+    family: Option<IpFamily>,
+    /// See [ResolvableSrvRecord::with_target_concurrency]
+    target_concurrency: Option<usize>,
+    /// Handed the entries with the `P` flag, see [ResolvableNaptrRecord::with_p_flag_hook]
+    p_flag_hook: Option<PFlagHook>,
+    /// The only services followed, when set
+    allowed_services: Option<Vec<NaptrServices>>,
+    /// Domains already queried higher up in the NAPTR chain, used to detect loops
+    visited: Vec<Domain>,
+    /// Used instead of querying the domain, see [ResolvableNaptrRecord::from_naptr_record]
+    prefetched: Option<NaptrRecord>,
+    outcome: Option<NaptrOutcome>,
+    resolvable_records: ResolvableVec<ResolvableEnum<C>, Target>,
+    peeked: Option<Target>,
 }
 
 #[async_trait]
@@ -24,15 +91,34 @@ where
     C: DnsClient,
 {
     fn state(&self) -> ResolvableState {
-        self.resolvable_srv_records.state()
+        match self.peeked {
+            Some(_) => ResolvableState::NonEmpty,
+            None => self.resolvable_records.state(),
+        }
     }
 
     async fn resolve_next(&mut self) -> Option<Target> {
-        if self.resolvable_srv_records.is_unset() {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+
+        if self.resolvable_records.is_unset() {
             self.resolve_domain().await;
         }
 
-        self.resolvable_srv_records.resolve_next().await
+        self.resolvable_records.resolve_next().await
+    }
+
+    fn lookahead(&mut self) -> Option<&mut Option<Target>> {
+        Some(&mut self.peeked)
+    }
+
+    async fn target_count(&mut self) -> usize {
+        if self.resolvable_records.is_unset() {
+            self.resolve_domain().await;
+        }
+
+        usize::from(self.peeked.is_some()) + self.resolvable_records.target_count().await
     }
 }
 
@@ -45,53 +131,265 @@ where
             dns_client,
             domain,
             available_transports,
-            resolvable_srv_records: Default::default(),
+            prefer_transport_order: false,
+            max_depth: DEFAULT_MAX_NAPTR_DEPTH,
+            default_ports: HashMap::new(),
+            family: None,
+            target_concurrency: None,
+            p_flag_hook: None,
+            allowed_services: None,
+            visited: vec![],
+            prefetched: None,
+            outcome: None,
+            resolvable_records: Default::default(),
+            peeked: None,
+        }
+    }
+
+    /// Create from a pre-fetched NaptrRecord (e.g., from a provisioning system): its entries are
+    /// followed without querying NAPTR for its domain. The SRV and A/AAAA queries, and the NAPTR
+    /// queries of non-terminal entries, are sent as usual.
+    pub fn from_naptr_record(
+        dns_client: C,
+        naptr_record: NaptrRecord,
+        available_transports: Vec<Transport>,
+    ) -> Self {
+        let domain = naptr_record.domain.clone();
+
+        Self {
+            prefetched: Some(naptr_record),
+            ..Self::new(dns_client, domain, available_transports)
         }
     }
 
-    //TODO: should probably resolve U + sip URI and A flag as well ?
+    /// Sets how many non-terminal NAPTR entries (empty flags) are followed in a chain before
+    /// giving up. A depth of 0 only considers terminal entries of the first NAPTR record.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Overrides the standard default port of the given transports, for entries with the `A`
+    /// flag
+    pub fn with_default_ports(mut self, default_ports: HashMap<Transport, Port>) -> Self {
+        self.default_ports = default_ports;
+        self
+    }
+
+    /// Only resolve the targets into addresses of the given family, `None` keeps both
+    pub fn with_family(mut self, family: Option<IpFamily>) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Resolve the targets of every SRV record concurrently, see
+    /// [ResolvableSrvRecord::with_target_concurrency]
+    pub fn with_target_concurrency(mut self, limit: Option<usize>) -> Self {
+        self.target_concurrency = limit;
+        self
+    }
+
+    /// Hands the entries with the `P` flag over to `hook`, whatever their service, as they are
+    /// found (in followed NAPTR records too). They are never resolved into targets, with or
+    /// without a hook.
+    pub fn with_p_flag_hook(mut self, hook: Option<PFlagHook>) -> Self {
+        self.p_flag_hook = hook;
+        self
+    }
+
+    /// Only follows the entries of the given services (in followed NAPTR records too), dropping
+    /// any other one even if it maps to a supported transport. `None` follows every service.
+    pub fn with_allowed_services(mut self, allowed_services: Option<Vec<NaptrServices>>) -> Self {
+        self.allowed_services = allowed_services;
+        self
+    }
+
+    /// Treats the order of the available transports as a preference: entries are tried by
+    /// order, then preference, then transport, instead of in the order of the record.
+    pub fn with_transport_preference(mut self) -> Self {
+        self.prefer_transport_order = true;
+        self
+    }
+
+    /// The outcome of the NAPTR query, `None` until it has been sent
+    pub fn outcome(&self) -> Option<&NaptrOutcome> {
+        self.outcome.as_ref()
+    }
+
+    /// Forget the outcome of the NAPTR query so that the next
+    /// [resolve_next](ResolvableExt::resolve_next) sends it again
+    pub(crate) fn reset(&mut self) {
+        self.outcome = None;
+        self.resolvable_records = Default::default();
+        self.peeked = None;
+    }
+
+    //TODO: should probably resolve U + sip URI as well ?
     async fn resolve_domain(&mut self) {
         use crate::SrvDomain;
 
-        let naptr_record = match self.dns_client.naptr_lookup(self.domain.clone()).await {
-            Some(naptr_record) => naptr_record,
+        let lookup = match &self.prefetched {
+            Some(naptr_record) => Ok(Some(naptr_record.clone())),
             None => {
-                self.resolvable_srv_records = ResolvableVec::empty();
+                debug_event!(domain = %self.domain, "NAPTR query");
+                self.dns_client.try_naptr_lookup(self.domain.clone()).await
+            }
+        };
+        let naptr_record = match lookup {
+            Ok(Some(naptr_record)) => naptr_record,
+            Ok(None) => {
+                debug_event!(domain = %self.domain, "NAPTR query returned no record");
+                self.outcome = Some(NaptrOutcome::NotFound);
+                self.resolvable_records = ResolvableVec::empty();
+                return;
+            }
+            Err(error) => {
+                debug_event!(domain = %self.domain, error = %error, "NAPTR query failed");
+                self.outcome = Some(NaptrOutcome::Failed(error));
+                self.resolvable_records = ResolvableVec::empty();
                 return;
             }
         };
+        debug_event!(
+            domain = %self.domain,
+            entries = naptr_record.entries.len(),
+            additional_srvs = naptr_record.additional_srvs.len(),
+            additional_hosts = naptr_record.additional_hosts.len(),
+            ttl = naptr_record.ttl,
+            "NAPTR query returned"
+        );
+
+        self.outcome = Some(NaptrOutcome::Found);
+
+        // protocol specific rewrites (RFC 3403 section 4.1) are up to the application
+        if let Some(hook) = &self.p_flag_hook {
+            naptr_record
+                .iter()
+                .filter(|entry| matches!(entry.flags, NaptrFlags::P))
+                .for_each(|entry| hook.call(entry));
+        }
 
         // Check if we have cached SRV records from ADDITIONAL section
         let has_additional_srvs = !naptr_record.additional_srvs.is_empty();
 
-        let resolvable_srv_records = naptr_record
+        let mut entries = naptr_record
             .iter()
-            .filter(|s| match s.services.transport() {
-                Some(transport) => self.available_transports.contains(&transport),
-                None => false,
+            .filter(|entry| {
+                self.allowed_services
+                    .as_ref()
+                    .is_none_or(|allowed_services| allowed_services.contains(&entry.services))
+            })
+            .filter_map(|entry| {
+                let transport = entry.services.transport()?;
+                let rank = self.available_transports.iter().position(|t| *t == transport)?;
+                Some((entry, rank))
             })
-            .filter(|s| matches!(s.flags, NaptrFlags::S))
+            .collect::<Vec<_>>();
+        if self.prefer_transport_order {
+            entries.sort_by_key(|(entry, rank)| (entry.sort_key(), *rank));
+        }
+
+        let resolvable_records = entries
+            .into_iter()
+            .map(|(entry, _)| entry)
             .filter_map(|e| {
+                if is_non_terminal(&e.flags) {
+                    return self.follow(e.replacement.clone()).map(Into::into);
+                }
+                if matches!(e.flags, NaptrFlags::A) {
+                    // the replacement is resolved through A/AAAA right away, there is no SRV
+                    // record to give a port. The transport of the service is already the secure
+                    // one when it has to be (e.g. TLS for SIPS+D2T).
+                    let transport = e.services.transport()?;
+                    return Some(
+                        ResolvableAddrRecord::new_with_family(
+                            self.dns_client.clone(),
+                            e.replacement.clone(),
+                            default_port_from(&self.default_ports, false, transport),
+                            transport,
+                            self.family,
+                        )
+                        .into(),
+                    );
+                }
+                if !matches!(e.flags, NaptrFlags::S) {
+                    return None;
+                }
+
                 if let Ok(srv_domain) = TryInto::<SrvDomain>::try_into(e.clone()) {
                     // Check if we have this SRV in additional section
                     if has_additional_srvs
                         && let Some(srv_record) = naptr_record.get_additional_srv(&srv_domain)
                     {
-                        // Use cached SRV record - it already has additional_hosts populated
-                        // Create ResolvableSrvRecord with pre-fetched data
-                        return Some(ResolvableSrvRecord::from_srv_record(
-                            self.dns_client.clone(),
-                            srv_record.clone(),
-                        ));
+                        // Use cached SRV record, its targets are resolved through the A/AAAA
+                        // records of the ADDITIONAL section shared by the whole NAPTR record
+                        return Some(
+                            ResolvableSrvRecord::from_srv_record_with_hosts(
+                                self.dns_client.clone(),
+                                srv_record.clone(),
+                                &naptr_record.additional_hosts,
+                                self.family,
+                            )
+                            .with_target_concurrency(self.target_concurrency)
+                            .into(),
+                        );
                     }
                     // No cached SRV, create one that will query DNS
-                    Some(ResolvableSrvRecord::new(self.dns_client.clone(), srv_domain))
+                    Some(
+                        ResolvableSrvRecord::new(self.dns_client.clone(), srv_domain)
+                            .with_family(self.family)
+                            .with_target_concurrency(self.target_concurrency)
+                            .into(),
+                    )
                 } else {
                     None
                 }
             })
-            .collect::<Vec<ResolvableSrvRecord<C>>>();
+            .collect::<Vec<ResolvableEnum<C>>>();
 
-        self.resolvable_srv_records = ResolvableVec::non_empty(resolvable_srv_records)
+        if resolvable_records.is_empty() {
+            debug_event!(domain = %self.domain, "NAPTR record has no usable entry");
+            self.outcome = Some(NaptrOutcome::Empty);
+        }
+        self.resolvable_records = ResolvableVec::non_empty(resolvable_records)
     }
+
+    /// NAPTR lookup on the replacement of a non-terminal entry, unless the depth limit is
+    /// reached or the replacement has already been queried in this chain
+    fn follow(&self, replacement: Domain) -> Option<Self> {
+        if self.max_depth == 0 {
+            return None;
+        }
+
+        let mut visited = self.visited.clone();
+        visited.push(self.domain.clone());
+        let replacement_key = domain_key(&replacement);
+        if visited.iter().any(|domain| domain_key(domain) == replacement_key) {
+            return None;
+        }
+
+        Some(Self {
+            dns_client: self.dns_client.clone(),
+            domain: replacement,
+            available_transports: self.available_transports.clone(),
+            prefer_transport_order: self.prefer_transport_order,
+            max_depth: self.max_depth - 1,
+            default_ports: self.default_ports.clone(),
+            family: self.family,
+            target_concurrency: self.target_concurrency,
+            p_flag_hook: self.p_flag_hook.clone(),
+            allowed_services: self.allowed_services.clone(),
+            visited,
+            prefetched: None,
+            outcome: None,
+            resolvable_records: Default::default(),
+            peeked: None,
+        })
+    }
+}
+
+/// An empty flags field means that the rewrite process continues with the replacement
+/// ([RFC 3403](https://datatracker.ietf.org/doc/html/rfc3403#section-4.1))
+pub(crate) fn is_non_terminal(flags: &NaptrFlags) -> bool {
+    matches!(flags, NaptrFlags::Other(flags) if flags.is_empty())
 }
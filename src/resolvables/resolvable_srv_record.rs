@@ -1,11 +1,17 @@
 use crate::{
-    DnsClient, Target,
-    records::SrvDomain,
+    DnsClient, IpFamily, Target, default_port,
+    records::{AddrRecord, SrvDomain, SrvRecord, additional_host, chain_ttl},
     resolvables::{
-        ResolvableAddrRecord, ResolvableExt, ResolvableIpAddr, ResolvableState, ResolvableVec,
+        CandidateSource, ResolvableAddrRecord, ResolvableExt, ResolvableIpAddr, ResolvableState,
+        ResolvableVec,
     },
 };
 use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use rsip::{Domain, Port, Transport};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
 pub struct ResolvableSrvRecord<C>
@@ -14,7 +20,14 @@ where
 {
     dns_client: C,
     domain: SrvDomain,
-    resolvable_addr_records: ResolvableVec<ResolvableAddrRecord<C>, Target>,
+    /// Only the addresses of this family are kept, when set
+    family: Option<IpFamily>,
+    /// How many A/AAAA queries of the targets may be in flight at once when they are all sent
+    /// eagerly, consumed once they have been
+    prefetch_targets: Option<usize>,
+    /// Built one target at a time, as the previous ones are exhausted
+    resolvable_addr_records: ResolvableVec<ResolvableAddrRecord<C>, Target, SrvTargets<C>>,
+    peeked: Option<Target>,
 }
 
 #[async_trait]
@@ -23,16 +36,45 @@ where
     C: DnsClient,
 {
     fn state(&self) -> ResolvableState {
-        self.resolvable_addr_records.state()
+        match self.peeked {
+            Some(_) => ResolvableState::NonEmpty,
+            None => self.resolvable_addr_records.state(),
+        }
     }
 
     async fn resolve_next(&mut self) -> Option<Target> {
+        if let Some(peeked) = self.peeked.take() {
+            return Some(peeked);
+        }
+
         if self.resolvable_addr_records.is_unset() {
             self.resolve_domain().await;
         }
+        if let Some(limit) = self.prefetch_targets.take() {
+            self.resolve_targets(limit).await;
+        }
 
         self.resolvable_addr_records.resolve_next().await
     }
+
+    fn lookahead(&mut self) -> Option<&mut Option<Target>> {
+        Some(&mut self.peeked)
+    }
+
+    async fn target_count(&mut self) -> usize {
+        self.prefetch().await;
+
+        // the targets not built yet count as one each too
+        let mut count = usize::from(self.peeked.is_some()) + self.resolvable_addr_records.pending();
+        for addr_record in self.resolvable_addr_records.iter_mut() {
+            // a target that didn't come with its addresses counts as one, sparing its query
+            count += match addr_record.is_unset() {
+                true => 1,
+                false => addr_record.target_count().await,
+            };
+        }
+        count
+    }
 }
 
 impl<C> ResolvableSrvRecord<C>
@@ -40,88 +82,204 @@ where
     C: DnsClient,
 {
     pub fn new(dns_client: C, domain: SrvDomain) -> Self {
-        Self { dns_client, domain, resolvable_addr_records: Default::default() }
+        Self {
+            dns_client,
+            domain,
+            family: None,
+            prefetch_targets: None,
+            resolvable_addr_records: Default::default(),
+            peeked: None,
+        }
+    }
+
+    /// Only resolve the targets into addresses of the given family, `None` keeps both
+    pub fn with_family(mut self, family: Option<IpFamily>) -> Self {
+        self.family = family;
+        self
+    }
+
+    /// Once the SRV record is known, send the A/AAAA queries of all of its targets at once (at
+    /// most `limit` of them in flight, 0 meaning no limit) instead of one after the other as
+    /// [resolve_next](ResolvableExt::resolve_next) gets to them. Targets are still returned in
+    /// priority/weight order. `None` (the default) keeps them lazy.
+    pub fn with_target_concurrency(mut self, limit: Option<usize>) -> Self {
+        self.prefetch_targets = limit;
+        self
+    }
+
+    /// Performs the SRV query (without resolving any target) if it hasn't been performed yet.
+    /// Allows callers to issue several SRV queries concurrently and consume them later on.
+    pub async fn prefetch(&mut self) {
+        if self.resolvable_addr_records.is_unset() {
+            self.resolve_domain().await;
+        }
     }
 
     /// Create from a pre-fetched SrvRecord (e.g., from NAPTR ADDITIONAL section)
     /// This immediately processes the SRV entries and uses cached additional_hosts
     pub fn from_srv_record(dns_client: C, srv_record: crate::records::SrvRecord) -> Self {
-        let transport = srv_record.transport();
-        let mut resolvable_addr_records = Vec::new();
+        Self::from_srv_record_with_hosts(dns_client, srv_record, &HashMap::new(), None)
+    }
 
-        // Process each SRV entry immediately
-        for (domain, port) in srv_record.domains_with_ports() {
-            // Check if we have additional A/AAAA records for this target
-            if let Some(addr_record) = srv_record.get_additional_for_target(&domain) {
-                // Use pre-fetched IP addresses from ADDITIONAL section (FAST PATH!)
-                for ip_addr in &addr_record.ip_addrs {
-                    resolvable_addr_records.push(ResolvableAddrRecord::from_resolvable_ip(
-                        dns_client.clone(),
-                        domain.clone(),
-                        port,
-                        transport,
-                        ResolvableIpAddr::new_with_ttl(*ip_addr, port, transport, addr_record.ttl),
-                    ));
-                }
-            } else {
-                // Fall back to separate A/AAAA query (SLOW PATH)
-                resolvable_addr_records.push(ResolvableAddrRecord::new(
-                    dns_client.clone(),
-                    domain,
-                    port,
-                    transport,
-                ));
-            }
-        }
+    /// Same as [from_srv_record](Self::from_srv_record), but targets missing from the
+    /// additional hosts of the SRV record are also looked up in `shared_hosts` (the additional
+    /// hosts of the NAPTR record the SRV record came with), and only the addresses of `family`
+    /// are kept when set
+    pub(crate) fn from_srv_record_with_hosts(
+        dns_client: C,
+        srv_record: crate::records::SrvRecord,
+        shared_hosts: &HashMap<Domain, AddrRecord>,
+        family: Option<IpFamily>,
+    ) -> Self {
+        let srv_targets = SrvTargets::new(&dns_client, &srv_record, shared_hosts, family);
 
         Self {
             dns_client,
             domain: srv_record.domain,
-            resolvable_addr_records: ResolvableVec::non_empty(resolvable_addr_records),
+            family,
+            prefetch_targets: None,
+            resolvable_addr_records: ResolvableVec::lazy(srv_targets),
+            peeked: None,
         }
     }
 
+    /// The A/AAAA queries of the targets that didn't come with their addresses, concurrently
+    async fn resolve_targets(&mut self, limit: usize) {
+        debug_event!(domain = %self.domain, limit, "resolving SRV targets concurrently");
+        self.resolvable_addr_records.build_all();
+        stream::iter(self.resolvable_addr_records.iter_mut())
+            .for_each_concurrent(limit, |addr_record| addr_record.prefetch())
+            .await;
+    }
+
     async fn resolve_domain(&mut self) {
-        match self.dns_client.srv_lookup(self.domain.clone()).await {
-            Some(srv_record) => {
-                let transport = srv_record.transport();
-                let mut resolvable_addr_records = Vec::new();
-
-                // Process each SRV entry
-                for (domain, port) in srv_record.domains_with_ports() {
-                    // Check if we have additional A/AAAA records for this target
-                    if let Some(addr_record) = srv_record.get_additional_for_target(&domain) {
-                        // Use pre-fetched IP addresses from ADDITIONAL section (FAST PATH!)
-                        for ip_addr in &addr_record.ip_addrs {
-                            resolvable_addr_records.push(ResolvableAddrRecord::from_resolvable_ip(
-                                self.dns_client.clone(),
-                                domain.clone(),
-                                port,
-                                transport,
-                                ResolvableIpAddr::new_with_ttl(
-                                    *ip_addr,
-                                    port,
-                                    transport,
-                                    addr_record.ttl,
-                                ),
-                            ));
-                        }
-                    } else {
-                        // Fall back to separate A/AAAA query (SLOW PATH)
-                        resolvable_addr_records.push(ResolvableAddrRecord::new(
-                            self.dns_client.clone(),
-                            domain,
-                            port,
-                            transport,
-                        ));
-                    }
-                }
+        debug_event!(domain = %self.domain, "SRV query");
+        match self.dns_client.try_srv_lookup(self.domain.clone()).await {
+            Ok(Some(srv_record)) => {
+                debug_event!(
+                    domain = %self.domain,
+                    entries = srv_record.entries.len(),
+                    additional_hosts = srv_record.additional_hosts.len(),
+                    ttl = srv_record.ttl,
+                    "SRV query returned"
+                );
+                let srv_targets =
+                    SrvTargets::new(&self.dns_client, &srv_record, &HashMap::new(), self.family);
 
-                self.resolvable_addr_records = ResolvableVec::non_empty(resolvable_addr_records)
+                self.resolvable_addr_records = ResolvableVec::lazy(srv_targets)
+            }
+            Ok(None) => {
+                debug_event!(domain = %self.domain, "SRV query returned no record");
+                self.resolvable_addr_records = ResolvableVec::empty();
             }
-            None => {
+            Err(_error) => {
+                debug_event!(domain = %self.domain, error = %_error, "SRV query failed");
                 self.resolvable_addr_records = ResolvableVec::empty();
             }
         }
     }
 }
+
+/// The targets of every SRV entry, turned into a [ResolvableAddrRecord] only once the previous
+/// ones are exhausted: straight from the A/AAAA records that came along (in the ADDITIONAL
+/// section of the SRV record or in `shared_hosts`) when there are some, through a separate
+/// A/AAAA query otherwise. Their TTL is capped by the one of the SRV record either way.
+#[derive(Debug, Clone)]
+struct SrvTargets<C: DnsClient> {
+    dns_client: C,
+    transport: Transport,
+    /// The TTL of the SRV record
+    ttl: u32,
+    targets: VecDeque<SrvTarget>,
+    family: Option<IpFamily>,
+}
+
+#[derive(Debug, Clone)]
+enum SrvTarget {
+    /// An address that came along with the SRV record (FAST PATH!)
+    Known { domain: Domain, port: Port, ip_addr: IpAddr, ttl: u32, siblings: Arc<Vec<IpAddr>> },
+    /// A target whose addresses have to be queried (SLOW PATH)
+    Unknown { domain: Domain, port: Port },
+}
+
+impl<C: DnsClient> SrvTargets<C> {
+    fn new(
+        dns_client: &C,
+        srv_record: &SrvRecord,
+        shared_hosts: &HashMap<Domain, AddrRecord>,
+        family: Option<IpFamily>,
+    ) -> Self {
+        let mut targets = VecDeque::new();
+
+        for (domain, port) in srv_record.domains_with_ports() {
+            // a zero port is no port to connect to, the transport default applies instead
+            let port = match port == Port::from(0) {
+                true => default_port(srv_record.domain.secure, srv_record.transport()),
+                false => port,
+            };
+
+            // Check if we have additional A/AAAA records for this target
+            let addr_record = srv_record
+                .get_additional_for_target(&domain)
+                .or_else(|| additional_host(shared_hosts, &domain));
+            match addr_record {
+                Some(addr_record) => {
+                    let ip_addrs = addr_record
+                        .ip_addrs
+                        .iter()
+                        .filter(|ip_addr| family.is_none_or(|family| family.contains(ip_addr)))
+                        .collect::<Vec<_>>();
+                    let siblings =
+                        Arc::new(ip_addrs.iter().map(|ip_addr| **ip_addr).collect::<Vec<_>>());
+                    targets.extend(ip_addrs.into_iter().map(|ip_addr| SrvTarget::Known {
+                        domain: domain.clone(),
+                        port,
+                        ip_addr: *ip_addr,
+                        ttl: chain_ttl(srv_record.ttl, addr_record.ttl_for(*ip_addr)),
+                        siblings: siblings.clone(),
+                    }))
+                }
+                None => targets.push_back(SrvTarget::Unknown { domain, port }),
+            }
+        }
+
+        Self {
+            dns_client: dns_client.clone(),
+            transport: srv_record.transport(),
+            ttl: srv_record.ttl,
+            targets,
+            family,
+        }
+    }
+}
+
+impl<C: DnsClient> CandidateSource<ResolvableAddrRecord<C>> for SrvTargets<C> {
+    fn next_candidate(&mut self) -> Option<ResolvableAddrRecord<C>> {
+        let addr_record = match self.targets.pop_front()? {
+            SrvTarget::Known { domain, port, ip_addr, ttl, siblings } => {
+                ResolvableAddrRecord::from_resolvable_ip(
+                    self.dns_client.clone(),
+                    domain,
+                    port,
+                    self.transport,
+                    ResolvableIpAddr::new_with_ttl(ip_addr, port, self.transport, ttl)
+                        .with_siblings(Some(siblings)),
+                )
+            }
+            SrvTarget::Unknown { domain, port } => ResolvableAddrRecord::new_with_family(
+                self.dns_client.clone(),
+                domain,
+                port,
+                self.transport,
+                self.family,
+            )
+            .with_ttl_cap(Some(self.ttl)),
+        };
+
+        Some(addr_record)
+    }
+
+    fn remaining(&self) -> usize {
+        self.targets.len()
+    }
+}
@@ -2,21 +2,51 @@ use crate::resolvables::{ResolvableExt, ResolvableItem, ResolvableState};
 use async_trait::async_trait;
 use std::{collections::VecDeque, marker::PhantomData};
 
+/// Builds the candidates of a lazy [ResolvableVec] one at a time, only once the previous ones are
+/// exhausted, so that a huge candidate set (like the targets of a SRV record) isn't built up
+/// front when most of it is never tried.
+pub trait CandidateSource<T>: Send {
+    /// Builds the next candidate, `None` once there is none left
+    fn next_candidate(&mut self) -> Option<T>;
+
+    /// How many candidates are left to build
+    fn remaining(&self) -> usize;
+}
+
+/// The [CandidateSource] of a [ResolvableVec] whose candidates are all given up front
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EagerCandidates;
+
+impl<T> CandidateSource<T> for EagerCandidates {
+    fn next_candidate(&mut self) -> Option<T> {
+        None
+    }
+
+    fn remaining(&self) -> usize {
+        0
+    }
+}
+
+/// Candidates tried in order: the items of the first one, then of the next one once it is
+/// exhausted and so on. The candidates are either all given up front, or built on demand by a
+/// [CandidateSource] (see [ResolvableVec::lazy]).
 #[derive(Debug, Clone)]
-pub struct ResolvableVec<T, I>(Option<VecDeque<T>>, PhantomData<I>)
+pub struct ResolvableVec<T, I, S = EagerCandidates>(Option<VecDeque<T>>, Option<S>, PhantomData<I>)
 where
     T: ResolvableExt<I> + std::marker::Send,
     I: ResolvableItem;
 
 #[async_trait]
-impl<T, I> ResolvableExt<I> for ResolvableVec<T, I>
+impl<T, I, S> ResolvableExt<I> for ResolvableVec<T, I, S>
 where
     T: ResolvableExt<I> + std::marker::Send,
     I: ResolvableItem,
+    S: CandidateSource<T>,
 {
     fn state(&self) -> ResolvableState {
         match &self.0 {
             None => ResolvableState::Unset,
+            Some(inner) if inner.is_empty() && self.pending() > 0 => ResolvableState::NonEmpty,
             Some(inner) => match inner.state() {
                 ResolvableState::Unset => ResolvableState::NonEmpty,
                 state => state,
@@ -25,35 +55,108 @@ where
     }
 
     async fn resolve_next(&mut self) -> Option<I> {
-        self.0.resolve_next().await
+        loop {
+            if let Some(next) = self.0.resolve_next().await {
+                return Some(next);
+            }
+            // every candidate built so far is exhausted (and gone)
+            if !self.build_next() {
+                return None;
+            }
+        }
+    }
+
+    fn lookahead(&mut self) -> Option<&mut Option<I>> {
+        if self.0.as_ref().is_some_and(VecDeque::is_empty) {
+            self.build_next();
+        }
+        self.0.lookahead()
+    }
+
+    async fn target_count(&mut self) -> usize {
+        // a candidate not built yet counts as one, like a domain whose addresses aren't known
+        self.0.target_count().await + self.pending()
     }
 }
 
-impl<T, I> ResolvableVec<T, I>
+impl<T, I, S> ResolvableVec<T, I, S>
 where
     T: ResolvableExt<I> + std::marker::Send,
     I: ResolvableItem,
+    S: CandidateSource<T>,
 {
     pub fn unset() -> Self {
-        Self(None, Default::default())
+        Self(None, None, Default::default())
     }
 
     pub fn empty() -> Self {
-        Self(Some(vec![].into()), Default::default())
+        Self(Some(vec![].into()), None, Default::default())
     }
 
     pub fn non_empty(stuff: impl Into<VecDeque<T>>) -> Self {
-        Self(Some(stuff.into()), Default::default())
+        Self(Some(stuff.into()), None, Default::default())
+    }
+
+    /// Candidates built by `source` as they are needed, instead of all up front
+    pub fn lazy(source: S) -> Self {
+        Self(Some(VecDeque::new()), Some(source), Default::default())
+    }
+
+    /// The candidate [resolve_next](ResolvableExt::resolve_next) asks first, the one the last
+    /// item came from
+    pub(crate) fn front(&self) -> Option<&T> {
+        self.0.as_ref()?.front()
+    }
+
+    /// Every candidate built and not exhausted yet, nothing when unset
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.0.iter_mut().flatten()
+    }
+
+    /// Builds every candidate left in the source right away, for when they are all needed anyway
+    pub(crate) fn build_all(&mut self) {
+        while self.build_next() {}
+    }
+
+    /// How many candidates (the inner resolvables, each of which might yield several items) have
+    /// not been exhausted yet, those not built yet included, `None` when unset
+    pub fn remaining(&self) -> Option<usize> {
+        self.0.as_ref().map(|inner| {
+            inner.iter().filter(|candidate| !candidate.is_empty()).count() + self.pending()
+        })
+    }
+
+    /// Whether it has been set and every candidate is exhausted, meaning that
+    /// [resolve_next](ResolvableExt::resolve_next) won't yield anything anymore
+    pub fn is_empty_result(&self) -> bool {
+        self.remaining() == Some(0)
+    }
+
+    /// How many candidates the source has yet to build
+    pub(crate) fn pending(&self) -> usize {
+        self.1.as_ref().map_or(0, |source| source.remaining())
+    }
+
+    /// Appends the next candidate of the source, if there is one
+    fn build_next(&mut self) -> bool {
+        let candidate = self.1.as_mut().and_then(|source| source.next_candidate());
+        match (candidate, self.0.as_mut()) {
+            (Some(candidate), Some(inner)) => {
+                inner.push_back(candidate);
+                true
+            }
+            _ => false,
+        }
     }
 }
 
-impl<T, I> Default for ResolvableVec<T, I>
+impl<T, I, S> Default for ResolvableVec<T, I, S>
 where
     T: ResolvableExt<I> + std::marker::Send,
     I: ResolvableItem,
 {
     fn default() -> Self {
-        Self(None, Default::default())
+        Self(None, None, Default::default())
     }
 }
 
@@ -63,7 +166,7 @@ where
     I: ResolvableItem,
 {
     fn from(from: Vec<T>) -> Self {
-        Self(Some(from.into()), Default::default())
+        Self(Some(from.into()), None, Default::default())
     }
 }
 
@@ -73,6 +176,6 @@ where
     I: ResolvableItem,
 {
     fn from(from: VecDeque<T>) -> Self {
-        Self(Some(from), Default::default())
+        Self(Some(from), None, Default::default())
     }
 }
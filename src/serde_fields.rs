@@ -0,0 +1,87 @@
+//! `#[serde(with = ...)]` helpers for the rsip types found in the records and in
+//! [Target](crate::Target), which don't implement serde themselves. Each one is encoded the way it
+//! is written in SIP/DNS: a domain and a transport as strings, a port as a number.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub(crate) mod domain {
+    use super::*;
+    use rsip::Domain;
+
+    pub(crate) fn serialize<S: Serializer>(
+        domain: &Domain,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(domain)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Domain, D::Error> {
+        String::deserialize(deserializer).map(Domain::from)
+    }
+}
+
+pub(crate) mod port {
+    use super::*;
+    use rsip::Port;
+
+    pub(crate) fn serialize<S: Serializer>(port: &Port, serializer: S) -> Result<S::Ok, S::Error> {
+        u16::from(*port).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Port, D::Error> {
+        u16::deserialize(deserializer).map(Port::from)
+    }
+}
+
+pub(crate) mod transport {
+    use super::*;
+    use rsip::Transport;
+    use std::convert::TryInto;
+
+    pub(crate) fn serialize<S: Serializer>(
+        transport: &Transport,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&transport.to_string().to_lowercase())
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Transport, D::Error> {
+        let transport = String::deserialize(deserializer)?;
+        rsip::common::transport::Tokenizer::from(transport.as_bytes())
+            .try_into()
+            .map_err(|_| serde::de::Error::custom(format!("unknown transport {}", transport)))
+    }
+}
+
+/// A map keyed by domain, as a map keyed by the domain string
+pub(crate) mod domain_map {
+    use super::*;
+    use rsip::Domain;
+    use std::collections::HashMap;
+
+    pub(crate) fn serialize<S, V>(
+        map: &HashMap<Domain, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        V: Serialize,
+    {
+        serializer.collect_map(map.iter().map(|(domain, value)| (domain.to_string(), value)))
+    }
+
+    pub(crate) fn deserialize<'de, D, V>(deserializer: D) -> Result<HashMap<Domain, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        V: Deserialize<'de>,
+    {
+        let map = HashMap::<String, V>::deserialize(deserializer)?;
+        Ok(map.into_iter().map(|(domain, value)| (Domain::from(domain), value)).collect())
+    }
+}
@@ -1,18 +1,123 @@
-use rsip::{Port, Transport};
-use std::net::{IpAddr, SocketAddr};
+use rsip::{Host, HostWithPort, Param, Port, Scheme, Transport, Uri};
+use std::cmp::Ordering;
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
+use std::sync::Arc;
+
+/// TTL, in seconds, of the targets and records whose actual TTL isn't known: an ip address host,
+/// a DNS answer without any record to take it from, or a record built without one. See also
+/// [Context::default_ttl](crate::Context::default_ttl).
+pub const DEFAULT_TTL: u32 = 300;
 
 /// The (ip, port, transport, ttl) tuple resolved that should be used as the next peer target.
+///
+/// Targets have a total order, so that a set of them can be put in a canonical order with a
+/// plain `sort`: by transport first (UDP, TCP, TLS, SCTP, TLS-SCTP, WS then WSS), then by port,
+/// then by address family (IPv4 first), then by the address bytes, then by TTL and finally by
+/// scope, the [siblings](Self::siblings) only breaking the remaining ties. It is unrelated to the
+/// order of preference DNS gives, see [TargetSortPolicy](crate::TargetSortPolicy) for that. Use
+/// [same_endpoint](Self::same_endpoint) to compare targets regardless of their siblings.
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Target {
     pub ip_addr: IpAddr,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::port"))]
     pub port: Port,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_fields::transport"))]
     pub transport: Transport,
     pub ttl: u32,
+    /// The scope (zone) of a link-local IPv6 address, like the interface index behind `%eth0`,
+    /// which [socket_addr](Self::socket_addr) needs to reach it. `None` for any other address.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub scope_id: Option<u32>,
+    /// Every address the host of the target resolved to, its own included, in the order they
+    /// are resolved into targets, so that a caller can fall back to another one when this one
+    /// is unreachable without resolving the host again. `None` when the target doesn't come
+    /// from the addresses of a host, like the target of an ip address host.
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub siblings: Option<Arc<Vec<IpAddr>>>,
 }
 
 impl Target {
+    /// The socket address of the target, carrying its [scope_id](Self::scope_id) when it's an
+    /// IPv6 one
     pub fn socket_addr(&self) -> SocketAddr {
-        SocketAddr::from((self.ip_addr, self.port.into()))
+        match (self.ip_addr, self.scope_id) {
+            (IpAddr::V6(ip_addr), Some(scope_id)) => {
+                SocketAddrV6::new(ip_addr, self.port.into(), 0, scope_id).into()
+            }
+            (ip_addr, _) => SocketAddr::from((ip_addr, self.port.into())),
+        }
+    }
+
+    /// The same target within the given IPv6 scope, see [scope_id](Self::scope_id)
+    pub fn with_scope_id(mut self, scope_id: Option<u32>) -> Self {
+        self.scope_id = scope_id;
+        self
+    }
+
+    /// The same target along with the addresses of its host, see [siblings](Self::siblings)
+    pub fn with_siblings(mut self, siblings: Option<Arc<Vec<IpAddr>>>) -> Self {
+        self.siblings = siblings;
+        self
+    }
+
+    /// Whether both targets share the same ip address, port, transport, TTL and scope, whatever
+    /// their [siblings](Self::siblings)
+    pub fn same_endpoint(&self, other: &Target) -> bool {
+        self.ip_addr == other.ip_addr
+            && self.port == other.port
+            && self.transport == other.transport
+            && self.ttl == other.ttl
+            && self.scope_id == other.scope_id
+    }
+
+    /// Builds an [Uri] pointing to this target, using the given scheme. The resolved transport
+    /// is carried over as a `transport` uri parameter, so the uri can be fed directly to `rsip`
+    /// when building the outgoing request.
+    pub fn to_uri(&self, scheme: Scheme) -> Uri {
+        Uri {
+            scheme: Some(scheme),
+            host_with_port: self.into(),
+            params: vec![Param::Transport(self.transport)],
+            ..Default::default()
+        }
+    }
+}
+
+impl Ord for Target {
+    fn cmp(&self, other: &Self) -> Ordering {
+        transport_rank(self.transport)
+            .cmp(&transport_rank(other.transport))
+            .then_with(|| u16::from(self.port).cmp(&u16::from(other.port)))
+            // IpAddr orders IPv4 before IPv6 and then by the address bytes
+            .then_with(|| self.ip_addr.cmp(&other.ip_addr))
+            .then_with(|| self.ttl.cmp(&other.ttl))
+            .then_with(|| self.scope_id.cmp(&other.scope_id))
+            .then_with(|| self.siblings.cmp(&other.siblings))
+    }
+}
+
+impl PartialOrd for Target {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn transport_rank(transport: Transport) -> u8 {
+    match transport {
+        Transport::Udp => 0,
+        Transport::Tcp => 1,
+        Transport::Tls => 2,
+        Transport::Sctp => 3,
+        Transport::TlsSctp => 4,
+        Transport::Ws => 5,
+        Transport::Wss => 6,
+    }
+}
+
+impl From<&Target> for HostWithPort {
+    fn from(target: &Target) -> Self {
+        HostWithPort { host: Host::IpAddr(target.ip_addr), port: Some(target.port) }
     }
 }
 
@@ -20,7 +125,7 @@ impl From<(IpAddr, Port, Transport)> for Target {
     fn from(from: (IpAddr, Port, Transport)) -> Target {
         let (ip_addr, port, transport) = from;
 
-        Target { ip_addr, port, transport, ttl: 300 }
+        Target { ip_addr, port, transport, ttl: DEFAULT_TTL, scope_id: None, siblings: None }
     }
 }
 
@@ -28,6 +133,6 @@ impl From<(IpAddr, Port, Transport, u32)> for Target {
     fn from(from: (IpAddr, Port, Transport, u32)) -> Target {
         let (ip_addr, port, transport, ttl) = from;
 
-        Target { ip_addr, port, transport, ttl }
+        Target { ip_addr, port, transport, ttl, scope_id: None, siblings: None }
     }
 }
@@ -0,0 +1,20 @@
+//! Internal helpers around the optional `tracing` instrumentation. Without the `tracing`
+//! feature they expand to nothing, so instrumented code carries neither a dependency nor a cost.
+
+/// Emits a `tracing` debug event, takes the same arguments as [tracing::debug!].
+macro_rules! debug_event {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        ::tracing::debug!($($arg)*);
+    }};
+}
+
+/// Emits a `tracing` warning event, takes the same arguments as [tracing::warn!].
+// only the hickory clients report warnings so far
+#[cfg_attr(not(feature = "hickory-dns"), allow(unused_macros))]
+macro_rules! warn_event {
+    ($($arg:tt)*) => {{
+        #[cfg(feature = "tracing")]
+        ::tracing::warn!($($arg)*);
+    }};
+}
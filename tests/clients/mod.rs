@@ -0,0 +1,7 @@
+pub mod native_async;
+pub mod override_dns_client;
+pub mod pool_dns_client;
+pub mod racing_dns_client;
+#[cfg(feature = "serde")]
+pub mod record_replay;
+pub mod throttled_dns_client;
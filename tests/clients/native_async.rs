@@ -0,0 +1,51 @@
+use crate::support::MockedDnsClient;
+use rsip::{Domain, Error, Transport};
+use rsip_dns::{records::*, *};
+use std::net::IpAddr;
+
+// A client written with plain `async fn`s, no attribute needed
+#[derive(Debug, Clone)]
+struct StaticDnsClient;
+
+impl DnsClient for StaticDnsClient {
+    async fn naptr_lookup(&self, _domain: Domain) -> Option<NaptrRecord> {
+        None
+    }
+    async fn srv_lookup(&self, _domain: SrvDomain) -> Option<SrvRecord> {
+        None
+    }
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        Ok(AddrRecord::from((domain, vec![IpAddr::from([192, 0, 2, 1])])))
+    }
+}
+
+fn assert_send<T: Send>(_: &T) {}
+
+// compile time check: the futures of any dns client can be moved across threads
+#[allow(dead_code)]
+fn lookup_futures_are_send<C: DnsClient>(dns_client: C, domain: Domain, srv_domain: SrvDomain) {
+    assert_send(&dns_client.naptr_lookup(domain.clone()));
+    assert_send(&dns_client.srv_lookup(srv_domain.clone()));
+    assert_send(&dns_client.ip_lookup(domain.clone()));
+    assert_send(&dns_client.try_naptr_lookup(domain));
+    assert_send(&dns_client.try_srv_lookup(srv_domain));
+}
+
+#[tokio::test]
+async fn default_try_lookups_report_missing_records() {
+    let dns_client = MockedDnsClient::default();
+
+    assert!(matches!(dns_client.try_naptr_lookup("example.com".into()).await, Ok(None)));
+    let srv_domain = SrvDomain::from((Domain::from("example.com"), Transport::Udp));
+    assert!(matches!(dns_client.try_srv_lookup(srv_domain).await, Ok(None)));
+}
+
+#[tokio::test]
+async fn lookups_can_be_spawned() {
+    let context = Context::builder(StaticDnsClient).host("example.com").build().unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = tokio::spawn(async move { lookup.resolve_next().await }).await.unwrap();
+
+    assert_eq!(target.map(|target| target.ip_addr), Some(IpAddr::from([192, 0, 2, 1])));
+}
@@ -0,0 +1,91 @@
+use crate::support::{MockedDnsClient, PanicDnsClient, RecordingDnsClient};
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+
+fn overrides() -> HashMap<Domain, Vec<IpAddr>> {
+    let mut overrides = HashMap::new();
+    overrides.insert(
+        "sip.example.com".into(),
+        vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2))],
+    );
+    overrides
+}
+
+#[tokio::test]
+async fn overridden_domains_never_hit_the_inner_client() {
+    let inner = RecordingDnsClient::new(PanicDnsClient);
+    let dns_client = OverrideDnsClient::new(inner.clone(), overrides()).with_ttl(30);
+
+    let addr_record = dns_client.ip_lookup("SIP.example.com.".into()).await.unwrap();
+    assert_eq!(addr_record.ip_addrs, overrides()[&Domain::from("sip.example.com")]);
+    assert_eq!(addr_record.ttl, 30);
+
+    let uri = rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: ("sip.example.com", 5080).into(),
+        ..Default::default()
+    };
+    let mut lookup = Lookup::from(
+        Context::initialize_from(uri, dns_client, SupportedTransports::any()).unwrap(),
+    );
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.ip_addr, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)));
+    assert_eq!(target.port, 5080.into());
+    assert_eq!(target.ttl, 30);
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.ip_addr, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+    assert!(lookup.resolve_next().await.is_none());
+
+    assert!(inner.queries().is_empty());
+}
+
+#[tokio::test]
+async fn other_domains_are_delegated() {
+    let a_record: AddrRecord =
+        (Domain::from("other.com"), vec![IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1))]).into();
+    let inner = RecordingDnsClient::new(MockedDnsClient {
+        a_record: Some(a_record.clone()),
+        ..Default::default()
+    });
+    let dns_client = OverrideDnsClient::new(inner.clone(), overrides());
+
+    let addr_record = dns_client.ip_lookup("other.com".into()).await.unwrap();
+    assert_eq!(addr_record.ip_addrs, a_record.ip_addrs);
+    assert_eq!(inner.ip_queries(), 1);
+}
+
+#[tokio::test]
+async fn overrides_replace_srv_additional_hosts() {
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Udp);
+    let mut additional_hosts: HashMap<Domain, AddrRecord> = HashMap::new();
+    additional_hosts.insert(
+        Domain::from("sip.example.com"),
+        (Domain::from("sip.example.com"), vec![IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1))]).into(),
+    );
+    let srv_record = SrvRecord::with_additional_hosts(
+        vec![SrvEntry {
+            priority: 10,
+            weight: 10,
+            port: 5060.into(),
+            target: "sip.example.com".into(),
+        }],
+        srv_domain.clone(),
+        60,
+        additional_hosts,
+    );
+    let inner = RecordingDnsClient::new(MockedDnsClient {
+        srv_record: Some(srv_record),
+        ..Default::default()
+    });
+    let dns_client = OverrideDnsClient::new(inner.clone(), overrides());
+
+    let srv_record = dns_client.try_srv_lookup(srv_domain).await.unwrap().unwrap();
+    let additional =
+        srv_record.get_additional_for_target(&Domain::from("sip.example.com")).unwrap();
+    assert_eq!(additional.ip_addrs, overrides()[&Domain::from("sip.example.com")]);
+    assert_eq!(inner.srv_queries(), 1);
+    assert_eq!(inner.ip_queries(), 0);
+}
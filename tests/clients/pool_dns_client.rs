@@ -0,0 +1,74 @@
+use crate::support::{MockedDnsClient, RecordingDnsClient};
+use rsip::Domain;
+use rsip_dns::{records::*, *};
+use std::net::{IpAddr, Ipv4Addr};
+
+fn answering(ip_addr: IpAddr) -> RecordingDnsClient<MockedDnsClient> {
+    RecordingDnsClient::new(MockedDnsClient {
+        a_record: Some(AddrRecord::from((Domain::from("sip.example.com"), vec![ip_addr]))),
+        ..Default::default()
+    })
+}
+
+fn ip_addrs() -> Vec<IpAddr> {
+    (1..=3).map(|index| IpAddr::V4(Ipv4Addr::new(192, 0, 2, index))).collect()
+}
+
+#[tokio::test]
+async fn round_robin_takes_each_client_in_turn() {
+    let clients = ip_addrs().into_iter().map(answering).collect::<Vec<_>>();
+    let dns_client = PoolDnsClient::new(clients.clone(), SelectionStrategy::RoundRobin);
+    // clones share the rotation
+    let cloned = dns_client.clone();
+
+    let mut answers = vec![];
+    for index in 0..9 {
+        let dns_client = match index % 2 {
+            0 => &dns_client,
+            _ => &cloned,
+        };
+        let addr_record = dns_client.ip_lookup("sip.example.com".into()).await.unwrap();
+        answers.extend(addr_record.ip_addrs);
+    }
+
+    assert_eq!(answers, ip_addrs().repeat(3));
+    for client in &clients {
+        assert_eq!(client.ip_queries(), 3);
+    }
+}
+
+#[tokio::test]
+async fn weighted_never_picks_a_client_without_weight() {
+    let clients = ip_addrs().into_iter().map(answering).collect::<Vec<_>>();
+    let dns_client =
+        PoolDnsClient::new(clients.clone(), SelectionStrategy::Weighted(vec![1, 0, 3]));
+
+    for _ in 0..50 {
+        dns_client.try_srv_lookup(SrvDomain::try_from("_sip._udp.example.com").unwrap()).await.ok();
+    }
+
+    assert!(clients[0].srv_queries() > 0);
+    assert_eq!(clients[1].srv_queries(), 0);
+    assert!(clients[2].srv_queries() > 0);
+    assert_eq!(clients.iter().map(|client| client.srv_queries()).sum::<usize>(), 50);
+}
+
+#[tokio::test]
+async fn random_sends_each_query_to_a_single_client() {
+    let clients = ip_addrs().into_iter().map(answering).collect::<Vec<_>>();
+    let dns_client = PoolDnsClient::new(clients.clone(), SelectionStrategy::Random);
+
+    for _ in 0..10 {
+        let addr_record = dns_client.ip_lookup("sip.example.com".into()).await.unwrap();
+        assert!(ip_addrs().contains(&addr_record.ip_addrs[0]));
+    }
+
+    assert_eq!(clients.iter().map(|client| client.ip_queries()).sum::<usize>(), 10);
+}
+
+#[test]
+#[should_panic(expected = "every pooled dns client needs a weight")]
+fn weights_must_match_the_clients() {
+    let clients = ip_addrs().into_iter().map(answering).collect::<Vec<_>>();
+    PoolDnsClient::new(clients, SelectionStrategy::Weighted(vec![1, 2]));
+}
@@ -0,0 +1,65 @@
+use crate::support::{MockedDnsClient, RecordingDnsClient};
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+
+const SLOW: Duration = Duration::from_millis(500);
+
+fn answering(ip_addr: IpAddr) -> MockedDnsClient {
+    MockedDnsClient {
+        a_record: Some(AddrRecord::from((Domain::from("sip.example.com"), vec![ip_addr]))),
+        ..Default::default()
+    }
+}
+
+fn srv_domain() -> SrvDomain {
+    SrvDomain::new("example.com".into(), Transport::Udp)
+}
+
+#[tokio::test]
+async fn the_fast_secondary_wins_over_the_slow_primary() {
+    let primary_ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let secondary_ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+    let primary = RecordingDnsClient::with_delay(answering(primary_ip), SLOW);
+    let secondary = RecordingDnsClient::new(answering(secondary_ip));
+    let dns_client = RacingDnsClient::new(primary.clone(), secondary.clone());
+
+    let started = Instant::now();
+    let addr_record = dns_client.ip_lookup("sip.example.com".into()).await.unwrap();
+
+    assert_eq!(addr_record.ip_addrs, vec![secondary_ip]);
+    assert!(started.elapsed() < SLOW);
+    // both clients were asked
+    assert_eq!(primary.ip_queries(), 1);
+    assert_eq!(secondary.ip_queries(), 1);
+}
+
+#[tokio::test]
+async fn waits_for_the_slow_primary_when_the_secondary_finds_nothing() {
+    let srv_record = SrvRecord::new(
+        vec![SrvEntry {
+            priority: 10,
+            weight: 10,
+            port: 5060.into(),
+            target: "sip.example.com".into(),
+        }],
+        srv_domain(),
+        60,
+    );
+    let primary = RecordingDnsClient::with_delay(
+        MockedDnsClient { srv_record: Some(srv_record), ..Default::default() },
+        SLOW,
+    );
+    let dns_client = RacingDnsClient::new(primary, MockedDnsClient::default());
+
+    let started = Instant::now();
+    let srv_record = dns_client.try_srv_lookup(srv_domain()).await.unwrap().unwrap();
+
+    assert_eq!(srv_record.targets(), vec![Domain::from("sip.example.com")]);
+    assert!(started.elapsed() >= SLOW);
+
+    // nothing anywhere
+    let dns_client = RacingDnsClient::new(MockedDnsClient::default(), MockedDnsClient::default());
+    assert!(dns_client.naptr_lookup("example.com".into()).await.is_none());
+}
@@ -0,0 +1,78 @@
+use crate::support::MockedDnsClient;
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::convert::TryInto;
+use std::net::{IpAddr, Ipv4Addr};
+
+fn mocked() -> MockedDnsClient {
+    MockedDnsClient {
+        srv_record: Some(SrvRecord::new(
+            vec![
+                SrvEntry { priority: 1, weight: 10, port: 5060, target: "sip1.example.com".into() },
+                SrvEntry { priority: 2, weight: 10, port: 5062, target: "sip2.example.com".into() },
+            ],
+            "_sip._udp.example.com".try_into().unwrap(),
+            300,
+        )),
+        a_record: Some(AddrRecord::from((
+            Domain::from("sip1.example.com"),
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))],
+        ))),
+        ..Default::default()
+    }
+}
+
+async fn targets_from<C: DnsClient>(dns_client: C) -> Vec<Target> {
+    let uri = rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: Domain::from("example.com").into(),
+        ..Default::default()
+    };
+    let context = Context::initialize_from(
+        uri,
+        dns_client,
+        SupportedTransports::only(vec![Transport::Udp, Transport::Tcp]),
+    )
+    .unwrap();
+
+    let mut lookup = Lookup::from(context);
+    let mut targets = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        targets.push(target);
+    }
+    targets
+}
+
+#[tokio::test]
+async fn replaying_a_recording_resolves_the_same_targets() {
+    let capturing_client = CapturingDnsClient::new(mocked());
+    let recorded_targets = targets_from(capturing_client.clone()).await;
+    assert!(!recorded_targets.is_empty());
+
+    let path = std::env::temp_dir().join(format!("rsip-dns-recording-{}.json", std::process::id()));
+    capturing_client.save(&path).unwrap();
+    let recording = Recording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(recording.queries.len(), capturing_client.recording().queries.len());
+    assert!(recording.queries.iter().any(|query| matches!(query, RecordedQuery::Naptr { .. })));
+    assert!(recording.queries.iter().any(|query| matches!(query, RecordedQuery::Srv { .. })));
+
+    let replay_client = ReplayDnsClient::new(recording);
+    let replayed_targets = targets_from(replay_client.clone()).await;
+
+    assert_eq!(replayed_targets, recorded_targets);
+    assert_eq!(replay_client.remaining(), 0);
+}
+
+#[tokio::test]
+async fn unrecorded_queries_fail() {
+    let replay_client = ReplayDnsClient::new(Recording::default());
+
+    assert!(replay_client.ip_lookup("sip.example.com".into()).await.is_err());
+    assert!(matches!(
+        replay_client.try_naptr_lookup("example.com".into()).await,
+        Err(DnsError::Other(_))
+    ));
+    assert!(replay_client.srv_lookup("_sip._udp.example.com".try_into().unwrap()).await.is_none());
+}
@@ -0,0 +1,42 @@
+use crate::support::{MockedDnsClient, RecordingDnsClient};
+use futures::future::join_all;
+use rsip::Domain;
+use rsip_dns::{records::*, *};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+fn answering() -> MockedDnsClient {
+    MockedDnsClient {
+        a_record: Some(AddrRecord::from((
+            Domain::from("sip.example.com"),
+            vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))],
+        ))),
+        ..Default::default()
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn in_flight_queries_never_exceed_the_limit() {
+    let inner = RecordingDnsClient::with_delay(answering(), Duration::from_millis(100));
+    let dns_client = ThrottledDnsClient::new(inner.clone(), 3);
+
+    // every clone shares the same limit
+    let lookups = (0..20).map(|_| {
+        let dns_client = dns_client.clone();
+        async move { dns_client.ip_lookup("sip.example.com".into()).await }
+    });
+    let results = join_all(lookups).await;
+
+    assert!(results.iter().all(Result::is_ok));
+    assert_eq!(inner.ip_queries(), 20);
+    assert_eq!(inner.max_in_flight(), 3);
+    assert_eq!(dns_client.available_slots(), 3);
+}
+
+#[tokio::test]
+async fn a_zero_limit_still_lets_queries_through() {
+    let dns_client = ThrottledDnsClient::new(answering(), 0);
+
+    assert_eq!(dns_client.available_slots(), 1);
+    assert!(dns_client.ip_lookup("sip.example.com".into()).await.is_ok());
+}
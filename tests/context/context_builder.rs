@@ -0,0 +1,47 @@
+use crate::support::PanicDnsClient;
+use rsip::{Host, Port, Transport};
+use rsip_dns::*;
+use std::net::{IpAddr, Ipv4Addr};
+
+#[test]
+fn builder_defaults() {
+    let context = Context::builder(PanicDnsClient).host("example.com").build().unwrap();
+
+    assert!(!context.secure);
+    assert_eq!(context.host, Host::from("example.com"));
+    assert_eq!(context.port, None);
+    assert_eq!(context.transport, None);
+    assert_eq!(context.supported_transports.all(), SupportedTransports::any().all());
+}
+
+#[test]
+fn builder_setters() {
+    let ip_addr = IpAddr::V4(Ipv4Addr::new(192, 168, 2, 13));
+    let context = Context::builder(PanicDnsClient)
+        .secure(true)
+        .host(ip_addr)
+        .port(Port::from(5061))
+        .transport(Transport::Tls)
+        .supported_transports(vec![Transport::Tls, Transport::Wss])
+        .build()
+        .unwrap();
+
+    assert!(context.secure);
+    assert_eq!(context.host, Host::from(ip_addr));
+    assert_eq!(context.port, Some(Port::from(5061)));
+    assert_eq!(context.transport, Some(Transport::Tls));
+    assert_eq!(context.supported_transports.all(), &vec![Transport::Tls, Transport::Wss]);
+}
+
+#[test]
+fn builder_validates_host() {
+    assert!(Context::builder(PanicDnsClient).build().is_err());
+    assert!(Context::builder(PanicDnsClient).host("").build().is_err());
+    assert!(Context::builder(PanicDnsClient).host(".").build().is_err());
+}
+
+#[test]
+fn builder_rejects_illegal_characters() {
+    assert!(Context::builder(PanicDnsClient).host("exa mple.com").build().is_err());
+    assert!(Context::builder(PanicDnsClient).host("example..com").build().is_err());
+}
@@ -0,0 +1,37 @@
+use crate::support::PanicDnsClient;
+use rsip::{Port, Transport};
+use rsip_dns::*;
+use std::net::{IpAddr, Ipv4Addr};
+
+#[test]
+fn follows_rfc_3263_for_every_transport() {
+    let expected: [(Transport, u16, u16); 7] = [
+        (Transport::Udp, 5060, 5061),
+        (Transport::Tcp, 5060, 5061),
+        (Transport::Sctp, 5060, 5061),
+        (Transport::Tls, 5061, 5061),
+        (Transport::TlsSctp, 5061, 5061),
+        (Transport::Ws, 80, 443),
+        (Transport::Wss, 443, 443),
+    ];
+
+    for (transport, insecure, secure) in expected {
+        assert_eq!(default_port(false, transport), Port::from(insecure), "sip over {}", transport);
+        assert_eq!(default_port(true, transport), Port::from(secure), "sips over {}", transport);
+    }
+}
+
+#[tokio::test]
+async fn sips_over_tcp_defaults_to_5061() {
+    let ip_addr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let context = Context::builder(PanicDnsClient)
+        .host(ip_addr)
+        .secure(true)
+        .transport(Transport::Tcp)
+        .build()
+        .unwrap();
+
+    let target = Lookup::from(context).resolve_next().await.unwrap();
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(target.port, 5061.into());
+}
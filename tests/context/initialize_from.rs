@@ -0,0 +1,90 @@
+use crate::support::PanicDnsClient;
+use rsip::{Domain, Error};
+use rsip_dns::*;
+
+fn initialize_from(host: &str) -> Result<Context<PanicDnsClient>, Error> {
+    let uri = rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: Domain::from(host).into(),
+        ..Default::default()
+    };
+
+    Context::initialize_from(uri, PanicDnsClient, SupportedTransports::any())
+}
+
+#[test]
+fn rejects_an_empty_host() {
+    for host in ["", "."] {
+        match initialize_from(host) {
+            Err(Error::Unexpected(message)) => {
+                assert!(message.contains("empty domain"), "{}", message)
+            }
+            other => panic!("expected an error for {:?}, got {:?}", host, other),
+        }
+    }
+}
+
+#[test]
+fn rejects_illegal_characters() {
+    for (host, illegal) in [("exa mple.com", ' '), ("example.com/sip", '/'), ("ex@mple.com", '@')] {
+        match initialize_from(host) {
+            Err(Error::Unexpected(message)) => {
+                assert!(message.contains(host), "{}", message);
+                assert!(
+                    message.contains(&format!("illegal character {:?}", illegal)),
+                    "{}",
+                    message
+                );
+            }
+            other => panic!("expected an error for {:?}, got {:?}", host, other),
+        }
+    }
+}
+
+#[test]
+fn rejects_malformed_labels() {
+    let long_label = format!("{}.com", "a".repeat(64));
+
+    for (host, reason) in [("example..com", "empty label"), (long_label.as_str(), "label longer")] {
+        match initialize_from(host) {
+            Err(Error::Unexpected(message)) => assert!(message.contains(reason), "{}", message),
+            other => panic!("expected an error for {:?}, got {:?}", host, other),
+        }
+    }
+}
+
+#[test]
+fn accepts_valid_hosts() {
+    for host in
+        ["example.com", "example.com.", "sip-1.example.com", "_sip.example.com", "bücher.de"]
+    {
+        assert!(initialize_from(host).is_ok(), "{}", host);
+    }
+}
+
+#[test]
+fn decodes_the_zone_of_ipv6_literals() {
+    use std::net::{IpAddr, Ipv6Addr};
+
+    let link_local = IpAddr::from(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1));
+    for host in ["[fe80::1%257]", "fe80::1%257", "[fe80::1%7]"] {
+        let context = initialize_from(host).unwrap();
+        assert_eq!(context.host, rsip::Host::IpAddr(link_local), "{}", host);
+        assert_eq!(context.scope_id, Some(7), "{}", host);
+    }
+
+    // interface names are looked up, the loopback one always exists
+    #[cfg(target_os = "linux")]
+    {
+        let context = initialize_from("[fe80::1%25lo]").unwrap();
+        assert_eq!(context.host, rsip::Host::IpAddr(link_local));
+        assert!(context.scope_id.is_some_and(|scope_id| scope_id > 0));
+    }
+
+    match initialize_from("[fe80::1%25no-such-interface0]") {
+        Err(Error::Unexpected(message)) => {
+            assert!(message.contains("no such interface"), "{}", message)
+        }
+        other => panic!("expected an error, got {:?}", other),
+    }
+}
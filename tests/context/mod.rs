@@ -0,0 +1,3 @@
+pub mod context_builder;
+pub mod default_port;
+pub mod initialize_from;
@@ -0,0 +1,61 @@
+use rsip_dns::DnsError;
+use std::error::Error;
+use std::io;
+
+fn read_answer() -> Result<Vec<u8>, DnsError> {
+    Ok(std::fs::read("/nonexistent/answer")?)
+}
+
+#[test]
+fn io_errors_convert_with_the_question_mark() {
+    let error = read_answer().unwrap_err();
+
+    assert!(matches!(error, DnsError::Io { .. }));
+    assert!(error.to_string().starts_with("DNS I/O error: "));
+    let source = error.source().expect("the io error");
+    assert_eq!(source.downcast_ref::<io::Error>().unwrap().kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn io_errors_are_the_source() {
+    let error = DnsError::io(
+        "Failed to send DNS query",
+        io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused"),
+    );
+
+    assert_eq!(error.to_string(), "Failed to send DNS query: connection refused");
+    let source = error.source().expect("the io error");
+    assert_eq!(
+        source.downcast_ref::<io::Error>().unwrap().kind(),
+        io::ErrorKind::ConnectionRefused
+    );
+
+    // boxed, the chain is still there to walk
+    let boxed: Box<dyn Error + Send + Sync> = error.into();
+    assert!(boxed.source().unwrap().is::<io::Error>());
+}
+
+#[test]
+fn other_errors_have_no_source() {
+    assert!(DnsError::ServerFailure.source().is_none());
+    assert!(DnsError::Timeout.source().is_none());
+    assert!(DnsError::Other("malformed response".into()).source().is_none());
+}
+
+#[test]
+fn io_errors_compare_by_context_and_error() {
+    let refused = || io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused");
+
+    assert_eq!(
+        DnsError::io("Failed to send DNS query", refused()),
+        DnsError::io("Failed to send DNS query", refused())
+    );
+    assert_ne!(
+        DnsError::io("Failed to send DNS query", refused()),
+        DnsError::io("Failed to receive DNS response", refused())
+    );
+    assert_ne!(
+        DnsError::io("Failed to send DNS query", refused()),
+        DnsError::Other("Failed to send DNS query: connection refused".into())
+    );
+}
@@ -0,0 +1 @@
+pub mod dns_error;
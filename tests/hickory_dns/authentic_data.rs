@@ -0,0 +1,64 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::Message;
+use rsip_dns::{DnsClient, DnsError, RecursiveHickoryClient};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+// Answers every SRV query with `srv_response`, with the AD bit set to `authentic_data`, handing
+// over each query it received
+async fn spawn_server(
+    authentic_data: bool,
+) -> (RecursiveHickoryClient, mpsc::UnboundedReceiver<Message>) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let (queries, received) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            let mut response = srv_response(&query);
+            response.set_authentic_data(authentic_data);
+            let _ = queries.send(query);
+
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    (RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5)), received)
+}
+
+#[tokio::test]
+async fn the_ad_bit_is_ignored_by_default() {
+    let (client, mut received) = spawn_server(false).await;
+
+    let srv_record = client.try_srv_lookup(srv_domain(1)).await.unwrap();
+    assert_eq!(srv_record.unwrap().entries.len(), 1);
+
+    let query = received.recv().await.unwrap();
+    assert!(!query.authentic_data());
+}
+
+#[tokio::test]
+async fn authenticated_responses_are_accepted() {
+    let (client, mut received) = spawn_server(true).await;
+    let client = client.with_require_authentic_data(true);
+
+    let srv_record = client.try_srv_lookup(srv_domain(1)).await.unwrap();
+    assert_eq!(srv_record.unwrap().entries.len(), 1);
+
+    // the query asks for the AD bit
+    let query = received.recv().await.unwrap();
+    assert!(query.authentic_data());
+}
+
+#[tokio::test]
+async fn unauthenticated_responses_are_rejected() {
+    let (client, _received) = spawn_server(false).await;
+    let client = client.with_require_authentic_data(true);
+
+    assert!(matches!(client.try_srv_lookup(srv_domain(1)).await, Err(DnsError::Other(_))));
+    assert!(client.srv_lookup(srv_domain(1)).await.is_none());
+}
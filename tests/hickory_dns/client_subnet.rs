@@ -0,0 +1,65 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::Message;
+use hickory_proto::rr::rdata::opt::{ClientSubnet, EdnsCode, EdnsOption};
+use ipnet::IpNet;
+use rsip_dns::{DnsClient, RecursiveHickoryClient};
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+// Answers every SRV query with `srv_response`, handing over each query it received
+pub(super) async fn spawn_capturing_server()
+-> (RecursiveHickoryClient, mpsc::UnboundedReceiver<Message>) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let (queries, received) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            let response = srv_response(&query);
+            let _ = queries.send(query);
+
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    (RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5)), received)
+}
+
+#[tokio::test]
+async fn queries_carry_the_client_subnet() {
+    let (client, mut received) = spawn_capturing_server().await;
+    let client = client.with_client_subnet(IpNet::from_str("198.51.100.17/24").unwrap());
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.unwrap();
+    assert_eq!(srv_record.entries.len(), 1);
+
+    let query = received.recv().await.unwrap();
+    let edns = query.extensions().as_ref().expect("an OPT record");
+    // the payload size is still advertised next to the subnet
+    assert_eq!(edns.max_payload(), 4096);
+    match edns.option(EdnsCode::Subnet) {
+        Some(EdnsOption::Subnet(subnet)) => {
+            // host bits are not leaked
+            assert_eq!(
+                subnet,
+                &ClientSubnet::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 0)), 24, 0)
+            );
+        }
+        other => panic!("expected a client subnet option, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn queries_have_no_opt_record_by_default() {
+    let (client, mut received) = spawn_capturing_server().await;
+
+    client.srv_lookup(srv_domain(1)).await.unwrap();
+
+    let query = received.recv().await.unwrap();
+    assert!(query.extensions().is_none());
+}
@@ -0,0 +1,73 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::Message;
+use hickory_proto::rr::rdata::{A, CNAME};
+use hickory_proto::rr::{Name, RData, Record};
+use rsip::Domain;
+use rsip_dns::{DnsClient, RecursiveHickoryClient};
+use std::net::{IpAddr, Ipv4Addr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+// Answers every query with the SRV record pointing to `sip.example.com.`, plus `additionals`
+async fn client_answered_with(additionals: Vec<Record>) -> RecursiveHickoryClient {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let mut response = srv_response(&Message::from_vec(&buf[..len]).unwrap());
+            response.add_additionals(additionals.clone());
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5))
+}
+
+fn cname(alias: &str, canonical: &str, ttl: u32) -> Record {
+    Record::from_rdata(
+        Name::from_str(alias).unwrap(),
+        ttl,
+        RData::CNAME(CNAME(Name::from_str(canonical).unwrap())),
+    )
+}
+
+fn a(name: &str, ip: Ipv4Addr, ttl: u32) -> Record {
+    Record::from_rdata(Name::from_str(name).unwrap(), ttl, RData::A(A(ip)))
+}
+
+#[tokio::test]
+async fn srv_target_resolves_through_a_cname_chain() {
+    let client = client_answered_with(vec![
+        cname("sip.example.com.", "edge.example.net.", 600),
+        cname("edge.example.net.", "pop1.example.net.", 30),
+        a("pop1.example.net.", Ipv4Addr::new(10, 0, 0, 1), 300),
+    ])
+    .await;
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record");
+
+    assert!(srv_record.has_complete_additionals());
+    let additional = srv_record
+        .get_additional_for_target(&Domain::from("sip.example.com."))
+        .expect("additional A/AAAA for the aliased target");
+    assert_eq!(additional.ip_addrs, vec![IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))]);
+    // the addresses are only valid as long as every CNAME on the way is
+    assert_eq!(additional.ttl, 30);
+}
+
+#[tokio::test]
+async fn cname_loops_fall_back_to_the_slow_path() {
+    let client = client_answered_with(vec![
+        cname("sip.example.com.", "edge.example.net.", 60),
+        cname("edge.example.net.", "sip.example.com.", 60),
+        a("other.example.net.", Ipv4Addr::new(10, 0, 0, 1), 300),
+    ])
+    .await;
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record");
+
+    assert!(!srv_record.has_complete_additionals());
+}
@@ -0,0 +1,224 @@
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::{A, AAAA, SRV};
+use hickory_proto::rr::{Name, RData, Record};
+use rsip::Transport;
+use rsip_dns::{
+    DnsClient, DohClient,
+    records::{SrvDomain, TtlBounds},
+};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+struct HttpRequest {
+    head: String,
+    body: Vec<u8>,
+}
+
+// Minimal HTTP/1.1 server: every request on every connection is answered by `respond`,
+// which gets the decoded dns-message and returns (status, dns-message).
+async fn spawn_mock_doh_server<F>(respond: F) -> SocketAddr
+where
+    F: Fn(&Message) -> (u16, Message) + Send + Sync + Copy + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                while let Some(request) = read_request(&mut stream).await {
+                    assert!(request.head.starts_with("POST /dns-query HTTP/1.1"));
+                    assert!(
+                        request
+                            .head
+                            .to_ascii_lowercase()
+                            .contains("content-type: application/dns-message")
+                    );
+
+                    let query = Message::from_vec(&request.body).unwrap();
+                    assert_eq!(query.id(), 0);
+
+                    let (status, response) = respond(&query);
+                    let body = response.to_vec().unwrap();
+                    let head = format!(
+                        "HTTP/1.1 {} OK\r\ncontent-type: application/dns-message\r\ncontent-length: {}\r\n\r\n",
+                        status,
+                        body.len()
+                    );
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(&body).await.unwrap();
+                }
+            });
+        }
+    });
+
+    addr
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<HttpRequest> {
+    let mut buf = Vec::new();
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        let mut chunk = [0u8; 1024];
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let content_length = head
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.eq_ignore_ascii_case("content-length")
+                .then(|| value.trim().parse::<usize>().ok())?
+        })
+        .unwrap_or(0);
+
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < content_length {
+        let mut chunk = vec![0u8; content_length - body.len()];
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+
+    Some(HttpRequest { head, body })
+}
+
+fn response_for(query: &Message) -> Message {
+    let mut response = Message::new();
+    response.set_id(query.id());
+    response.set_message_type(MessageType::Response);
+    response.set_response_code(ResponseCode::NoError);
+    response.add_queries(query.queries().to_vec());
+    response
+}
+
+fn srv_with_additionals(query: &Message) -> (u16, Message) {
+    let mut response = response_for(query);
+
+    let srv_name = query.queries()[0].name().clone();
+    let target = Name::from_str("sip1.example.com.").unwrap();
+    response.add_answer(Record::from_rdata(
+        srv_name,
+        120,
+        RData::SRV(SRV::new(10, 20, 5060, target.clone())),
+    ));
+    response.add_additional(Record::from_rdata(
+        target.clone(),
+        60,
+        RData::A(A::from(Ipv4Addr::new(192, 0, 2, 10))),
+    ));
+    response.add_additional(Record::from_rdata(
+        target,
+        90,
+        RData::AAAA(AAAA::from(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 10))),
+    ));
+
+    (200, response)
+}
+
+fn endpoint(addr: SocketAddr) -> String {
+    format!("http://{}/dns-query", addr)
+}
+
+#[tokio::test]
+async fn resolves_srv_with_additional_hosts_over_https() {
+    let addr = spawn_mock_doh_server(srv_with_additionals).await;
+    let client = DohClient::new(&endpoint(addr)).unwrap();
+
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Udp);
+    let srv_record = client.srv_lookup(srv_domain.clone()).await.expect("srv record over DoH");
+
+    assert_eq!(srv_record.domain, srv_domain);
+    assert_eq!(srv_record.ttl, 120);
+    assert_eq!(srv_record.entries.len(), 1);
+    assert_eq!(srv_record.entries[0].port, 5060.into());
+    assert!(srv_record.has_complete_additionals());
+
+    let addr_record = srv_record
+        .get_additional_for_target(&"sip1.example.com.".into())
+        .expect("additional A/AAAA for target");
+    assert_eq!(addr_record.ip_addrs.len(), 2);
+    assert_eq!(addr_record.ttl, 60);
+    assert_eq!(addr_record.ttl_for(addr_record.ip_addrs[0]), 60);
+    assert_eq!(addr_record.ttl_for(addr_record.ip_addrs[1]), 90);
+}
+
+#[tokio::test]
+async fn shares_http_client_between_lookups() {
+    let addr = spawn_mock_doh_server(srv_with_additionals).await;
+    let client = DohClient::with_http_client(
+        &endpoint(addr),
+        reqwest::Client::new(),
+        Duration::from_secs(2),
+    )
+    .unwrap();
+    let cloned = client.clone();
+
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Tcp);
+
+    assert!(client.srv_lookup(srv_domain.clone()).await.is_some());
+    assert!(cloned.srv_lookup(srv_domain).await.is_some());
+}
+
+#[tokio::test]
+async fn treats_error_responses_as_missing_records() {
+    let addr = spawn_mock_doh_server(|query| {
+        let mut response = response_for(query);
+        response.set_response_code(ResponseCode::ServFail);
+        (200, response)
+    })
+    .await;
+    let client = DohClient::new(&endpoint(addr)).unwrap();
+
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Udp);
+
+    assert!(client.srv_lookup(srv_domain).await.is_none());
+    assert!(client.ip_lookup("example.com".into()).await.is_err());
+}
+
+#[tokio::test]
+async fn treats_http_errors_as_missing_records() {
+    let addr = spawn_mock_doh_server(|query| (500, response_for(query))).await;
+    let client = DohClient::new(&endpoint(addr)).unwrap();
+
+    assert!(client.naptr_lookup("example.com".into()).await.is_none());
+}
+
+#[tokio::test]
+async fn honors_the_query_options_and_ttl_bounds() {
+    // only answers the queries without the RD bit, and never sets the AD bit
+    let addr = spawn_mock_doh_server(|query| match query.recursion_desired() {
+        true => (500, response_for(query)),
+        false => srv_with_additionals(query),
+    })
+    .await;
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Udp);
+
+    let client = DohClient::new(&endpoint(addr)).unwrap();
+    assert!(client.srv_lookup(srv_domain.clone()).await.is_none());
+
+    let client = client.with_recursion_desired(false).with_ttl_bounds(TtlBounds::new(0, 100));
+    let srv_record = client.srv_lookup(srv_domain.clone()).await.expect("srv record over DoH");
+    assert_eq!(srv_record.ttl, 100);
+    assert_eq!(srv_record.source, Some(addr));
+
+    let client = client.with_require_authentic_data(true);
+    assert!(client.srv_lookup(srv_domain).await.is_none());
+}
+
+#[test]
+fn rejects_invalid_endpoints() {
+    assert!(DohClient::new("not a url").is_err());
+}
@@ -0,0 +1,149 @@
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::{A, SRV};
+use hickory_proto::rr::{Name, RData, Record};
+use rsip::Transport;
+use rsip_dns::{DnsClient, RecursiveHickoryClient, records::SrvDomain};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+const SERVER_NAME: &str = "dns.test";
+
+struct MockDotServer {
+    addr: SocketAddr,
+    client_config: Arc<rustls::ClientConfig>,
+    handle: JoinHandle<Option<String>>,
+    /// The address the connection came from
+    peer: oneshot::Receiver<SocketAddr>,
+}
+
+// Accepts a single TLS connection, answers the first framed query with a SRV answer
+// and returns the SNI the client presented.
+async fn spawn_mock_dot_server() -> MockDotServer {
+    let certified = rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()]).unwrap();
+    let cert_der = certified.cert.der().clone();
+    let key_der =
+        rustls::pki_types::PrivatePkcs8KeyDer::from(certified.signing_key.serialize_der());
+
+    let server_config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert_der.clone()], key_der.into())
+        .unwrap();
+
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(cert_der).unwrap();
+    let client_config =
+        rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+    let (peer_tx, peer) = oneshot::channel();
+
+    let handle = tokio::spawn(async move {
+        let (stream, from) = listener.accept().await.ok()?;
+        let _ = peer_tx.send(from);
+        let mut stream = acceptor.accept(stream).await.ok()?;
+        let sni = stream.get_ref().1.server_name().map(ToString::to_string);
+
+        let mut len_buf = [0u8; 2];
+        stream.read_exact(&mut len_buf).await.ok()?;
+        let mut query = vec![0u8; u16::from_be_bytes(len_buf) as usize];
+        stream.read_exact(&mut query).await.ok()?;
+
+        let response = srv_response(&Message::from_vec(&query).ok()?).to_vec().ok()?;
+        let mut framed = (response.len() as u16).to_be_bytes().to_vec();
+        framed.extend_from_slice(&response);
+        stream.write_all(&framed).await.ok()?;
+        stream.flush().await.ok()?;
+
+        sni
+    });
+
+    MockDotServer { addr, client_config: Arc::new(client_config), handle, peer }
+}
+
+fn srv_response(query: &Message) -> Message {
+    let mut response = Message::new();
+    response.set_id(query.id());
+    response.set_message_type(MessageType::Response);
+    response.set_response_code(ResponseCode::NoError);
+    response.add_queries(query.queries().to_vec());
+
+    let srv_name = query.queries()[0].name().clone();
+    let target = Name::from_str("sip1.example.com.").unwrap();
+    response.add_answer(Record::from_rdata(
+        srv_name,
+        120,
+        RData::SRV(SRV::new(10, 20, 5061, target.clone())),
+    ));
+    response.add_additional(Record::from_rdata(
+        target,
+        60,
+        RData::A(A::from(Ipv4Addr::new(192, 0, 2, 10))),
+    ));
+
+    response
+}
+
+#[tokio::test]
+async fn resolves_srv_over_tls() {
+    let server = spawn_mock_dot_server().await;
+    let client = RecursiveHickoryClient::with_dot_config(
+        server.addr,
+        SERVER_NAME.to_string(),
+        Duration::from_secs(5),
+        server.client_config.clone(),
+    );
+
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Tls);
+    let srv_record = client.srv_lookup(srv_domain.clone()).await.expect("srv record over DoT");
+
+    assert_eq!(srv_record.domain, srv_domain);
+    assert_eq!(srv_record.ttl, 120);
+    assert_eq!(srv_record.entries.len(), 1);
+    assert_eq!(srv_record.entries[0].port, 5061.into());
+    assert_eq!(srv_record.entries[0].target, "sip1.example.com.".into());
+    assert!(srv_record.has_complete_additionals());
+
+    assert_eq!(server.handle.await.unwrap(), Some(SERVER_NAME.to_string()));
+}
+
+#[tokio::test]
+async fn rejects_untrusted_server_certificate() {
+    let server = spawn_mock_dot_server().await;
+    let client = RecursiveHickoryClient::with_dot(
+        server.addr,
+        SERVER_NAME.to_string(),
+        Duration::from_secs(5),
+    );
+
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Tls);
+
+    assert!(client.srv_lookup(srv_domain).await.is_none());
+    assert_eq!(server.handle.await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn binds_to_the_given_local_addr() {
+    let server = spawn_mock_dot_server().await;
+    // a local address with a port nobody is bound to
+    let local_addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+    let client = RecursiveHickoryClient::with_dot_config(
+        server.addr,
+        SERVER_NAME.to_string(),
+        Duration::from_secs(5),
+        server.client_config.clone(),
+    )
+    .with_local_addr(local_addr);
+
+    let srv_domain = SrvDomain::new("example.com".into(), Transport::Tls);
+
+    assert!(client.srv_lookup(srv_domain).await.is_some());
+    assert_eq!(server.peer.await.unwrap(), local_addr);
+}
@@ -0,0 +1,80 @@
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::{Resolver, TokioResolver};
+use rsip_dns::{AsyncHickoryClient, DnsClient, HickoryClient};
+use std::net::{IpAddr, Ipv4Addr};
+use tokio::net::UdpSocket;
+
+// A resolver whose only name server answers every query with an empty response carrying
+// `response_code`
+async fn resolver_answered_with(response_code: ResponseCode) -> TokioResolver {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let port = socket.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+
+            let mut response = Message::new();
+            response.set_id(query.id());
+            response.set_message_type(MessageType::Response);
+            response.set_response_code(response_code);
+            response.add_queries(query.queries().to_vec());
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    let config = ResolverConfig::from_parts(
+        None,
+        vec![],
+        NameServerConfigGroup::from_ips_clear(&[IpAddr::V4(Ipv4Addr::LOCALHOST)], port, true),
+    );
+    let mut options = ResolverOpts::default();
+    options.attempts = 1;
+    options.cache_size = 0;
+
+    Resolver::builder_with_config(config, TokioConnectionProvider::default())
+        .with_options(options)
+        .build()
+}
+
+async fn ip_lookup_error<C: DnsClient>(dns_client: C) -> String {
+    dns_client.ip_lookup("example.com".into()).await.unwrap_err().to_string()
+}
+
+#[tokio::test]
+async fn missing_records_are_reported_as_such() {
+    for response_code in [ResponseCode::NoError, ResponseCode::NXDomain] {
+        let resolver = resolver_answered_with(response_code).await;
+
+        let error = ip_lookup_error(HickoryClient::new(resolver.clone())).await;
+        assert!(error.contains("No A or AAAA records found"), "{}", error);
+
+        let error = ip_lookup_error(AsyncHickoryClient::new(resolver)).await;
+        assert!(error.contains("No A or AAAA records found"), "{}", error);
+    }
+}
+
+#[tokio::test]
+async fn server_failures_are_exposed() {
+    let resolver = resolver_answered_with(ResponseCode::ServFail).await;
+
+    let error = ip_lookup_error(HickoryClient::new(resolver.clone())).await;
+    assert!(error.contains("DNS server failure"), "{}", error);
+
+    let error = ip_lookup_error(AsyncHickoryClient::new(resolver)).await;
+    assert!(error.contains("DNS server failure"), "{}", error);
+}
+
+#[tokio::test]
+async fn refusals_are_exposed() {
+    let resolver = resolver_answered_with(ResponseCode::Refused).await;
+
+    let error = ip_lookup_error(HickoryClient::new(resolver.clone())).await;
+    assert!(error.contains("refused"), "{}", error);
+
+    let error = ip_lookup_error(AsyncHickoryClient::new(resolver)).await;
+    assert!(error.contains("refused"), "{}", error);
+}
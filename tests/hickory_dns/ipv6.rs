@@ -0,0 +1,36 @@
+use super::local_addr::spawn_name_server;
+use super::shared_socket::srv_domain;
+use rsip_dns::{DnsClient, RecursiveHickoryClient};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+#[tokio::test]
+async fn queries_an_ipv6_name_server() {
+    let (name_server, server) = spawn_name_server(Ipv6Addr::LOCALHOST.into()).await;
+    assert!(name_server.is_ipv6());
+    let client = RecursiveHickoryClient::with_timeout(name_server, Duration::from_secs(5));
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record over IPv6");
+
+    assert_eq!(srv_record.entries[0].port, 5001.into());
+    assert!(server.await.unwrap().is_ipv6());
+}
+
+#[tokio::test]
+async fn shared_socket_queries_an_ipv6_name_server() {
+    let (name_server, server) = spawn_name_server(Ipv6Addr::LOCALHOST.into()).await;
+    let client =
+        RecursiveHickoryClient::with_timeout(name_server, Duration::from_secs(5)).shared_socket();
+
+    assert!(client.srv_lookup(srv_domain(1)).await.is_some());
+    assert!(server.await.unwrap().is_ipv6());
+}
+
+#[tokio::test]
+async fn rejects_a_local_addr_of_another_family() {
+    let (name_server, _server) = spawn_name_server(Ipv6Addr::LOCALHOST.into()).await;
+    let client = RecursiveHickoryClient::with_timeout(name_server, Duration::from_secs(5))
+        .with_local_addr(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)));
+
+    assert!(client.ip_lookup("example.com".into()).await.is_err());
+}
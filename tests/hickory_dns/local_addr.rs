@@ -0,0 +1,71 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::Message;
+use rsip_dns::{DnsClient, DnsError, RecursiveHickoryClient};
+use std::error::Error;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+// Answers a single query on `ip` and returns the address it came from
+pub async fn spawn_name_server(ip: IpAddr) -> (SocketAddr, JoinHandle<SocketAddr>) {
+    let socket = UdpSocket::bind((ip, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+        let response = srv_response(&Message::from_vec(&buf[..len]).unwrap());
+        socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+
+        from
+    });
+
+    (addr, handle)
+}
+
+// A local address with a port nobody is bound to
+async fn free_local_addr() -> SocketAddr {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    socket.local_addr().unwrap()
+}
+
+#[tokio::test]
+async fn binds_to_the_given_local_addr() {
+    let (name_server, server) = spawn_name_server(Ipv4Addr::LOCALHOST.into()).await;
+    let local_addr = free_local_addr().await;
+    let client = RecursiveHickoryClient::with_timeout(name_server, Duration::from_secs(5))
+        .with_local_addr(local_addr);
+
+    assert!(client.srv_lookup(srv_domain(1)).await.is_some());
+    assert_eq!(server.await.unwrap(), local_addr);
+}
+
+#[tokio::test]
+async fn shared_socket_binds_to_the_given_local_addr() {
+    let (name_server, server) = spawn_name_server(Ipv4Addr::LOCALHOST.into()).await;
+    let local_addr = free_local_addr().await;
+    let client = RecursiveHickoryClient::with_timeout(name_server, Duration::from_secs(5))
+        .with_local_addr(local_addr)
+        .shared_socket();
+
+    assert!(client.srv_lookup(srv_domain(1)).await.is_some());
+    assert_eq!(server.await.unwrap(), local_addr);
+}
+
+#[tokio::test]
+async fn a_local_addr_in_use_is_an_io_error() {
+    let (name_server, _server) = spawn_name_server(Ipv4Addr::LOCALHOST.into()).await;
+    let taken = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let client = RecursiveHickoryClient::with_timeout(name_server, Duration::from_secs(5))
+        .with_local_addr(taken.local_addr().unwrap());
+
+    let error = client.try_srv_lookup(srv_domain(1)).await.unwrap_err();
+    assert!(matches!(error, DnsError::Io { .. }));
+    assert!(error.to_string().starts_with("Failed to bind UDP socket: "));
+
+    let source = error.source().expect("the io error");
+    let io_error = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io_error.kind(), io::ErrorKind::AddrInUse);
+}
@@ -0,0 +1,22 @@
+pub mod authentic_data;
+pub mod client_subnet;
+pub mod cname_additionals;
+#[cfg(feature = "dns-over-https")]
+pub mod dns_over_https;
+#[cfg(feature = "dns-over-tls")]
+pub mod dns_over_tls;
+pub mod ip_lookup_errors;
+pub mod ipv6;
+pub mod local_addr;
+pub mod naptr_parsing;
+#[cfg(feature = "metrics")]
+pub mod query_metrics;
+pub mod raw_lookup;
+pub mod record_source;
+pub mod recursion_desired;
+pub mod response_codes;
+pub mod shared_socket;
+pub mod sync_recursive_client;
+pub mod timeouts;
+pub mod truncation;
+pub mod ttl_bounds;
@@ -0,0 +1,117 @@
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::NAPTR;
+use hickory_proto::rr::{Name, RData, Record};
+use rsip_dns::records::*;
+use rsip_dns::{DnsClient, RecursiveHickoryClient};
+use std::convert::TryFrom;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+// every service string, in both cases, along with what it parses into
+const SERVICES: [(&[u8], &str); 20] = [
+    (b"SIP+D2T", "SipD2t"),
+    (b"SIP+D2U", "SipD2u"),
+    (b"SIP+D2S", "SipD2s"),
+    (b"SIP+D2W", "SipD2w"),
+    (b"SIPS+D2T", "SipsD2t"),
+    (b"SIPS+D2U", "SipsD2u"),
+    (b"SIPS+D2S", "SipsD2s"),
+    (b"SIPS+D2W", "SipsD2w"),
+    (b"sip+d2t", "SipD2t"),
+    (b"sip+d2u", "SipD2u"),
+    (b"sip+d2s", "SipD2s"),
+    (b"sip+d2w", "SipD2w"),
+    (b"sips+d2t", "SipsD2t"),
+    (b"sips+d2u", "SipsD2u"),
+    (b"sips+d2s", "SipsD2s"),
+    (b"sips+d2w", "SipsD2w"),
+    (b"Sip+D2t", "SipD2t"),
+    (b"E2U+sip", "Other(\"E2U+sip\")"),
+    (b"SIP+\xff", "Other(\"SIP+\u{fffd}\")"),
+    (b"", "Other(\"\")"),
+];
+
+fn naptr(order: u16, flags: &[u8], services: &[u8]) -> RData {
+    RData::NAPTR(NAPTR::new(
+        order,
+        10,
+        flags.into(),
+        services.into(),
+        Box::default(),
+        Name::from_str("_sip._udp.example.com.").unwrap(),
+    ))
+}
+
+fn records() -> Vec<RData> {
+    let mut records: Vec<RData> = SERVICES
+        .iter()
+        .enumerate()
+        .map(|(order, (services, _))| naptr(order as u16, b"S", services))
+        .collect();
+    records.push(naptr(SERVICES.len() as u16, b"s", b"SIP+D2U"));
+    records.push(naptr(SERVICES.len() as u16 + 1, b"SA", b"SIP+D2U"));
+    records
+}
+
+// Answers every query with `records()`
+async fn naptr_server() -> RecursiveHickoryClient {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            let mut response = Message::new();
+            response.set_id(query.id());
+            response.set_message_type(MessageType::Response);
+            response.set_response_code(ResponseCode::NoError);
+            response.add_queries(query.queries().to_vec());
+            for rdata in records() {
+                response.add_answer(Record::from_rdata(
+                    query.queries()[0].name().clone(),
+                    60,
+                    rdata,
+                ));
+            }
+
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5))
+}
+
+fn assert_parsed(entries: &[NaptrEntry]) {
+    assert_eq!(entries.len(), SERVICES.len() + 2);
+    for (entry, (_, expected)) in entries.iter().zip(SERVICES) {
+        assert_eq!(format!("{:?}", entry.services), expected);
+        assert!(matches!(entry.flags, NaptrFlags::S));
+    }
+
+    let flags = entries[SERVICES.len()..]
+        .iter()
+        .map(|entry| format!("{:?}", entry.flags))
+        .collect::<Vec<_>>();
+    assert_eq!(flags, vec!["S", "Other([83, 65])"]);
+}
+
+#[test]
+fn parses_every_service_from_rdata() {
+    let entries =
+        records().into_iter().map(|rdata| NaptrEntry::try_from(rdata).unwrap()).collect::<Vec<_>>();
+
+    assert_parsed(&entries);
+    assert!(NaptrEntry::try_from(RData::A(Ipv4Addr::LOCALHOST.into())).is_err());
+}
+
+#[tokio::test]
+async fn the_recursive_client_parses_every_service_the_same_way() {
+    let client = naptr_server().await;
+
+    let naptr_record = client.naptr_lookup("example.com".into()).await.expect("naptr record");
+
+    assert_parsed(&naptr_record.entries);
+}
@@ -0,0 +1,75 @@
+use super::shared_socket::srv_domain;
+use super::sync_recursive_client::spawn_blocking_server;
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use rsip_dns::{DnsClient, RecursiveHickoryClient, SyncRecursiveClient};
+use std::collections::HashMap;
+use std::time::Duration;
+
+// The value of every counter and the number of samples of every histogram recorded so far,
+// keyed by metric name and record type
+fn recorded(snapshotter: &Snapshotter) -> HashMap<(String, String), u64> {
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| {
+            let key = key.key();
+            let record_type = key
+                .labels()
+                .find(|label| label.key() == "record_type")
+                .unwrap()
+                .value()
+                .to_string();
+            let value = match value {
+                DebugValue::Counter(count) => count,
+                DebugValue::Histogram(samples) => samples.len() as u64,
+                DebugValue::Gauge(_) => panic!("unexpected gauge {}", key.name()),
+            };
+
+            ((key.name().to_string(), record_type), value)
+        })
+        .collect()
+}
+
+fn metric(name: &str, record_type: &str) -> (String, String) {
+    (name.to_string(), record_type.to_string())
+}
+
+#[test]
+fn queries_are_counted_and_timed() {
+    let name_server = spawn_blocking_server();
+    let client = RecursiveHickoryClient::with_timeout(name_server, Duration::from_secs(5));
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    metrics::with_local_recorder(&recorder, || {
+        runtime.block_on(async {
+            assert!(client.srv_lookup(srv_domain(1)).await.is_some());
+            assert!(client.srv_lookup(srv_domain(2)).await.is_some());
+        })
+    });
+
+    let recorded = recorded(&snapshotter);
+    assert_eq!(recorded[&metric("dns_queries_total", "SRV")], 2);
+    assert_eq!(recorded[&metric("dns_query_duration_seconds", "SRV")], 2);
+}
+
+#[test]
+fn blocking_queries_are_counted_and_timed_by_record_type() {
+    let name_server = spawn_blocking_server();
+    let client = SyncRecursiveClient::with_timeout(name_server, Duration::from_secs(5));
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+
+    metrics::with_local_recorder(&recorder, || {
+        client.blocking_srv_lookup(srv_domain(1)).unwrap();
+        client.blocking_ip_lookup("sip.example.com".into()).unwrap();
+    });
+
+    let recorded = recorded(&snapshotter);
+    for record_type in ["SRV", "A", "AAAA"] {
+        assert_eq!(recorded[&metric("dns_queries_total", record_type)], 1);
+        assert_eq!(recorded[&metric("dns_query_duration_seconds", record_type)], 1);
+    }
+}
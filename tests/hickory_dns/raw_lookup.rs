@@ -0,0 +1,82 @@
+use crate::support::MockedDnsClient;
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::{CNAME, TXT};
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use rsip_dns::{DnsClient, DnsError, RecursiveHickoryClient};
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+// Answers TXT queries for `example.com.` through a CNAME, and any other query with NXDOMAIN
+async fn txt_server() -> RecursiveHickoryClient {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            let question = &query.queries()[0];
+
+            let mut response = Message::new();
+            response.set_id(query.id());
+            response.set_message_type(MessageType::Response);
+            response.add_queries(query.queries().to_vec());
+            if question.name() == &Name::from_str("example.com.").unwrap()
+                && question.query_type() == RecordType::TXT
+            {
+                response.set_response_code(ResponseCode::NoError);
+                response.add_answer(Record::from_rdata(
+                    question.name().clone(),
+                    300,
+                    RData::CNAME(CNAME(Name::from_str("txt.example.net.").unwrap())),
+                ));
+                response.add_answer(Record::from_rdata(
+                    Name::from_str("txt.example.net.").unwrap(),
+                    60,
+                    RData::TXT(TXT::new(vec!["v=spf1 -all".into()])),
+                ));
+            } else {
+                response.set_response_code(ResponseCode::NXDomain);
+            }
+
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5))
+}
+
+#[tokio::test]
+async fn fetches_txt_records_through_the_recursive_client() {
+    let client = txt_server().await;
+
+    let rdata = client.raw_lookup("example.com".into(), RecordType::TXT).await.unwrap();
+
+    // the CNAME that led to the TXT record is left out
+    assert_eq!(rdata.len(), 1);
+    match &rdata[0] {
+        RData::TXT(txt) => assert_eq!(txt.to_string(), "v=spf1 -all"),
+        other => panic!("expected a TXT record, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn missing_records_are_an_empty_list() {
+    let client = txt_server().await;
+
+    let rdata = client.raw_lookup("missing.example.com".into(), RecordType::TXT).await.unwrap();
+
+    assert!(rdata.is_empty());
+}
+
+#[tokio::test]
+async fn clients_without_raw_lookups_fail() {
+    let client = MockedDnsClient::default();
+
+    assert!(matches!(
+        client.raw_lookup("example.com".into(), RecordType::TXT).await,
+        Err(DnsError::Other(_))
+    ));
+}
@@ -0,0 +1,80 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use rsip::Domain;
+use rsip_dns::records::{AddrRecord, SrvRecord};
+use rsip_dns::{DnsClient, RecursiveHickoryClient};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+// Answers SRV queries with an additional A record for the target, and A queries directly
+async fn spawn_name_server() -> (RecursiveHickoryClient, SocketAddr) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            let name = query.queries()[0].name().clone();
+
+            let response = match query.queries()[0].query_type() {
+                RecordType::SRV => {
+                    let mut response = srv_response(&query);
+                    response.add_additional(Record::from_rdata(
+                        Name::from_str("sip.example.com.").unwrap(),
+                        300,
+                        RData::A(A(Ipv4Addr::new(10, 0, 0, 1))),
+                    ));
+                    response
+                }
+                record_type => {
+                    let mut response = Message::new();
+                    response.set_id(query.id());
+                    response.set_message_type(MessageType::Response);
+                    response.set_response_code(ResponseCode::NoError);
+                    response.add_queries(query.queries().to_vec());
+                    if record_type == RecordType::A {
+                        response.add_answer(Record::from_rdata(
+                            name,
+                            300,
+                            RData::A(A(Ipv4Addr::new(10, 0, 0, 1))),
+                        ));
+                    }
+                    response
+                }
+            };
+
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    (RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5)), addr)
+}
+
+#[tokio::test]
+async fn records_carry_the_answering_server() {
+    let (client, name_server) = spawn_name_server().await;
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record");
+    assert_eq!(srv_record.source, Some(name_server));
+    let additional = srv_record
+        .get_additional_for_target(&Domain::from("sip.example.com."))
+        .expect("additional A for the target");
+    assert_eq!(additional.source, Some(name_server));
+
+    let addr_record = client.ip_lookup("sip.example.com".into()).await.unwrap();
+    assert_eq!(addr_record.source, Some(name_server));
+}
+
+#[test]
+fn records_built_locally_have_no_source() {
+    let addr_record = AddrRecord::from((Domain::from("example.com"), vec![]));
+    assert_eq!(addr_record.source, None);
+
+    let srv_record = SrvRecord::new(vec![], srv_domain(1), 300);
+    assert_eq!(srv_record.source, None);
+}
@@ -0,0 +1,28 @@
+use super::client_subnet::spawn_capturing_server;
+use super::shared_socket::srv_domain;
+use rsip_dns::DnsClient;
+
+#[tokio::test]
+async fn queries_are_recursive_by_default() {
+    let (client, mut received) = spawn_capturing_server().await;
+
+    client.srv_lookup(srv_domain(1)).await.unwrap();
+
+    let query = received.recv().await.unwrap();
+    assert!(query.recursion_desired());
+}
+
+#[tokio::test]
+async fn recursion_can_be_turned_off_for_authoritative_servers() {
+    let (client, mut received) = spawn_capturing_server().await;
+    let client = client.with_recursion_desired(false);
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.unwrap();
+    assert_eq!(srv_record.entries.len(), 1);
+
+    let query = received.recv().await.unwrap();
+    assert!(!query.recursion_desired());
+
+    // on the wire, RD is the lowest bit of the third header byte
+    assert_eq!(query.to_vec().unwrap()[2] & 0x01, 0);
+}
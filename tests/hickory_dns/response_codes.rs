@@ -0,0 +1,64 @@
+use super::shared_socket::srv_domain;
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use rsip_dns::{DnsClient, DnsError, RecursiveHickoryClient};
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+// Answers every query with an empty response carrying `response_code`
+async fn client_answered_with(response_code: ResponseCode) -> RecursiveHickoryClient {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+
+            let mut response = Message::new();
+            response.set_id(query.id());
+            response.set_message_type(MessageType::Response);
+            response.set_response_code(response_code);
+            response.add_queries(query.queries().to_vec());
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5))
+}
+
+#[tokio::test]
+async fn nxdomain_is_a_missing_record() {
+    let client = client_answered_with(ResponseCode::NXDomain).await;
+
+    assert!(matches!(client.try_naptr_lookup("example.com".into()).await, Ok(None)));
+    assert!(matches!(client.try_srv_lookup(srv_domain(1)).await, Ok(None)));
+}
+
+#[tokio::test]
+async fn nodata_is_a_missing_record() {
+    let client = client_answered_with(ResponseCode::NoError).await;
+
+    assert!(matches!(client.try_naptr_lookup("example.com".into()).await, Ok(None)));
+    assert!(matches!(client.try_srv_lookup(srv_domain(1)).await, Ok(None)));
+}
+
+#[tokio::test]
+async fn servfail_is_a_server_failure() {
+    let client = client_answered_with(ResponseCode::ServFail).await;
+
+    assert_eq!(
+        client.try_naptr_lookup("example.com".into()).await.err(),
+        Some(DnsError::ServerFailure)
+    );
+    assert_eq!(client.try_srv_lookup(srv_domain(1)).await.err(), Some(DnsError::ServerFailure));
+    assert!(client.srv_lookup(srv_domain(1)).await.is_none());
+}
+
+#[tokio::test]
+async fn refused_is_reported() {
+    let client = client_answered_with(ResponseCode::Refused).await;
+
+    assert_eq!(client.try_naptr_lookup("example.com".into()).await.err(), Some(DnsError::Refused));
+    assert_eq!(client.try_srv_lookup(srv_domain(1)).await.err(), Some(DnsError::Refused));
+}
@@ -0,0 +1,113 @@
+use futures::future::join_all;
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::SRV;
+use hickory_proto::rr::{Name, RData, Record};
+use rsip::Transport;
+use rsip_dns::{DnsClient, RecursiveHickoryClient, records::SrvDomain};
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+const LOOKUPS: u16 = 200;
+
+pub fn srv_domain(n: u16) -> SrvDomain {
+    SrvDomain::new(format!("host{}.example.com", n).into(), Transport::Udp)
+}
+
+// Answers `_sip._udp.hostN.example.com` with a single SRV entry on port 5000 + N
+pub fn srv_response(query: &Message) -> Message {
+    let srv_name = query.queries()[0].name().clone();
+    let n: u16 = srv_name.to_string().split('.').nth(2).unwrap()["host".len()..].parse().unwrap();
+
+    let mut response = Message::new();
+    response.set_id(query.id());
+    response.set_message_type(MessageType::Response);
+    response.set_response_code(ResponseCode::NoError);
+    response.add_queries(query.queries().to_vec());
+    response.add_answer(Record::from_rdata(
+        srv_name,
+        60,
+        RData::SRV(SRV::new(10, 10, 5000 + n, Name::from_str("sip.example.com.").unwrap())),
+    ));
+
+    response
+}
+
+// Waits for `batch` queries before answering any of them, then answers them in reverse order.
+// Returns the source addresses the queries came from.
+async fn spawn_batching_server(batch: usize) -> (SocketAddr, JoinHandle<HashSet<SocketAddr>>) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    let handle = tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        let mut queries = Vec::with_capacity(batch);
+        while queries.len() < batch {
+            let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+            queries.push((Message::from_vec(&buf[..len]).unwrap(), from));
+        }
+
+        for (query, from) in queries.iter().rev() {
+            let response = srv_response(query).to_vec().unwrap();
+            socket.send_to(&response, from).await.unwrap();
+        }
+
+        queries.into_iter().map(|(_, from)| from).collect()
+    });
+
+    (addr, handle)
+}
+
+#[tokio::test]
+async fn shared_socket_demultiplexes_concurrent_lookups() {
+    let (addr, server) = spawn_batching_server(LOOKUPS as usize).await;
+    let client = RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5)).shared_socket();
+
+    let records = join_all((0..LOOKUPS).map(|n| {
+        let client = client.clone();
+        async move { client.srv_lookup(srv_domain(n)).await }
+    }))
+    .await;
+
+    for (n, srv_record) in (0..LOOKUPS).zip(records) {
+        let srv_record = srv_record.expect("srv record");
+        assert_eq!(srv_record.domain, srv_domain(n));
+        assert_eq!(srv_record.entries[0].port, (5000 + n).into());
+    }
+
+    // every query, from every clone, went out of the same socket
+    assert_eq!(server.await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn ignores_responses_with_a_foreign_id() {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+        let query = Message::from_vec(&buf[..len]).unwrap();
+
+        let mut spoofed = srv_response(&query);
+        spoofed.set_id(query.id().wrapping_add(1));
+        spoofed.answers_mut()[0].set_data(RData::SRV(SRV::new(
+            10,
+            10,
+            6666,
+            Name::from_str("evil.example.com.").unwrap(),
+        )));
+        socket.send_to(&spoofed.to_vec().unwrap(), from).await.unwrap();
+        socket.send_to(&srv_response(&query).to_vec().unwrap(), from).await.unwrap();
+    });
+
+    let client = RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5));
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record");
+
+    assert_eq!(srv_record.entries.len(), 1);
+    assert_eq!(srv_record.entries[0].port, 5001.into());
+    assert_eq!(srv_record.entries[0].target, "sip.example.com.".into());
+}
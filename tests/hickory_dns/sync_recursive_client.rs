@@ -0,0 +1,86 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::{RData, Record, RecordType, rdata::A};
+use rsip_dns::{DnsError, SyncRecursiveClient};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+
+// Answers every query from a blocking socket on its own thread: SRV queries with
+// `srv_response`, A queries with 192.0.2.1 and any other query with no record
+pub(super) fn spawn_blocking_server() -> SocketAddr {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf) {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            let response = match query.queries()[0].query_type() {
+                RecordType::SRV => srv_response(&query),
+                record_type => addr_response(&query, record_type),
+            };
+            socket.send_to(&response.to_vec().unwrap(), from).unwrap();
+        }
+    });
+
+    addr
+}
+
+fn addr_response(query: &Message, record_type: RecordType) -> Message {
+    let mut response = Message::new();
+    response.set_id(query.id());
+    response.set_message_type(MessageType::Response);
+    response.set_response_code(ResponseCode::NoError);
+    response.add_queries(query.queries().to_vec());
+    if record_type == RecordType::A {
+        response.add_answer(Record::from_rdata(
+            query.queries()[0].name().clone(),
+            60,
+            RData::A(A::new(192, 0, 2, 1)),
+        ));
+    }
+
+    response
+}
+
+#[test]
+fn srv_lookup() {
+    let name_server = spawn_blocking_server();
+    let client = SyncRecursiveClient::with_timeout(name_server, Duration::from_secs(5));
+
+    let srv_record = client.blocking_srv_lookup(srv_domain(3)).unwrap().unwrap();
+    assert_eq!(srv_record.entries.len(), 1);
+    assert_eq!(srv_record.entries[0].port, 5003.into());
+    assert_eq!(srv_record.source, Some(name_server));
+}
+
+#[test]
+fn ip_lookup() {
+    let name_server = spawn_blocking_server();
+    let client = SyncRecursiveClient::with_timeout(name_server, Duration::from_secs(5));
+
+    let addr_record = client.blocking_ip_lookup("sip.example.com".into()).unwrap();
+    assert_eq!(addr_record.ip_addrs, vec![IpAddr::from(Ipv4Addr::new(192, 0, 2, 1))]);
+    assert_eq!(addr_record.ttl, 60);
+}
+
+#[test]
+fn silent_name_server_times_out() {
+    // bound, so that nothing reports the port as unreachable, but never answering
+    let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+    let client =
+        SyncRecursiveClient::with_timeout(silent.local_addr().unwrap(), Duration::from_millis(100));
+
+    assert!(matches!(client.blocking_srv_lookup(srv_domain(1)), Err(DnsError::Timeout)));
+}
+
+#[test]
+fn unauthenticated_responses_are_rejected_when_required() {
+    let name_server = spawn_blocking_server();
+    let client = SyncRecursiveClient::with_timeout(name_server, Duration::from_secs(5))
+        .with_require_authentic_data(true);
+
+    // the server never sets the AD bit
+    assert!(matches!(client.blocking_srv_lookup(srv_domain(2)), Err(DnsError::Other(_))));
+}
@@ -0,0 +1,61 @@
+use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use hickory_resolver::name_server::TokioConnectionProvider;
+use hickory_resolver::{Resolver, TokioResolver};
+use rsip::Transport;
+use rsip_dns::{AsyncHickoryClient, DnsClient, DnsError, HickoryClient, records::SrvDomain};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+const CLIENT_TIMEOUT: Duration = Duration::from_millis(200);
+
+// A name server that swallows every query, with a resolver that would wait on it much longer
+// than the client timeout.
+async fn slow_resolver() -> (TokioResolver, UdpSocket) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let port = socket.local_addr().unwrap().port();
+
+    let config = ResolverConfig::from_parts(
+        None,
+        vec![],
+        NameServerConfigGroup::from_ips_clear(&[IpAddr::V4(Ipv4Addr::LOCALHOST)], port, true),
+    );
+    let mut options = ResolverOpts::default();
+    options.timeout = Duration::from_secs(30);
+    options.attempts = 1;
+
+    let resolver = Resolver::builder_with_config(config, TokioConnectionProvider::default())
+        .with_options(options)
+        .build();
+
+    (resolver, socket)
+}
+
+fn srv_domain() -> SrvDomain {
+    SrvDomain::new("example.com".into(), Transport::Udp)
+}
+
+async fn assert_times_out<C: DnsClient>(dns_client: C) {
+    let started = Instant::now();
+    assert!(dns_client.naptr_lookup("example.com".into()).await.is_none());
+    assert!(dns_client.srv_lookup(srv_domain()).await.is_none());
+    assert!(dns_client.ip_lookup("example.com".into()).await.is_err());
+    assert_eq!(dns_client.try_srv_lookup(srv_domain()).await.err(), Some(DnsError::Timeout));
+
+    // 5 lookups in total (A and AAAA for ip_lookup), each bounded by the client timeout
+    assert!(started.elapsed() < CLIENT_TIMEOUT * 5 + Duration::from_secs(2));
+}
+
+#[tokio::test]
+async fn async_hickory_client_times_out() {
+    let (resolver, _socket) = slow_resolver().await;
+
+    assert_times_out(AsyncHickoryClient::with_timeout(resolver, CLIENT_TIMEOUT)).await;
+}
+
+#[tokio::test]
+async fn hickory_client_times_out() {
+    let (resolver, _socket) = slow_resolver().await;
+
+    assert_times_out(HickoryClient::with_timeout(resolver, CLIENT_TIMEOUT)).await;
+}
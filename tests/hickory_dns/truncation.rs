@@ -0,0 +1,120 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::Message;
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{Name, RData, Record};
+use rsip::Domain;
+use rsip_dns::{DnsClient, RecursiveHickoryClient};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+
+const UDP_ADDR: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+const TCP_ADDR: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+
+// The SRV response, with an additional A record for the target
+fn response(query: &Message, ip: Ipv4Addr) -> Message {
+    let mut response = srv_response(query);
+    response.add_additional(Record::from_rdata(
+        Name::from_str("sip.example.com.").unwrap(),
+        300,
+        RData::A(A(ip)),
+    ));
+    response
+}
+
+// Answers over UDP with `udp_reply` and over TCP (on the same port) with a regular response,
+// the target resolving to UDP_ADDR and TCP_ADDR respectively. Returns the server address along
+// with the number of TCP queries.
+async fn spawn_name_server(udp_reply: fn(&Message) -> Vec<u8>) -> (SocketAddr, Arc<AtomicUsize>) {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let listener = TcpListener::bind(addr).await.unwrap();
+    let tcp_queries = Arc::new(AtomicUsize::new(0));
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            socket.send_to(&udp_reply(&query), from).await.unwrap();
+        }
+    });
+
+    let counter = tcp_queries.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            counter.fetch_add(1, Ordering::SeqCst);
+
+            let len = stream.read_u16().await.unwrap();
+            let mut buf = vec![0u8; len as usize];
+            stream.read_exact(&mut buf).await.unwrap();
+            let query = Message::from_vec(&buf).unwrap();
+
+            let bytes = response(&query, TCP_ADDR).to_vec().unwrap();
+            stream.write_u16(bytes.len() as u16).await.unwrap();
+            stream.write_all(&bytes).await.unwrap();
+        }
+    });
+
+    (addr, tcp_queries)
+}
+
+fn buffer_sized(query: &Message) -> Vec<u8> {
+    // a valid message padded up to exactly the size of the receive buffer
+    let mut bytes = response(query, UDP_ADDR).to_vec().unwrap();
+    bytes.resize(4096, 0);
+    bytes
+}
+
+async fn additional_ip(client: &RecursiveHickoryClient) -> IpAddr {
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record");
+    let additional = srv_record
+        .get_additional_for_target(&Domain::from("sip.example.com."))
+        .expect("additional A for the target");
+    additional.ip_addrs[0]
+}
+
+#[tokio::test]
+async fn buffer_sized_datagram_is_asked_again_over_tcp() {
+    let (addr, tcp_queries) = spawn_name_server(buffer_sized).await;
+    let client = RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5));
+
+    assert_eq!(additional_ip(&client).await, IpAddr::V4(TCP_ADDR));
+    assert_eq!(tcp_queries.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn buffer_sized_datagram_on_a_shared_socket_is_asked_again_over_tcp() {
+    let (addr, tcp_queries) = spawn_name_server(buffer_sized).await;
+    let client = RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5)).shared_socket();
+
+    assert_eq!(additional_ip(&client).await, IpAddr::V4(TCP_ADDR));
+    assert_eq!(tcp_queries.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn truncated_response_is_asked_again_over_tcp() {
+    let (addr, tcp_queries) = spawn_name_server(|query| {
+        let mut response = response(query, UDP_ADDR);
+        response.set_truncated(true);
+        response.to_vec().unwrap()
+    })
+    .await;
+    let client = RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5));
+
+    assert_eq!(additional_ip(&client).await, IpAddr::V4(TCP_ADDR));
+    assert_eq!(tcp_queries.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn regular_response_stays_on_udp() {
+    let (addr, tcp_queries) =
+        spawn_name_server(|query| response(query, UDP_ADDR).to_vec().unwrap()).await;
+    let client = RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5));
+
+    assert_eq!(additional_ip(&client).await, IpAddr::V4(UDP_ADDR));
+    assert_eq!(tcp_queries.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,90 @@
+use super::shared_socket::{srv_domain, srv_response};
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use hickory_proto::rr::rdata::A;
+use hickory_proto::rr::{Name, RData, Record, RecordType};
+use rsip::Domain;
+use rsip_dns::{DnsClient, RecursiveHickoryClient, records::TtlBounds};
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+
+const BOUNDS: TtlBounds = TtlBounds { min: 30, max: 3600 };
+
+// SRV queries get a TTL of 0 and an additional A record that lives for 10 weeks, A queries get a
+// TTL of 0
+async fn spawn_name_server() -> RecursiveHickoryClient {
+    let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let addr = socket.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 4096];
+        while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+            let query = Message::from_vec(&buf[..len]).unwrap();
+            let name = query.queries()[0].name().clone();
+
+            let response = match query.queries()[0].query_type() {
+                RecordType::SRV => {
+                    let mut response = srv_response(&query);
+                    response.answers_mut()[0].set_ttl(0);
+                    response.add_additional(Record::from_rdata(
+                        Name::from_str("sip.example.com.").unwrap(),
+                        10 * 7 * 24 * 3600,
+                        RData::A(A(Ipv4Addr::new(10, 0, 0, 1))),
+                    ));
+                    response
+                }
+                record_type => {
+                    let mut response = Message::new();
+                    response.set_id(query.id());
+                    response.set_message_type(MessageType::Response);
+                    response.set_response_code(ResponseCode::NoError);
+                    response.add_queries(query.queries().to_vec());
+                    if record_type == RecordType::A {
+                        response.add_answer(Record::from_rdata(
+                            name,
+                            0,
+                            RData::A(A(Ipv4Addr::new(10, 0, 0, 1))),
+                        ));
+                    }
+                    response
+                }
+            };
+
+            socket.send_to(&response.to_vec().unwrap(), from).await.unwrap();
+        }
+    });
+
+    RecursiveHickoryClient::with_timeout(addr, Duration::from_secs(5)).with_ttl_bounds(BOUNDS)
+}
+
+#[tokio::test]
+async fn zero_ttl_is_raised_to_min() {
+    let client = spawn_name_server().await;
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record");
+    assert_eq!(srv_record.ttl, BOUNDS.min);
+
+    let addr_record = client.ip_lookup("sip.example.com".into()).await.unwrap();
+    assert_eq!(addr_record.ttl, BOUNDS.min);
+    assert_eq!(addr_record.per_family_ttl, Some((BOUNDS.min, BOUNDS.min)));
+}
+
+#[tokio::test]
+async fn huge_ttl_is_capped_at_max() {
+    let client = spawn_name_server().await;
+
+    let srv_record = client.srv_lookup(srv_domain(1)).await.expect("srv record");
+    let additional = srv_record
+        .get_additional_for_target(&Domain::from("sip.example.com."))
+        .expect("additional A for the target");
+    assert_eq!(additional.ttl, BOUNDS.max);
+}
+
+#[test]
+fn default_bounds_leave_ttls_untouched() {
+    let bounds = TtlBounds::default();
+
+    assert_eq!(bounds.clamp(0), 0);
+    assert_eq!(bounds.clamp(u32::MAX), u32::MAX);
+}
@@ -1,4 +1,10 @@
+pub mod clients;
+pub mod context;
+pub mod error;
+#[cfg(feature = "hickory-dns")]
+pub mod hickory_dns;
 pub mod lookups;
 pub mod records;
 pub mod resolvables;
 pub mod support;
+pub mod targets;
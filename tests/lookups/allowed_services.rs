@@ -0,0 +1,118 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, NaptrMap, SrvConfig, SrvMap};
+use crate::support::{Query, RecordingDnsClient};
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+fn dns_client() -> RecordingDnsClient<CustomDnsClient> {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![
+            (
+                10,
+                10,
+                NaptrFlags::S,
+                NaptrServices::Other("SIP+D2X".into()),
+                SrvDomain::try_from("_sip._udp.exotic.example.com").unwrap(),
+            ),
+            (
+                20,
+                10,
+                NaptrFlags::S,
+                NaptrServices::SipD2u,
+                SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+            ),
+            (
+                30,
+                10,
+                NaptrFlags::S,
+                NaptrServices::SipsD2t,
+                SrvDomain::try_from("_sips._tcp.example.com").unwrap(),
+            ),
+        ],
+    );
+
+    let mut srv_map = SrvMap::new();
+    for (srv_domain, target) in [
+        ("_sip._udp.exotic.example.com", "exotic.example.com"),
+        ("_sip._udp.example.com", "udp.example.com"),
+        ("_sips._tcp.example.com", "tls.example.com"),
+    ] {
+        srv_map.insert(
+            SrvDomain::try_from(srv_domain).unwrap(),
+            vec![(10, 10, 5060.into(), target.into())],
+        );
+    }
+
+    let mut a_records = ARecords::new();
+    a_records.insert("exotic.example.com".into(), vec![IpAddr::from([192, 0, 2, 1])]);
+    a_records.insert("udp.example.com".into(), vec![IpAddr::from([192, 0, 2, 2])]);
+    a_records.insert("tls.example.com".into(), vec![IpAddr::from([192, 0, 2, 3])]);
+
+    RecordingDnsClient::new(
+        CustomDnsConfig {
+            naptr: NaptrConfig::Map(naptr_map),
+            srv: SrvConfig::Map(srv_map),
+            a: a_records.into(),
+        }
+        .into(),
+    )
+}
+
+async fn transports(context: Context<RecordingDnsClient<CustomDnsClient>>) -> Vec<Transport> {
+    let mut lookup = Lookup::from(context);
+    let mut transports = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        transports.push(target.transport);
+    }
+    transports
+}
+
+#[tokio::test]
+async fn every_service_with_a_transport_is_followed_by_default() {
+    let dns_client = dns_client();
+    let context =
+        Context::builder(dns_client.clone()).host(Domain::from("example.com")).build().unwrap();
+
+    assert_eq!(transports(context).await, vec![Transport::Udp, Transport::Tls]);
+}
+
+#[tokio::test]
+async fn unlisted_services_are_dropped() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host(Domain::from("example.com"))
+        .allowed_services(vec![NaptrServices::SipsD2t])
+        .build()
+        .unwrap();
+
+    assert_eq!(transports(context).await, vec![Transport::Tls]);
+    // neither the exotic service nor SIP+D2U is followed
+    assert_eq!(
+        dns_client.queries(),
+        vec![
+            Query::Naptr("example.com".into()),
+            Query::Srv(SrvDomain::try_from("_sips._tcp.example.com").unwrap()),
+            Query::Ip("tls.example.com".into()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn other_services_are_never_followed() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host(Domain::from("example.com"))
+        .allowed_services(vec![NaptrServices::SipD2u, NaptrServices::SipsD2t])
+        .build()
+        .unwrap();
+
+    assert_eq!(transports(context).await, vec![Transport::Udp, Transport::Tls]);
+    assert!(
+        !dns_client
+            .queries()
+            .contains(&Query::Srv(SrvDomain::try_from("_sip._udp.exotic.example.com").unwrap()))
+    );
+}
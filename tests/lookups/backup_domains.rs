@@ -0,0 +1,96 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::{Query, RecordingDnsClient};
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use testing_utils::Randomize;
+
+// primary.example.com has no record at all, backup.example.com publishes an SRV record
+fn dns_client(backup_ip: IpAddr) -> RecordingDnsClient<CustomDnsClient> {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.backup.example.com").unwrap(),
+        vec![(10, 5, 5062.into(), "server.backup.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("server.backup.example.com".into(), vec![backup_ip]);
+
+    let config =
+        CustomDnsConfig { naptr: NaptrMap::new().into(), srv: srv_map.into(), a: a_records.into() };
+
+    RecordingDnsClient::new(CustomDnsClient::from(config))
+}
+
+#[tokio::test]
+async fn moves_to_the_backup_domain_once_the_host_is_exhausted() {
+    let backup_ip: IpAddr = Randomize::random();
+    let dns_client = dns_client(backup_ip);
+
+    let context = Context::builder(dns_client.clone())
+        .host("primary.example.com")
+        .backup_domain("backup.example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.ip_addr, backup_ip);
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(target.port, 5062.into());
+    assert!(lookup.resolve_next().await.is_none());
+
+    // the whole flow of the primary domain runs before the backup domain is queried
+    let primary = Domain::from("primary.example.com");
+    let backup = Domain::from("backup.example.com");
+    assert_eq!(
+        dns_client.queries()[..4],
+        [
+            Query::Naptr(primary.clone()),
+            Query::Srv(SrvDomain::from((primary.clone(), Transport::Udp))),
+            Query::Srv(SrvDomain::from((primary.clone(), Transport::Tcp))),
+            Query::Ip(primary),
+        ]
+    );
+    assert_eq!(dns_client.queries()[4], Query::Naptr(backup));
+}
+
+#[tokio::test]
+async fn yields_the_targets_of_every_domain_in_order() {
+    let primary_ip: IpAddr = Randomize::random();
+    let backup_ip: IpAddr = Randomize::random();
+
+    // no NAPTR nor SRV record, both domains resolve through their A/AAAA records only
+    let mut a_records = ARecords::new();
+    a_records.insert("primary.example.com".into(), vec![primary_ip]);
+    a_records.insert("backup.example.com".into(), vec![backup_ip]);
+    let config = CustomDnsConfig {
+        naptr: NaptrMap::new().into(),
+        srv: SrvMap::new().into(),
+        a: a_records.into(),
+    };
+
+    let context = Context::builder(CustomDnsClient::from(config))
+        .host("primary.example.com")
+        .backup_domain("backup.example.com")
+        .supported_transports(vec![Transport::Udp])
+        .default_port(Transport::Udp, 5080)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    // transport and port rules apply to every domain
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(
+        (target.ip_addr, target.port, target.transport),
+        (primary_ip, 5080.into(), Transport::Udp)
+    );
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(
+        (target.ip_addr, target.port, target.transport),
+        (backup_ip, 5080.into(), Transport::Udp)
+    );
+    assert!(lookup.resolve_next().await.is_none());
+}
@@ -0,0 +1,137 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::{Query, RecordingDnsClient};
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::time::Duration;
+use testing_utils::Randomize;
+
+fn recording_client() -> (RecordingDnsClient<CustomDnsClient>, ARecords) {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![(10, 5, 5060.into(), "udp-server.example.com".into())],
+    );
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![(10, 5, 5062.into(), "tcp-server.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("udp-server.example.com".into(), vec![Randomize::random()]);
+    a_records.insert("tcp-server.example.com".into(), vec![Randomize::random()]);
+
+    let config = CustomDnsConfig {
+        naptr: NaptrMap::new().into(),
+        srv: srv_map.into(),
+        a: a_records.clone().into(),
+    };
+    let dns_client: CustomDnsClient = config.into();
+
+    (RecordingDnsClient::with_delay(dns_client, Duration::from_millis(20)), a_records)
+}
+
+/// Holds the `_sip._tcp` SRV answer back for a while
+#[derive(Debug, Clone)]
+struct SlowTcpDnsClient(RecordingDnsClient<CustomDnsClient>);
+
+const SLOW: Duration = Duration::from_secs(1);
+
+impl DnsClient for SlowTcpDnsClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.0.naptr_lookup(domain).await
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        let slow = domain.protocol == Transport::Tcp;
+        let srv_record = self.0.srv_lookup(domain).await;
+        if slow {
+            tokio::time::sleep(SLOW).await;
+        }
+        srv_record
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, rsip::Error> {
+        self.0.ip_lookup(domain).await
+    }
+}
+
+async fn resolve_all(
+    dns_client: RecordingDnsClient<CustomDnsClient>,
+    concurrent: bool,
+) -> Vec<Target> {
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .concurrent_srv_fallbacks(concurrent)
+        .build()
+        .unwrap();
+
+    let mut lookup = Lookup::from(context);
+    let mut targets = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        targets.push(target);
+    }
+
+    targets
+}
+
+#[tokio::test]
+async fn sequential_srv_fallbacks_by_default() {
+    let (dns_client, a_records) = recording_client();
+
+    let targets = resolve_all(dns_client.clone(), false).await;
+
+    assert_eq!(targets.len(), 2);
+    assert_eq!(targets[0].transport, Transport::Udp);
+    assert_eq!(targets[0].ip_addr, a_records[&Domain::from("udp-server.example.com")][0]);
+    assert_eq!(targets[1].transport, Transport::Tcp);
+    assert_eq!(targets[1].ip_addr, a_records[&Domain::from("tcp-server.example.com")][0]);
+    assert_eq!(dns_client.max_in_flight(), 1);
+}
+
+#[tokio::test]
+async fn concurrent_srv_fallbacks_keep_transport_order() {
+    let (dns_client, a_records) = recording_client();
+
+    let targets = resolve_all(dns_client.clone(), true).await;
+
+    assert_eq!(targets.len(), 2);
+    assert_eq!(targets[0].transport, Transport::Udp);
+    assert_eq!(targets[0].port, 5060.into());
+    assert_eq!(targets[0].ip_addr, a_records[&Domain::from("udp-server.example.com")][0]);
+    assert_eq!(targets[1].transport, Transport::Tcp);
+    assert_eq!(targets[1].port, 5062.into());
+    assert_eq!(targets[1].ip_addr, a_records[&Domain::from("tcp-server.example.com")][0]);
+
+    // both SRV queries were in flight at the same time, right after the NAPTR one
+    assert_eq!(dns_client.max_in_flight(), 2);
+    let queries = dns_client.queries();
+    assert_eq!(queries[0], Query::Naptr("example.com".into()));
+    assert!(matches!(queries[1], Query::Srv(_)));
+    assert!(matches!(queries[2], Query::Srv(_)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn concurrent_srv_fallbacks_do_not_wait_for_slower_transports() {
+    let (dns_client, _) = recording_client();
+    let context = Context::builder(SlowTcpDnsClient(dns_client.clone()))
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .concurrent_srv_fallbacks(true)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+    let start = tokio::time::Instant::now();
+
+    let first = lookup.resolve_next().await.unwrap();
+    assert_eq!(first.transport, Transport::Udp);
+    assert!(start.elapsed() < SLOW);
+    // the TCP query was fired along with the UDP one, and is still in flight
+    assert_eq!(dns_client.srv_queries(), 2);
+
+    let second = lookup.resolve_next().await.unwrap();
+    assert_eq!(second.transport, Transport::Tcp);
+    assert!(start.elapsed() >= SLOW);
+    assert!(lookup.resolve_next().await.is_none());
+}
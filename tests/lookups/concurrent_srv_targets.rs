@@ -0,0 +1,72 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::RecordingDnsClient;
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::time::Duration;
+use testing_utils::Randomize;
+
+const TARGETS: [&str; 3] = ["a.example.com", "b.example.com", "c.example.com"];
+
+// A SRV record with 3 targets of decreasing priority, none of them in the ADDITIONAL section
+fn recording_client() -> (RecordingDnsClient<CustomDnsClient>, Vec<IpAddr>) {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        TARGETS
+            .iter()
+            .enumerate()
+            .map(|(index, target)| (10 + index as u16, 5, 5060.into(), (*target).into()))
+            .collect(),
+    );
+
+    let ip_addrs = TARGETS.iter().map(|_| Randomize::random()).collect::<Vec<IpAddr>>();
+    let mut a_records = ARecords::new();
+    for (target, ip_addr) in TARGETS.iter().zip(&ip_addrs) {
+        a_records.insert((*target).into(), vec![*ip_addr]);
+    }
+
+    let config =
+        CustomDnsConfig { naptr: NaptrMap::new().into(), srv: srv_map.into(), a: a_records.into() };
+
+    (RecordingDnsClient::with_delay(config.into(), Duration::from_millis(20)), ip_addrs)
+}
+
+async fn resolve_all(context: Context<RecordingDnsClient<CustomDnsClient>>) -> Vec<IpAddr> {
+    let mut lookup = Lookup::from(context);
+
+    let mut ip_addrs = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        ip_addrs.push(target.ip_addr);
+    }
+    ip_addrs
+}
+
+#[tokio::test]
+async fn resolves_targets_concurrently_within_the_limit() {
+    let (dns_client, ip_addrs) = recording_client();
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .transport(Transport::Udp)
+        .concurrent_srv_targets(2)
+        .build()
+        .unwrap();
+
+    // still in priority order
+    assert_eq!(resolve_all(context).await, ip_addrs);
+    assert_eq!(dns_client.max_in_flight(), 2);
+}
+
+#[tokio::test]
+async fn resolves_targets_lazily_by_default() {
+    let (dns_client, ip_addrs) = recording_client();
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .transport(Transport::Udp)
+        .build()
+        .unwrap();
+
+    assert_eq!(resolve_all(context).await, ip_addrs);
+    assert_eq!(dns_client.max_in_flight(), 1);
+}
@@ -0,0 +1,108 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::{MockedDnsClient, Query, RecordingDnsClient};
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::net::{IpAddr, Ipv4Addr};
+use testing_utils::Randomize;
+
+// No SRV records at all, only the A/AAAA of the domain itself
+fn dns_client() -> CustomDnsClient {
+    let mut a_records = ARecords::new();
+    a_records.insert("example.com".into(), vec![Randomize::random()]);
+
+    CustomDnsConfig {
+        naptr: NaptrMap::new().into(),
+        srv: SrvMap::new().into(),
+        a: a_records.into(),
+    }
+    .into()
+}
+
+#[tokio::test]
+async fn addr_fallback_uses_the_overridden_port() {
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .skip_naptr(true)
+        .default_port(Transport::Udp, 5080)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.transport, Transport::Udp);
+    assert_eq!(target.port, 5080.into());
+    assert!(lookup.resolve_next().await.is_none());
+}
+
+#[tokio::test]
+async fn addr_fallback_of_an_explicit_transport_uses_the_overridden_port() {
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .transport(Transport::Tcp)
+        .default_port(Transport::Udp, 5080)
+        .default_port(Transport::Tcp, 5090)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(target.port, 5090.into());
+}
+
+#[tokio::test]
+async fn standard_port_without_override() {
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .skip_naptr(true)
+        .default_port(Transport::Tcp, 5090)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.port, 5060.into());
+}
+
+#[tokio::test]
+async fn naptr_a_flag_uses_the_overridden_port() {
+    let naptr_record = NaptrRecord::new(
+        vec![NaptrEntry {
+            order: 10,
+            preference: 10,
+            flags: NaptrFlags::A,
+            services: NaptrServices::SipD2u,
+            regexp: vec![],
+            replacement: "sip.example.com".into(),
+        }],
+        "example.com".into(),
+        300,
+    );
+    let ip_addr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let dns_client = RecordingDnsClient::new(MockedDnsClient {
+        naptr_record: Some(naptr_record),
+        a_record: Some(AddrRecord::from(("sip.example.com".into(), vec![ip_addr]))),
+        ..Default::default()
+    });
+
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .default_port(Transport::Udp, 5080)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.ip_addr, ip_addr);
+    assert_eq!(target.transport, Transport::Udp);
+    assert_eq!(target.port, 5080.into());
+    assert!(lookup.resolve_next().await.is_none());
+
+    assert_eq!(
+        dns_client.queries(),
+        vec![Query::Naptr("example.com".into()), Query::Ip("sip.example.com".into())]
+    );
+}
@@ -39,6 +39,40 @@ async fn context_lookup() {
     assert!(lookup.resolve_next().await.is_none());
 }
 
+#[tokio::test]
+async fn port_and_transport_skip_naptr_and_srv() {
+    let a_records = setup_dns_state();
+
+    // RFC 3263 section 4.2: with an explicit port the host is resolved through A/AAAA only,
+    // NAPTR and SRV queries would panic
+    let dns_config = CustomDnsConfig {
+        naptr: NaptrConfig::Panic,
+        srv: SrvConfig::Panic,
+        a: a_records.clone().into(),
+    };
+
+    let dns_client: CustomDnsClient = dns_config.into();
+
+    let uri = rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: ("example.com", 5070).into(),
+        params: vec![rsip::Param::Transport(rsip::Transport::Tcp)],
+        ..Default::default()
+    };
+
+    let mut lookup = Lookup::from(
+        Context::initialize_from(uri, dns_client.clone(), SupportedTransports::any()).unwrap(),
+    );
+
+    assert!(matches!(lookup, Lookup::DomainWithPort { .. }));
+
+    let expected_transport = rsip::Transport::Tcp;
+    assert_lookup!(lookup, a_records, expected_transport, 5070, "example.com", first);
+    assert_lookup!(lookup, a_records, expected_transport, 5070, "example.com", last);
+
+    assert!(lookup.resolve_next().await.is_none());
+}
+
 fn setup_dns_state() -> ARecords {
     let mut a_records = ARecords::new();
 
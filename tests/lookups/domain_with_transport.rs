@@ -25,6 +25,7 @@ async fn context_lookup() {
         port: None,
         dns_client: dns_client.clone(),
         supported_transports: rsip_dns::SupportedTransports::any(),
+        ..Default::default()
     };
 
     let mut lookup = Lookup::from(context);
@@ -0,0 +1,61 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, NaptrMap, SrvConfig, SrvMap};
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, resolvables::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+// The NAPTR record of example.com is present but has no entry at all
+fn dns_client() -> CustomDnsClient {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert("example.com".into(), vec![]);
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![(10, 10, 5060.into(), "sip.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("sip.example.com".into(), vec![IpAddr::from([192, 0, 2, 1])]);
+
+    CustomDnsConfig {
+        naptr: NaptrConfig::Map(naptr_map),
+        srv: SrvConfig::Map(srv_map),
+        a: a_records.into(),
+    }
+    .into()
+}
+
+#[tokio::test]
+async fn an_empty_record_is_not_a_success() {
+    let mut naptr = ResolvableNaptrRecord::new(
+        dns_client(),
+        Domain::from("example.com"),
+        SupportedTransports::any().all().clone(),
+    );
+
+    assert!(naptr.resolve_next().await.is_none());
+    assert_eq!(naptr.outcome(), Some(&NaptrOutcome::Empty));
+}
+
+#[tokio::test]
+async fn an_empty_record_falls_back_to_srv() {
+    let context = Context::builder(dns_client()).host(Domain::from("example.com")).build().unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.ip_addr, IpAddr::from([192, 0, 2, 1]));
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(lookup.last_resolution_method(), Some(ResolutionMethod::Srv));
+}
+
+#[tokio::test]
+async fn an_empty_record_ends_a_naptr_only_lookup() {
+    let context = Context::builder(dns_client())
+        .host(Domain::from("example.com"))
+        .fallback_policy(FallbackPolicy::NaptrOnly)
+        .build()
+        .unwrap();
+
+    assert!(Lookup::from(context).resolve_next().await.is_none());
+}
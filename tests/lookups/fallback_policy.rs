@@ -0,0 +1,109 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::RecordingDnsClient;
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use testing_utils::Randomize;
+
+fn dns_client(naptr: bool, srv: bool) -> RecordingDnsClient<CustomDnsClient> {
+    let mut naptr_map = NaptrMap::new();
+    if naptr {
+        naptr_map.insert(
+            "example.com".into(),
+            vec![(
+                50,
+                50,
+                NaptrFlags::S,
+                NaptrServices::SipD2u,
+                SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+            )],
+        );
+    }
+
+    let mut srv_map = SrvMap::new();
+    if srv {
+        srv_map.insert(
+            SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+            vec![(10, 5, 5070.into(), "udp-server.example.com".into())],
+        );
+    }
+
+    let mut a_records = ARecords::new();
+    a_records.insert("udp-server.example.com".into(), vec![Randomize::random()]);
+    a_records.insert("example.com".into(), vec![Randomize::random()]);
+
+    let config =
+        CustomDnsConfig { naptr: naptr_map.into(), srv: srv_map.into(), a: a_records.into() };
+
+    RecordingDnsClient::new(config.into())
+}
+
+async fn first_target(
+    dns_client: RecordingDnsClient<CustomDnsClient>,
+    policy: FallbackPolicy,
+) -> Option<Target> {
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .fallback_policy(policy)
+        .build()
+        .unwrap();
+
+    Lookup::from(context).resolve_next().await
+}
+
+#[tokio::test]
+async fn full_policy_reaches_addr_fallback() {
+    let dns_client = dns_client(false, false);
+
+    let target = first_target(dns_client.clone(), FallbackPolicy::Full).await.unwrap();
+
+    assert_eq!(target.port, 5060.into());
+    assert_eq!(target.transport, Transport::Udp);
+    assert_eq!(dns_client.naptr_queries(), 1);
+    assert_eq!(dns_client.srv_queries(), 2);
+    assert_eq!(dns_client.ip_queries(), 1);
+}
+
+#[tokio::test]
+async fn naptr_only_policy_stops_after_naptr() {
+    let dns_client = dns_client(false, true);
+
+    assert!(first_target(dns_client.clone(), FallbackPolicy::NaptrOnly).await.is_none());
+    assert_eq!(dns_client.naptr_queries(), 1);
+    assert_eq!(dns_client.srv_queries(), 0);
+    assert_eq!(dns_client.ip_queries(), 0);
+
+    let dns_client = self::dns_client(true, true);
+    let target = first_target(dns_client, FallbackPolicy::NaptrOnly).await.unwrap();
+    assert_eq!(target.port, 5070.into());
+}
+
+#[tokio::test]
+async fn srv_only_policy_skips_naptr_and_addr_fallback() {
+    let dns_client = dns_client(true, false);
+
+    assert!(first_target(dns_client.clone(), FallbackPolicy::SrvOnly).await.is_none());
+    assert_eq!(dns_client.naptr_queries(), 0);
+    assert_eq!(dns_client.srv_queries(), 2);
+    assert_eq!(dns_client.ip_queries(), 0);
+
+    let dns_client = self::dns_client(false, true);
+    let target = first_target(dns_client.clone(), FallbackPolicy::SrvOnly).await.unwrap();
+    assert_eq!(target.port, 5070.into());
+    assert_eq!(dns_client.naptr_queries(), 0);
+}
+
+#[tokio::test]
+async fn no_addr_fallback_policy_stops_after_srv() {
+    let dns_client = dns_client(false, false);
+
+    assert!(first_target(dns_client.clone(), FallbackPolicy::NoAddrFallback).await.is_none());
+    assert_eq!(dns_client.naptr_queries(), 1);
+    assert_eq!(dns_client.srv_queries(), 2);
+    assert_eq!(dns_client.ip_queries(), 0);
+
+    let dns_client = self::dns_client(false, true);
+    let target = first_target(dns_client, FallbackPolicy::NoAddrFallback).await.unwrap();
+    assert_eq!(target.port, 5070.into());
+}
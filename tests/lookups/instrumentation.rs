@@ -0,0 +1,40 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use testing_utils::Randomize;
+use tracing_test::traced_test;
+
+#[tokio::test]
+#[traced_test]
+async fn emits_lookup_events() {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![(10, 5, 5060.into(), "udp-server.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("udp-server.example.com".into(), vec![Randomize::random()]);
+
+    let config =
+        CustomDnsConfig { naptr: NaptrMap::new().into(), srv: srv_map.into(), a: a_records.into() };
+    let dns_client: CustomDnsClient = config.into();
+
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    assert!(lookup.resolve_next().await.is_some());
+
+    assert!(logs_contain("lookup{host=example.com"));
+    assert!(logs_contain("NAPTR query returned no record"));
+    assert!(logs_contain("from=\"TryingNaptr\" to=\"TryingSrvFallbacks\""));
+    assert!(logs_contain("SRV query returned"));
+    assert!(logs_contain("entries=1"));
+    assert!(logs_contain("A/AAAA query returned"));
+    assert!(logs_contain("resolved next target"));
+}
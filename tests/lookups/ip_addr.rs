@@ -16,7 +16,7 @@ async fn context_lookup() {
     );
     assert!(matches!(lookup, Lookup::IpAddr { .. }));
 
-    let Target { ip_addr, port, transport, ttl } = lookup.resolve_next().await.unwrap();
+    let Target { ip_addr, port, transport, ttl, .. } = lookup.resolve_next().await.unwrap();
     assert_eq!(ip_addr, host_ip_addr);
     assert_eq!(port, 5060.into());
     assert_eq!(transport, rsip::Transport::Udp);
@@ -24,3 +24,112 @@ async fn context_lookup() {
 
     assert!(lookup.resolve_next().await.is_none());
 }
+
+async fn only_target(uri: rsip::Uri) -> Target {
+    let mut lookup = Lookup::from(
+        Context::initialize_from(uri, PanicDnsClient, SupportedTransports::any()).unwrap(),
+    );
+    assert!(matches!(lookup, Lookup::IpAddr { .. }));
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert!(lookup.resolve_next().await.is_none());
+
+    target
+}
+
+fn ip_uri(
+    scheme: rsip::Scheme,
+    port: Option<u16>,
+    transport: Option<rsip::Transport>,
+) -> rsip::Uri {
+    rsip::Uri {
+        scheme: Some(scheme),
+        host_with_port: (IpAddr::from([192, 168, 1, 10]), port).into(),
+        params: transport.map(rsip::Param::Transport).into_iter().collect(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn ip_with_transport_param() {
+    use rsip::{Scheme, Transport};
+
+    for transport in [Transport::Udp, Transport::Tcp, Transport::Tls, Transport::Ws, Transport::Wss]
+    {
+        let target = only_target(ip_uri(Scheme::Sip, None, Some(transport))).await;
+
+        assert_eq!(target.ip_addr, IpAddr::from([192, 168, 1, 10]));
+        assert_eq!(target.transport, transport);
+        assert_eq!(target.port, transport.default_port());
+    }
+
+    let target = only_target(ip_uri(Scheme::Sip, None, Some(Transport::Tls))).await;
+    assert_eq!(target.port, 5061.into());
+}
+
+#[tokio::test]
+async fn ip_with_port_and_transport_param() {
+    use rsip::{Scheme, Transport};
+
+    let target = only_target(ip_uri(Scheme::Sip, Some(5080), Some(Transport::Tcp))).await;
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(target.port, 5080.into());
+
+    let target = only_target(ip_uri(Scheme::Sip, Some(5070), None)).await;
+    assert_eq!(target.transport, Transport::Udp);
+    assert_eq!(target.port, 5070.into());
+}
+
+#[tokio::test]
+async fn sips_ip_defaults_to_tls() {
+    use rsip::{Scheme, Transport};
+
+    let target = only_target(ip_uri(Scheme::Sips, None, None)).await;
+    assert_eq!(target.transport, Transport::Tls);
+    assert_eq!(target.port, 5061.into());
+}
+
+#[tokio::test]
+async fn ip_with_configured_default_ttl() {
+    let context = Context::builder(PanicDnsClient)
+        .host(IpAddr::from([192, 168, 1, 10]))
+        .default_ttl(60)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.ttl, 60);
+    assert!(lookup.resolve_next().await.is_none());
+}
+
+#[tokio::test]
+async fn maddr_takes_precedence_over_the_uri_host() {
+    use rsip::Transport;
+
+    // the PanicDnsClient makes sure that example.com is never looked up
+    let uri = rsip::Uri::try_from("sip:example.com;transport=tcp;maddr=203.0.113.5").unwrap();
+    let target = only_target(uri).await;
+
+    assert_eq!(target.ip_addr, IpAddr::from([203, 0, 113, 5]));
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(target.port, 5060.into());
+}
+
+#[tokio::test]
+async fn zone_scoped_ipv6_literal() {
+    use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
+
+    // the PanicDnsClient makes sure that the literal is never looked up as a domain
+    let uri = rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: rsip::Domain::from("[fe80::1%253]").into(),
+        ..Default::default()
+    };
+    let target = only_target(uri).await;
+
+    let ip_addr = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+    assert_eq!(target.ip_addr, IpAddr::from(ip_addr));
+    assert_eq!(target.scope_id, Some(3));
+    assert_eq!(target.socket_addr(), SocketAddr::from(SocketAddrV6::new(ip_addr, 5060, 0, 3)));
+}
@@ -0,0 +1,113 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::MockedDnsClient;
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const IPV4: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+const IPV6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+// The SRV target and the domain itself both have an A and an AAAA record
+fn dns_client() -> CustomDnsClient {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![(10, 5, 5060.into(), "server.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("server.example.com".into(), vec![IPV6, IPV4]);
+    a_records.insert("example.com".into(), vec![IPV6, IPV4]);
+
+    CustomDnsConfig { naptr: NaptrMap::new().into(), srv: srv_map.into(), a: a_records.into() }
+        .into()
+}
+
+async fn resolve_all<C: DnsClient>(context: Context<C>) -> Vec<IpAddr> {
+    let mut lookup = Lookup::from(context);
+
+    let mut ip_addrs = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        ip_addrs.push(target.ip_addr);
+    }
+    ip_addrs
+}
+
+#[tokio::test]
+async fn keeps_both_families_by_default() {
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .build()
+        .unwrap();
+
+    assert_eq!(resolve_all(context).await, vec![IPV6, IPV4]);
+}
+
+#[tokio::test]
+async fn drops_ipv6_addresses_in_v4_only_mode() {
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .ip_family(IpFamily::V4)
+        .build()
+        .unwrap();
+
+    assert_eq!(resolve_all(context).await, vec![IPV4]);
+}
+
+#[tokio::test]
+async fn applies_to_the_addr_fallback_and_domains_with_port() {
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .supported_transports(vec![Transport::Tcp])
+        .ip_family(IpFamily::V6)
+        .build()
+        .unwrap();
+    assert_eq!(resolve_all(context).await, vec![IPV6]);
+
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .port(5060)
+        .ip_family(IpFamily::V4)
+        .build()
+        .unwrap();
+    assert_eq!(resolve_all(context).await, vec![IPV4]);
+}
+
+#[tokio::test]
+async fn applies_to_additional_hosts() {
+    let domain = SrvDomain::try_from("_sip._udp.example.com").unwrap();
+    let entry = SrvEntry {
+        priority: 10,
+        weight: 5,
+        port: 5060.into(),
+        target: Domain::from("server.example.com"),
+    };
+    let additional_hosts = HashMap::from([(
+        Domain::from("server.example.com"),
+        AddrRecord::from((Domain::from("server.example.com"), vec![IPV6, IPV4])),
+    )]);
+    let dns_client = MockedDnsClient {
+        srv_record: Some(SrvRecord::with_additional_hosts(
+            vec![entry],
+            domain,
+            300,
+            additional_hosts,
+        )),
+        a_record: Some(AddrRecord::from((Domain::from("example.com"), vec![IPV6]))),
+        ..Default::default()
+    };
+
+    // the A/AAAA fallback of the explicit transport has no IPv4 address to give
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .transport(Transport::Udp)
+        .ip_family(IpFamily::V4)
+        .build()
+        .unwrap();
+
+    assert_eq!(resolve_all(context).await, vec![IPV4]);
+}
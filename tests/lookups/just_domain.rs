@@ -24,6 +24,7 @@ async fn context_lookup() {
         port: None,
         dns_client: dns_client.clone(),
         supported_transports: rsip_dns::SupportedTransports::any(),
+        ..Default::default()
     };
 
     let mut lookup = Lookup::from(context);
@@ -56,6 +57,28 @@ async fn context_lookup() {
     assert!(lookup.resolve_next().await.is_none());
 }
 
+#[test]
+fn try_new_rejects_ip_hosts() {
+    use crate::support::PanicDnsClient;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    let context = Context::builder(PanicDnsClient)
+        .host(IpAddr::V4(Ipv4Addr::new(192, 168, 2, 13)))
+        .build()
+        .unwrap();
+
+    assert!(JustDomainLookup::try_new(context).is_err());
+}
+
+#[test]
+fn try_new_accepts_domain_hosts() {
+    use crate::support::PanicDnsClient;
+
+    let context = Context::builder(PanicDnsClient).host("example.com").build().unwrap();
+
+    assert!(JustDomainLookup::try_new(context).is_ok());
+}
+
 fn setup_dns_state() -> (NaptrMap, SrvMap, ARecords) {
     let mut naptr_map = NaptrMap::new();
     naptr_map.insert(
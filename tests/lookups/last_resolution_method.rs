@@ -0,0 +1,83 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr};
+
+const SERVER: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+const FALLBACK: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+// `example.com` resolves through A/AAAA to FALLBACK, the SRV target to SERVER
+fn dns_client(with_naptr: bool, with_srv: bool) -> CustomDnsClient {
+    let srv_domain = SrvDomain::try_from("_sip._udp.example.com").unwrap();
+
+    let mut naptr_map = NaptrMap::new();
+    if with_naptr {
+        naptr_map.insert(
+            "example.com".into(),
+            vec![(10, 10, NaptrFlags::S, NaptrServices::SipD2u, srv_domain.clone())],
+        );
+    }
+
+    let mut srv_map = SrvMap::new();
+    if with_srv {
+        srv_map.insert(srv_domain, vec![(10, 5, 5060.into(), "server.example.com".into())]);
+    }
+
+    let mut a_records = ARecords::new();
+    a_records.insert("server.example.com".into(), vec![SERVER]);
+    a_records.insert("example.com".into(), vec![FALLBACK]);
+
+    CustomDnsConfig { naptr: naptr_map.into(), srv: srv_map.into(), a: a_records.into() }.into()
+}
+
+async fn assert_resolved_through(
+    dns_client: CustomDnsClient,
+    ip_addr: IpAddr,
+    method: ResolutionMethod,
+) {
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+    assert_eq!(lookup.last_resolution_method(), None);
+
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(ip_addr));
+    assert_eq!(lookup.last_resolution_method(), Some(method));
+
+    assert!(lookup.resolve_next().await.is_none());
+    assert_eq!(lookup.last_resolution_method(), None);
+}
+
+#[tokio::test]
+async fn reports_naptr() {
+    assert_resolved_through(dns_client(true, true), SERVER, ResolutionMethod::Naptr).await;
+}
+
+#[tokio::test]
+async fn reports_the_srv_fallback() {
+    assert_resolved_through(dns_client(false, true), SERVER, ResolutionMethod::Srv).await;
+}
+
+#[tokio::test]
+async fn reports_the_addr_fallback() {
+    assert_resolved_through(dns_client(false, false), FALLBACK, ResolutionMethod::Addr).await;
+}
+
+#[tokio::test]
+async fn reports_an_explicit_transport_resolved_through_srv() {
+    let context = Context::builder(dns_client(false, true))
+        .host("example.com")
+        .transport(Transport::Udp)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    assert!(lookup.resolve_next().await.is_some());
+    assert_eq!(lookup.last_resolution_method(), Some(ResolutionMethod::Srv));
+
+    lookup.reset();
+    assert_eq!(lookup.last_resolution_method(), None);
+}
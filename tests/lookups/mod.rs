@@ -1,6 +1,6 @@
 macro_rules! assert_lookup {
     ($lookup:expr, $a_records:expr, $transport:ident, $port:expr, $a_domain:expr, $index:ident) => {
-        let Target { ip_addr, port, transport, ttl } = $lookup.resolve_next().await.unwrap();
+        let Target { ip_addr, port, transport, ttl, .. } = $lookup.resolve_next().await.unwrap();
         assert_eq!(transport, $transport);
         assert_eq!(port, $port.into());
         assert_eq!(ttl, 300); // Default TTL from test DNS client
@@ -15,10 +15,42 @@ use rsip::{Domain, Error, Port};
 use rsip_dns::{DnsClient, records::*};
 use std::{collections::HashMap, net::IpAddr};
 
+pub mod allowed_services;
+pub mod backup_domains;
+pub mod concurrent_srv;
+pub mod concurrent_srv_targets;
+pub mod default_ports;
 pub mod domain_with_port;
 pub mod domain_with_transport;
+pub mod empty_naptr;
+pub mod fallback_policy;
+#[cfg(feature = "tracing")]
+pub mod instrumentation;
 pub mod ip_addr;
+pub mod ip_family;
 pub mod just_domain;
+pub mod last_resolution_method;
+pub mod overall_deadline;
+pub mod p_flag_hook;
+pub mod peek_next;
+pub mod prefer_ipv6;
+pub mod query_budget;
+pub mod reset;
+pub mod resolution_report;
+pub mod resolution_tree;
+pub mod resolve_all_sorted;
+pub mod resolve_many;
+pub mod resolve_until;
+pub mod sctp;
+pub mod server_failure;
+pub mod skip_naptr;
+pub mod socket_addrs;
+pub mod srv_only;
+pub mod synthesize_all_transports;
+pub mod target_count;
+pub mod target_ranker;
+pub mod transport_param;
+pub mod transport_preference;
 pub mod ttl_tracking;
 
 #[derive(Clone, Default)]
@@ -91,6 +123,7 @@ fn naptr_records_from_naptr_map(naptr_map: NaptrMap) -> NaptrRecords {
                 },
                 ttl: 300,
                 additional_srvs: HashMap::new(),
+                additional_hosts: HashMap::new(),
             },
         );
     }
@@ -123,7 +156,6 @@ fn srv_records_from_srv_map(srv_map: SrvMap) -> SrvRecords {
     srv_records
 }
 
-#[async_trait::async_trait]
 impl DnsClient for CustomDnsClient {
     async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
         log::info!("requested NAPTR for {}", domain);
@@ -139,7 +171,9 @@ impl DnsClient for CustomDnsClient {
         log::info!("requested A for {}", domain);
 
         match self.a_records.clone().unwrap().get(&domain).cloned() {
-            Some(ip_addrs) => Ok(AddrRecord { domain, ip_addrs, ttl: 300 }),
+            Some(ip_addrs) => {
+                Ok(AddrRecord { domain, ip_addrs, ttl: 300, per_family_ttl: None, source: None })
+            }
             None => Err(Error::Unexpected(format!("Could not find anything for {}", domain))),
         }
     }
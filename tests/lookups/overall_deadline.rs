@@ -0,0 +1,84 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::RecordingDnsClient;
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::time::Duration;
+use testing_utils::Randomize;
+use tokio::time::Instant;
+
+const TARGETS: [&str; 3] = ["a.example.com", "b.example.com", "c.example.com"];
+const DELAY: Duration = Duration::from_millis(100);
+
+// A SRV record with 3 targets, every query taking `DELAY` to be answered
+fn slow_client() -> (RecordingDnsClient<CustomDnsClient>, Vec<IpAddr>) {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        TARGETS
+            .iter()
+            .enumerate()
+            .map(|(index, target)| (10 + index as u16, 5, 5060.into(), (*target).into()))
+            .collect(),
+    );
+
+    let ip_addrs = TARGETS.iter().map(|_| Randomize::random()).collect::<Vec<IpAddr>>();
+    let mut a_records = ARecords::new();
+    for (target, ip_addr) in TARGETS.iter().zip(&ip_addrs) {
+        a_records.insert((*target).into(), vec![*ip_addr]);
+    }
+
+    let config =
+        CustomDnsConfig { naptr: NaptrMap::new().into(), srv: srv_map.into(), a: a_records.into() };
+
+    (RecordingDnsClient::with_delay(config.into(), DELAY), ip_addrs)
+}
+
+fn lookup(
+    dns_client: RecordingDnsClient<CustomDnsClient>,
+) -> Lookup<RecordingDnsClient<CustomDnsClient>> {
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .transport(Transport::Udp)
+        .overall_deadline(Duration::from_millis(250))
+        .build()
+        .unwrap();
+
+    Lookup::from(context)
+}
+
+#[tokio::test(start_paused = true)]
+async fn stops_yielding_once_the_deadline_elapsed() {
+    let (dns_client, ip_addrs) = slow_client();
+    let mut lookup = lookup(dns_client.clone());
+
+    // SRV, then A/AAAA of the first target
+    let started = Instant::now();
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(ip_addrs[0]));
+
+    // the A/AAAA query of the second target is still in flight when the deadline elapses
+    assert!(lookup.resolve_next().await.is_none());
+    assert!(started.elapsed() >= Duration::from_millis(250));
+    assert!(started.elapsed() < Duration::from_millis(300));
+    assert!(lookup.resolve_next().await.is_none());
+    assert_eq!(dns_client.ip_queries(), 2);
+
+    // a reset starts the deadline over
+    lookup.reset();
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(ip_addrs[0]));
+}
+
+#[tokio::test(start_paused = true)]
+async fn no_deadline_by_default() {
+    let (dns_client, ip_addrs) = slow_client();
+    let context =
+        Context::builder(dns_client).host("example.com").transport(Transport::Udp).build().unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let mut resolved = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        resolved.push(target.ip_addr);
+    }
+    assert_eq!(resolved, ip_addrs);
+}
@@ -0,0 +1,86 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, NaptrMap, SrvConfig, SrvMap};
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+
+// The preferred NAPTR entry has the P flag, and points to a SRV record that doesn't exist
+fn dns_client() -> CustomDnsClient {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![
+            (
+                10,
+                10,
+                NaptrFlags::P,
+                NaptrServices::SipD2u,
+                SrvDomain::try_from("_sip._udp.enum.example.com").unwrap(),
+            ),
+            (
+                20,
+                10,
+                NaptrFlags::S,
+                NaptrServices::SipD2t,
+                SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+            ),
+        ],
+    );
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![(10, 10, 5060.into(), "sip.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("sip.example.com".into(), vec![IpAddr::from([192, 0, 2, 1])]);
+
+    CustomDnsConfig {
+        naptr: NaptrConfig::Map(naptr_map),
+        srv: SrvConfig::Map(srv_map),
+        a: a_records.into(),
+    }
+    .into()
+}
+
+async fn targets(mut lookup: Lookup<CustomDnsClient>) -> Vec<Target> {
+    let mut targets = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        targets.push(target);
+    }
+    targets
+}
+
+#[tokio::test]
+async fn p_flag_entries_are_handed_to_the_hook() {
+    let seen = Arc::new(Mutex::new(vec![]));
+    let hook_seen = seen.clone();
+    let context = Context::builder(dns_client())
+        .host(Domain::from("example.com"))
+        .on_p_flag(move |entry: &NaptrEntry| hook_seen.lock().unwrap().push(entry.clone()))
+        .build()
+        .unwrap();
+
+    let targets = targets(Lookup::from(context)).await;
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 1);
+    assert!(matches!(seen[0].flags, NaptrFlags::P));
+    assert_eq!(seen[0].replacement, Domain::from("_sip._udp.enum.example.com"));
+    // the P entry itself is not resolved
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].transport, Transport::Tcp);
+}
+
+#[tokio::test]
+async fn p_flag_entries_are_ignored_by_default() {
+    let context = Context::builder(dns_client()).host(Domain::from("example.com")).build().unwrap();
+
+    let targets = targets(Lookup::from(context)).await;
+
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].ip_addr, IpAddr::from([192, 0, 2, 1]));
+    assert_eq!(targets[0].transport, Transport::Tcp);
+}
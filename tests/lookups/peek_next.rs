@@ -0,0 +1,73 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvMap};
+use crate::support::RecordingDnsClient;
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use testing_utils::Randomize;
+
+fn dns_client() -> RecordingDnsClient<CustomDnsClient> {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![
+            (10, 5, 5062.into(), "tcp1.example.com".into()),
+            (20, 5, 5064.into(), "tcp2.example.com".into()),
+        ],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("tcp1.example.com".into(), vec![Randomize::random()]);
+    a_records.insert("tcp2.example.com".into(), vec![Randomize::random()]);
+    a_records.insert("example.com".into(), vec![Randomize::random()]);
+
+    let config =
+        CustomDnsConfig { naptr: NaptrConfig::Panic, srv: srv_map.into(), a: a_records.into() };
+    RecordingDnsClient::new(CustomDnsClient::from(config))
+}
+
+#[tokio::test]
+async fn peek_then_resolve_returns_the_same_target() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .skip_naptr(true)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let peeked = lookup.peek_next().await.cloned().unwrap();
+    assert_eq!(peeked.port, 5062.into());
+    // peeking again neither skips nor queries anything
+    let queries = dns_client.queries().len();
+    assert_eq!(lookup.peek_next().await, Some(&peeked));
+    assert_eq!(dns_client.queries().len(), queries);
+
+    assert_eq!(lookup.resolve_next().await, Some(peeked));
+
+    let next = lookup.resolve_next().await.unwrap();
+    assert_eq!(next.port, 5064.into());
+    assert!(lookup.peek_next().await.is_none());
+    assert!(lookup.resolve_next().await.is_none());
+}
+
+#[tokio::test]
+async fn peek_moves_on_to_the_next_resolvable() {
+    let dns_client = dns_client();
+    // no SRV for udp, so the only target comes from the A/AAAA fallback
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .transport(Transport::Udp)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let peeked = lookup.peek_next().await.cloned().unwrap();
+    assert_eq!(peeked.transport, Transport::Udp);
+    assert_eq!(peeked.port, 5060.into());
+
+    assert_eq!(lookup.resolve_next().await, Some(peeked));
+    assert!(lookup.resolve_next().await.is_none());
+    assert_eq!(dns_client.srv_queries(), 1);
+    assert_eq!(dns_client.ip_queries(), 1);
+}
@@ -0,0 +1,127 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, NaptrMap, SrvConfig, SrvMap};
+use rsip::{Domain, Port};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+const V4_1: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1));
+const V4_2: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 2));
+const V6_1: IpAddr = IpAddr::V6(std::net::Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+const V6_2: IpAddr = IpAddr::V6(std::net::Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2));
+
+fn dns_client() -> CustomDnsClient {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![(
+            10,
+            10,
+            NaptrFlags::S,
+            NaptrServices::SipD2u,
+            SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        )],
+    );
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![
+            (10, 10, 5060.into(), "sip1.example.com".into()),
+            (20, 10, 5060.into(), "sip2.example.com".into()),
+        ],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("example.com".into(), vec![V4_1, V6_1, V4_2, V6_2]);
+    a_records.insert("sip1.example.com".into(), vec![V4_1, V6_1]);
+    a_records.insert("sip2.example.com".into(), vec![V4_2, V6_2]);
+
+    CustomDnsConfig {
+        naptr: NaptrConfig::Map(naptr_map),
+        srv: SrvConfig::Map(srv_map),
+        a: a_records.into(),
+    }
+    .into()
+}
+
+async fn ip_addrs(context: Context<CustomDnsClient>) -> Vec<IpAddr> {
+    let mut lookup = Lookup::from(context);
+    let mut ip_addrs = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        ip_addrs.push(target.ip_addr);
+    }
+    ip_addrs
+}
+
+#[tokio::test]
+async fn ipv6_addresses_of_a_host_come_first() {
+    let context = Context::builder(dns_client())
+        .host(Domain::from("example.com"))
+        .port(Port::from(5060))
+        .prefer_ipv6(true)
+        .build()
+        .unwrap();
+
+    let ip_addrs = ip_addrs(context).await;
+
+    assert_eq!(ip_addrs, vec![V6_1, V6_2, V4_1, V4_2]);
+    let first_v4 = ip_addrs.iter().position(IpAddr::is_ipv4).unwrap();
+    assert!(ip_addrs[first_v4..].iter().all(IpAddr::is_ipv4));
+}
+
+#[tokio::test]
+async fn ipv6_targets_of_every_srv_target_come_first() {
+    let context = Context::builder(dns_client())
+        .host(Domain::from("example.com"))
+        .prefer_ipv6(true)
+        .build()
+        .unwrap();
+
+    // each family keeps the SRV priority order: sip1 before sip2
+    let ip_addrs = ip_addrs(context).await;
+
+    assert_eq!(ip_addrs, vec![V6_1, V6_2, V4_1, V4_2]);
+    let first_v4 = ip_addrs.iter().position(IpAddr::is_ipv4).unwrap();
+    assert!(ip_addrs[first_v4..].iter().all(IpAddr::is_ipv4));
+}
+
+#[tokio::test]
+async fn reordered_targets_keep_their_resolution_method() {
+    let context = Context::builder(dns_client())
+        .host(Domain::from("example.com"))
+        .prefer_ipv6(true)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    assert_eq!(lookup.target_count().await, 4);
+    assert_eq!(lookup.peek_next().await.map(|target| target.ip_addr), Some(V6_1));
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(V6_1));
+    assert_eq!(lookup.last_resolution_method(), Some(ResolutionMethod::Naptr));
+    assert_eq!(lookup.target_count().await, 3);
+}
+
+#[tokio::test]
+async fn answer_order_is_kept_by_default() {
+    let context = Context::builder(dns_client())
+        .host(Domain::from("example.com"))
+        .port(Port::from(5060))
+        .build()
+        .unwrap();
+
+    assert_eq!(ip_addrs(context).await, vec![V4_1, V6_1, V4_2, V6_2]);
+}
+
+#[tokio::test]
+async fn peeked_target_is_returned_by_the_next_resolution() {
+    let context = Context::builder(dns_client())
+        .host(Domain::from("example.com"))
+        .prefer_ipv6(true)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    assert_eq!(lookup.peek_next().await.map(|target| target.ip_addr), Some(V6_1));
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(V6_1));
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(V6_2));
+}
@@ -0,0 +1,118 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, NaptrMap, SrvConfig, SrvMap};
+use crate::support::RecordingDnsClient;
+use rsip::{Domain, Transport};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+const TARGETS: u8 = 10;
+
+/// A NAPTR record pointing to a SRV record with many targets, each of its own host
+fn dns_client() -> RecordingDnsClient<CustomDnsClient> {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![(
+            10,
+            10,
+            NaptrFlags::S,
+            NaptrServices::SipD2u,
+            SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        )],
+    );
+
+    let mut srv_map = SrvMap::new();
+    let mut a_records = ARecords::new();
+    let mut srv_entries = vec![];
+    for index in 0..TARGETS {
+        let target = format!("host{}.example.com", index);
+        srv_entries.push((index as u16, 10, 5060.into(), target.clone().into()));
+        a_records.insert(target.into(), vec![IpAddr::from([192, 0, 2, index + 1])]);
+    }
+    srv_map.insert(SrvDomain::try_from("_sip._udp.example.com").unwrap(), srv_entries);
+
+    RecordingDnsClient::new(
+        CustomDnsConfig {
+            naptr: NaptrConfig::Map(naptr_map),
+            srv: SrvConfig::Map(srv_map),
+            a: a_records.into(),
+        }
+        .into(),
+    )
+}
+
+async fn targets<C: DnsClient>(lookup: &mut Lookup<C>) -> Vec<Target> {
+    let mut targets = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        targets.push(target);
+    }
+    targets
+}
+
+#[tokio::test]
+async fn resolution_stops_once_the_budget_is_spent() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host(Domain::from("example.com"))
+        .query_budget(5)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    // NAPTR, SRV, then the A/AAAA records of 3 of the targets
+    let targets = targets(&mut lookup).await;
+    assert_eq!(targets.len(), 3);
+    assert!(targets.iter().all(|target| target.transport == Transport::Udp));
+    assert_eq!(dns_client.queries().len(), 5);
+    assert_eq!(lookup.take_report().queries, 5);
+
+    // the lookup doesn't go on past its budget
+    assert_eq!(lookup.resolve_next().await, None);
+    assert_eq!(dns_client.queries().len(), 5);
+}
+
+#[tokio::test]
+async fn reset_gives_the_budget_back() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host(Domain::from("example.com"))
+        .query_budget(3)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    assert_eq!(targets(&mut lookup).await.len(), 1);
+    lookup.reset();
+    assert_eq!(targets(&mut lookup).await.len(), 1);
+    assert_eq!(dns_client.queries().len(), 6);
+}
+
+#[tokio::test]
+async fn without_budget_every_target_is_resolved() {
+    let dns_client = dns_client();
+    let context =
+        Context::builder(dns_client.clone()).host(Domain::from("example.com")).build().unwrap();
+    let mut lookup = Lookup::from(context);
+
+    assert_eq!(targets(&mut lookup).await.len(), TARGETS as usize);
+    assert_eq!(dns_client.queries().len(), 2 + TARGETS as usize);
+}
+
+#[tokio::test]
+async fn srv_only_is_not_charged_to_the_budget() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host(Domain::from("example.com"))
+        .query_budget(5)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    for _ in 0..5 {
+        assert!(lookup.srv_only().await.is_some());
+    }
+
+    assert_eq!(targets(&mut lookup).await.len(), 3);
+    assert_eq!(lookup.take_report().queries, 5);
+    assert_eq!(dns_client.queries().len(), 10);
+}
@@ -0,0 +1,51 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvMap};
+use crate::support::RecordingDnsClient;
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use testing_utils::Randomize;
+
+#[tokio::test]
+async fn reset_repeats_the_resolution() {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![
+            (10, 5, 5062.into(), "tcp1.example.com".into()),
+            (20, 5, 5064.into(), "tcp2.example.com".into()),
+        ],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("tcp1.example.com".into(), vec![Randomize::random()]);
+    a_records.insert("tcp2.example.com".into(), vec![Randomize::random()]);
+
+    let config =
+        CustomDnsConfig { naptr: NaptrConfig::Panic, srv: srv_map.into(), a: a_records.into() };
+    let dns_client = RecordingDnsClient::new(CustomDnsClient::from(config));
+
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .skip_naptr(true)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let mut first_run = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        first_run.push(target);
+    }
+    assert_eq!(first_run.len(), 2);
+    let queries = dns_client.queries();
+
+    lookup.reset();
+
+    let mut second_run = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        second_run.push(target);
+    }
+    assert_eq!(second_run, first_run);
+    // every query is issued again
+    assert_eq!(dns_client.queries()[queries.len()..], queries[..]);
+}
@@ -0,0 +1,64 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::PanicDnsClient;
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr};
+
+const SERVER: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+// NAPTR points to the TCP SRV record, whose only target has a single address
+fn dns_client() -> CustomDnsClient {
+    let srv_domain = SrvDomain::try_from("_sip._tcp.example.com").unwrap();
+
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![(10, 10, NaptrFlags::S, NaptrServices::SipD2t, srv_domain.clone())],
+    );
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(srv_domain, vec![(10, 5, 5062.into(), "tcp-server.example.com".into())]);
+
+    let mut a_records = ARecords::new();
+    a_records.insert("tcp-server.example.com".into(), vec![SERVER]);
+
+    CustomDnsConfig { naptr: naptr_map.into(), srv: srv_map.into(), a: a_records.into() }.into()
+}
+
+#[tokio::test]
+async fn reports_a_naptr_resolution() {
+    let context = Context::builder(dns_client()).host("example.com").build().unwrap();
+    let mut lookup = Lookup::from(context);
+
+    // a peeked target is only accounted for once
+    assert_eq!(lookup.peek_next().await.map(|target| target.ip_addr), Some(SERVER));
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(SERVER));
+    assert!(lookup.resolve_next().await.is_none());
+
+    assert_eq!(
+        lookup.take_report(),
+        ResolutionReport {
+            method: Some(ResolutionMethod::Naptr),
+            // NAPTR, SRV and A/AAAA
+            queries: 3,
+            transport: Some(Transport::Tcp),
+            ttls: vec![300],
+        }
+    );
+    // taking the report starts a new one
+    assert_eq!(lookup.take_report(), ResolutionReport::default());
+}
+
+#[tokio::test]
+async fn reports_an_ip_address_host() {
+    let context = Context::builder(PanicDnsClient).host(SERVER).build().unwrap();
+    let mut lookup = Lookup::from(context);
+
+    assert!(lookup.resolve_next().await.is_some());
+
+    let report = lookup.take_report();
+    assert_eq!(report.method, Some(ResolutionMethod::IpAddr));
+    assert_eq!(report.queries, 0);
+    assert_eq!(report.transport, Some(Transport::Udp));
+}
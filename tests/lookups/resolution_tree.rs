@@ -0,0 +1,84 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, NaptrMap, SrvConfig, SrvMap};
+use crate::support::{PanicDnsClient, RecordingDnsClient};
+use rsip::{Domain, Host};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+fn dns_client() -> RecordingDnsClient<CustomDnsClient> {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![(
+            10,
+            10,
+            NaptrFlags::S,
+            NaptrServices::SipD2u,
+            SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        )],
+    );
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![(10, 10, 5060.into(), "udp.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("udp.example.com".into(), vec![IpAddr::from([192, 0, 2, 1])]);
+
+    RecordingDnsClient::new(
+        CustomDnsConfig {
+            naptr: NaptrConfig::Map(naptr_map),
+            srv: SrvConfig::Map(srv_map),
+            a: a_records.into(),
+        }
+        .into(),
+    )
+}
+
+#[tokio::test]
+async fn expands_naptr_into_srv_into_addresses() {
+    let dns_client = dns_client();
+    let context =
+        Context::builder(dns_client.clone()).host(Domain::from("example.com")).build().unwrap();
+
+    let tree = resolution_tree(&context).await;
+
+    let naptr = tree.naptr.unwrap();
+    assert_eq!(naptr.domain, Domain::from("example.com"));
+    assert_eq!(naptr.ttl, 300);
+    assert_eq!(naptr.branches.len(), 1);
+    assert_eq!(naptr.branches[0].entry.services, NaptrServices::SipD2u);
+
+    let Some(NaptrChild::Srv(srv)) = &naptr.branches[0].child else {
+        panic!("expected the NAPTR entry to point to a SRV record");
+    };
+    assert_eq!(srv.domain, SrvDomain::try_from("_sip._udp.example.com").unwrap());
+    assert_eq!(srv.ttl, 300);
+    assert_eq!(srv.branches.len(), 1);
+    assert_eq!(srv.branches[0].addrs.domain, Domain::from("udp.example.com"));
+    assert_eq!(srv.branches[0].addrs.ip_addrs, vec![IpAddr::from([192, 0, 2, 1])]);
+    assert_eq!(srv.branches[0].addrs.ttl, 300);
+
+    // the NAPTR record was followed, so neither SRV nor A records of the host were queried
+    assert!(tree.srvs.is_empty());
+    assert!(tree.addrs.is_none());
+    assert_eq!(dns_client.naptr_queries(), 1);
+    assert_eq!(dns_client.srv_queries(), 1);
+    assert_eq!(dns_client.ip_queries(), 1);
+}
+
+#[tokio::test]
+async fn ip_addr_host_needs_no_query() {
+    let context = Context::builder(PanicDnsClient)
+        .host(Host::IpAddr(IpAddr::from([192, 0, 2, 1])))
+        .build()
+        .unwrap();
+
+    let tree = resolution_tree(&context).await;
+
+    assert!(tree.naptr.is_none());
+    assert!(tree.srvs.is_empty());
+    assert!(tree.addrs.is_none());
+}
@@ -0,0 +1,161 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, NaptrMap, SrvMap};
+use rsip::{Port, Transport};
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+const UDP1_V4: [u8; 4] = [192, 0, 2, 1];
+const UDP1_V6: [u16; 8] = [0x2001, 0xdb8, 0, 0, 0, 0, 0, 1];
+const UDP2_V4: [u8; 4] = [192, 0, 2, 2];
+const TCP1_V4: [u8; 4] = [192, 0, 2, 3];
+const TCP1_V6: [u16; 8] = [0x2001, 0xdb8, 0, 0, 0, 0, 0, 3];
+
+// NAPTR prefers UDP, whose SRV record has 2 hosts, while TCP has a single host. Both UDP1 and
+// TCP1 are dual stack.
+fn lookup() -> Lookup<CustomDnsClient> {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![
+            (
+                10,
+                10,
+                NaptrFlags::S,
+                NaptrServices::SipD2u,
+                SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+            ),
+            (
+                20,
+                10,
+                NaptrFlags::S,
+                NaptrServices::SipD2t,
+                SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+            ),
+        ],
+    );
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![
+            (10, 5, 5060.into(), "udp1.example.com".into()),
+            (20, 5, 5070.into(), "udp2.example.com".into()),
+        ],
+    );
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![(10, 5, 5062.into(), "tcp1.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("udp1.example.com".into(), vec![UDP1_V4.into(), UDP1_V6.into()]);
+    a_records.insert("udp2.example.com".into(), vec![UDP2_V4.into()]);
+    a_records.insert("tcp1.example.com".into(), vec![TCP1_V4.into(), TCP1_V6.into()]);
+
+    let dns_client: CustomDnsClient =
+        CustomDnsConfig { naptr: naptr_map.into(), srv: srv_map.into(), a: a_records.into() }
+            .into();
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .build()
+        .unwrap();
+
+    Lookup::from(context)
+}
+
+fn tuples(targets: Vec<Target>) -> Vec<(IpAddr, Port, Transport)> {
+    targets.into_iter().map(|target| (target.ip_addr, target.port, target.transport)).collect()
+}
+
+#[tokio::test]
+async fn default_policy_keeps_the_resolution_order() {
+    let targets = lookup().resolve_all_sorted(TargetSortPolicy::default()).await;
+
+    assert_eq!(
+        tuples(targets),
+        vec![
+            (UDP1_V4.into(), 5060.into(), Transport::Udp),
+            (UDP1_V6.into(), 5060.into(), Transport::Udp),
+            (UDP2_V4.into(), 5070.into(), Transport::Udp),
+            (TCP1_V4.into(), 5062.into(), Transport::Tcp),
+            (TCP1_V6.into(), 5062.into(), Transport::Tcp),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sorts_by_transport_then_srv_then_family() {
+    let policy =
+        TargetSortPolicy::new(vec![Transport::Tcp, Transport::Udp]).with_ip_family(IpFamily::V6);
+
+    let targets = lookup().resolve_all_sorted(policy).await;
+
+    assert_eq!(
+        tuples(targets),
+        vec![
+            (TCP1_V6.into(), 5062.into(), Transport::Tcp),
+            (TCP1_V4.into(), 5062.into(), Transport::Tcp),
+            (UDP1_V6.into(), 5060.into(), Transport::Udp),
+            (UDP1_V4.into(), 5060.into(), Transport::Udp),
+            (UDP2_V4.into(), 5070.into(), Transport::Udp),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn hosts_on_the_same_port_are_sorted_apart() {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![
+            (10, 5, 5060.into(), "udp1.example.com".into()),
+            (20, 5, 5060.into(), "udp2.example.com".into()),
+        ],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("udp1.example.com".into(), vec![UDP1_V4.into(), UDP1_V6.into()]);
+    a_records.insert("udp2.example.com".into(), vec![UDP2_V4.into(), TCP1_V6.into()]);
+
+    let dns_client: CustomDnsClient =
+        CustomDnsConfig { naptr: NaptrConfig::Panic, srv: srv_map.into(), a: a_records.into() }
+            .into();
+    let context =
+        Context::builder(dns_client).host("example.com").transport(Transport::Udp).build().unwrap();
+    let policy = TargetSortPolicy::default().with_ip_family(IpFamily::V6);
+
+    let targets = Lookup::from(context).resolve_all_sorted(policy).await;
+
+    // the IPv6 address of udp2 doesn't jump ahead of udp1, which has a higher SRV priority
+    assert_eq!(
+        tuples(targets),
+        vec![
+            (UDP1_V6.into(), 5060.into(), Transport::Udp),
+            (UDP1_V4.into(), 5060.into(), Transport::Udp),
+            (TCP1_V6.into(), 5060.into(), Transport::Udp),
+            (UDP2_V4.into(), 5060.into(), Transport::Udp),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn unlisted_transports_go_last() {
+    let policy = TargetSortPolicy::new(vec![Transport::Tcp]);
+
+    let targets = lookup().resolve_all_sorted(policy).await;
+
+    let transports = targets.iter().map(|target| target.transport).collect::<Vec<_>>();
+    assert_eq!(
+        transports,
+        vec![Transport::Tcp, Transport::Tcp, Transport::Udp, Transport::Udp, Transport::Udp]
+    );
+}
+
+#[tokio::test]
+async fn drains_the_lookup() {
+    let mut lookup = lookup();
+
+    assert_eq!(lookup.resolve_all_sorted(TargetSortPolicy::default()).await.len(), 5);
+    assert!(lookup.resolve_next().await.is_none());
+}
@@ -0,0 +1,59 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvConfig};
+use crate::support::RecordingDnsClient;
+use futures::StreamExt;
+use rsip_dns::*;
+use std::net::IpAddr;
+use std::time::Duration;
+
+const DOMAINS: usize = 6;
+
+fn dns_client() -> CustomDnsClient {
+    let mut a_records = ARecords::new();
+    for index in 0..DOMAINS {
+        a_records.insert(
+            format!("proxy{}.example.com", index).into(),
+            vec![IpAddr::from([192, 0, 2, index as u8])],
+        );
+    }
+
+    CustomDnsConfig { naptr: NaptrConfig::Panic, srv: SrvConfig::Panic, a: a_records.into() }.into()
+}
+
+fn contexts<C: DnsClient>(dns_client: C) -> Vec<Context<C>> {
+    (0..DOMAINS)
+        .map(|index| {
+            Context::builder(dns_client.clone())
+                .host(format!("proxy{}.example.com", index).as_str())
+                .port(rsip::Port::from(5060))
+                .build()
+                .unwrap()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn yields_the_targets_of_every_context_by_index() {
+    let mut results =
+        resolve_many(contexts(dns_client()), 3).collect::<Vec<(usize, Vec<Target>)>>().await;
+    results.sort_by_key(|(index, _)| *index);
+
+    assert_eq!(results.len(), DOMAINS);
+    for (expected_index, (index, targets)) in results.into_iter().enumerate() {
+        assert_eq!(index, expected_index);
+        assert_eq!(
+            targets.iter().map(|target| target.ip_addr).collect::<Vec<_>>(),
+            vec![IpAddr::from([192, 0, 2, index as u8])]
+        );
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn caps_the_lookups_in_flight() {
+    let dns_client = RecordingDnsClient::with_delay(dns_client(), Duration::from_millis(100));
+
+    let results = resolve_many(contexts(dns_client.clone()), 2).collect::<Vec<_>>().await;
+
+    assert_eq!(results.len(), DOMAINS);
+    assert_eq!(dns_client.ip_queries(), DOMAINS);
+    assert_eq!(dns_client.max_in_flight(), 2);
+}
@@ -0,0 +1,80 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvConfig};
+use rsip_dns::*;
+use std::net::IpAddr;
+
+fn lookup() -> Lookup<CustomDnsClient> {
+    let mut a_records = ARecords::new();
+    a_records.insert(
+        "example.com".into(),
+        vec![
+            IpAddr::from([192, 0, 2, 1]),
+            IpAddr::from([192, 0, 2, 2]),
+            IpAddr::from([192, 0, 2, 3]),
+            IpAddr::from([192, 0, 2, 4]),
+        ],
+    );
+
+    let dns_config =
+        CustomDnsConfig { naptr: NaptrConfig::Panic, srv: SrvConfig::Panic, a: a_records.into() };
+
+    let uri = rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: ("example.com", 5060).into(),
+        ..Default::default()
+    };
+
+    Lookup::from(
+        Context::initialize_from(
+            uri,
+            CustomDnsClient::from(dns_config),
+            SupportedTransports::any(),
+        )
+        .unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn stops_at_the_first_success() {
+    let mut lookup = lookup();
+    let mut attempts = vec![];
+
+    let connected = lookup
+        .resolve_until(|target| {
+            attempts.push(target.ip_addr);
+            let attempt = attempts.len();
+            async move {
+                match attempt {
+                    1 | 2 => Err(format!("attempt {} refused", attempt)),
+                    _ => Ok(target.socket_addr()),
+                }
+            }
+        })
+        .await;
+
+    assert_eq!(connected, Ok(([192, 0, 2, 3], 5060).into()));
+    assert_eq!(
+        attempts,
+        vec![
+            IpAddr::from([192, 0, 2, 1]),
+            IpAddr::from([192, 0, 2, 2]),
+            IpAddr::from([192, 0, 2, 3])
+        ]
+    );
+
+    // the remaining target is left for a later attempt
+    assert_eq!(
+        lookup.resolve_next().await.map(|target| target.ip_addr),
+        Some([192, 0, 2, 4].into())
+    );
+}
+
+#[tokio::test]
+async fn returns_every_error_once_exhausted() {
+    let mut lookup = lookup();
+
+    let result: Result<(), Vec<IpAddr>> =
+        lookup.resolve_until(|target| async move { Err(target.ip_addr) }).await;
+
+    assert_eq!(result.unwrap_err().len(), 4);
+    assert!(lookup.resolve_next().await.is_none());
+}
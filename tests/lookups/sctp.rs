@@ -0,0 +1,100 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::{Query, RecordingDnsClient};
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+const SCTP_SERVER: [u8; 4] = [192, 0, 2, 10];
+const TLS_SCTP_SERVER: [u8; 4] = [192, 0, 2, 11];
+
+fn srv_domain(name: &str) -> SrvDomain {
+    SrvDomain::try_from(name).unwrap()
+}
+
+// NAPTR lists SIP+D2S, SIPS+D2S and SIPS+D2U, SRV exists for every one of them
+fn dns_client() -> RecordingDnsClient<CustomDnsClient> {
+    let mut naptr_map = NaptrMap::new();
+    naptr_map.insert(
+        "example.com".into(),
+        vec![
+            (10, 10, NaptrFlags::S, NaptrServices::SipsD2u, srv_domain("_sips._udp.example.com")),
+            (20, 10, NaptrFlags::S, NaptrServices::SipD2s, srv_domain("_sip._sctp.example.com")),
+            (30, 10, NaptrFlags::S, NaptrServices::SipsD2s, srv_domain("_sips._sctp.example.com")),
+        ],
+    );
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        srv_domain("_sips._udp.example.com"),
+        vec![(10, 5, 5061.into(), "dtls.example.com".into())],
+    );
+    srv_map.insert(
+        srv_domain("_sip._sctp.example.com"),
+        vec![(10, 5, 5060.into(), "sctp.example.com".into())],
+    );
+    srv_map.insert(
+        srv_domain("_sips._sctp.example.com"),
+        vec![(10, 5, 5061.into(), "tls-sctp.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("dtls.example.com".into(), vec![[192, 0, 2, 12].into()]);
+    a_records.insert("sctp.example.com".into(), vec![SCTP_SERVER.into()]);
+    a_records.insert("tls-sctp.example.com".into(), vec![TLS_SCTP_SERVER.into()]);
+
+    let config =
+        CustomDnsConfig { naptr: naptr_map.into(), srv: srv_map.into(), a: a_records.into() };
+    RecordingDnsClient::new(config.into())
+}
+
+async fn resolve_all(dns_client: RecordingDnsClient<CustomDnsClient>) -> Vec<Target> {
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Sctp, Transport::TlsSctp])
+        .build()
+        .unwrap();
+
+    let mut lookup = Lookup::from(context);
+    let mut targets = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        targets.push(target);
+    }
+
+    targets
+}
+
+#[test]
+fn maps_d2s_services_to_sctp() {
+    assert_eq!(NaptrServices::SipD2s.transport(), Some(Transport::Sctp));
+    assert_eq!(NaptrServices::SipsD2s.transport(), Some(Transport::TlsSctp));
+    // no DTLS transport to map SIPS+D2U to
+    assert_eq!(NaptrServices::SipsD2u.transport(), None);
+}
+
+#[tokio::test]
+async fn resolves_sip_d2s_naptr_entries() {
+    let dns_client = dns_client();
+
+    let targets = resolve_all(dns_client.clone()).await;
+
+    assert_eq!(targets.len(), 2);
+    assert_eq!(targets[0].ip_addr, IpAddr::from(SCTP_SERVER));
+    assert_eq!(targets[0].port, 5060.into());
+    assert_eq!(targets[0].transport, Transport::Sctp);
+    assert_eq!(targets[1].ip_addr, IpAddr::from(TLS_SCTP_SERVER));
+    assert_eq!(targets[1].port, 5061.into());
+    assert_eq!(targets[1].transport, Transport::TlsSctp);
+
+    // the SIPS+D2U entry is filtered out without any query
+    assert_eq!(
+        dns_client.queries(),
+        vec![
+            Query::Naptr("example.com".into()),
+            Query::Srv(srv_domain("_sip._sctp.example.com")),
+            Query::Ip("sctp.example.com".into()),
+            Query::Srv(srv_domain("_sips._sctp.example.com")),
+            Query::Ip("tls-sctp.example.com".into()),
+        ]
+    );
+}
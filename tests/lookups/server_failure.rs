@@ -0,0 +1,143 @@
+use crate::support::RecordingDnsClient;
+use rsip::{Domain, Error, Transport};
+use rsip_dns::{records::*, *};
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+
+/// What the name server answers to a NAPTR query
+#[derive(Debug, Clone, Copy)]
+enum Answer {
+    Record,
+    NxDomain,
+    ServFail,
+    Refused,
+    Timeout,
+}
+
+/// Answers NAPTR queries following a script (NXDOMAIN once the script runs out). The NAPTR
+/// record points to `_sip._tcp` (port 5080) while SRV fallbacks start with `_sip._udp`
+/// (port 5070), so the first target tells which path produced it.
+#[derive(Debug, Clone)]
+struct ScriptedDnsClient {
+    naptr_answers: Arc<Mutex<VecDeque<Answer>>>,
+}
+
+impl ScriptedDnsClient {
+    fn new(naptr_answers: Vec<Answer>) -> RecordingDnsClient<Self> {
+        RecordingDnsClient::new(Self { naptr_answers: Arc::new(Mutex::new(naptr_answers.into())) })
+    }
+}
+
+impl DnsClient for ScriptedDnsClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.try_naptr_lookup(domain).await.ok().flatten()
+    }
+
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        let port = match domain.protocol {
+            Transport::Tcp => 5080,
+            _ => 5070,
+        };
+        let entry = SrvEntry {
+            priority: 10,
+            weight: 5,
+            port: port.into(),
+            target: "sip.example.com".into(),
+        };
+
+        Some(SrvRecord::new(vec![entry], domain, 300))
+    }
+
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        Ok(AddrRecord {
+            domain,
+            ip_addrs: vec![IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))],
+            ttl: 300,
+            per_family_ttl: None,
+            source: None,
+        })
+    }
+
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        let answer = self.naptr_answers.lock().unwrap().pop_front().unwrap_or(Answer::NxDomain);
+
+        match answer {
+            Answer::Record => {
+                let entry = NaptrEntry {
+                    order: 50,
+                    preference: 50,
+                    flags: NaptrFlags::S,
+                    services: NaptrServices::SipD2t,
+                    regexp: vec![],
+                    replacement: "_sip._tcp.example.com".into(),
+                };
+                Ok(Some(NaptrRecord::new(vec![entry], domain, 300)))
+            }
+            Answer::NxDomain => Ok(None),
+            Answer::ServFail => Err(DnsError::ServerFailure),
+            Answer::Refused => Err(DnsError::Refused),
+            Answer::Timeout => Err(DnsError::Timeout),
+        }
+    }
+}
+
+async fn first_target(dns_client: RecordingDnsClient<ScriptedDnsClient>) -> Target {
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .build()
+        .unwrap();
+
+    Lookup::from(context).resolve_next().await.unwrap()
+}
+
+#[tokio::test]
+async fn nxdomain_goes_straight_to_srv_fallbacks() {
+    let dns_client = ScriptedDnsClient::new(vec![Answer::NxDomain]);
+
+    let target = first_target(dns_client.clone()).await;
+
+    assert_eq!((target.transport, target.port), (Transport::Udp, 5070.into()));
+    assert_eq!(dns_client.naptr_queries(), 1);
+}
+
+#[tokio::test]
+async fn servfail_retries_naptr_once() {
+    let dns_client = ScriptedDnsClient::new(vec![Answer::ServFail, Answer::Record]);
+
+    let target = first_target(dns_client.clone()).await;
+
+    assert_eq!((target.transport, target.port), (Transport::Tcp, 5080.into()));
+    assert_eq!(dns_client.naptr_queries(), 2);
+}
+
+#[tokio::test]
+async fn timeout_retries_naptr_once() {
+    let dns_client = ScriptedDnsClient::new(vec![Answer::Timeout, Answer::Record]);
+
+    let target = first_target(dns_client.clone()).await;
+
+    assert_eq!((target.transport, target.port), (Transport::Tcp, 5080.into()));
+    assert_eq!(dns_client.naptr_queries(), 2);
+}
+
+#[tokio::test]
+async fn repeated_servfail_falls_back_to_srv() {
+    let dns_client = ScriptedDnsClient::new(vec![Answer::ServFail, Answer::ServFail]);
+
+    let target = first_target(dns_client.clone()).await;
+
+    assert_eq!((target.transport, target.port), (Transport::Udp, 5070.into()));
+    assert_eq!(dns_client.naptr_queries(), 2);
+}
+
+#[tokio::test]
+async fn refused_is_not_retried() {
+    let dns_client = ScriptedDnsClient::new(vec![Answer::Refused, Answer::Record]);
+
+    let target = first_target(dns_client.clone()).await;
+
+    assert_eq!((target.transport, target.port), (Transport::Udp, 5070.into()));
+    assert_eq!(dns_client.naptr_queries(), 1);
+}
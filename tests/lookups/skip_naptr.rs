@@ -0,0 +1,40 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvMap};
+use crate::support::RecordingDnsClient;
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use testing_utils::Randomize;
+
+#[tokio::test]
+async fn skips_naptr_query() {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![(10, 5, 5062.into(), "tcp-server.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("tcp-server.example.com".into(), vec![Randomize::random()]);
+
+    // NAPTR panics if it is ever queried
+    let config =
+        CustomDnsConfig { naptr: NaptrConfig::Panic, srv: srv_map.into(), a: a_records.into() };
+    let dns_client = RecordingDnsClient::new(CustomDnsClient::from(config));
+
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp, Transport::Tcp])
+        .skip_naptr(true)
+        .build()
+        .unwrap();
+
+    let mut lookup = Lookup::from(context);
+
+    let target = lookup.resolve_next().await.unwrap();
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(target.port, 5062.into());
+    assert!(lookup.resolve_next().await.is_none());
+
+    assert_eq!(dns_client.naptr_queries(), 0);
+    assert_eq!(dns_client.srv_queries(), 2);
+}
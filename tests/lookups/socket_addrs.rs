@@ -0,0 +1,53 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvConfig};
+use futures::StreamExt;
+use rsip_dns::*;
+use std::net::{IpAddr, SocketAddr};
+
+fn lookup() -> Lookup<CustomDnsClient> {
+    let mut a_records = ARecords::new();
+    a_records.insert(
+        "example.com".into(),
+        vec![IpAddr::from([192, 0, 2, 1]), IpAddr::from([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1])],
+    );
+
+    let dns_config =
+        CustomDnsConfig { naptr: NaptrConfig::Panic, srv: SrvConfig::Panic, a: a_records.into() };
+
+    let uri = rsip::Uri {
+        scheme: Some(rsip::Scheme::Sip),
+        host_with_port: ("example.com", 5070).into(),
+        ..Default::default()
+    };
+
+    Lookup::from(
+        Context::initialize_from(
+            uri,
+            CustomDnsClient::from(dns_config),
+            SupportedTransports::any(),
+        )
+        .unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn socket_addrs_match_the_targets() {
+    let mut targets = vec![];
+    let mut target_lookup = lookup();
+    while let Some(target) = target_lookup.resolve_next().await {
+        targets.push(target);
+    }
+
+    let mut lookup = lookup();
+    let socket_addrs = lookup.socket_addrs().collect::<Vec<SocketAddr>>().await;
+
+    assert_eq!(
+        socket_addrs,
+        vec![
+            SocketAddr::from(([192, 0, 2, 1], 5070)),
+            SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, 1], 5070)),
+        ]
+    );
+    assert_eq!(socket_addrs, targets.iter().map(|target| target.socket_addr()).collect::<Vec<_>>());
+    // the stream drained the lookup
+    assert!(lookup.resolve_next().await.is_none());
+}
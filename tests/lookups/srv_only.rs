@@ -0,0 +1,68 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvMap};
+use crate::support::{Query, RecordingDnsClient};
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+fn dns_client() -> RecordingDnsClient<CustomDnsClient> {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        vec![
+            (10, 60, 5060.into(), "server1.example.com".into()),
+            (20, 40, 5062.into(), "server2.example.com".into()),
+        ],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("server1.example.com".into(), vec![IpAddr::from([192, 0, 2, 1])]);
+    a_records.insert("server2.example.com".into(), vec![IpAddr::from([192, 0, 2, 2])]);
+
+    let dns_config =
+        CustomDnsConfig { naptr: NaptrConfig::Panic, srv: srv_map.into(), a: a_records.into() };
+
+    RecordingDnsClient::new(CustomDnsClient::from(dns_config))
+}
+
+#[tokio::test]
+async fn returns_the_srv_record_without_resolving_its_targets() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host("example.com")
+        .transport(Transport::Tcp)
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    let srv_record = lookup.srv_only().await.unwrap();
+
+    assert_eq!(srv_record.domain, SrvDomain::try_from("_sip._tcp.example.com").unwrap());
+    let entries = srv_record
+        .entries
+        .iter()
+        .map(|entry| {
+            (entry.priority, entry.weight, u16::from(entry.port), entry.target.to_string())
+        })
+        .collect::<Vec<_>>();
+    assert!(entries.contains(&(10, 60, 5060, "server1.example.com".into())));
+    assert!(entries.contains(&(20, 40, 5062, "server2.example.com".into())));
+    assert_eq!(dns_client.queries(), vec![Query::Srv("_sip._tcp.example.com".try_into().unwrap())]);
+    assert_eq!(dns_client.ip_queries(), 0);
+
+    // the resolution is left untouched
+    assert_eq!(lookup.resolve_next().await.map(|target| target.port), Some(5060.into()));
+}
+
+#[tokio::test]
+async fn is_none_for_ip_addresses() {
+    let dns_client = dns_client();
+    let context = Context::builder(dns_client.clone())
+        .host(IpAddr::from([192, 0, 2, 1]))
+        .transport(Transport::Tcp)
+        .build()
+        .unwrap();
+
+    assert!(Lookup::from(context).srv_only().await.is_none());
+    assert!(dns_client.queries().is_empty());
+}
@@ -0,0 +1,64 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use rsip::Transport;
+use rsip_dns::*;
+use std::net::IpAddr;
+use testing_utils::Randomize;
+
+// No NAPTR nor SRV record, only the A/AAAA of the domain itself
+fn dns_client(ip_addr: IpAddr) -> CustomDnsClient {
+    let mut a_records = ARecords::new();
+    a_records.insert("example.com".into(), vec![ip_addr]);
+
+    CustomDnsConfig {
+        naptr: NaptrMap::new().into(),
+        srv: SrvMap::new().into(),
+        a: a_records.into(),
+    }
+    .into()
+}
+
+async fn resolve_all(context: Context<CustomDnsClient>) -> Vec<Target> {
+    let mut lookup = Lookup::from(context);
+
+    let mut targets = vec![];
+    while let Some(target) = lookup.resolve_next().await {
+        targets.push(target);
+    }
+    targets
+}
+
+#[tokio::test]
+async fn synthesizes_a_target_per_available_transport() {
+    let ip_addr: IpAddr = Randomize::random();
+    let context = Context::builder(dns_client(ip_addr))
+        .host("example.com")
+        .supported_transports(SupportedTransports::ordered(vec![Transport::Udp, Transport::Tcp]))
+        .synthesize_all_transports(true)
+        .build()
+        .unwrap();
+
+    let targets = resolve_all(context).await;
+
+    assert_eq!(
+        targets
+            .iter()
+            .map(|target| (target.ip_addr, target.port, target.transport))
+            .collect::<Vec<_>>(),
+        vec![(ip_addr, 5060.into(), Transport::Udp), (ip_addr, 5060.into(), Transport::Tcp)]
+    );
+}
+
+#[tokio::test]
+async fn only_the_default_transport_otherwise() {
+    let ip_addr: IpAddr = Randomize::random();
+    let context = Context::builder(dns_client(ip_addr))
+        .host("example.com")
+        .supported_transports(SupportedTransports::ordered(vec![Transport::Udp, Transport::Tcp]))
+        .build()
+        .unwrap();
+
+    let targets = resolve_all(context).await;
+
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].transport, Transport::Udp);
+}
@@ -0,0 +1,49 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr};
+
+const FIRST: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+const SECOND: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+// no NAPTR record, the UDP SRV record has two targets with an address each
+fn dns_client() -> CustomDnsClient {
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        vec![
+            (10, 5, 5060.into(), "first.example.com".into()),
+            (20, 5, 5060.into(), "second.example.com".into()),
+        ],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("first.example.com".into(), vec![FIRST]);
+    a_records.insert("second.example.com".into(), vec![SECOND]);
+
+    CustomDnsConfig { naptr: NaptrMap::new().into(), srv: srv_map.into(), a: a_records.into() }
+        .into()
+}
+
+#[tokio::test]
+async fn moves_past_a_step_without_targets() {
+    let context = Context::builder(dns_client())
+        .host("example.com")
+        .supported_transports(vec![Transport::Udp])
+        .build()
+        .unwrap();
+    let mut lookup = Lookup::from(context);
+
+    // the NAPTR step has nothing to count, the SRV fallback has both targets
+    assert_eq!(lookup.target_count().await, 2);
+
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(FIRST));
+    assert_eq!(lookup.target_count().await, 1);
+    assert_eq!(lookup.resolve_next().await.map(|target| target.ip_addr), Some(SECOND));
+    assert_eq!(lookup.target_count().await, 0);
+    assert!(lookup.resolve_next().await.is_none());
+
+    // the target peeked while counting is only accounted for once
+    assert_eq!(lookup.take_report().ttls.len(), 2);
+}
@@ -0,0 +1,62 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use rsip::Transport;
+use rsip_dns::*;
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+const FIRST: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+const SECOND: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+
+// A/AAAA only, answering FIRST then SECOND
+fn context() -> ContextBuilder<CustomDnsClient> {
+    let mut a_records = ARecords::new();
+    a_records.insert("example.com".into(), vec![FIRST, SECOND]);
+    let dns_client: CustomDnsClient = CustomDnsConfig {
+        naptr: NaptrMap::new().into(),
+        srv: SrvMap::new().into(),
+        a: a_records.into(),
+    }
+    .into();
+
+    Context::builder(dns_client).host("example.com").port(5060).transport(Transport::Udp)
+}
+
+async fn ranked(context: ContextBuilder<CustomDnsClient>) -> Vec<IpAddr> {
+    let mut lookup = Lookup::from(context.build().unwrap());
+
+    let targets = lookup.resolve_all_sorted(TargetSortPolicy::default()).await;
+    targets.into_iter().map(|target| target.ip_addr).collect()
+}
+
+#[derive(Debug)]
+struct ReversingRanker;
+
+impl TargetRanker for ReversingRanker {
+    fn rank(&self, mut targets: Vec<Target>) -> Vec<Target> {
+        targets.reverse();
+        targets
+    }
+}
+
+#[tokio::test]
+async fn targets_keep_their_order_by_default() {
+    assert_eq!(ranked(context()).await, vec![FIRST, SECOND]);
+}
+
+#[tokio::test]
+async fn a_custom_ranker_reorders_the_targets() {
+    assert_eq!(ranked(context().target_ranker(ReversingRanker)).await, vec![SECOND, FIRST]);
+}
+
+#[tokio::test]
+async fn the_rtt_ranker_puts_the_fastest_target_first() {
+    let ranker = RttRanker::new();
+    // measured on another transport, hence unrelated
+    ranker.record(FIRST, Transport::Tcp, Duration::from_millis(1));
+    assert_eq!(ranked(context().target_ranker(ranker.clone())).await, vec![FIRST, SECOND]);
+
+    ranker.record(FIRST, Transport::Udp, Duration::from_millis(80));
+    ranker.record(SECOND, Transport::Udp, Duration::from_millis(20));
+    assert_eq!(ranked(context().target_ranker(ranker.clone())).await, vec![SECOND, FIRST]);
+    assert_eq!(ranker.rtt(SECOND, Transport::Udp), Some(Duration::from_millis(20)));
+}
@@ -0,0 +1,86 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrConfig, SrvMap};
+use crate::support::{Query, RecordingDnsClient};
+use rsip::{Param, Scheme, Transport};
+use rsip_dns::{records::*, *};
+use testing_utils::Randomize;
+
+const TRANSPORTS: [Transport; 7] = [
+    Transport::Udp,
+    Transport::Tcp,
+    Transport::Tls,
+    Transport::Sctp,
+    Transport::TlsSctp,
+    Transport::Ws,
+    Transport::Wss,
+];
+
+// No SRV records at all, only the A/AAAA of the domain itself
+fn dns_client() -> RecordingDnsClient<CustomDnsClient> {
+    let mut a_records = ARecords::new();
+    a_records.insert("example.com".into(), vec![Randomize::random()]);
+
+    let config = CustomDnsConfig {
+        naptr: NaptrConfig::Panic,
+        srv: SrvMap::new().into(),
+        a: a_records.into(),
+    };
+    RecordingDnsClient::new(CustomDnsClient::from(config))
+}
+
+fn expected_srv_domain(transport: Transport) -> SrvDomain {
+    SrvDomain::new("example.com".into(), transport)
+}
+
+#[tokio::test]
+async fn transport_param_restricts_the_lookup() {
+    for transport in TRANSPORTS {
+        let dns_client = dns_client();
+        let uri = rsip::Uri {
+            scheme: Some(Scheme::Sip),
+            host_with_port: ("example.com", Option::<u16>::None).into(),
+            params: vec![Param::Transport(transport)],
+            ..Default::default()
+        };
+        let context =
+            Context::initialize_from(uri, dns_client.clone(), SupportedTransports::any()).unwrap();
+        assert_eq!(context.transport, Some(transport));
+
+        let mut lookup = Lookup::from(context);
+        assert!(matches!(lookup, Lookup::DomainWithTransport { .. }));
+
+        let target = lookup.resolve_next().await.unwrap();
+        assert_eq!(target.transport, transport);
+        assert_eq!(target.port, transport.default_port());
+        assert!(lookup.resolve_next().await.is_none());
+
+        assert_eq!(
+            dns_client.queries(),
+            vec![Query::Srv(expected_srv_domain(transport)), Query::Ip("example.com".into())]
+        );
+    }
+}
+
+#[tokio::test]
+async fn just_domain_lookup_honors_the_context_transport() {
+    for transport in TRANSPORTS {
+        let dns_client = dns_client();
+        let context = Context::builder(dns_client.clone())
+            .host("example.com")
+            .transport(transport)
+            .skip_naptr(true)
+            .build()
+            .unwrap();
+
+        let mut lookup = JustDomainLookup::try_new(context).unwrap();
+
+        let target = lookup.resolve_next().await.unwrap();
+        assert_eq!(target.transport, transport);
+        assert_eq!(target.port, transport.default_port());
+        assert!(lookup.resolve_next().await.is_none());
+
+        assert_eq!(
+            dns_client.queries(),
+            vec![Query::Srv(expected_srv_domain(transport)), Query::Ip("example.com".into())]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use super::{ARecords, CustomDnsClient, CustomDnsConfig, NaptrMap, SrvMap};
+use crate::support::{Query, RecordingDnsClient};
+use rsip::Transport;
+use rsip_dns::{records::*, *};
+use std::convert::TryFrom;
+use testing_utils::Randomize;
+
+fn srv_domain(name: &str) -> SrvDomain {
+    SrvDomain::try_from(name).unwrap()
+}
+
+// NAPTR (when `naptr` is set) lists UDP before TLS with the same order and preference, SRV
+// exists for every transport
+fn dns_client(naptr: bool) -> RecordingDnsClient<CustomDnsClient> {
+    let mut naptr_map = NaptrMap::new();
+    if naptr {
+        naptr_map.insert(
+            "example.com".into(),
+            vec![
+                (50, 50, NaptrFlags::S, NaptrServices::SipD2u, srv_domain("_sip._udp.example.com")),
+                (
+                    50,
+                    50,
+                    NaptrFlags::S,
+                    NaptrServices::SipsD2t,
+                    srv_domain("_sips._tcp.example.com"),
+                ),
+            ],
+        );
+    }
+
+    let mut srv_map = SrvMap::new();
+    srv_map.insert(
+        srv_domain("_sip._udp.example.com"),
+        vec![(10, 5, 5060.into(), "udp-server.example.com".into())],
+    );
+    srv_map.insert(
+        srv_domain("_sip._tcp.example.com"),
+        vec![(10, 5, 5060.into(), "tcp-server.example.com".into())],
+    );
+    srv_map.insert(
+        srv_domain("_sips._tcp.example.com"),
+        vec![(10, 5, 5061.into(), "tls-server.example.com".into())],
+    );
+
+    let mut a_records = ARecords::new();
+    a_records.insert("udp-server.example.com".into(), vec![Randomize::random()]);
+    a_records.insert("tcp-server.example.com".into(), vec![Randomize::random()]);
+    a_records.insert("tls-server.example.com".into(), vec![Randomize::random()]);
+
+    let config =
+        CustomDnsConfig { naptr: naptr_map.into(), srv: srv_map.into(), a: a_records.into() };
+
+    RecordingDnsClient::new(config.into())
+}
+
+async fn first_target(
+    dns_client: RecordingDnsClient<CustomDnsClient>,
+    supported_transports: SupportedTransports,
+) -> Target {
+    let context = Context::builder(dns_client)
+        .host("example.com")
+        .supported_transports(supported_transports)
+        .build()
+        .unwrap();
+
+    Lookup::from(context).resolve_next().await.unwrap()
+}
+
+fn first_srv_query(dns_client: &RecordingDnsClient<CustomDnsClient>) -> Option<SrvDomain> {
+    dns_client.queries().into_iter().find_map(|query| match query {
+        Query::Srv(srv_domain) => Some(srv_domain),
+        _ => None,
+    })
+}
+
+#[tokio::test]
+async fn ordered_transports_break_naptr_ties() {
+    let dns_client = dns_client(true);
+    let supported_transports = SupportedTransports::ordered(vec![Transport::Tls, Transport::Udp]);
+
+    let target = first_target(dns_client.clone(), supported_transports).await;
+
+    assert_eq!(target.transport, Transport::Tls);
+    assert_eq!(first_srv_query(&dns_client), Some(srv_domain("_sips._tcp.example.com")));
+}
+
+#[tokio::test]
+async fn unordered_transports_keep_the_naptr_order() {
+    let dns_client = dns_client(true);
+    let supported_transports = SupportedTransports::only(vec![Transport::Tls, Transport::Udp]);
+
+    let target = first_target(dns_client.clone(), supported_transports).await;
+
+    assert_eq!(target.transport, Transport::Udp);
+    assert_eq!(first_srv_query(&dns_client), Some(srv_domain("_sip._udp.example.com")));
+}
+
+#[tokio::test]
+async fn ordered_transports_drive_srv_fallbacks() {
+    let dns_client = dns_client(false);
+    let supported_transports = SupportedTransports::ordered(vec![Transport::Tcp, Transport::Udp]);
+
+    let target = first_target(dns_client.clone(), supported_transports).await;
+
+    assert_eq!(target.transport, Transport::Tcp);
+    assert_eq!(first_srv_query(&dns_client), Some(srv_domain("_sip._tcp.example.com")));
+}
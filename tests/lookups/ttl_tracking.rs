@@ -24,6 +24,7 @@ async fn ttl_from_naptr_srv_chain() {
         port: None,
         dns_client: dns_client.clone(),
         supported_transports: rsip_dns::SupportedTransports::any(),
+        ..Default::default()
     };
 
     let mut lookup = Lookup::from(context);
@@ -59,6 +60,7 @@ async fn ttl_from_domain_with_transport() {
         port: None,
         dns_client: dns_client.clone(),
         supported_transports: rsip_dns::SupportedTransports::any(),
+        ..Default::default()
     };
 
     let mut lookup = Lookup::from(context);
@@ -115,7 +117,7 @@ async fn ttl_from_ip_address() {
         .unwrap(),
     );
 
-    let Target { ip_addr, port, transport, ttl } = lookup.resolve_next().await.unwrap();
+    let Target { ip_addr, port, transport, ttl, .. } = lookup.resolve_next().await.unwrap();
     assert_eq!(ip_addr, host_ip_addr);
     assert_eq!(port, 5060.into());
     assert_eq!(transport, rsip::Transport::Udp);
@@ -0,0 +1,109 @@
+use rsip::{Domain, Transport};
+use rsip_dns::{DEFAULT_TTL, Target, records::AddrRecord};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const IPV4: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+const IPV6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+#[test]
+fn ttl_for_uses_per_family_ttls() {
+    let record = AddrRecord {
+        domain: Domain::from("example.com"),
+        ip_addrs: vec![IPV4, IPV6],
+        ttl: 30,
+        per_family_ttl: Some((30, 600)),
+        source: None,
+    };
+
+    assert_eq!(record.ttl_for(IPV4), 30);
+    assert_eq!(record.ttl_for(IPV6), 600);
+}
+
+#[test]
+fn ttl_for_falls_back_to_ttl() {
+    let record: AddrRecord = (Domain::from("example.com"), vec![IPV4, IPV6]).into();
+
+    assert_eq!(record.per_family_ttl, None);
+    assert_eq!(record.ttl_for(IPV4), 300);
+    assert_eq!(record.ttl_for(IPV6), 300);
+}
+
+fn addr_record(ip_addrs: Vec<IpAddr>, ttl: u32) -> AddrRecord {
+    AddrRecord {
+        domain: Domain::from("example.com"),
+        ip_addrs,
+        ttl,
+        per_family_ttl: Some((ttl, ttl)),
+        source: None,
+    }
+}
+
+#[test]
+fn merge_joins_disjoint_addresses() {
+    let merged = addr_record(vec![IPV4], 60).merge(addr_record(vec![IPV6], 600)).unwrap();
+
+    assert_eq!(merged.ip_addrs, vec![IPV4, IPV6]);
+    assert_eq!(merged.ttl, 60);
+    // each family keeps the TTL of its own records
+    assert_eq!(merged.per_family_ttl, Some((60, 600)));
+}
+
+#[test]
+fn merge_keeps_overlapping_addresses_once() {
+    let other_ipv4 = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2));
+    let merged =
+        addr_record(vec![IPV4, IPV6], 300).merge(addr_record(vec![other_ipv4, IPV4], 120)).unwrap();
+
+    assert_eq!(merged.ip_addrs, vec![IPV4, IPV6, other_ipv4]);
+    assert_eq!(merged.ttl, 120);
+    assert_eq!(merged.ttl_for(IPV4), 120);
+    assert_eq!(merged.ttl_for(IPV6), 300);
+}
+
+#[test]
+fn merge_requires_the_same_domain() {
+    let other: AddrRecord = (Domain::from("example.org"), vec![IPV6]).into();
+    assert!(addr_record(vec![IPV4], 60).merge(other).is_err());
+
+    let same: AddrRecord = (Domain::from("Example.com."), vec![IPV6]).into();
+    assert!(addr_record(vec![IPV4], 60).merge(same).is_ok());
+}
+
+#[test]
+fn into_targets_carries_the_ttl_and_transport() {
+    let record = AddrRecord {
+        domain: Domain::from("example.com"),
+        ip_addrs: vec![IPV6, IPV4],
+        ttl: 30,
+        per_family_ttl: Some((30, 600)),
+        source: None,
+    };
+
+    assert_eq!(
+        record.into_targets(5061.into(), Transport::Tls),
+        vec![
+            Target {
+                ip_addr: IPV6,
+                port: 5061.into(),
+                transport: Transport::Tls,
+                ttl: 600,
+                scope_id: None,
+                siblings: None
+            },
+            Target {
+                ip_addr: IPV4,
+                port: 5061.into(),
+                transport: Transport::Tls,
+                ttl: 30,
+                scope_id: None,
+                siblings: None
+            },
+        ]
+    );
+
+    let record = AddrRecord::from((Domain::from("example.com"), vec![IPV4]));
+    let targets = record.into_targets(5060.into(), Transport::Udp);
+    assert_eq!(targets.len(), 1);
+    assert_eq!(targets[0].ttl, DEFAULT_TTL);
+    assert_eq!(targets[0].transport, Transport::Udp);
+}
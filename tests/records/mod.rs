@@ -1 +1,6 @@
+pub mod addr_record;
+pub mod naptr_record;
+#[cfg(feature = "serde")]
+pub mod serde_round_trip;
 pub mod srv_domain;
+pub mod srv_record;
@@ -0,0 +1,225 @@
+use rsip::{Domain, Transport};
+use rsip_dns::records::*;
+use std::{collections::HashMap, net::IpAddr};
+
+fn entry_with_regexp(regexp: &[u8]) -> NaptrEntry {
+    NaptrEntry {
+        order: 10,
+        preference: 10,
+        flags: NaptrFlags::U,
+        services: NaptrServices::Other("E2U+sip".into()),
+        regexp: regexp.to_vec(),
+        replacement: ".".into(),
+    }
+}
+
+#[test]
+fn parses_regexp() {
+    let entry = entry_with_regexp(b"!^.*$!sip:info@example.com!");
+
+    assert_eq!(
+        entry.parsed_regexp(),
+        Some(NaptrRegexp {
+            delimiter: '!',
+            pattern: "^.*$".into(),
+            replacement: "sip:info@example.com".into(),
+            flags: "".into(),
+        })
+    );
+}
+
+#[test]
+fn parses_regexp_with_flags_and_escaped_delimiters() {
+    let entry = entry_with_regexp(br"/^\+1(.*)\/x$/sip:\1@example.com/i");
+
+    assert_eq!(
+        entry.parsed_regexp(),
+        Some(NaptrRegexp {
+            delimiter: '/',
+            pattern: r"^\+1(.*)/x$".into(),
+            replacement: r"sip:\1@example.com".into(),
+            flags: "i".into(),
+        })
+    );
+}
+
+#[test]
+fn rejects_invalid_regexps() {
+    assert_eq!(entry_with_regexp(b"").parsed_regexp(), None);
+    assert_eq!(entry_with_regexp(b"!^.*$!sip:info@example.com").parsed_regexp(), None);
+    assert_eq!(entry_with_regexp(b"!^.*$!sip:info@example.com!i!").parsed_regexp(), None);
+    assert_eq!(entry_with_regexp(b"1^.*$1sip:info@example.com1").parsed_regexp(), None);
+    assert_eq!(entry_with_regexp(b"!^.*$!sip:info@example.com!\\").parsed_regexp(), None);
+    assert_eq!(entry_with_regexp(&[b'!', 0xff, b'!', b'a', b'!']).parsed_regexp(), None);
+}
+
+fn s_entry(services: NaptrServices, replacement: &str) -> NaptrEntry {
+    NaptrEntry {
+        order: 10,
+        preference: 10,
+        flags: NaptrFlags::S,
+        services,
+        regexp: vec![],
+        replacement: replacement.into(),
+    }
+}
+
+fn srv_record(srv_domain: &str, target: &str) -> (SrvDomain, SrvRecord) {
+    let srv_domain = SrvDomain::try_from(srv_domain).unwrap();
+    let entry = SrvEntry { priority: 1, weight: 1, port: 5060.into(), target: target.into() };
+    (srv_domain.clone(), SrvRecord::new(vec![entry], srv_domain, 300))
+}
+
+fn naptr_record(additional_srvs: Vec<(SrvDomain, SrvRecord)>) -> NaptrRecord {
+    NaptrRecord::with_additional_srvs(
+        vec![
+            s_entry(NaptrServices::SipD2u, "_sip._udp.example.com"),
+            s_entry(NaptrServices::SipD2t, "_sip._tcp.example.com"),
+            s_entry(NaptrServices::SipsD2t, "_sips._tcp.example.com"),
+        ],
+        "example.com".into(),
+        300,
+        additional_srvs.into_iter().collect(),
+    )
+}
+
+#[test]
+fn reports_partial_srv_coverage() {
+    let naptr_record = naptr_record(vec![srv_record("_sip._udp.example.com", "sip.example.com")]);
+
+    assert_eq!(naptr_record.srv_coverage(&[Transport::Udp, Transport::Tcp]), 0.5);
+    assert_eq!(naptr_record.srv_coverage(&[Transport::Udp]), 1.0);
+    assert_eq!(naptr_record.srv_coverage(&[Transport::Tcp, Transport::Tls]), 0.0);
+    // no entry for the transport, nothing left to query
+    assert_eq!(naptr_record.srv_coverage(&[Transport::Ws]), 1.0);
+    assert!(!naptr_record.has_complete_srvs());
+}
+
+#[test]
+fn reports_complete_srv_coverage() {
+    let target = Domain::from("sip.example.com");
+    let ip_addrs = vec![IpAddr::from([192, 0, 2, 1])];
+    let naptr_record = naptr_record(vec![
+        srv_record("_sip._udp.example.com", "sip.example.com"),
+        srv_record("_sip._tcp.example.com", "sip.example.com"),
+        srv_record("_sips._tcp.example.com", "sip.example.com"),
+    ]);
+
+    assert_eq!(naptr_record.srv_coverage(&[Transport::Udp, Transport::Tcp, Transport::Tls]), 1.0);
+    // the SRV records are there, but resolving their target still takes a query
+    assert!(!naptr_record.has_complete_srvs());
+
+    let naptr_record = naptr_record
+        .with_shared_hosts(HashMap::from([(target.clone(), AddrRecord::from((target, ip_addrs)))]));
+    assert!(naptr_record.has_complete_srvs());
+}
+
+#[test]
+fn parses_flags_case_insensitively() {
+    for (bytes, expected) in [(b"S", "S"), (b"s", "S"), (b"a", "A"), (b"U", "U"), (b"p", "P")] {
+        let flags = NaptrFlags::from(&bytes[..]);
+        assert_eq!(format!("{:?}", flags), expected);
+        assert!(flags.is_recognized());
+    }
+
+    // no flag at all is a non-terminal entry
+    assert!(NaptrFlags::from(&b""[..]).is_recognized());
+}
+
+#[test]
+fn keeps_multi_character_and_non_ascii_flags() {
+    let flags = NaptrFlags::from(&b"SA"[..]);
+    assert!(matches!(&flags, NaptrFlags::Other(bytes) if bytes == b"SA"));
+    assert!(!flags.is_recognized());
+
+    let non_ascii = "é".as_bytes();
+    let flags = NaptrFlags::from(non_ascii);
+    assert!(matches!(&flags, NaptrFlags::Other(bytes) if bytes == non_ascii));
+    assert!(!flags.is_recognized());
+
+    let flags = NaptrFlags::from(&[0xff][..]);
+    assert!(matches!(&flags, NaptrFlags::Other(bytes) if bytes == &[0xff]));
+}
+
+#[test]
+fn keeps_unknown_services_lossily() {
+    assert!(matches!(NaptrServices::from_bytes_lossy(b"sips+d2t"), NaptrServices::SipsD2t));
+    assert!(matches!(
+        NaptrServices::from_bytes_lossy(b"E2U+sip"),
+        NaptrServices::Other(services) if services == "E2U+sip"
+    ));
+    assert!(matches!(
+        NaptrServices::from_bytes_lossy(b"SIP+\xff"),
+        NaptrServices::Other(services) if services == "SIP+\u{fffd}"
+    ));
+}
+
+#[test]
+fn sorts_by_order_then_preference() {
+    let entry = |order, preference, replacement: &str| NaptrEntry {
+        order,
+        preference,
+        ..s_entry(NaptrServices::SipD2u, replacement)
+    };
+    let naptr_record = NaptrRecord::new(
+        vec![
+            entry(20, 10, "_sip._udp.late.example.com"),
+            entry(10, 50, "_sip._udp.least-preferred.example.com"),
+            entry(10, 5, "_sip._udp.most-preferred.example.com"),
+            entry(10, 50, "_sip._udp.least-preferred-tie.example.com"),
+        ],
+        "example.com".into(),
+        300,
+    );
+
+    let sorted = naptr_record.sorted();
+    assert_eq!(
+        sorted.iter().map(NaptrEntry::sort_key).collect::<Vec<_>>(),
+        vec![(10, 5), (10, 50), (10, 50), (20, 10)]
+    );
+    // entries of the same order are broken by preference, equal ones keep the record order
+    assert_eq!(
+        sorted.iter().map(|entry| entry.replacement.to_string()).collect::<Vec<_>>(),
+        vec![
+            "_sip._udp.most-preferred.example.com",
+            "_sip._udp.least-preferred.example.com",
+            "_sip._udp.least-preferred-tie.example.com",
+            "_sip._udp.late.example.com",
+        ]
+    );
+}
+
+#[test]
+fn maps_every_service_to_its_transport() {
+    let services = [
+        (&b"SIP+D2T"[..], Some(Transport::Tcp)),
+        (b"SIP+D2U", Some(Transport::Udp)),
+        (b"SIP+D2S", Some(Transport::Sctp)),
+        (b"SIP+D2W", Some(Transport::Ws)),
+        (b"SIPS+D2T", Some(Transport::Tls)),
+        // no DTLS transport to map to
+        (b"SIPS+D2U", None),
+        (b"SIPS+D2S", Some(Transport::TlsSctp)),
+        (b"SIPS+D2W", Some(Transport::Wss)),
+        (b"E2U+sip", None),
+    ];
+
+    for (bytes, transport) in services {
+        let services = NaptrServices::from_bytes_lossy(bytes);
+        // a new variant fails to compile here until it's added to the cases above
+        match services {
+            NaptrServices::SipD2t
+            | NaptrServices::SipD2u
+            | NaptrServices::SipD2s
+            | NaptrServices::SipD2w
+            | NaptrServices::SipsD2t
+            | NaptrServices::SipsD2u
+            | NaptrServices::SipsD2s
+            | NaptrServices::SipsD2w
+            | NaptrServices::Other(_) => {}
+        }
+
+        assert_eq!(services.transport(), transport, "{}", String::from_utf8_lossy(bytes));
+        assert_eq!(services.secure(), bytes.starts_with(b"SIPS"));
+    }
+}
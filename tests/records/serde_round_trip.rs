@@ -0,0 +1,151 @@
+use rsip::{Domain, Transport};
+use rsip_dns::{Target, records::*};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const IPV4: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+const IPV6: IpAddr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+    serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+}
+
+fn addr_record() -> AddrRecord {
+    AddrRecord {
+        domain: Domain::from("server.example.com"),
+        ip_addrs: vec![IPV4, IPV6],
+        ttl: 60,
+        per_family_ttl: Some((60, 120)),
+        source: Some(SocketAddr::new(IPV4, 53)),
+    }
+}
+
+fn srv_record() -> SrvRecord {
+    let entry = SrvEntry {
+        priority: 10,
+        weight: 5,
+        port: 5061.into(),
+        target: Domain::from("server.example.com"),
+    };
+    let additional_hosts = HashMap::from([(Domain::from("server.example.com"), addr_record())]);
+
+    SrvRecord::with_additional_hosts(
+        vec![entry],
+        SrvDomain::try_from("_sips._tcp.example.com").unwrap(),
+        90,
+        additional_hosts,
+    )
+}
+
+fn assert_addr_records_eq(left: &AddrRecord, right: &AddrRecord) {
+    assert_eq!(left.domain, right.domain);
+    assert_eq!(left.ip_addrs, right.ip_addrs);
+    assert_eq!(left.ttl, right.ttl);
+    assert_eq!(left.per_family_ttl, right.per_family_ttl);
+    assert_eq!(left.source, right.source);
+}
+
+fn assert_srv_records_eq(left: &SrvRecord, right: &SrvRecord) {
+    assert_eq!(format!("{:?}", left.entries), format!("{:?}", right.entries));
+    assert_eq!(left.domain, right.domain);
+    assert_eq!(left.ttl, right.ttl);
+    assert_eq!(left.source, right.source);
+    assert_eq!(left.additional_hosts.len(), right.additional_hosts.len());
+    for (domain, addr_record) in &left.additional_hosts {
+        assert_addr_records_eq(addr_record, &right.additional_hosts[domain]);
+    }
+}
+
+#[test]
+fn target() {
+    for transport in [Transport::Udp, Transport::Tcp, Transport::Tls, Transport::Wss] {
+        let target = Target {
+            ip_addr: IPV6,
+            port: 5080.into(),
+            transport,
+            ttl: 30,
+            scope_id: None,
+            siblings: None,
+        };
+        assert_eq!(round_trip(&target), target);
+    }
+}
+
+#[test]
+fn target_keeps_its_siblings() {
+    let target = Target::from((IPV4, 5060.into(), Transport::Udp))
+        .with_siblings(Some(std::sync::Arc::new(vec![IPV4, IPV6])));
+
+    assert_eq!(round_trip(&target).siblings, target.siblings);
+}
+
+#[test]
+fn addr_record_keeps_every_field() {
+    let addr_record = addr_record();
+    assert_addr_records_eq(&round_trip(&addr_record), &addr_record);
+}
+
+#[test]
+fn srv_record_keeps_its_additional_hosts() {
+    let srv_record = srv_record();
+    let json = serde_json::to_value(&srv_record).unwrap();
+
+    // human readable encoding of the rsip types
+    assert_eq!(json["domain"], "_sips._tcp.example.com");
+    assert_eq!(json["entries"][0]["port"], 5061);
+    assert_eq!(json["entries"][0]["target"], "server.example.com");
+    assert!(json["additional_hosts"]["server.example.com"].is_object());
+
+    assert_srv_records_eq(&round_trip(&srv_record), &srv_record);
+}
+
+#[test]
+fn naptr_record_keeps_its_additional_srvs_and_hosts() {
+    let entries = vec![
+        NaptrEntry {
+            order: 50,
+            preference: 50,
+            flags: NaptrFlags::S,
+            services: NaptrServices::SipsD2t,
+            regexp: vec![],
+            replacement: Domain::from("_sips._tcp.example.com"),
+        },
+        NaptrEntry {
+            order: 90,
+            preference: 50,
+            flags: NaptrFlags::Other(b"X".to_vec()),
+            services: NaptrServices::Other("E2U+sip".into()),
+            regexp: b"!^.*$!sip:info@example.com!".to_vec(),
+            replacement: Domain::from("."),
+        },
+    ];
+    let srv_record = srv_record();
+    let naptr_record = NaptrRecord {
+        entries,
+        domain: Domain::from("example.com"),
+        ttl: 3600,
+        additional_srvs: HashMap::from([(srv_record.domain.clone(), srv_record)]),
+        additional_hosts: HashMap::from([(Domain::from("server.example.com"), addr_record())]),
+    };
+
+    let decoded = round_trip(&naptr_record);
+
+    assert_eq!(format!("{:?}", decoded.entries), format!("{:?}", naptr_record.entries));
+    assert_eq!(decoded.domain, naptr_record.domain);
+    assert_eq!(decoded.ttl, naptr_record.ttl);
+    assert_eq!(decoded.additional_srvs.len(), 1);
+    for (srv_domain, srv_record) in &naptr_record.additional_srvs {
+        assert_srv_records_eq(&decoded.additional_srvs[srv_domain], srv_record);
+    }
+    assert_eq!(decoded.additional_hosts.len(), 1);
+    for (domain, addr_record) in &naptr_record.additional_hosts {
+        assert_addr_records_eq(&decoded.additional_hosts[domain], addr_record);
+    }
+}
+
+#[test]
+fn invalid_srv_domain_is_rejected() {
+    assert!(serde_json::from_str::<SrvDomain>("\"_sip._udp.example.com\"").is_ok());
+    assert!(serde_json::from_str::<SrvDomain>("\"example.com\"").is_err());
+}
@@ -44,3 +44,153 @@ fn parses_srv_domain_correctly() {
     assert_eq!(srv_domain.transport(), rsip::Transport::TlsSctp);
     assert_eq!(srv_domain.to_string(), srv_domain_str);
 }
+
+#[test]
+fn parses_srv_domain_from_str() {
+    let srv_domain: SrvDomain = "_sips._tcp.example.com".parse().unwrap();
+    assert!(srv_domain.secure);
+    assert_eq!(srv_domain.protocol, rsip::Transport::Tcp);
+    assert_eq!(srv_domain.domain, rsip::Domain::from("example.com"));
+
+    let srv_domain: SrvDomain = "_sip._udp.example.com".parse().unwrap();
+    assert!(!srv_domain.secure);
+    assert_eq!(srv_domain.protocol, rsip::Transport::Udp);
+    assert_eq!(srv_domain.domain, rsip::Domain::from("example.com"));
+}
+
+#[test]
+fn round_trips_srv_domain() {
+    use rsip::Transport;
+    use testing_utils::Randomize;
+
+    let domains = ["example.com", "sip.example.com.", "a.b.c.example.org"];
+    for domain in domains {
+        for secure in [true, false] {
+            for protocol in [Transport::Udp, Transport::Tcp, Transport::Sctp, Transport::Ws] {
+                let srv_domain = SrvDomain { domain: domain.into(), protocol, secure };
+
+                let rendered = srv_domain.to_string();
+                let parsed: SrvDomain = rendered.parse().unwrap();
+                assert_eq!(parsed, srv_domain);
+                assert_eq!(parsed.to_string(), rendered);
+            }
+        }
+    }
+
+    for _ in 0..100 {
+        let srv_domain = SrvDomain::random();
+        let srv_domain = SrvDomain { protocol: srv_domain.protocol.protocol(), ..srv_domain };
+
+        let parsed: SrvDomain = srv_domain.to_string().parse().unwrap();
+        assert_eq!(parsed, srv_domain);
+    }
+}
+
+#[test]
+fn rejects_malformed_srv_domains() {
+    let malformed = [
+        "",
+        "example.com",
+        "sip._tcp.example.com",
+        "_sip.tcp.example.com",
+        "_sip._tcp",
+        "_sip._tcp.",
+        "_sip._foo.example.com",
+        "_sip._tls.example.com",
+        "_http._tcp.example.com",
+    ];
+
+    for input in malformed {
+        assert!(SrvDomain::try_from(input).is_err(), "{} should be rejected", input);
+        assert!(input.parse::<SrvDomain>().is_err(), "{} should be rejected", input);
+    }
+}
+
+#[test]
+fn rejects_srv_domains_with_an_invalid_target() {
+    let long_label = "a".repeat(64);
+    let long_name = format!("_sip._udp.{}example.com", "abcdefghi.".repeat(25));
+    let malformed = [
+        "_sip._udp..".to_string(),
+        "_sip._udp..example.com".to_string(),
+        "_sip._udp.example..com".to_string(),
+        "_sip._udp.example.com..".to_string(),
+        "_sip._udp._tcp.example.com".to_string(),
+        "_sip._udp.sip._foo.example.com".to_string(),
+        "__sip._udp.example.com".to_string(),
+        "_sip.__udp.example.com".to_string(),
+        "_._.example.com".to_string(),
+        "_sip._udp.\u{0}".to_string(),
+        "_sip._udp.exa mple.com".to_string(),
+        format!("_sip._udp.{}.com", long_label),
+        long_name,
+    ];
+
+    for input in &malformed {
+        assert!(SrvDomain::try_from(input.as_str()).is_err(), "{} should be rejected", input);
+    }
+
+    // the limits themselves are fine, and so is a trailing dot
+    let longest_label = "a".repeat(63);
+    assert!(SrvDomain::try_from(format!("_sip._udp.{}.com", longest_label).as_str()).is_ok());
+    assert!(SrvDomain::try_from("_sip._udp.example.com.").is_ok());
+}
+
+#[test]
+fn renders_websocket_srv_domains() {
+    let srv_domain = SrvDomain::from((rsip::Domain::from("example.com"), rsip::Transport::Ws));
+    assert_eq!(srv_domain.to_string(), "_sip._ws.example.com");
+    assert_eq!(srv_domain.transport(), rsip::Transport::Ws);
+
+    let srv_domain = SrvDomain { secure: true, ..srv_domain };
+    assert_eq!(srv_domain.to_string(), "_sips._ws.example.com");
+    assert_eq!(srv_domain.transport(), rsip::Transport::Wss);
+}
+
+#[test]
+fn derives_secure_and_protocol_from_the_transport() {
+    use rsip::{Domain, Transport};
+
+    let srv_domain = SrvDomain::new(Domain::from("example.com"), Transport::Tls);
+    assert!(srv_domain.secure);
+    assert_eq!(srv_domain.protocol, Transport::Tcp);
+    assert_eq!(srv_domain.transport(), Transport::Tls);
+    assert_eq!(srv_domain.to_string(), "_sips._tcp.example.com");
+
+    let srv_domain = SrvDomain::new(Domain::from("example.com"), Transport::Wss);
+    assert_eq!(srv_domain.to_string(), "_sips._ws.example.com");
+
+    let srv_domain = SrvDomain::new(Domain::from("example.com"), Transport::Udp);
+    assert!(!srv_domain.secure);
+    assert_eq!(srv_domain.to_string(), "_sip._udp.example.com");
+
+    // a sips URI asks for the secure service even over an insecure transport
+    let srv_domain = SrvDomain::secure(Domain::from("example.com"), Transport::Tcp);
+    assert_eq!(srv_domain.to_string(), "_sips._tcp.example.com");
+    assert_eq!(srv_domain.transport(), Transport::Tls);
+
+    let srv_domain = SrvDomain::insecure(Domain::from("example.com"), Transport::Tls);
+    assert_eq!(srv_domain.to_string(), "_sip._tcp.example.com");
+}
+
+#[test]
+fn try_new_rejects_inconsistent_secure_and_protocol() {
+    use rsip::{Domain, Transport};
+
+    let domain = Domain::from("example.com");
+
+    // an insecure service over a secure transport, which would render as `_sip._tls`
+    assert!(SrvDomain::try_new(domain.clone(), Transport::Tls, false).is_err());
+    assert!(SrvDomain::try_new(domain.clone(), Transport::Wss, false).is_err());
+    // a secure service over a protocol without a secure counterpart
+    assert!(SrvDomain::try_new(domain.clone(), Transport::Udp, true).is_err());
+
+    let srv_domain = SrvDomain::try_new(domain.clone(), Transport::Tls, true).unwrap();
+    assert_eq!(srv_domain, SrvDomain::new(domain.clone(), Transport::Tls));
+    assert_eq!(srv_domain.to_string(), "_sips._tcp.example.com");
+
+    let srv_domain = SrvDomain::try_new(domain.clone(), Transport::Tcp, true).unwrap();
+    assert_eq!(srv_domain.to_string(), "_sips._tcp.example.com");
+    let srv_domain = SrvDomain::try_new(domain, Transport::Udp, false).unwrap();
+    assert_eq!(srv_domain.to_string(), "_sip._udp.example.com");
+}
@@ -0,0 +1,173 @@
+use rsip::Domain;
+use rsip_dns::records::*;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+fn entry(priority: u16, weight: u16, target: &str) -> SrvEntry {
+    SrvEntry { priority, weight, port: 5060.into(), target: target.into() }
+}
+
+fn srv_record(entries: Vec<SrvEntry>) -> SrvRecord {
+    SrvRecord::new(entries, SrvDomain::try_from("_sip._udp.example.com").unwrap(), 300)
+}
+
+#[test]
+fn weighted_round_robin_is_proportional_to_weights() {
+    let srv_record = srv_record(vec![
+        entry(10, 10, "a.example.com"),
+        entry(10, 30, "b.example.com"),
+        entry(10, 60, "c.example.com"),
+        entry(20, 100, "backup.example.com"),
+    ]);
+
+    let mut cursor = 0;
+    let mut firsts: HashMap<String, usize> = HashMap::new();
+    let mut previous_first = None;
+    let mut longest_streak = 0;
+    let mut streak = 0;
+    for _ in 0..10_000 {
+        let ordering = srv_record.weighted_round_robin(&mut cursor);
+
+        assert_eq!(ordering.len(), 4);
+        assert_eq!(ordering[3].target, "backup.example.com".into());
+
+        let first = ordering[0].target.to_string();
+        streak = match previous_first.as_ref() == Some(&first) {
+            true => streak + 1,
+            false => 1,
+        };
+        longest_streak = longest_streak.max(streak);
+        *firsts.entry(first.clone()).or_default() += 1;
+        previous_first = Some(first);
+    }
+
+    assert_eq!(cursor, 10_000);
+    assert_eq!(firsts["a.example.com"], 1_000);
+    assert_eq!(firsts["b.example.com"], 3_000);
+    assert_eq!(firsts["c.example.com"], 6_000);
+    // interleaved, not handed out in long runs
+    assert!(longest_streak <= 3, "longest streak was {}", longest_streak);
+}
+
+#[test]
+fn weighted_round_robin_takes_turns_on_zero_weights() {
+    let srv_record = srv_record(vec![entry(10, 0, "a.example.com"), entry(10, 0, "b.example.com")]);
+
+    let mut cursor = 0;
+    let firsts = (0..4)
+        .map(|_| srv_record.weighted_round_robin(&mut cursor)[0].target.to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(firsts, vec!["a.example.com", "b.example.com", "a.example.com", "b.example.com"]);
+}
+
+#[test]
+fn weight_distribution_follows_the_weights() {
+    let srv_record = srv_record(vec![
+        entry(10, 20, "a.example.com"),
+        entry(10, 10, "b.example.com"),
+        entry(20, 100, "backup.example.com"),
+    ]);
+
+    let distribution = weight_distribution(&srv_record, 3_000);
+
+    let a = distribution[&Domain::from("a.example.com")] as f64;
+    let b = distribution[&Domain::from("b.example.com")] as f64;
+    assert!((a / b - 2.0).abs() < 0.1, "a: {}, b: {}", a, b);
+    assert_eq!(a + b, 3_000.0);
+    // a lower priority entry never comes first
+    assert!(!distribution.contains_key(&Domain::from("backup.example.com")));
+}
+
+#[test]
+fn priority_tiers_group_entries_by_ascending_priority() {
+    let srv_record = srv_record(vec![
+        entry(20, 10, "b1.example.com"),
+        entry(30, 0, "c1.example.com"),
+        entry(10, 60, "a1.example.com"),
+        entry(20, 10, "b2.example.com"),
+        entry(10, 40, "a2.example.com"),
+        entry(10, 0, "a3.example.com"),
+    ]);
+
+    let tiers = srv_record.priority_tiers();
+
+    assert_eq!(tiers.len(), 3);
+    let targets = |tier: &Vec<SrvEntry>| {
+        let mut targets = tier.iter().map(|entry| entry.target.to_string()).collect::<Vec<_>>();
+        targets.sort();
+        targets
+    };
+    assert!(tiers[0].iter().all(|entry| entry.priority == 10));
+    assert_eq!(targets(&tiers[0]), vec!["a1.example.com", "a2.example.com", "a3.example.com"]);
+    assert!(tiers[1].iter().all(|entry| entry.priority == 20));
+    assert_eq!(targets(&tiers[1]), vec!["b1.example.com", "b2.example.com"]);
+    assert!(tiers[2].iter().all(|entry| entry.priority == 30));
+    assert_eq!(targets(&tiers[2]), vec!["c1.example.com"]);
+
+    assert!(srv_record(vec![]).priority_tiers().is_empty());
+}
+
+#[test]
+fn priority_tiers_are_shuffled_by_weight() {
+    let srv_record = srv_record(vec![
+        entry(10, 90, "heavy.example.com"),
+        entry(10, 10, "light.example.com"),
+        entry(20, 100, "backup.example.com"),
+    ]);
+
+    let heavy_firsts = (0..2_000)
+        .filter(|_| srv_record.priority_tiers()[0][0].target == Domain::from("heavy.example.com"))
+        .count();
+
+    // 91 of the 101 possible picks select the heavy entry
+    assert!((1_600..1_950).contains(&heavy_firsts), "heavy entry first {} times", heavy_firsts);
+}
+
+#[test]
+fn merge_srv_records_orders_by_priority_then_transport_then_weight() {
+    use rsip::Transport;
+
+    let udp = SrvRecord::new(
+        vec![entry(20, 10, "udp-backup"), entry(10, 10, "udp-light"), entry(10, 50, "udp-heavy")],
+        SrvDomain::try_from("_sip._udp.example.com").unwrap(),
+        300,
+    );
+    let tcp = SrvRecord::new(
+        vec![entry(10, 5, "tcp-primary"), entry(30, 10, "tcp-backup")],
+        SrvDomain::try_from("_sip._tcp.example.com").unwrap(),
+        300,
+    );
+
+    let merged = merge_srv_records(
+        vec![(Transport::Udp, udp), (Transport::Tcp, tcp)],
+        &[Transport::Tcp, Transport::Udp],
+    );
+
+    assert_eq!(
+        merged
+            .iter()
+            .map(|(entry, transport)| (entry.target.to_string(), *transport))
+            .collect::<Vec<_>>(),
+        vec![
+            ("tcp-primary".to_string(), Transport::Tcp),
+            ("udp-heavy".to_string(), Transport::Udp),
+            ("udp-light".to_string(), Transport::Udp),
+            ("udp-backup".to_string(), Transport::Udp),
+            ("tcp-backup".to_string(), Transport::Tcp),
+        ]
+    );
+}
+
+#[test]
+fn additional_hosts_filled_directly_are_matched_whatever_their_key() {
+    let target = Domain::from("Host.Example.com.");
+    let ip_addr = std::net::IpAddr::from([192, 0, 2, 1]);
+    let mut srv_record = srv_record(vec![entry(10, 10, "host.example.com")]);
+    srv_record.additional_hosts.insert(target.clone(), AddrRecord::from((target, vec![ip_addr])));
+
+    let addr_record = srv_record.get_additional_for_target(&"host.example.com".into()).unwrap();
+    assert_eq!(addr_record.ip_addrs, vec![ip_addr]);
+    assert!(srv_record.get_additional_for_target(&"HOST.example.com.".into()).is_some());
+    assert!(srv_record.has_complete_additionals());
+}
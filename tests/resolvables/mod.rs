@@ -2,3 +2,4 @@ pub mod resolvable_addr_record;
 pub mod resolvable_ip_addr;
 pub mod resolvable_naptr_record;
 pub mod resolvable_srv_record;
+pub mod resolvable_vec;
@@ -1,6 +1,6 @@
 use crate::support::MockedDnsClient;
 use rsip::{Domain, Port, Transport};
-use rsip_dns::{Target, records::*, resolvables::*};
+use rsip_dns::{IpFamily, Target, records::*, resolvables::*};
 
 #[tokio::test]
 async fn resolves_correctly() {
@@ -13,12 +13,15 @@ async fn resolves_correctly() {
             domain: domain.clone(),
             ip_addrs: vec![Randomize::random(), Randomize::random()],
             ttl: 300,
+            per_family_ttl: None,
+            source: None,
         }),
         ..Default::default()
     };
 
     let port = Port::random();
     let transport = Transport::random();
+    let siblings = Some(std::sync::Arc::new(dns_client.a_record.clone().unwrap().ip_addrs));
 
     let mut resolvable = ResolvableAddrRecord::new(dns_client.clone(), domain, port, transport);
 
@@ -28,7 +31,9 @@ async fn resolves_correctly() {
             ip_addr: *ip_addr,
             port,
             transport,
-            ttl: 300
+            ttl: 300,
+            scope_id: None,
+            siblings: siblings.clone(),
         })
     );
     assert_eq!(
@@ -37,8 +42,112 @@ async fn resolves_correctly() {
             ip_addr: *ip_addr,
             port,
             transport,
-            ttl: 300
+            ttl: 300,
+            scope_id: None,
+            siblings: siblings.clone(),
         })
     );
     assert!(resolvable.resolve_next().await.is_none());
 }
+
+#[tokio::test]
+async fn uses_per_family_ttls() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    let domain = Domain::from("example.com");
+    let ipv4 = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let ipv6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+    let dns_client = MockedDnsClient {
+        a_record: Some(AddrRecord {
+            domain: domain.clone(),
+            ip_addrs: vec![ipv4, ipv6],
+            ttl: 60,
+            per_family_ttl: Some((60, 3600)),
+            source: None,
+        }),
+        ..Default::default()
+    };
+
+    let mut resolvable =
+        ResolvableAddrRecord::new(dns_client, domain, Port::from(5060), Transport::Udp);
+
+    assert_eq!(
+        resolvable.resolve_next().await.map(|target| (target.ip_addr, target.ttl)),
+        Some((ipv4, 60))
+    );
+    assert_eq!(
+        resolvable.resolve_next().await.map(|target| (target.ip_addr, target.ttl)),
+        Some((ipv6, 3600))
+    );
+    assert_eq!(resolvable.resolve_next().await, None);
+}
+
+#[tokio::test]
+async fn counts_its_addresses_exactly() {
+    use crate::support::RecordingDnsClient;
+    use testing_utils::Randomize;
+
+    let domain = Domain::random();
+    let dns_client = RecordingDnsClient::new(MockedDnsClient {
+        a_record: Some(AddrRecord::from((
+            domain.clone(),
+            vec![Randomize::random(), Randomize::random()],
+        ))),
+        ..Default::default()
+    });
+
+    let mut resolvable =
+        ResolvableAddrRecord::new(dns_client.clone(), domain, Port::random(), Transport::random());
+
+    assert_eq!(resolvable.target_count().await, 2);
+    assert!(resolvable.resolve_next().await.is_some());
+    assert_eq!(resolvable.target_count().await, 1);
+    assert!(resolvable.resolve_next().await.is_some());
+    assert_eq!(resolvable.target_count().await, 0);
+
+    // the A/AAAA query sent to count is the one the targets come from
+    assert_eq!(dns_client.ip_queries(), 1);
+}
+
+#[tokio::test]
+async fn every_target_carries_the_addresses_of_its_host() {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::sync::Arc;
+
+    let domain = Domain::from("example.com");
+    let ip_addrs = vec![
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+        IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)),
+    ];
+    let dns_client = MockedDnsClient {
+        a_record: Some(AddrRecord::from((domain.clone(), ip_addrs.clone()))),
+        ..Default::default()
+    };
+
+    let mut resolvable =
+        ResolvableAddrRecord::new(dns_client.clone(), domain.clone(), 5060.into(), Transport::Udp);
+    for ip_addr in &ip_addrs {
+        let target = resolvable.resolve_next().await.unwrap();
+        assert_eq!(target.ip_addr, *ip_addr);
+        assert_eq!(target.siblings, Some(Arc::new(ip_addrs.clone())));
+    }
+    assert_eq!(resolvable.resolve_next().await, None);
+
+    // the addresses of a family that is filtered out are no siblings
+    let mut resolvable = ResolvableAddrRecord::new_with_family(
+        dns_client,
+        domain,
+        5060.into(),
+        Transport::Udp,
+        Some(IpFamily::V4),
+    );
+    let ipv4_addrs = vec![ip_addrs[0], ip_addrs[2]];
+    for ip_addr in &ipv4_addrs {
+        let target = resolvable.resolve_next().await.unwrap();
+        assert_eq!(target.ip_addr, *ip_addr);
+        assert_eq!(target.siblings, Some(Arc::new(ipv4_addrs.clone())));
+    }
+    assert_eq!(resolvable.resolve_next().await, None);
+}
@@ -42,3 +42,18 @@ async fn resolves_with_custom_ttl() {
 
     assert!(resolvable.resolve_next().await.is_none());
 }
+
+#[tokio::test]
+async fn counts_its_single_target() {
+    use testing_utils::Randomize;
+
+    let mut resolvable =
+        ResolvableIpAddr::new(Randomize::random(), Randomize::random(), Randomize::random());
+
+    assert_eq!(resolvable.target_count().await, 1);
+    assert!(resolvable.peek_next().await.is_some());
+    assert_eq!(resolvable.target_count().await, 1);
+
+    assert!(resolvable.resolve_next().await.is_some());
+    assert_eq!(resolvable.target_count().await, 0);
+}
@@ -61,7 +61,6 @@ async fn resolves_correctly() {
 #[derive(Debug, Clone, Default)]
 pub struct CustomMockedDnsClient;
 
-#[async_trait::async_trait]
 impl DnsClient for CustomMockedDnsClient {
     async fn naptr_lookup(&self, _domain: Domain) -> Option<NaptrRecord> {
         Some(NAPTR_RECORD.clone())
@@ -74,6 +73,8 @@ impl DnsClient for CustomMockedDnsClient {
             ip_addrs: IP_ADDRS.get(&domain.to_string()).unwrap().clone(),
             domain,
             ttl: 300,
+            per_family_ttl: None,
+            source: None,
         })
     }
 }
@@ -95,6 +96,7 @@ static NAPTR_RECORD: Lazy<NaptrRecord> = Lazy::new(|| {
         domain: DOMAIN.clone(),
         ttl: 300,
         additional_srvs: std::collections::HashMap::new(),
+        additional_hosts: std::collections::HashMap::new(),
     }
 });
 
@@ -150,7 +152,6 @@ async fn resolves_with_custom_ttl() {
     #[derive(Debug, Clone)]
     struct CustomTtlDnsClient;
 
-    #[async_trait::async_trait]
     impl DnsClient for CustomTtlDnsClient {
         async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
             Some(NaptrRecord {
@@ -165,6 +166,7 @@ async fn resolves_with_custom_ttl() {
                 domain: domain.clone(),
                 ttl: 300,
                 additional_srvs: std::collections::HashMap::new(),
+                additional_hosts: std::collections::HashMap::new(),
             })
         }
         async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
@@ -184,6 +186,8 @@ async fn resolves_with_custom_ttl() {
                 ip_addrs: vec![Randomize::random()],
                 domain,
                 ttl: 360, // Different TTL for A record - this should be the one used
+                per_family_ttl: None,
+                source: None,
             })
         }
     }
@@ -201,3 +205,305 @@ async fn resolves_with_custom_ttl() {
 
     assert!(resolvable.resolve_next().await.is_none());
 }
+
+#[tokio::test]
+async fn resolves_websocket_services() {
+    use testing_utils::Randomize;
+
+    #[derive(Debug, Clone)]
+    struct WebSocketDnsClient;
+
+    impl DnsClient for WebSocketDnsClient {
+        async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+            Some(NaptrRecord::new(
+                vec![NaptrEntry {
+                    order: 10,
+                    preference: 10,
+                    flags: NaptrFlags::S,
+                    services: NaptrServices::SipsD2w,
+                    regexp: vec![],
+                    replacement: "_sips._ws.example.com.".into(),
+                }],
+                domain,
+                300,
+            ))
+        }
+        async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+            assert_eq!(domain.to_string(), "_sips._ws.example.com.");
+
+            Some(SrvRecord::new(
+                vec![SrvEntry {
+                    priority: 1,
+                    weight: 1,
+                    port: 443.into(),
+                    target: Domain::from("wss.example.com"),
+                }],
+                domain,
+                300,
+            ))
+        }
+        async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+            Ok(AddrRecord {
+                ip_addrs: vec![Randomize::random()],
+                domain,
+                ttl: 300,
+                per_family_ttl: None,
+                source: None,
+            })
+        }
+    }
+
+    assert_eq!(NaptrServices::SipD2w.transport(), Some(Transport::Ws));
+    assert_eq!(NaptrServices::SipsD2w.transport(), Some(Transport::Wss));
+
+    let mut resolvable = ResolvableNaptrRecord::new(
+        WebSocketDnsClient,
+        Domain::from("example.com"),
+        vec![Transport::Wss],
+    );
+
+    let target = resolvable.resolve_next().await.unwrap();
+    assert_eq!(target.transport, Transport::Wss);
+    assert_eq!(target.port, 443.into());
+
+    assert!(resolvable.resolve_next().await.is_none());
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChainedNaptrDnsClient {
+    naptr_records: HashMap<Domain, NaptrRecord>,
+    naptr_queries: std::sync::Arc<std::sync::Mutex<Vec<Domain>>>,
+}
+
+impl ChainedNaptrDnsClient {
+    fn with_naptr(mut self, domain: &str, flags: NaptrFlags, replacement: &str) -> Self {
+        self.naptr_records.insert(
+            domain.into(),
+            NaptrRecord {
+                entries: vec![NaptrEntry {
+                    order: 10,
+                    preference: 10,
+                    flags,
+                    services: NaptrServices::SipD2u,
+                    regexp: vec![],
+                    replacement: replacement.into(),
+                }],
+                domain: domain.into(),
+                ttl: 300,
+                additional_srvs: HashMap::new(),
+                additional_hosts: HashMap::new(),
+            },
+        );
+        self
+    }
+
+    fn naptr_queries(&self) -> Vec<Domain> {
+        self.naptr_queries.lock().unwrap().clone()
+    }
+}
+
+impl DnsClient for ChainedNaptrDnsClient {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.naptr_queries.lock().unwrap().push(domain.clone());
+        self.naptr_records.get(&domain).cloned()
+    }
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        Some(SrvRecord::new(
+            vec![SrvEntry {
+                priority: 1,
+                weight: 1,
+                port: 5070.into(),
+                target: "sip.example.com".into(),
+            }],
+            domain,
+            300,
+        ))
+    }
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        Ok(AddrRecord {
+            domain,
+            ip_addrs: vec![IpAddr::from([192, 0, 2, 1])],
+            ttl: 300,
+            per_family_ttl: None,
+            source: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn follows_non_terminal_naptr_chain() {
+    let dns_client = ChainedNaptrDnsClient::default()
+        .with_naptr("example.com", NaptrFlags::Other(vec![]), "hop1.example.com")
+        .with_naptr("hop1.example.com", NaptrFlags::Other(vec![]), "hop2.example.com")
+        .with_naptr("hop2.example.com", NaptrFlags::S, "_sip._udp.example.com");
+
+    let mut resolvable =
+        ResolvableNaptrRecord::new(dns_client.clone(), "example.com".into(), vec![Transport::Udp]);
+
+    let target = resolvable.resolve_next().await.unwrap();
+    assert_eq!(target.ip_addr, IpAddr::from([192, 0, 2, 1]));
+    assert_eq!(target.port, 5070.into());
+    assert_eq!(target.transport, Transport::Udp);
+    assert!(resolvable.resolve_next().await.is_none());
+
+    assert_eq!(
+        dns_client.naptr_queries(),
+        vec![
+            Domain::from("example.com"),
+            Domain::from("hop1.example.com"),
+            Domain::from("hop2.example.com")
+        ]
+    );
+}
+
+#[tokio::test]
+async fn stops_naptr_chain_at_max_depth() {
+    let dns_client = ChainedNaptrDnsClient::default()
+        .with_naptr("example.com", NaptrFlags::Other(vec![]), "hop1.example.com")
+        .with_naptr("hop1.example.com", NaptrFlags::Other(vec![]), "hop2.example.com")
+        .with_naptr("hop2.example.com", NaptrFlags::S, "_sip._udp.example.com");
+
+    let mut resolvable =
+        ResolvableNaptrRecord::new(dns_client.clone(), "example.com".into(), vec![Transport::Udp])
+            .with_max_depth(1);
+
+    assert!(resolvable.resolve_next().await.is_none());
+    assert_eq!(
+        dns_client.naptr_queries(),
+        vec![Domain::from("example.com"), Domain::from("hop1.example.com")]
+    );
+}
+
+#[tokio::test]
+async fn detects_naptr_chain_loops() {
+    let dns_client = ChainedNaptrDnsClient::default()
+        .with_naptr("example.com", NaptrFlags::Other(vec![]), "hop1.example.com")
+        .with_naptr("hop1.example.com", NaptrFlags::Other(vec![]), "example.com.");
+
+    let mut resolvable =
+        ResolvableNaptrRecord::new(dns_client.clone(), "example.com".into(), vec![Transport::Udp])
+            .with_max_depth(10);
+
+    assert!(resolvable.resolve_next().await.is_none());
+    assert_eq!(
+        dns_client.naptr_queries(),
+        vec![Domain::from("example.com"), Domain::from("hop1.example.com")]
+    );
+}
+
+#[tokio::test]
+async fn shares_additional_hosts_between_srv_records() {
+    use crate::support::{MockedDnsClient, Query, RecordingDnsClient};
+
+    let target = Domain::from("sip.example.com");
+    let naptr_entry = |services, replacement: &str| NaptrEntry {
+        order: 10,
+        preference: 10,
+        flags: NaptrFlags::S,
+        services,
+        regexp: vec![],
+        replacement: replacement.into(),
+    };
+    let srv_record = |srv_domain: &str, port: u16| {
+        let srv_domain = SrvDomain::try_from(srv_domain).unwrap();
+        let srv_entry =
+            SrvEntry { priority: 1, weight: 1, port: port.into(), target: target.clone() };
+        (srv_domain.clone(), SrvRecord::new(vec![srv_entry], srv_domain, 300))
+    };
+
+    // both SRV records point to the same target, whose A record is kept once
+    let naptr_record = NaptrRecord::with_additional_srvs(
+        vec![
+            naptr_entry(NaptrServices::SipD2u, "_sip._udp.example.com"),
+            naptr_entry(NaptrServices::SipD2t, "_sip._tcp.example.com"),
+        ],
+        DOMAIN.clone(),
+        300,
+        HashMap::from([
+            srv_record("_sip._udp.example.com", 5060),
+            srv_record("_sip._tcp.example.com", 5061),
+        ]),
+    )
+    .with_shared_hosts(HashMap::from([(
+        target.clone(),
+        AddrRecord::from((target.clone(), vec![IpAddr::from([192, 0, 2, 10])])),
+    )]));
+
+    let dns_client = RecordingDnsClient::new(MockedDnsClient {
+        naptr_record: Some(naptr_record),
+        ..Default::default()
+    });
+    let mut resolvable = ResolvableNaptrRecord::new(
+        dns_client.clone(),
+        DOMAIN.clone(),
+        vec![Transport::Udp, Transport::Tcp],
+    );
+
+    let udp_target = resolvable.resolve_next().await.unwrap();
+    assert_eq!(udp_target.ip_addr, IpAddr::from([192, 0, 2, 10]));
+    assert_eq!((udp_target.port, udp_target.transport), (5060.into(), Transport::Udp));
+
+    let tcp_target = resolvable.resolve_next().await.unwrap();
+    assert_eq!(tcp_target.ip_addr, IpAddr::from([192, 0, 2, 10]));
+    assert_eq!((tcp_target.port, tcp_target.transport), (5061.into(), Transport::Tcp));
+    assert!(resolvable.resolve_next().await.is_none());
+
+    // the fast path is taken for both SRV records, no SRV or A/AAAA query goes out
+    assert_eq!(dns_client.queries(), vec![Query::Naptr(DOMAIN.clone())]);
+}
+
+#[tokio::test]
+async fn counts_the_targets_of_its_srv_records() {
+    use crate::support::RecordingDnsClient;
+
+    let dns_client = RecordingDnsClient::new(CustomMockedDnsClient);
+    let mut resolvable = ResolvableNaptrRecord::new(
+        dns_client.clone(),
+        NAPTR_RECORD.domain.clone(),
+        Transport::all().to_vec(),
+    );
+
+    // the SRV record of the single entry has 2 targets, whose addresses aren't queried
+    assert_eq!(resolvable.target_count().await, 2);
+    assert_eq!(dns_client.naptr_queries(), 1);
+    assert_eq!(dns_client.srv_queries(), 1);
+    assert_eq!(dns_client.ip_queries(), 0);
+
+    let mut targets = 0;
+    while resolvable.resolve_next().await.is_some() {
+        targets += 1;
+    }
+    assert_eq!(targets, 4);
+    assert_eq!(dns_client.naptr_queries(), 1);
+    assert_eq!(dns_client.srv_queries(), 1);
+}
+
+#[tokio::test]
+async fn resolves_a_prefetched_naptr_record() {
+    use crate::support::RecordingDnsClient;
+
+    let dns_client = RecordingDnsClient::new(CustomMockedDnsClient);
+    let naptr_record =
+        NaptrRecord { domain: Domain::from("provisioned.example.com"), ..NAPTR_RECORD.clone() };
+    let mut resolvable = ResolvableNaptrRecord::from_naptr_record(
+        dns_client.clone(),
+        naptr_record,
+        Transport::all().to_vec(),
+    );
+
+    let mut ip_addrs = vec![];
+    while let Some(target) = resolvable.resolve_next().await {
+        ip_addrs.push(target.ip_addr);
+    }
+    let expected = SRV_TARGETS
+        .iter()
+        .flat_map(|target| IP_ADDRS.get(&target.to_string()).unwrap().clone())
+        .collect::<Vec<_>>();
+    assert_eq!(ip_addrs, expected);
+
+    // only the SRV and A/AAAA queries were sent
+    assert_eq!(resolvable.outcome(), Some(&NaptrOutcome::Found));
+    assert_eq!(dns_client.naptr_queries(), 0);
+    assert_eq!(dns_client.srv_queries(), 1);
+    assert_eq!(dns_client.ip_queries(), 2);
+}
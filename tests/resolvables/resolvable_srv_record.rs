@@ -56,7 +56,6 @@ async fn resolves_correctly() {
 #[derive(Debug, Clone, Default)]
 pub struct CustomMockedDnsClient;
 
-#[async_trait::async_trait]
 impl DnsClient for CustomMockedDnsClient {
     async fn naptr_lookup(&self, _domain: Domain) -> Option<NaptrRecord> {
         unimplemented!()
@@ -69,6 +68,8 @@ impl DnsClient for CustomMockedDnsClient {
             ip_addrs: IP_ADDRS.get(&domain.to_string()).unwrap().clone(),
             domain,
             ttl: 300,
+            per_family_ttl: None,
+            source: None,
         })
     }
 }
@@ -119,7 +120,6 @@ async fn resolves_with_custom_ttl() {
     #[derive(Debug, Clone)]
     struct CustomTtlDnsClient;
 
-    #[async_trait::async_trait]
     impl DnsClient for CustomTtlDnsClient {
         async fn naptr_lookup(&self, _domain: Domain) -> Option<NaptrRecord> {
             unimplemented!()
@@ -141,17 +141,15 @@ async fn resolves_with_custom_ttl() {
                 ip_addrs: vec![Randomize::random()],
                 domain,
                 ttl: 600, // Custom TTL
+                per_family_ttl: None,
+                source: None,
             })
         }
     }
 
     let mut resolvable = ResolvableSrvRecord::new(
         CustomTtlDnsClient,
-        SrvDomain {
-            domain: Domain::from("example.com"),
-            protocol: rsip::Transport::Tcp,
-            secure: false,
-        },
+        SrvDomain::new(Domain::from("example.com"), rsip::Transport::Tcp),
     );
 
     let target = resolvable.resolve_next().await;
@@ -160,3 +158,132 @@ async fn resolves_with_custom_ttl() {
 
     assert!(resolvable.resolve_next().await.is_none());
 }
+
+#[tokio::test]
+async fn zero_port_uses_the_transport_default() {
+    use crate::support::PanicDnsClient;
+    use std::net::Ipv4Addr;
+
+    let target = Domain::from("server.example.com");
+    let ip_addr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let srv_record = SrvRecord::with_additional_hosts(
+        vec![SrvEntry { priority: 1, weight: 2, port: 0.into(), target: target.clone() }],
+        SrvDomain::secure(Domain::from("example.com"), rsip::Transport::Tcp),
+        300,
+        HashMap::from([(target.clone(), AddrRecord::from((target, vec![ip_addr])))]),
+    );
+
+    let mut resolvable = ResolvableSrvRecord::from_srv_record(PanicDnsClient, srv_record);
+
+    let target = resolvable.resolve_next().await.unwrap();
+    assert_eq!(target.ip_addr, ip_addr);
+    assert_eq!(target.transport, rsip::Transport::Tls);
+    assert_eq!(target.port, 5061.into());
+    assert!(resolvable.resolve_next().await.is_none());
+}
+
+#[tokio::test]
+async fn counts_each_target_without_addresses_as_one() {
+    use crate::support::RecordingDnsClient;
+
+    let dns_client = RecordingDnsClient::new(CustomMockedDnsClient);
+    let mut resolvable = ResolvableSrvRecord::new(dns_client.clone(), SRV_RECORD.domain.clone());
+
+    // both targets are counted once, without querying their addresses
+    assert_eq!(resolvable.target_count().await, 2);
+    assert_eq!(dns_client.srv_queries(), 1);
+    assert_eq!(dns_client.ip_queries(), 0);
+
+    // the first target is now known to have 2 addresses, one of them consumed
+    assert!(resolvable.resolve_next().await.is_some());
+    assert_eq!(resolvable.target_count().await, 2);
+
+    let mut remaining = 0;
+    while resolvable.resolve_next().await.is_some() {
+        remaining += 1;
+    }
+    assert_eq!(remaining, 3);
+    assert_eq!(resolvable.target_count().await, 0);
+    assert_eq!(dns_client.srv_queries(), 1);
+}
+
+#[tokio::test]
+async fn matches_additional_hosts_regardless_of_trailing_dot_and_case() {
+    use crate::support::PanicDnsClient;
+    use std::net::Ipv4Addr;
+
+    let ip_addr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let srv_record = SrvRecord::with_additional_hosts(
+        vec![SrvEntry {
+            priority: 1,
+            weight: 2,
+            port: 5060.into(),
+            target: Domain::from("sip.example.com"),
+        }],
+        SrvDomain::new(Domain::from("example.com"), rsip::Transport::Udp),
+        300,
+        HashMap::from([(
+            Domain::from("SIP.example.com."),
+            AddrRecord::from((Domain::from("SIP.example.com."), vec![ip_addr])),
+        )]),
+    );
+    assert!(srv_record.has_complete_additionals());
+
+    // the panicking client proves that no A/AAAA query is sent
+    let mut resolvable = ResolvableSrvRecord::from_srv_record(PanicDnsClient, srv_record);
+
+    assert_eq!(resolvable.resolve_next().await.map(|target| target.ip_addr), Some(ip_addr));
+    assert!(resolvable.resolve_next().await.is_none());
+}
+
+#[tokio::test]
+async fn targets_carry_the_minimum_of_the_srv_and_addr_ttls() {
+    use crate::support::MockedDnsClient;
+    use std::net::Ipv4Addr;
+
+    let known = Domain::from("known.example.com");
+    let ip_addr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let addr_record = |domain: Domain, ttl| AddrRecord {
+        domain,
+        ip_addrs: vec![ip_addr],
+        ttl,
+        per_family_ttl: None,
+        source: None,
+    };
+    let srv_record = |ttl, additional_ttl| {
+        SrvRecord::with_additional_hosts(
+            vec![
+                SrvEntry { priority: 1, weight: 2, port: 5060.into(), target: known.clone() },
+                SrvEntry {
+                    priority: 2,
+                    weight: 2,
+                    port: 5060.into(),
+                    target: Domain::from("queried.example.com"),
+                },
+            ],
+            SrvDomain::new(Domain::from("example.com"), rsip::Transport::Udp),
+            ttl,
+            HashMap::from([(known.clone(), addr_record(known.clone(), additional_ttl))]),
+        )
+    };
+
+    // the SRV record expires first, through the ADDITIONAL section and a separate query alike
+    let dns_client = MockedDnsClient {
+        a_record: Some(addr_record(Domain::from("queried.example.com"), 900)),
+        ..Default::default()
+    };
+    let mut resolvable = ResolvableSrvRecord::from_srv_record(dns_client, srv_record(60, 600));
+    assert_eq!(resolvable.resolve_next().await.map(|target| target.ttl), Some(60));
+    assert_eq!(resolvable.resolve_next().await.map(|target| target.ttl), Some(60));
+    assert!(resolvable.resolve_next().await.is_none());
+
+    // the A records expire first
+    let dns_client = MockedDnsClient {
+        a_record: Some(addr_record(Domain::from("queried.example.com"), 45)),
+        ..Default::default()
+    };
+    let mut resolvable = ResolvableSrvRecord::from_srv_record(dns_client, srv_record(3600, 30));
+    assert_eq!(resolvable.resolve_next().await.map(|target| target.ttl), Some(30));
+    assert_eq!(resolvable.resolve_next().await.map(|target| target.ttl), Some(45));
+    assert!(resolvable.resolve_next().await.is_none());
+}
@@ -0,0 +1,126 @@
+use rsip::Transport;
+use rsip_dns::{Target, resolvables::*};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn ip_addr(last: u8) -> ResolvableIpAddr {
+    ResolvableIpAddr::new(IpAddr::from([192, 0, 2, last]), 5060.into(), Transport::Udp)
+}
+
+#[test]
+fn unset_has_no_remaining_count() {
+    let resolvable: ResolvableVec<ResolvableIpAddr, Target> = ResolvableVec::unset();
+
+    assert_eq!(resolvable.remaining(), None);
+    assert!(!resolvable.is_empty_result());
+}
+
+#[tokio::test]
+async fn empty_has_nothing_remaining() {
+    let mut resolvable: ResolvableVec<ResolvableIpAddr, Target> = ResolvableVec::empty();
+
+    assert_eq!(resolvable.remaining(), Some(0));
+    assert!(resolvable.is_empty_result());
+    assert!(resolvable.resolve_next().await.is_none());
+}
+
+#[tokio::test]
+async fn non_empty_counts_down_the_remaining_candidates() {
+    let mut resolvable: ResolvableVec<ResolvableIpAddr, Target> =
+        ResolvableVec::non_empty(vec![ip_addr(1), ip_addr(2)]);
+
+    assert_eq!(resolvable.remaining(), Some(2));
+    assert!(!resolvable.is_empty_result());
+
+    assert!(resolvable.resolve_next().await.is_some());
+    assert_eq!(resolvable.remaining(), Some(1));
+    assert!(!resolvable.is_empty_result());
+
+    assert!(resolvable.resolve_next().await.is_some());
+    assert_eq!(resolvable.remaining(), Some(0));
+    assert!(resolvable.is_empty_result());
+    assert!(resolvable.resolve_next().await.is_none());
+}
+
+#[tokio::test]
+async fn counts_the_targets_of_every_candidate() {
+    let mut resolvable: ResolvableVec<ResolvableIpAddr, Target> =
+        ResolvableVec::non_empty(vec![ip_addr(1), ip_addr(2), ip_addr(3)]);
+    assert_eq!(resolvable.target_count().await, 3);
+
+    assert!(resolvable.resolve_next().await.is_some());
+    assert_eq!(resolvable.target_count().await, 2);
+
+    let mut resolvable: ResolvableVec<ResolvableIpAddr, Target> = ResolvableVec::unset();
+    assert_eq!(resolvable.target_count().await, 0);
+}
+
+// Builds `total` candidates, counting how many of them have been built
+#[derive(Debug, Clone)]
+struct CountingSource {
+    next: u8,
+    total: u8,
+    built: Arc<AtomicUsize>,
+}
+
+impl CandidateSource<ResolvableIpAddr> for CountingSource {
+    fn next_candidate(&mut self) -> Option<ResolvableIpAddr> {
+        if self.next == self.total {
+            return None;
+        }
+        self.next += 1;
+        self.built.fetch_add(1, Ordering::SeqCst);
+        Some(ip_addr(self.next))
+    }
+
+    fn remaining(&self) -> usize {
+        (self.total - self.next) as usize
+    }
+}
+
+#[tokio::test]
+async fn lazy_only_builds_the_consumed_candidates() {
+    let built = Arc::new(AtomicUsize::new(0));
+    let mut resolvable: ResolvableVec<ResolvableIpAddr, Target, CountingSource> =
+        ResolvableVec::lazy(CountingSource { next: 0, total: 200, built: built.clone() });
+
+    assert_eq!(resolvable.remaining(), Some(200));
+    assert_eq!(built.load(Ordering::SeqCst), 0);
+
+    for last in 1..=3 {
+        let target = resolvable.resolve_next().await.unwrap();
+        assert_eq!(target.ip_addr, IpAddr::from([192, 0, 2, last]));
+    }
+    assert_eq!(built.load(Ordering::SeqCst), 3);
+    assert_eq!(resolvable.remaining(), Some(197));
+    // the candidates left count as one each, without being built
+    assert_eq!(resolvable.target_count().await, 197);
+    assert_eq!(built.load(Ordering::SeqCst), 3);
+
+    // peeking builds the next one only
+    assert_eq!(resolvable.peek_next().await.unwrap().ip_addr, IpAddr::from([192, 0, 2, 4]));
+    assert_eq!(built.load(Ordering::SeqCst), 4);
+}
+
+#[tokio::test]
+async fn lazy_yields_every_candidate() {
+    let built = Arc::new(AtomicUsize::new(0));
+    let mut resolvable: ResolvableVec<ResolvableIpAddr, Target, CountingSource> =
+        ResolvableVec::lazy(CountingSource { next: 0, total: 3, built: built.clone() });
+
+    let mut targets = vec![];
+    while let Some(target) = resolvable.resolve_next().await {
+        targets.push(target.ip_addr);
+    }
+
+    assert_eq!(
+        targets,
+        vec![
+            IpAddr::from([192, 0, 2, 1]),
+            IpAddr::from([192, 0, 2, 2]),
+            IpAddr::from([192, 0, 2, 3])
+        ]
+    );
+    assert!(resolvable.is_empty_result());
+}
@@ -9,7 +9,6 @@ pub struct MockedDnsClient {
     pub aaaa_record: Option<AddrRecord>,
 }
 
-#[async_trait::async_trait]
 impl DnsClient for MockedDnsClient {
     async fn naptr_lookup(&self, _domain: Domain) -> Option<NaptrRecord> {
         self.naptr_record.clone()
@@ -4,7 +4,6 @@ use rsip_dns::{records::*, DnsClient};
 #[derive(Debug, Clone, Default)]
 pub struct PanicDnsClient;
 
-#[async_trait::async_trait]
 impl DnsClient for PanicDnsClient {
     async fn naptr_lookup(&self, _domain: Domain) -> Option<NaptrRecord> {
         panic!("should never call naptr_entries_for, yet it did!")
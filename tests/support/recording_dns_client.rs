@@ -0,0 +1,98 @@
+use rsip::{Domain, Error};
+use rsip_dns::{DnsClient, DnsError, records::*};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+/// A query as seen by the [RecordingDnsClient]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Naptr(Domain),
+    Srv(SrvDomain),
+    Ip(Domain),
+}
+
+/// Wraps another dns client, recording every query that goes through it and how many of them
+/// were in flight at the same time. An optional delay makes concurrency observable.
+#[derive(Debug, Clone)]
+pub struct RecordingDnsClient<C: DnsClient> {
+    inner: C,
+    delay: Option<Duration>,
+    queries: Arc<Mutex<Vec<Query>>>,
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: Arc<AtomicUsize>,
+}
+
+impl<C: DnsClient> RecordingDnsClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            delay: None,
+            queries: Default::default(),
+            in_flight: Default::default(),
+            max_in_flight: Default::default(),
+        }
+    }
+
+    pub fn with_delay(inner: C, delay: Duration) -> Self {
+        Self { delay: Some(delay), ..Self::new(inner) }
+    }
+
+    pub fn queries(&self) -> Vec<Query> {
+        self.queries.lock().unwrap().clone()
+    }
+
+    pub fn naptr_queries(&self) -> usize {
+        self.queries().iter().filter(|query| matches!(query, Query::Naptr(_))).count()
+    }
+
+    pub fn srv_queries(&self) -> usize {
+        self.queries().iter().filter(|query| matches!(query, Query::Srv(_))).count()
+    }
+
+    pub fn ip_queries(&self) -> usize {
+        self.queries().iter().filter(|query| matches!(query, Query::Ip(_))).count()
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight.load(Ordering::SeqCst)
+    }
+
+    async fn record(&self, query: Query) {
+        self.queries.lock().unwrap().push(query);
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<C: DnsClient> DnsClient for RecordingDnsClient<C> {
+    async fn naptr_lookup(&self, domain: Domain) -> Option<NaptrRecord> {
+        self.record(Query::Naptr(domain.clone())).await;
+        self.inner.naptr_lookup(domain).await
+    }
+    async fn srv_lookup(&self, domain: SrvDomain) -> Option<SrvRecord> {
+        self.record(Query::Srv(domain.clone())).await;
+        self.inner.srv_lookup(domain).await
+    }
+    async fn ip_lookup(&self, domain: Domain) -> Result<AddrRecord, Error> {
+        self.record(Query::Ip(domain.clone())).await;
+        self.inner.ip_lookup(domain).await
+    }
+    async fn try_naptr_lookup(&self, domain: Domain) -> Result<Option<NaptrRecord>, DnsError> {
+        self.record(Query::Naptr(domain.clone())).await;
+        self.inner.try_naptr_lookup(domain).await
+    }
+    async fn try_srv_lookup(&self, domain: SrvDomain) -> Result<Option<SrvRecord>, DnsError> {
+        self.record(Query::Srv(domain.clone())).await;
+        self.inner.try_srv_lookup(domain).await
+    }
+}
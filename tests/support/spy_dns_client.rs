@@ -51,7 +51,6 @@ impl SpyDnsClient {
     }
 }
 
-#[async_trait::async_trait]
 impl DnsClient for SpyDnsClient {
     async fn naptr_entries_for(&self, _domain: Domain) -> NaptrEntries {
         let mut inner = self.inner.lock().unwrap();
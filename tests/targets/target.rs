@@ -0,0 +1,128 @@
+use rsip::{Host, HostWithPort, Port, Scheme, Transport};
+use rsip_dns::Target;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+#[test]
+fn converts_into_host_with_port() {
+    let target = Target::from((
+        IpAddr::V4(Ipv4Addr::new(192, 168, 2, 13)),
+        Port::from(5060),
+        Transport::Udp,
+    ));
+
+    let host_with_port = HostWithPort::from(&target);
+    assert_eq!(host_with_port.host, Host::IpAddr(target.ip_addr));
+    assert_eq!(host_with_port.port, Some(Port::from(5060)));
+    assert_eq!(host_with_port.to_string(), "192.168.2.13:5060");
+}
+
+#[test]
+fn converts_into_uri() {
+    let target = Target::from((
+        IpAddr::V4(Ipv4Addr::new(192, 168, 2, 13)),
+        Port::from(5061),
+        Transport::Tls,
+    ));
+
+    let uri = target.to_uri(Scheme::Sips);
+    assert_eq!(uri.scheme, Some(Scheme::Sips));
+    assert_eq!(uri.host_with_port, HostWithPort::from(&target));
+    assert_eq!(uri.transport(), Some(&Transport::Tls));
+}
+
+#[test]
+fn converts_ipv6_target_into_uri() {
+    let ip_addr = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+    let target = Target::from((ip_addr, Port::from(5060), Transport::Tcp));
+
+    let host_with_port = HostWithPort::from(&target);
+    assert_eq!(host_with_port.host, Host::IpAddr(ip_addr));
+    assert_eq!(host_with_port.to_string(), "[2001:db8::1]:5060");
+
+    let uri = target.to_uri(Scheme::Sip);
+    assert_eq!(uri.scheme, Some(Scheme::Sip));
+    assert_eq!(uri.host_with_port.host, Host::IpAddr(ip_addr));
+    assert_eq!(uri.host_with_port.port, Some(Port::from(5060)));
+    assert_eq!(uri.transport(), Some(&Transport::Tcp));
+}
+
+#[test]
+fn sorts_by_transport_port_family_then_address() {
+    let v4 = |last| IpAddr::V4(Ipv4Addr::new(192, 0, 2, last));
+    let v6 = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1));
+
+    let mut targets = vec![
+        Target::from((v4(1), Port::from(5061), Transport::Tls)),
+        Target::from((v6, Port::from(5060), Transport::Udp)),
+        Target::from((v4(2), Port::from(5060), Transport::Udp)),
+        Target::from((v4(1), Port::from(5080), Transport::Udp)),
+        Target::from((v4(1), Port::from(5060), Transport::Tcp)),
+        Target::from((v4(1), Port::from(5060), Transport::Udp)),
+    ];
+    targets.sort();
+
+    assert_eq!(
+        targets,
+        vec![
+            Target::from((v4(1), Port::from(5060), Transport::Udp)),
+            Target::from((v4(2), Port::from(5060), Transport::Udp)),
+            Target::from((v6, Port::from(5060), Transport::Udp)),
+            Target::from((v4(1), Port::from(5080), Transport::Udp)),
+            Target::from((v4(1), Port::from(5060), Transport::Tcp)),
+            Target::from((v4(1), Port::from(5061), Transport::Tls)),
+        ]
+    );
+}
+
+#[test]
+fn the_order_agrees_with_equality() {
+    let target = Target::from((
+        IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)),
+        Port::from(5060),
+        Transport::Udp,
+        60,
+    ));
+    let longer_lived = Target { ttl: 3600, ..target.clone() };
+
+    assert_eq!(target.cmp(&target.clone()), std::cmp::Ordering::Equal);
+    assert!(target < longer_lived);
+}
+
+#[test]
+fn siblings_break_ties_between_same_endpoints() {
+    use std::sync::Arc;
+
+    let ip_addr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+    let target = Target::from((ip_addr, Port::from(5060), Transport::Udp));
+    let with_siblings = target
+        .clone()
+        .with_siblings(Some(Arc::new(vec![ip_addr, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2))])));
+
+    assert_ne!(target, with_siblings);
+    assert_eq!(target.cmp(&with_siblings), std::cmp::Ordering::Less);
+    assert!(target.same_endpoint(&with_siblings));
+    assert!(!target.same_endpoint(&Target { ttl: 3600, ..with_siblings }));
+}
+
+#[test]
+fn socket_addr_carries_the_scope_id() {
+    use std::net::SocketAddr;
+
+    let link_local = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+    let target = Target::from((IpAddr::V6(link_local), Port::from(5060), Transport::Udp));
+    assert_eq!(target.scope_id, None);
+
+    match target.clone().with_scope_id(Some(3)).socket_addr() {
+        SocketAddr::V6(socket_addr) => {
+            assert_eq!(*socket_addr.ip(), link_local);
+            assert_eq!(socket_addr.port(), 5060);
+            assert_eq!(socket_addr.scope_id(), 3);
+        }
+        socket_addr => panic!("expected an IPv6 socket address, got {}", socket_addr),
+    }
+
+    match target.socket_addr() {
+        SocketAddr::V6(socket_addr) => assert_eq!(socket_addr.scope_id(), 0),
+        socket_addr => panic!("expected an IPv6 socket address, got {}", socket_addr),
+    }
+}